@@ -1,5 +1,24 @@
-
 fn main() {
+    println!("cargo::rustc-check-cfg=cfg(armv8m)");
+    println!("cargo::rustc-check-cfg=cfg(armv6m)");
+    println!("cargo::rustc-check-cfg=cfg(has_fpu)");
+
+    // ARMv8-M Mainline (Cortex-M33) uses the PSPLIM-aware port
+    let target = std::env::var("TARGET").unwrap_or_default();
+    if target.starts_with("thumbv8m.main") {
+        println!("cargo:rustc-cfg=armv8m");
+    }
+
+    // ARMv6-M (Cortex-M0/M0+) has no CLZ instruction
+    if target.starts_with("thumbv6m") {
+        println!("cargo:rustc-cfg=armv6m");
+    }
+
+    // Hard-float targets stack the FP extension on exception entry
+    if target.starts_with("thumb") && target.ends_with("eabihf") {
+        println!("cargo:rustc-cfg=has_fpu");
+    }
+
     if std::env::var("CARGO_FEATURE_DEFMT").is_ok() {
         println!("cargo:rustc-link-arg=-Tdefmt.x");
     }
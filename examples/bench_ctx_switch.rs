@@ -0,0 +1,99 @@
+//! Context Switch Benchmark - task semaphore ping-pong timed with the DWT
+//!
+//! Ping posts Pong's task semaphore and pends on its own; Pong, one
+//! priority higher, runs at once, posts back and pends again. Each round
+//! trip is two context switches plus a post and a pend on each side,
+//! timed with the timestamp counter (the DWT cycle counter on Cortex-M3
+//! and up). Ends with a `PASS` or `FAIL` line for a hardware-in-loop rig.
+
+#![no_std]
+#![no_main]
+#![allow(static_mut_refs)]
+
+use core::ptr::{addr_of_mut, NonNull};
+
+use cortex_m_rt::entry;
+use ucosiii::task::{os_task_sem_pend, os_task_sem_post, OsTcb};
+use ucosiii::time::{os_time_dly, os_ts_diff, os_ts_get};
+use ucosiii::types::{opt, OsStkElement};
+use ucosiii::os_task_create;
+
+/// Round trips measured
+const ROUNDS: u32 = 10_000;
+
+/// Cycles per switch, post and pend included, above which the run fails
+const MAX_CYCLES_PER_SWITCH: u32 = 1_000;
+
+// ============ Task Storage ============
+
+static mut PING_STK: [OsStkElement; 256] = [0; 256];
+static mut PING_TCB: OsTcb = OsTcb::new();
+static mut PONG_STK: [OsStkElement; 256] = [0; 256];
+static mut PONG_TCB: OsTcb = OsTcb::new();
+
+fn tcb(tcb: *mut OsTcb) -> NonNull<OsTcb> {
+    NonNull::new(tcb).unwrap()
+}
+
+// ============ Tasks ============
+
+fn pong_task(_: *mut ()) -> ! {
+    let ping = tcb(unsafe { addr_of_mut!(PING_TCB) });
+    loop {
+        let _ = os_task_sem_pend(0, opt::PEND_BLOCKING);
+        let _ = os_task_sem_post(ping, opt::POST_FIFO);
+    }
+}
+
+fn ping_task(_: *mut ()) -> ! {
+    let pong = tcb(unsafe { addr_of_mut!(PONG_TCB) });
+
+    // Let both tasks settle into their pends before timing
+    let _ = os_time_dly(10);
+
+    let start = os_ts_get();
+    for _ in 0..ROUNDS {
+        let _ = os_task_sem_post(pong, opt::POST_FIFO);
+        let _ = os_task_sem_pend(0, opt::PEND_BLOCKING);
+    }
+    let cycles = os_ts_diff(start, os_ts_get());
+    let per_switch = cycles / (2 * ROUNDS);
+
+    ucosiii::info!("{} round trips in {} cycles: {} cycles/switch", ROUNDS, cycles, per_switch);
+    if per_switch <= MAX_CYCLES_PER_SWITCH {
+        ucosiii::info!("PASS");
+    } else {
+        ucosiii::error!("FAIL: {} cycles/switch above {}", per_switch, MAX_CYCLES_PER_SWITCH);
+    }
+
+    loop {
+        let _ = os_time_dly(1000);
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    ucosiii::os_init().expect("OS init failed");
+
+    os_task_create(
+        unsafe { &mut PONG_TCB },
+        unsafe { &mut PONG_STK },
+        "Pong",
+        pong_task,
+        4,
+    ).expect("Pong task failed");
+
+    os_task_create(
+        unsafe { &mut PING_TCB },
+        unsafe { &mut PING_STK },
+        "Ping",
+        ping_task,
+        5,
+    ).expect("Ping task failed");
+
+    ucosiii::os_start().expect("OS start failed");
+
+    loop { cortex_m::asm::nop(); }
+}
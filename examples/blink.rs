@@ -1,20 +1,25 @@
 //! Blink Example - LED blinking using RTOS on STM32F401
+//!
+//! `main` only initializes the kernel and hands over to a startup task
+//! with `os_run`. The startup task does the board bring-up in task
+//! context, where it may delay or block, then creates the other tasks.
 
 #![no_std]
 #![no_main]
 
 use cortex_m_rt::entry;
-use ucosiii::task::OsTcb;
+use ucosiii::task::{OsTcb, Stack};
 use ucosiii::time::os_time_dly;
-use ucosiii::types::OsStkElement;
-use ucosiii::os_task_create;
+use ucosiii::os_task_create_stk;
 
 #[cfg(feature = "pac")]
 use stm32_metapac as pac;
 
 // ============ Task Storage ============
 
-static mut BLINK_STK: [OsStkElement; 512] = [0; 512];
+static mut INIT_STK: Stack<512> = Stack::new();
+static mut INIT_TCB: OsTcb = OsTcb::new();
+static mut BLINK_STK: Stack<512> = Stack::new();
 static mut BLINK_TCB: OsTcb = OsTcb::new();
 
 // ============ LED Control ============
@@ -39,7 +44,7 @@ fn led_on() {}
 #[cfg(not(feature = "pac"))]
 fn led_off() {}
 
-// ============ Task ============
+// ============ Tasks ============
 
 fn blink_task(_: *mut ()) -> ! {
     ucosiii::info!("Blink task started");
@@ -54,42 +59,41 @@ fn blink_task(_: *mut ()) -> ! {
     }
 }
 
-fn test_task(_: *mut ()) -> ! {
-    ucosiii::info!("test task started");
+/// Bring up the board, create the application tasks, then keep running
+fn init_task(_: *mut ()) -> ! {
+    ucosiii::info!("Startup task started");
+
+    // Blocking bring-up is fine here, e.g. letting a supply settle
+    let _ = os_time_dly(10);
+    led_init();
+
+    os_task_create_stk(
+        unsafe { &mut *core::ptr::addr_of_mut!(BLINK_TCB) },
+        unsafe { &mut *core::ptr::addr_of_mut!(BLINK_STK) },
+        "Blink",
+        blink_task,
+        5,
+    ).expect("Blink task failed");
+
     loop {
-        ucosiii::info!("Test Task");
+        ucosiii::info!("Startup task alive");
         let _ = os_time_dly(1000);
     }
 }
-static mut TEST_STK: [OsStkElement; 512] = [0; 512];
-static mut TEST_TCB: OsTcb = OsTcb::new();
 
 // ============ Main ============
 
 #[entry]
 fn main() -> ! {   
-    led_init();
-    
     ucosiii::os_init().expect("OS init failed");
     
-    os_task_create(
-        unsafe { &mut BLINK_TCB },
-        unsafe { &mut BLINK_STK },
-        "Blink",
-        blink_task,
-        5,
-    ).expect("Blink task failed");
-    
-    os_task_create(
-        unsafe { &mut TEST_TCB },
-        unsafe { &mut TEST_STK },
-        "test",
-        test_task,
-        5,
-    ).expect("Testtask failed");
-    
     ucosiii::info!("Starting RTOS");
-    ucosiii::os_start().expect("OS start failed");
+    ucosiii::os_run(
+        unsafe { &mut *core::ptr::addr_of_mut!(INIT_TCB) },
+        unsafe { &mut *core::ptr::addr_of_mut!(INIT_STK) },
+        init_task,
+        4,
+    ).expect("OS start failed");
     
     loop { cortex_m::asm::nop(); }
 }
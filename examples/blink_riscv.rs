@@ -0,0 +1,77 @@
+//! Blink Example - LED blinking using RTOS on a SiFive FE310 (HiFive1 Rev B)
+//!
+//! Build with `--target riscv32imac-unknown-none-elf --no-default-features --features full`.
+
+#![no_std]
+#![no_main]
+
+use riscv_rt::entry;
+use ucosiii::task::OsTcb;
+use ucosiii::time::os_time_dly;
+use ucosiii::types::OsStkElement;
+use ucosiii::os_task_create;
+
+/// `mtime` runs from the 32.768 kHz RTC clock on the FE310
+const MTIME_HZ: u32 = 32_768;
+
+// ============ Task Storage ============
+
+static mut BLINK_STK: [OsStkElement; 512] = [0; 512];
+static mut BLINK_TCB: OsTcb = OsTcb::new();
+
+// ============ LED Control ============
+
+/// Green LED on GPIO 19
+const LED_PIN: u32 = 19;
+const GPIO_BASE: usize = 0x1001_2000;
+const GPIO_OUTPUT_EN: *mut u32 = (GPIO_BASE + 0x08) as *mut u32;
+const GPIO_OUTPUT_VAL: *mut u32 = (GPIO_BASE + 0x0C) as *mut u32;
+
+fn led_init() {
+    unsafe {
+        let en = core::ptr::read_volatile(GPIO_OUTPUT_EN);
+        core::ptr::write_volatile(GPIO_OUTPUT_EN, en | (1 << LED_PIN));
+    }
+}
+
+fn led_set(on: bool) {
+    unsafe {
+        let val = core::ptr::read_volatile(GPIO_OUTPUT_VAL);
+        // LEDs are active low
+        let val = if on { val & !(1 << LED_PIN) } else { val | (1 << LED_PIN) };
+        core::ptr::write_volatile(GPIO_OUTPUT_VAL, val);
+    }
+}
+
+// ============ Task ============
+
+fn blink_task(_: *mut ()) -> ! {
+    loop {
+        led_set(true);
+        let _ = os_time_dly(500);
+
+        led_set(false);
+        let _ = os_time_dly(500);
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    led_init();
+
+    ucosiii::os_init().expect("OS init failed");
+
+    os_task_create(
+        unsafe { &mut BLINK_TCB },
+        unsafe { &mut BLINK_STK },
+        "Blink",
+        blink_task,
+        5,
+    ).expect("Blink task failed");
+
+    ucosiii::os_start_with_clock(MTIME_HZ).expect("OS start failed");
+
+    loop {}
+}
@@ -0,0 +1,80 @@
+//! Button ISR Example - a user interrupt handler waking a task
+//!
+//! The EXTI handler for the user button (PC13 on Nucleo-F401RE) is wrapped
+//! in `os_isr!`, so the semaphore post switches straight to the waiting
+//! task when the handler returns instead of at the next tick.
+
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use stm32f4xx_hal::gpio::Edge;
+use stm32f4xx_hal::pac::{self, interrupt};
+use stm32f4xx_hal::prelude::*;
+use ucosiii::sem::Semaphore;
+use ucosiii::task::OsTcb;
+use ucosiii::types::{OsStkElement, Timeout};
+use ucosiii::{os_isr, os_task_create};
+
+/// EXTI pending register (write 1 to clear)
+const EXTI_PR: *mut u32 = 0x4001_3C14 as *mut u32;
+const BUTTON_LINE: u32 = 13;
+
+static BUTTON: Semaphore = Semaphore::new(0);
+
+// ============ Task Storage ============
+
+static mut BUTTON_STK: [OsStkElement; 256] = [0; 256];
+static mut BUTTON_TCB: OsTcb = OsTcb::new();
+
+// ============ Interrupt ============
+
+#[interrupt]
+fn EXTI15_10() {
+    os_isr! {
+        unsafe { EXTI_PR.write_volatile(1 << BUTTON_LINE) };
+        let _ = BUTTON.signal();
+    }
+}
+
+// ============ Task ============
+
+fn button_task(_: *mut ()) -> ! {
+    let mut presses = 0u32;
+    loop {
+        if BUTTON.wait(Timeout::Forever).is_ok() {
+            presses += 1;
+            ucosiii::info!("button pressed ({} times)", presses);
+        }
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    ucosiii::os_init().expect("OS init failed");
+
+    BUTTON.create(0, "Button").expect("Semaphore creation failed");
+
+    let mut dp = pac::Peripherals::take().unwrap();
+    let mut syscfg = dp.SYSCFG.constrain();
+    let gpioc = dp.GPIOC.split();
+    let mut button = gpioc.pc13.into_pull_up_input();
+    button.make_interrupt_source(&mut syscfg);
+    button.trigger_on_edge(&mut dp.EXTI, Edge::Falling);
+    button.enable_interrupt(&mut dp.EXTI);
+    unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::EXTI15_10) };
+
+    os_task_create(
+        unsafe { &mut BUTTON_TCB },
+        unsafe { &mut BUTTON_STK },
+        "Button",
+        button_task,
+        5,
+    ).expect("Button task failed");
+
+    ucosiii::os_start().expect("OS start failed");
+
+    loop { cortex_m::asm::nop(); }
+}
@@ -0,0 +1,78 @@
+//! embassy Bridge Example - async code inside a μC/OS task
+//!
+//! The `Async` task hosts an embassy executor whose futures sleep with
+//! `embassy_time::Timer`, driven by the kernel tick. The `Sync` task is
+//! an ordinary μC/OS task at higher priority and preempts the executor as
+//! usual.
+
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use embassy_time::{Duration, Timer};
+use ucosiii::embassy::OsExecutor;
+use ucosiii::task::OsTcb;
+use ucosiii::time::os_time_dly;
+use ucosiii::types::OsStkElement;
+use ucosiii::os_task_create;
+
+// ============ Task Storage ============
+
+static mut ASYNC_STK: [OsStkElement; 1024] = [0; 1024];
+static mut ASYNC_TCB: OsTcb = OsTcb::new();
+static mut SYNC_STK: [OsStkElement; 256] = [0; 256];
+static mut SYNC_TCB: OsTcb = OsTcb::new();
+
+static EXECUTOR: OsExecutor = OsExecutor::new();
+
+// ============ Async Side ============
+
+#[embassy_executor::task]
+async fn ticker() {
+    let mut n = 0u32;
+    loop {
+        Timer::after(Duration::from_millis(250)).await;
+        n += 1;
+        ucosiii::info!("async tick {}", n);
+    }
+}
+
+fn async_task(_: *mut ()) -> ! {
+    EXECUTOR.run(|spawner| spawner.must_spawn(ticker()))
+}
+
+// ============ Sync Side ============
+
+fn sync_task(_: *mut ()) -> ! {
+    loop {
+        ucosiii::info!("sync task");
+        let _ = os_time_dly(1000);
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    ucosiii::os_init().expect("OS init failed");
+
+    os_task_create(
+        unsafe { &mut ASYNC_TCB },
+        unsafe { &mut ASYNC_STK },
+        "Async",
+        async_task,
+        10,
+    ).expect("Async task failed");
+
+    os_task_create(
+        unsafe { &mut SYNC_TCB },
+        unsafe { &mut SYNC_STK },
+        "Sync",
+        sync_task,
+        5,
+    ).expect("Sync task failed");
+
+    ucosiii::os_start().expect("OS start failed");
+
+    loop { cortex_m::asm::nop(); }
+}
@@ -0,0 +1,88 @@
+//! Fault Recovery Example - a faulting task is removed, the rest keep running
+//!
+//! A low-priority "Shell" task dereferences an unmapped address after a few
+//! seconds. With `fault-recovery` the HardFault handler removes it, the
+//! `task_fault` hook logs the report and the LED keeps blinking.
+
+#![no_std]
+#![no_main]
+
+use core::ptr::NonNull;
+use cortex_m_rt::entry;
+use ucosiii::task::{OsFaultReport, OsTcb};
+use ucosiii::time::os_time_dly;
+use ucosiii::types::OsStkElement;
+use ucosiii::{os_app_hooks_set, os_task_create, OsAppHooks};
+
+// ============ Task Storage ============
+
+static mut BLINK_STK: [OsStkElement; 256] = [0; 256];
+static mut BLINK_TCB: OsTcb = OsTcb::new();
+static mut SHELL_STK: [OsStkElement; 256] = [0; 256];
+static mut SHELL_TCB: OsTcb = OsTcb::new();
+
+// ============ Hooks ============
+
+fn on_task_fault(tcb: NonNull<OsTcb>, report: &OsFaultReport) {
+    ucosiii::info!(
+        "task '{}' removed: pc {=u32:#x} cfsr {=u32:#x} bfar {=u32:#x}",
+        unsafe { tcb.as_ref() }.name,
+        report.pc,
+        report.cfsr,
+        report.bfar,
+    );
+}
+
+// ============ Tasks ============
+
+fn blink_task(_: *mut ()) -> ! {
+    loop {
+        ucosiii::info!("alive");
+        let _ = os_time_dly(500);
+    }
+}
+
+fn shell_task(_: *mut ()) -> ! {
+    let _ = os_time_dly(3000);
+
+    // Reserved region on STM32F4: the load raises a precise BusFault
+    let bad = 0xF000_0000 as *const u32;
+    let value = unsafe { bad.read_volatile() };
+    ucosiii::error!("read {} from a bad address, not expected", value);
+
+    loop {
+        let _ = os_time_dly(1000);
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    ucosiii::os_init().expect("OS init failed");
+
+    os_app_hooks_set(OsAppHooks {
+        task_fault: Some(on_task_fault),
+        ..OsAppHooks::new()
+    });
+
+    os_task_create(
+        unsafe { &mut BLINK_TCB },
+        unsafe { &mut BLINK_STK },
+        "Blink",
+        blink_task,
+        5,
+    ).expect("Blink task failed");
+
+    os_task_create(
+        unsafe { &mut SHELL_TCB },
+        unsafe { &mut SHELL_STK },
+        "Shell",
+        shell_task,
+        20,
+    ).expect("Shell task failed");
+
+    ucosiii::os_start().expect("OS start failed");
+
+    loop { cortex_m::asm::nop(); }
+}
@@ -0,0 +1,101 @@
+//! I2C Sensor Example - driver init delays through the scheduler
+//!
+//! A minimal SHT3x-style driver takes any `DelayNs`. Handing it
+//! `OsDelay` makes its reset and conversion waits block the calling task
+//! instead of spinning, so lower-priority tasks keep running.
+//!
+//! Wiring (STM32F401): SCL = PB8, SDA = PB9, sensor address 0x44.
+
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+use stm32f4xx_hal::{i2c::I2c as HalI2c, pac, prelude::*};
+use ucosiii::task::OsTcb;
+use ucosiii::time::{os_time_dly, OsDelay};
+use ucosiii::types::OsStkElement;
+use ucosiii::os_task_create;
+
+// ============ Sensor Driver ============
+
+const SENSOR_ADDR: u8 = 0x44;
+
+struct Sensor<I2C, D> {
+    i2c: I2C,
+    delay: D,
+}
+
+impl<I2C: I2c, D: DelayNs> Sensor<I2C, D> {
+    fn new(i2c: I2C, delay: D) -> Self {
+        Sensor { i2c, delay }
+    }
+
+    /// Soft reset, then wait for the sensor to come back
+    fn init(&mut self) -> Result<(), I2C::Error> {
+        self.i2c.write(SENSOR_ADDR, &[0x30, 0xA2])?;
+        self.delay.delay_ms(2);
+        Ok(())
+    }
+
+    /// Single-shot measurement, returns the raw temperature word
+    fn read_raw(&mut self) -> Result<u16, I2C::Error> {
+        self.i2c.write(SENSOR_ADDR, &[0x24, 0x00])?;
+        self.delay.delay_ms(16);
+
+        let mut buf = [0u8; 6];
+        self.i2c.read(SENSOR_ADDR, &mut buf)?;
+        Ok(u16::from_be_bytes([buf[0], buf[1]]))
+    }
+}
+
+// ============ Task Storage ============
+
+static mut SENSOR_STK: [OsStkElement; 1024] = [0; 1024];
+static mut SENSOR_TCB: OsTcb = OsTcb::new();
+
+// ============ Task ============
+
+fn sensor_task(_: *mut ()) -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Keep the 16 MHz reset clock that `os_start` programmed SysTick for
+    let rcc = dp.RCC.constrain();
+    let clocks = rcc.cfgr.freeze();
+
+    let gpiob = dp.GPIOB.split();
+    let i2c = HalI2c::new(dp.I2C1, (gpiob.pb8, gpiob.pb9), 100.kHz(), &clocks);
+
+    let mut sensor = Sensor::new(i2c, OsDelay::new());
+    if sensor.init().is_err() {
+        ucosiii::info!("sensor not responding");
+    }
+
+    loop {
+        match sensor.read_raw() {
+            Ok(raw) => ucosiii::info!("raw temperature {}", raw),
+            Err(_) => ucosiii::info!("read failed"),
+        }
+        let _ = os_time_dly(1000);
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    ucosiii::os_init().expect("OS init failed");
+
+    os_task_create(
+        unsafe { &mut SENSOR_TCB },
+        unsafe { &mut SENSOR_STK },
+        "Sensor",
+        sensor_task,
+        5,
+    ).expect("Sensor task failed");
+
+    ucosiii::os_start().expect("OS start failed");
+
+    loop { cortex_m::asm::nop(); }
+}
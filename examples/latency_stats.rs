@@ -0,0 +1,99 @@
+//! Latency Stats Example - context switch and masked-interrupt cycles
+//!
+//! Two tasks ping-pong through a semaphore to generate context switches;
+//! a monitor task prints `os_stats()` once per second over defmt and
+//! starts a new measurement window.
+
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use ucosiii::kernel::{os_stats, os_stats_reset};
+use ucosiii::sem::Semaphore;
+use ucosiii::task::OsTcb;
+use ucosiii::time::os_time_dly;
+use ucosiii::types::{OsStkElement, Timeout};
+use ucosiii::os_task_create;
+
+// ============ Task Storage ============
+
+static mut PING_STK: [OsStkElement; 256] = [0; 256];
+static mut PING_TCB: OsTcb = OsTcb::new();
+static mut PONG_STK: [OsStkElement; 256] = [0; 256];
+static mut PONG_TCB: OsTcb = OsTcb::new();
+static mut MON_STK: [OsStkElement; 512] = [0; 512];
+static mut MON_TCB: OsTcb = OsTcb::new();
+
+static PING: Semaphore = Semaphore::new(0);
+
+// ============ Tasks ============
+
+fn ping_task(_: *mut ()) -> ! {
+    loop {
+        let _ = PING.signal();
+        let _ = os_time_dly(1);
+    }
+}
+
+fn pong_task(_: *mut ()) -> ! {
+    loop {
+        let _ = PING.wait(Timeout::Forever);
+    }
+}
+
+fn monitor_task(_: *mut ()) -> ! {
+    loop {
+        let _ = os_time_dly(1000);
+
+        let stats = os_stats();
+        os_stats_reset();
+
+        ucosiii::info!(
+            "ctx sw: min {} avg {} max {} cycles over {} switches",
+            stats.ctx_sw.min,
+            stats.ctx_sw.avg(),
+            stats.ctx_sw.max,
+            stats.ctx_sw.count,
+        );
+        if let Some(at) = stats.cs_max_at {
+            ucosiii::info!("irq masked: max {} cycles at {}:{}", stats.cs_max, at.file(), at.line());
+        }
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    ucosiii::os_init().expect("OS init failed");
+
+    PING.create(0, "Ping").expect("Semaphore creation failed");
+
+    os_task_create(
+        unsafe { &mut MON_TCB },
+        unsafe { &mut MON_STK },
+        "Monitor",
+        monitor_task,
+        3,
+    ).expect("Monitor task failed");
+
+    os_task_create(
+        unsafe { &mut PONG_TCB },
+        unsafe { &mut PONG_STK },
+        "Pong",
+        pong_task,
+        5,
+    ).expect("Pong task failed");
+
+    os_task_create(
+        unsafe { &mut PING_TCB },
+        unsafe { &mut PING_STK },
+        "Ping",
+        ping_task,
+        6,
+    ).expect("Ping task failed");
+
+    ucosiii::os_start().expect("OS start failed");
+
+    loop { cortex_m::asm::nop(); }
+}
@@ -13,7 +13,7 @@ use cortex_m_rt::entry;
 use defmt::info;
 use ucosiii::task::OsTcb;
 use ucosiii::time::os_time_dly;
-use ucosiii::types::OsStkElement;
+use ucosiii::types::{OsStkElement, Timeout};
 use ucosiii::mutex::Mutex;
 use ucosiii::os_task_create;
 
@@ -36,12 +36,12 @@ fn high_task_fn(_arg: *mut ()) -> ! {
     loop {
         let n = HIGH_RUNS.fetch_add(1, Ordering::Relaxed) + 1;
         
-        let _ = MTX.lock(0, 0);
+        let _ = MTX.lock(Timeout::Forever);
         info!("[HIGH] acquired #{}", n);
         
         for _ in 0..1_000 { cortex_m::asm::nop(); }
         
-        let _ = MTX.unlock(0);
+        let _ = MTX.unlock();
         let _ = os_time_dly(100);
     }
 }
@@ -59,12 +59,12 @@ fn low_task_fn(_arg: *mut ()) -> ! {
     loop {
         let n = LOW_RUNS.fetch_add(1, Ordering::Relaxed) + 1;
         
-        let _ = MTX.lock(0, 0);
+        let _ = MTX.lock(Timeout::Forever);
         info!("[LOW] holding #{}", n);
         
         for _ in 0..100_000 { cortex_m::asm::nop(); }
         
-        let _ = MTX.unlock(0);
+        let _ = MTX.unlock();
         let _ = os_time_dly(200);
     }
 }
@@ -10,7 +10,7 @@ use cortex_m_rt::entry;
 use defmt::info;
 use ucosiii::task::OsTcb;
 use ucosiii::time::os_time_dly;
-use ucosiii::types::OsStkElement;
+use ucosiii::types::{OsStkElement, Timeout};
 use ucosiii::sem::Semaphore;
 use ucosiii::os_task_create;
 
@@ -27,7 +27,7 @@ static mut CONSUMER_TCB: OsTcb = OsTcb::new();
 fn producer_task(_arg: *mut ()) -> ! {
     loop {
         let n = PRODUCED.fetch_add(1, Ordering::Relaxed) + 1;
-        let _ = SEM.signal(0);
+        let _ = SEM.signal();
         info!("[P] produced #{}", n);
         let _ = os_time_dly(200);
     }
@@ -35,7 +35,7 @@ fn producer_task(_arg: *mut ()) -> ! {
 
 fn consumer_task(_arg: *mut ()) -> ! {
     loop {
-        let _ = SEM.wait(0, 0);
+        let _ = SEM.wait(Timeout::Forever);
         let n = CONSUMED.fetch_add(1, Ordering::Relaxed) + 1;
         info!("[C] consumed #{}", n);
         for _ in 0..10_000 { cortex_m::asm::nop(); }
@@ -0,0 +1,121 @@
+//! Stack Alignment Example - tasks start with an AAPCS-aligned stack
+//!
+//! Each task checks its SP and does 64-bit arithmetic on entry, before
+//! anything else can realign the stack. `ldrd`/`strd` on a misaligned
+//! stack and varargs formatting of `u64` are where a bad initial frame
+//! shows up. Run it after touching the port's stack frame or PendSV.
+//!
+//! The typed `Stack` task loses no words to the alignment; its free count
+//! from `os_task_stk_chk` is reported once it is running.
+
+#![no_std]
+#![no_main]
+
+use core::hint::black_box;
+
+use cortex_m_rt::entry;
+use ucosiii::task::{os_task_stk_chk, OsTcb, Stack};
+use ucosiii::time::os_time_dly;
+use ucosiii::types::OsStkElement;
+use ucosiii::{os_task_create, os_task_create_stk};
+
+// ============ Task Storage ============
+
+static mut WIDE_STK: [OsStkElement; 256] = [0; 256];
+static mut WIDE_TCB: OsTcb = OsTcb::new();
+// Odd size: the stack top is not 8-byte aligned
+static mut ODD_STK: [OsStkElement; 255] = [0; 255];
+static mut ODD_TCB: OsTcb = OsTcb::new();
+// Aligned base and top by construction
+static mut TYPED_STK: Stack<256> = Stack::new();
+static mut TYPED_TCB: OsTcb = OsTcb::new();
+
+// ============ Tasks ============
+
+fn sp() -> usize {
+    let sp: usize;
+    unsafe { core::arch::asm!("mov {}, sp", out(reg) sp) };
+    sp
+}
+
+/// Check the entry SP and exercise 64-bit loads and stores
+#[cfg_attr(not(feature = "defmt"), allow(unused_variables))]
+fn check_entry(name: &str, seed: u64) -> u64 {
+    let entry_sp = sp();
+    let mut acc: [u64; 4] = black_box([seed, seed << 17, !seed, 0x0123_4567_89ab_cdef]);
+    for (i, v) in acc.iter_mut().enumerate() {
+        *v = v.wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(i as u32 + 1);
+    }
+    let sum = black_box(acc).iter().fold(0u64, |s, &v| s ^ v);
+
+    if entry_sp % 8 != 0 {
+        ucosiii::error!("{} entered with misaligned SP {=usize:x}", name, entry_sp);
+    } else {
+        ucosiii::info!("{} SP {=usize:x} aligned, sum {=u64:x}", name, entry_sp, sum);
+    }
+    sum
+}
+
+fn wide_task(_: *mut ()) -> ! {
+    let mut sum = check_entry("wide", 0xdead_beef_0000_0001);
+    loop {
+        sum = black_box(sum.wrapping_add(sum >> 3));
+        let _ = os_time_dly(1000);
+    }
+}
+
+fn odd_task(_: *mut ()) -> ! {
+    let mut sum = check_entry("odd", 0x0000_0001_cafe_f00d);
+    loop {
+        sum = black_box(sum.wrapping_add(sum >> 5));
+        let _ = os_time_dly(1000);
+    }
+}
+
+#[cfg_attr(not(feature = "defmt"), allow(unused_variables))]
+fn typed_task(_: *mut ()) -> ! {
+    let mut sum = check_entry("typed", 0x0000_0002_0bad_c0de);
+    if let Ok((free, used)) = os_task_stk_chk(None) {
+        ucosiii::info!("typed stack: {} words free, {} used", free, used);
+    }
+    loop {
+        sum = black_box(sum.wrapping_add(sum >> 7));
+        let _ = os_time_dly(1000);
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    ucosiii::os_init().expect("OS init failed");
+
+    os_task_create(
+        unsafe { &mut *core::ptr::addr_of_mut!(WIDE_TCB) },
+        unsafe { &mut *core::ptr::addr_of_mut!(WIDE_STK) },
+        "Wide",
+        wide_task,
+        5,
+    ).expect("Wide task failed");
+
+    os_task_create(
+        unsafe { &mut *core::ptr::addr_of_mut!(ODD_TCB) },
+        unsafe { &mut *core::ptr::addr_of_mut!(ODD_STK) },
+        "Odd",
+        odd_task,
+        6,
+    ).expect("Odd task failed");
+
+    os_task_create_stk(
+        unsafe { &mut *core::ptr::addr_of_mut!(TYPED_TCB) },
+        unsafe { &mut *core::ptr::addr_of_mut!(TYPED_STK) },
+        "Typed",
+        typed_task,
+        7,
+    ).expect("Typed task failed");
+
+    ucosiii::info!("Starting RTOS");
+    ucosiii::os_start().expect("OS start failed");
+
+    loop { cortex_m::asm::nop(); }
+}
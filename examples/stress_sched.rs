@@ -0,0 +1,144 @@
+//! Scheduler Stress - priority ordering under randomized load
+//!
+//! Eight workers at four priorities (two per level) sleep and spin for
+//! pseudo-random lengths. Every time a worker runs it checks that no
+//! higher priority task is ready, which preemptive scheduling guarantees;
+//! a violation is logged at once and fails the run. A supervisor at the
+//! top priority checks that every worker keeps making progress and ends
+//! with a `PASS` or `FAIL` line for a hardware-in-loop rig.
+
+#![no_std]
+#![no_main]
+#![allow(static_mut_refs)]
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use cortex_m_rt::entry;
+use ucosiii::kernel::current_prio;
+use ucosiii::sched::os_highest_ready_prio;
+use ucosiii::task::{OsTaskFn, OsTcb};
+use ucosiii::time::os_time_dly;
+use ucosiii::types::{OsPrio, OsStkElement};
+use ucosiii::os_task_create;
+
+const WORKERS: usize = 8;
+
+/// Priority of the first level; worker `i` runs at `BASE_PRIO + i / 2`
+const BASE_PRIO: OsPrio = 4;
+
+/// Supervisor checks, one per second
+const CHECKS: u32 = 10;
+
+// ============ Shared Counters ============
+
+/// Passes of each worker
+static RUNS: [AtomicU32; WORKERS] = [const { AtomicU32::new(0) }; WORKERS];
+
+/// Times a worker ran while a higher priority task was ready
+static VIOLATIONS: AtomicU32 = AtomicU32::new(0);
+
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+// ============ Task Storage ============
+
+static mut WORKER_STK: [[OsStkElement; 256]; WORKERS] = [[0; 256]; WORKERS];
+static mut WORKER_TCB: [OsTcb; WORKERS] = [const { OsTcb::new() }; WORKERS];
+static mut SUPER_STK: [OsStkElement; 512] = [0; 512];
+static mut SUPER_TCB: OsTcb = OsTcb::new();
+
+// ============ Tasks ============
+
+/// One entry point per worker, which tells it its index
+const ENTRIES: [OsTaskFn; WORKERS] = [
+    worker_task::<0>, worker_task::<1>, worker_task::<2>, worker_task::<3>,
+    worker_task::<4>, worker_task::<5>, worker_task::<6>, worker_task::<7>,
+];
+
+/// xorshift32, enough to vary the load
+fn next(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+/// No task above the caller's priority may be ready while it runs
+fn check_order(worker: usize) {
+    let mine = current_prio();
+    let highest = os_highest_ready_prio();
+    if highest < mine {
+        VIOLATIONS.fetch_add(1, Ordering::Relaxed);
+        ucosiii::error!("worker {} at prio {} ran while prio {} was ready", worker, mine, highest);
+    }
+}
+
+fn worker_task<const W: usize>(_: *mut ()) -> ! {
+    let worker = W;
+    let mut seed = 0x9E37_79B9 ^ (worker as u32 + 1);
+
+    loop {
+        let _ = os_time_dly(1 + next(&mut seed) % 8);
+        check_order(worker);
+
+        for _ in 0..next(&mut seed) % 2_000 {
+            cortex_m::asm::nop();
+        }
+        check_order(worker);
+
+        RUNS[worker].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn supervisor_task(_: *mut ()) -> ! {
+    let mut last = [0u32; WORKERS];
+
+    for check in 0..CHECKS {
+        let _ = os_time_dly(1000);
+
+        for (worker, runs) in RUNS.iter().enumerate() {
+            let runs = runs.load(Ordering::Relaxed);
+            if runs == last[worker] {
+                ucosiii::error!("worker {} made no progress in check {}", worker, check);
+                FAILED.store(true, Ordering::Relaxed);
+            }
+            last[worker] = runs;
+        }
+    }
+
+    let violations = VIOLATIONS.load(Ordering::Relaxed);
+    if violations == 0 && !FAILED.load(Ordering::Relaxed) {
+        ucosiii::info!("PASS");
+    } else {
+        ucosiii::error!("FAIL: {} ordering violations", violations);
+    }
+
+    loop {
+        let _ = os_time_dly(1000);
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    ucosiii::os_init().expect("OS init failed");
+
+    os_task_create(
+        unsafe { &mut SUPER_TCB },
+        unsafe { &mut SUPER_STK },
+        "Supervisor",
+        supervisor_task,
+        BASE_PRIO - 1,
+    ).expect("Supervisor task failed");
+
+    let tcbs = unsafe { WORKER_TCB.iter_mut() };
+    let stks = unsafe { WORKER_STK.iter_mut() };
+    for (worker, (tcb, stk)) in tcbs.zip(stks).enumerate() {
+        os_task_create(tcb, stk, "Worker", ENTRIES[worker], BASE_PRIO + (worker / 2) as OsPrio)
+            .expect("Worker task failed");
+    }
+
+    ucosiii::os_start().expect("OS start failed");
+
+    loop { cortex_m::asm::nop(); }
+}
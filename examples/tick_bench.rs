@@ -0,0 +1,89 @@
+//! Tick Queue Benchmark - cycle cost of the two delay backends
+//!
+//! Runs `TickWheel` and `TickList` side by side with 4 and 64 delayed
+//! tasks, measured with the DWT cycle counter: inserting every task with a
+//! spread of delays, then one tick with nothing due and one tick expiring
+//! a task. The build's `tick-list`/`tick-wheel` feature only picks which
+//! one the kernel uses; both are measured here.
+
+#![no_std]
+#![no_main]
+
+use core::hint::black_box;
+use core::ptr::NonNull;
+
+use cortex_m::peripheral::{DWT, Peripherals};
+use cortex_m_rt::entry;
+use ucosiii::kernel::{TickList, TickWheel};
+use ucosiii::task::OsTcb;
+use ucosiii::types::OsTick;
+
+const MAX_TASKS: usize = 64;
+
+static mut TCBS: [OsTcb; MAX_TASKS] = [const { OsTcb::new() }; MAX_TASKS];
+
+/// Cycles for insert, an idle tick and an expiring tick
+struct Costs {
+    insert: u32,
+    idle_tick: u32,
+    expire_tick: u32,
+}
+
+/// Delay of task `i`: spread over a few wheel rotations, only task 0 at 2
+fn delay(i: usize) -> OsTick {
+    (i as OsTick * 7) % 100 + 2
+}
+
+macro_rules! measure {
+    ($queue:ty, $tasks:expr) => {{
+        let tcbs = unsafe { &mut *core::ptr::addr_of_mut!(TCBS) };
+        let mut q = <$queue>::new();
+
+        let start = DWT::cycle_count();
+        for (i, tcb) in tcbs[..$tasks].iter_mut().enumerate() {
+            unsafe { q.insert(NonNull::from(tcb), delay(i)) };
+        }
+        let insert = DWT::cycle_count().wrapping_sub(start) / $tasks as u32;
+
+        // Tick 1: nothing is due
+        let start = DWT::cycle_count();
+        black_box(q.pop_expired(1));
+        let idle_tick = DWT::cycle_count().wrapping_sub(start);
+
+        // Tick 2: task 0 is due
+        let start = DWT::cycle_count();
+        while black_box(q.pop_expired(2)).is_some() {}
+        let expire_tick = DWT::cycle_count().wrapping_sub(start);
+
+        // Only task 0 expired; unlink the rest for the next run
+        for tcb in tcbs[1..$tasks].iter_mut() {
+            unsafe { q.remove(NonNull::from(tcb)) };
+        }
+
+        Costs { insert, idle_tick, expire_tick }
+    }};
+}
+
+#[cfg_attr(not(feature = "defmt"), allow(unused_variables))]
+fn report(name: &str, tasks: usize, c: Costs) {
+    ucosiii::info!(
+        "{} x{}: insert {} cycles, idle tick {}, expiring tick {}",
+        name, tasks, c.insert, c.idle_tick, c.expire_tick
+    );
+    // Keep the results observable when logging is compiled out
+    black_box((c.insert, c.idle_tick, c.expire_tick));
+}
+
+#[entry]
+fn main() -> ! {
+    let mut cp = Peripherals::take().unwrap();
+    cp.DCB.enable_trace();
+    cp.DWT.enable_cycle_counter();
+
+    report("wheel", 4, measure!(TickWheel, 4));
+    report("list", 4, measure!(TickList, 4));
+    report("wheel", 64, measure!(TickWheel, 64));
+    report("list", 64, measure!(TickList, 64));
+
+    loop { cortex_m::asm::nop(); }
+}
@@ -0,0 +1,162 @@
+//! Timeout Matrix - pends with timeouts racing posts
+//!
+//! The driver pends on a semaphore with a timeout while a lower priority
+//! helper posts it after a set delay. For each pair of post delay and
+//! timeout in `CASES` the outcome is checked: a post before the timeout
+//! wakes the driver with `Ok` when it lands, a later one times the pend
+//! out and stays in the count, and when both fall on the same tick either
+//! is fine but the post must be seen exactly once. The non-blocking and
+//! task semaphore timeouts are checked as well. Ends with a `PASS` or
+//! `FAIL` line for a hardware-in-loop rig.
+
+#![no_std]
+#![no_main]
+#![allow(static_mut_refs)]
+
+use core::ptr::{addr_of_mut, NonNull};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use cortex_m_rt::entry;
+use ucosiii::sem::Semaphore;
+use ucosiii::task::{os_task_sem_pend, os_task_sem_post, OsTcb};
+use ucosiii::time::{os_time_dly, os_time_get};
+use ucosiii::types::{opt, OsStkElement, OsTick, Timeout};
+use ucosiii::{os_task_create, OsError};
+
+/// Pairs of (post after, pend timeout) in ticks
+const CASES: [(OsTick, OsTick); 8] = [
+    (1, 10),
+    (3, 10),
+    (9, 10),
+    (10, 10),
+    (11, 10),
+    (20, 10),
+    (2, 3),
+    (5, 3),
+];
+
+/// Ticks an outcome may land away from its expected time: the pend starts
+/// part way through a tick
+const SLACK: OsTick = 1;
+
+static SEM: Semaphore = Semaphore::new(0);
+
+/// Ticks the helper waits before posting
+static DELAY: AtomicU32 = AtomicU32::new(0);
+
+// ============ Task Storage ============
+
+static mut DRIVER_STK: [OsStkElement; 512] = [0; 512];
+static mut DRIVER_TCB: OsTcb = OsTcb::new();
+static mut HELPER_STK: [OsStkElement; 256] = [0; 256];
+static mut HELPER_TCB: OsTcb = OsTcb::new();
+
+fn near(elapsed: OsTick, expected: OsTick) -> bool {
+    elapsed.abs_diff(expected) <= SLACK
+}
+
+// ============ Tasks ============
+
+fn helper_task(_: *mut ()) -> ! {
+    loop {
+        let _ = os_task_sem_pend(0, opt::PEND_BLOCKING);
+        let _ = os_time_dly(DELAY.load(Ordering::Relaxed));
+        let _ = SEM.signal();
+    }
+}
+
+/// Run one case; returns whether its outcome was allowed
+fn run_case(helper: NonNull<OsTcb>, post_at: OsTick, timeout: OsTick) -> bool {
+    DELAY.store(post_at, Ordering::Relaxed);
+    let start = os_time_get();
+    let _ = os_task_sem_post(helper, opt::POST_FIFO);
+
+    let result = SEM.wait(Timeout::Ticks(timeout));
+    let elapsed = os_time_get().wrapping_sub(start);
+
+    // Let a late post land, then collect it
+    let _ = os_time_dly(post_at + 2 * SLACK);
+    let leftover = SEM.accept().unwrap_or(0);
+
+    let ok = match result {
+        Ok(_) => post_at <= timeout && near(elapsed, post_at) && leftover == 0,
+        Err(OsError::Timeout) => post_at >= timeout && near(elapsed, timeout) && leftover == 1,
+        Err(_) => false,
+    };
+    if !ok {
+        ucosiii::error!(
+            "post at {} timeout {}: {} after {} ticks, {} left",
+            post_at,
+            timeout,
+            result.is_ok(),
+            elapsed,
+            leftover
+        );
+    }
+    ok
+}
+
+fn driver_task(_: *mut ()) -> ! {
+    let helper = NonNull::new(unsafe { addr_of_mut!(HELPER_TCB) }).unwrap();
+    let mut failures = 0u32;
+
+    for &(post_at, timeout) in CASES.iter() {
+        if !run_case(helper, post_at, timeout) {
+            failures += 1;
+        }
+    }
+
+    // No count and no wait
+    if SEM.wait(Timeout::NoWait) != Err(OsError::PendWouldBlock) {
+        ucosiii::error!("non-blocking pend did not fail with PendWouldBlock");
+        failures += 1;
+    }
+
+    // Nobody posts the driver's own task semaphore
+    let start = os_time_get();
+    let result = os_task_sem_pend(5, opt::PEND_BLOCKING);
+    let elapsed = os_time_get().wrapping_sub(start);
+    if result != Err(OsError::Timeout) || !near(elapsed, 5) {
+        ucosiii::error!("task semaphore pend: timed out {} after {} ticks", result.is_err(), elapsed);
+        failures += 1;
+    }
+
+    if failures == 0 {
+        ucosiii::info!("PASS");
+    } else {
+        ucosiii::error!("FAIL: {} checks failed", failures);
+    }
+
+    loop {
+        let _ = os_time_dly(1000);
+    }
+}
+
+// ============ Main ============
+
+#[entry]
+fn main() -> ! {
+    ucosiii::os_init().expect("OS init failed");
+
+    SEM.create(0, "Matrix").expect("Semaphore creation failed");
+
+    os_task_create(
+        unsafe { &mut DRIVER_TCB },
+        unsafe { &mut DRIVER_STK },
+        "Driver",
+        driver_task,
+        4,
+    ).expect("Driver task failed");
+
+    os_task_create(
+        unsafe { &mut HELPER_TCB },
+        unsafe { &mut HELPER_STK },
+        "Helper",
+        helper_task,
+        5,
+    ).expect("Helper task failed");
+
+    ucosiii::os_start().expect("OS start failed");
+
+    loop { cortex_m::asm::nop(); }
+}
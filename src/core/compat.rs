@@ -0,0 +1,301 @@
+//! μC/OS-III C API names (`os3-compat` feature)
+//!
+//! Thin wrappers with the names and argument order of the C services, for
+//! porting existing μC/OS-III code line by line. Each keeps the trailing
+//! `OS_ERR *p_err` out-parameter as `&mut OsError`, set to `OsError::None`
+//! on success; `OsError` carries the documented C error values, so
+//! `err as u16` compares equal to `OS_ERR_*` codes. New code should call
+//! the Rust services directly.
+//!
+//! Kernel objects are passed as `&mut`, tasks as `*mut OsTcb` where a null
+//! pointer means the calling task, as in C. Arguments this kernel has no use
+//! for (`stk_limit`, `q_size`, `p_ext`) are accepted and ignored. `p_ts`
+//! receives the `os_ts_get` timestamp taken when the pend returns.
+//!
+//! | C service | Rust equivalent |
+//! |---|---|
+//! | `OSInit` | [`os_init`](crate::kernel::os_init) |
+//! | `OSStart` | [`os_start`](crate::kernel::os_start) |
+//! | `OSIntEnter` / `OSIntExit` | [`os_int_enter`](crate::kernel::os_int_enter) / [`os_int_exit`](crate::kernel::os_int_exit) |
+//! | `OSSched` | [`os_sched`](crate::sched::os_sched) |
+//! | `OSSchedLock` / `OSSchedUnlock` | [`os_sched_lock`](crate::kernel::os_sched_lock) / [`os_sched_unlock`](crate::kernel::os_sched_unlock) |
+//! | `OSSafetyCriticalStart` | [`os_safety_critical_start`](crate::kernel::os_safety_critical_start) |
+//! | `OSTaskCreate` | [`os_task_create`](crate::task::os_task_create) |
+//! | `OSTaskDel` | [`os_task_del`](crate::task::os_task_del) |
+//! | `OSTaskSuspend` / `OSTaskResume` | [`os_task_suspend`](crate::task::os_task_suspend) / [`os_task_resume`](crate::task::os_task_resume) |
+//! | `OSTaskTimeQuantaSet` | [`os_task_time_quanta_set`](crate::task::os_task_time_quanta_set) |
+//! | `OSTaskSemPend` / `OSTaskSemPost` | [`os_task_sem_pend`](crate::task::os_task_sem_pend) / [`os_task_sem_post`](crate::task::os_task_sem_post) |
+//! | `OSTimeDly` | [`os_time_dly`](crate::time::os_time_dly), relative delays only |
+//! | `OSTimeDlyHMSM` | [`os_time_dly_hmsm`](crate::time::os_time_dly_hmsm) |
+//! | `OSTimeDlyResume` | [`os_time_dly_resume`](crate::time::os_time_dly_resume) |
+//! | `OSTimeGet` | [`os_time_get`](crate::time::os_time_get) |
+//! | `OSSemCreate` / `OSSemPend` / `OSSemPost` / `OSSemSet` | [`OsSem`](crate::sem::OsSem) `create` / `pend` / `post` / `set` |
+//! | `OSMutexCreate` / `OSMutexPend` / `OSMutexPost` | [`OsMutex`](crate::mutex::OsMutex) `create` / `pend` / `post` |
+//!
+//! ```ignore
+//! use ucosiii::compat::*;
+//!
+//! let mut err = OS_ERR_NONE;
+//! let ctr = OSSemPend(unsafe { &mut *addr_of_mut!(APP_SEM) }, 100, OS_OPT_PEND_BLOCKING, null_mut(), &mut err);
+//! if err != OS_ERR_NONE { /* ... */ }
+//! ```
+
+#![allow(non_snake_case, non_camel_case_types)]
+
+use core::ptr::NonNull;
+
+use crate::error::{OsError, OsResult};
+use crate::task::{OsTaskFn, OsTcb};
+use crate::types::{opt, OsOpt, OsPrio, OsSemCtr, OsStkElement, OsTick};
+
+// ============ Types ============
+
+pub type OS_ERR = OsError;
+pub type OS_TCB = OsTcb;
+pub type OS_TASK_PTR = OsTaskFn;
+pub type OS_PRIO = OsPrio;
+pub type OS_TICK = OsTick;
+pub type OS_OPT = OsOpt;
+pub type OS_SEM_CTR = OsSemCtr;
+pub type CPU_STK = OsStkElement;
+pub type CPU_STK_SIZE = usize;
+pub type CPU_TS = u32;
+#[cfg(feature = "sem")]
+pub type OS_SEM = crate::sem::OsSem;
+#[cfg(feature = "mutex")]
+pub type OS_MUTEX = crate::mutex::OsMutex;
+
+// ============ Constants ============
+
+pub const OS_ERR_NONE: OsError = OsError::None;
+
+pub const OS_OPT_NONE: OsOpt = opt::NONE;
+pub const OS_OPT_PEND_BLOCKING: OsOpt = opt::PEND_BLOCKING;
+pub const OS_OPT_PEND_NON_BLOCKING: OsOpt = opt::PEND_NON_BLOCKING;
+pub const OS_OPT_POST_FIFO: OsOpt = opt::POST_FIFO;
+pub const OS_OPT_POST_LIFO: OsOpt = opt::POST_LIFO;
+pub const OS_OPT_POST_ALL: OsOpt = opt::POST_ALL;
+pub const OS_OPT_POST_NO_SCHED: OsOpt = opt::POST_NO_SCHED;
+pub const OS_OPT_POST_1: OsOpt = opt::POST_FIFO;
+pub const OS_OPT_TIME_DLY: OsOpt = 0x0000;
+pub const OS_OPT_TIME_HMSM_STRICT: OsOpt = opt::TIME_HMSM_STRICT;
+pub const OS_OPT_TIME_HMSM_NON_STRICT: OsOpt = opt::TIME_HMSM_NON_STRICT;
+pub const OS_OPT_TASK_NONE: OsOpt = opt::TASK_NONE;
+pub const OS_OPT_TASK_STK_CHK: OsOpt = opt::TASK_STK_CHK;
+pub const OS_OPT_TASK_STK_CLR: OsOpt = opt::TASK_STK_CLR;
+pub const OS_OPT_TASK_SAVE_FP: OsOpt = opt::TASK_SAVE_FP;
+
+// ============ Helpers ============
+
+/// Store the error of `r` in `p_err`, returning the value or its default
+#[inline]
+fn out<T: Default>(r: OsResult<T>, p_err: &mut OsError) -> T {
+    match r {
+        Ok(v) => {
+            *p_err = OsError::None;
+            v
+        }
+        Err(e) => {
+            *p_err = e;
+            T::default()
+        }
+    }
+}
+
+/// Write the return timestamp if the caller asked for it
+#[inline]
+unsafe fn ts_out(p_ts: *mut CPU_TS) {
+    if !p_ts.is_null() {
+        unsafe { p_ts.write(crate::time::os_ts_get()) };
+    }
+}
+
+// ============ Kernel ============
+
+pub fn OSInit(p_err: &mut OS_ERR) {
+    out(crate::kernel::os_init(), p_err)
+}
+
+pub fn OSStart(p_err: &mut OS_ERR) {
+    out(crate::kernel::os_start(), p_err)
+}
+
+pub fn OSIntEnter() {
+    crate::kernel::os_int_enter()
+}
+
+pub fn OSIntExit() {
+    crate::kernel::os_int_exit()
+}
+
+pub fn OSSched() {
+    crate::sched::os_sched()
+}
+
+pub fn OSSchedLock(p_err: &mut OS_ERR) {
+    out(crate::kernel::os_sched_lock(), p_err)
+}
+
+pub fn OSSchedUnlock(p_err: &mut OS_ERR) {
+    out(crate::kernel::os_sched_unlock(), p_err)
+}
+
+pub fn OSSafetyCriticalStart() {
+    crate::kernel::os_safety_critical_start()
+}
+
+// ============ Tasks ============
+
+/// `OSTaskCreate`
+///
+/// `stk_limit`, `q_size` and `p_ext` are ignored. A `time_quanta` of 0
+/// takes `OsConfig::time_quanta_default`, as in C.
+///
+/// # Safety
+/// `p_tcb` and the `stk_size` words at `p_stk_base` must be `'static` and
+/// used by no other task.
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn OSTaskCreate(
+    p_tcb: *mut OS_TCB,
+    p_name: &'static str,
+    p_task: OS_TASK_PTR,
+    p_arg: *mut (),
+    prio: OS_PRIO,
+    p_stk_base: *mut CPU_STK,
+    _stk_limit: CPU_STK_SIZE,
+    stk_size: CPU_STK_SIZE,
+    _q_size: u16,
+    time_quanta: OS_TICK,
+    _p_ext: *mut (),
+    opt: OS_OPT,
+    p_err: &mut OS_ERR,
+) {
+    let quanta = if time_quanta == 0 { crate::kernel::cfg().time_quanta_default } else { time_quanta };
+    let r = unsafe {
+        crate::task::os_task_create_raw(p_tcb, p_name, p_task, p_arg, prio, p_stk_base, stk_size, quanta, opt)
+    };
+    out(r, p_err)
+}
+
+pub fn OSTaskDel(p_tcb: *mut OS_TCB, p_err: &mut OS_ERR) {
+    out(crate::task::os_task_del(NonNull::new(p_tcb)), p_err)
+}
+
+pub fn OSTaskSuspend(p_tcb: *mut OS_TCB, p_err: &mut OS_ERR) {
+    out(crate::task::os_task_suspend(NonNull::new(p_tcb)), p_err)
+}
+
+pub fn OSTaskResume(p_tcb: *mut OS_TCB, p_err: &mut OS_ERR) {
+    let r = NonNull::new(p_tcb).ok_or(OsError::TcbInvalid).and_then(crate::task::os_task_resume);
+    out(r, p_err)
+}
+
+pub fn OSTaskTimeQuantaSet(p_tcb: *mut OS_TCB, time_quanta: OS_TICK, p_err: &mut OS_ERR) {
+    out(crate::task::os_task_time_quanta_set(NonNull::new(p_tcb), time_quanta), p_err)
+}
+
+/// # Safety
+/// `p_ts` must be null or valid for a write.
+pub unsafe fn OSTaskSemPend(timeout: OS_TICK, opt: OS_OPT, p_ts: *mut CPU_TS, p_err: &mut OS_ERR) -> OS_SEM_CTR {
+    let ctr = out(crate::task::os_task_sem_pend(timeout, opt), p_err);
+    unsafe { ts_out(p_ts) };
+    ctr
+}
+
+pub fn OSTaskSemPost(p_tcb: *mut OS_TCB, opt: OS_OPT, p_err: &mut OS_ERR) -> OS_SEM_CTR {
+    let r = match NonNull::new(p_tcb) {
+        Some(tcb) => Ok(tcb),
+        None => crate::task::current().map(|t| t.as_ptr()).ok_or(OsError::TcbInvalid),
+    };
+    out(r.and_then(|tcb| crate::task::os_task_sem_post(tcb, opt)), p_err)
+}
+
+// ============ Time ============
+
+/// `OSTimeDly`
+///
+/// Only `OS_OPT_TIME_DLY` (relative) is supported; other options fail with
+/// `OptInvalid`. A delay of 0 fails with `TimeZeroDly`, as in C.
+pub fn OSTimeDly(dly: OS_TICK, opt: OS_OPT, p_err: &mut OS_ERR) {
+    let r = if opt != OS_OPT_TIME_DLY {
+        Err(OsError::OptInvalid)
+    } else if dly == 0 {
+        Err(OsError::TimeZeroDly)
+    } else {
+        crate::time::os_time_dly(dly)
+    };
+    out(r, p_err)
+}
+
+pub fn OSTimeDlyHMSM(hours: u16, minutes: u16, seconds: u16, milli: u32, opt: OS_OPT, p_err: &mut OS_ERR) {
+    out(crate::time::os_time_dly_hmsm(hours, minutes, seconds, milli, opt), p_err)
+}
+
+pub fn OSTimeDlyResume(p_tcb: *mut OS_TCB, p_err: &mut OS_ERR) {
+    let r = NonNull::new(p_tcb).ok_or(OsError::TcbInvalid).and_then(crate::time::os_time_dly_resume);
+    out(r, p_err)
+}
+
+pub fn OSTimeGet(p_err: &mut OS_ERR) -> OS_TICK {
+    *p_err = OsError::None;
+    crate::time::os_time_get()
+}
+
+// ============ Semaphores ============
+
+#[cfg(feature = "sem")]
+pub fn OSSemCreate(p_sem: &mut OS_SEM, p_name: &'static str, cnt: OS_SEM_CTR, p_err: &mut OS_ERR) {
+    out(p_sem.create(cnt, p_name), p_err)
+}
+
+/// # Safety
+/// `p_ts` must be null or valid for a write.
+#[cfg(feature = "sem")]
+pub unsafe fn OSSemPend(
+    p_sem: &mut OS_SEM,
+    timeout: OS_TICK,
+    opt: OS_OPT,
+    p_ts: *mut CPU_TS,
+    p_err: &mut OS_ERR,
+) -> OS_SEM_CTR {
+    let ctr = out(p_sem.pend(timeout, opt), p_err);
+    unsafe { ts_out(p_ts) };
+    ctr
+}
+
+/// `OSSemPost`
+///
+/// Returns the count after the post; a post handed to a waiter leaves it
+/// unchanged.
+#[cfg(feature = "sem")]
+pub fn OSSemPost(p_sem: &mut OS_SEM, opt: OS_OPT, p_err: &mut OS_ERR) -> OS_SEM_CTR {
+    match out(p_sem.post(opt).map(Some), p_err) {
+        Some(res) => res.count(),
+        None => 0,
+    }
+}
+
+#[cfg(feature = "sem")]
+pub fn OSSemSet(p_sem: &mut OS_SEM, cnt: OS_SEM_CTR, p_err: &mut OS_ERR) {
+    out(p_sem.set(cnt), p_err)
+}
+
+// ============ Mutexes ============
+
+#[cfg(feature = "mutex")]
+pub fn OSMutexCreate(p_mutex: &mut OS_MUTEX, p_name: &'static str, p_err: &mut OS_ERR) {
+    out(p_mutex.create(p_name), p_err)
+}
+
+/// # Safety
+/// `p_ts` must be null or valid for a write.
+#[cfg(feature = "mutex")]
+pub unsafe fn OSMutexPend(p_mutex: &mut OS_MUTEX, timeout: OS_TICK, opt: OS_OPT, p_ts: *mut CPU_TS, p_err: &mut OS_ERR) {
+    out(p_mutex.pend(timeout, opt), p_err);
+    unsafe { ts_out(p_ts) };
+}
+
+#[cfg(feature = "mutex")]
+pub fn OSMutexPost(p_mutex: &mut OS_MUTEX, opt: OS_OPT, p_err: &mut OS_ERR) {
+    out(p_mutex.post(opt), p_err)
+}
@@ -1,27 +1,293 @@
 //! Compile-time configuration for μC/OS-III
 //!
 //! These constants control the behavior and resource limits of the RTOS.
+//!
+//! The priority count, tick rate and tick wheel size can be picked with
+//! cargo features without forking the crate; enable at most one feature per
+//! group:
+//!
+//! | Constant | Features | Default |
+//! |---|---|---|
+//! | `CFG_PRIO_MAX` | `prio-8`, `prio-16`, `prio-32`, `prio-64`, `prio-128`, `prio-256` | 64 |
+//! | `CFG_TICK_RATE_HZ` | `tick-100hz`, `tick-250hz`, `tick-500hz`, `tick-1khz` | 1000 |
+//! | `CFG_TICK_WHEEL_SIZE` | `tickwheel-8`, `tickwheel-16`, `tickwheel-32`, `tickwheel-64` | 16 |
+
+/// Number of features enabled in a group
+const fn count(flags: &[bool]) -> usize {
+    let mut n = 0;
+    let mut i = 0;
+    while i < flags.len() {
+        n += flags[i] as usize;
+        i += 1;
+    }
+    n
+}
 
 /// Maximum number of priority levels
-pub const CFG_PRIO_MAX: usize = 64;
+pub const CFG_PRIO_MAX: usize = if cfg!(feature = "prio-8") {
+    8
+} else if cfg!(feature = "prio-16") {
+    16
+} else if cfg!(feature = "prio-32") {
+    32
+} else if cfg!(feature = "prio-128") {
+    128
+} else if cfg!(feature = "prio-256") {
+    256
+} else {
+    64
+};
+
+const _: () = assert!(
+    count(&[
+        cfg!(feature = "prio-8"),
+        cfg!(feature = "prio-16"),
+        cfg!(feature = "prio-32"),
+        cfg!(feature = "prio-64"),
+        cfg!(feature = "prio-128"),
+        cfg!(feature = "prio-256"),
+    ]) <= 1,
+    "enable at most one prio-* feature"
+);
 
 /// System tick rate in Hz
-pub const CFG_TICK_RATE_HZ: u32 = 1000;
+pub const CFG_TICK_RATE_HZ: u32 = if cfg!(feature = "tick-100hz") {
+    100
+} else if cfg!(feature = "tick-250hz") {
+    250
+} else if cfg!(feature = "tick-500hz") {
+    500
+} else {
+    1000
+};
+
+const _: () = assert!(
+    count(&[
+        cfg!(feature = "tick-100hz"),
+        cfg!(feature = "tick-250hz"),
+        cfg!(feature = "tick-500hz"),
+        cfg!(feature = "tick-1khz"),
+    ]) <= 1,
+    "enable at most one tick-* rate feature"
+);
+
+/// Core clock assumed by `os_start` (the STM32 16 MHz HSI reset clock)
+///
+/// Boards that reconfigure the clock tree must call `os_start_with_clock`
+/// with the real core frequency instead.
+pub const CFG_CPU_CLK_HZ: u32 = 16_000_000;
+
+/// Fastest core clock the tick configuration is checked against
+///
+/// SysTick counts at most 2^24 core cycles per tick, so the tick rate must
+/// be at least `CFG_CPU_CLK_MAX_HZ / 2^24`.
+pub const CFG_CPU_CLK_MAX_HZ: u32 = 480_000_000;
 
 /// Default time quanta for round-robin scheduling
 pub const CFG_TIME_QUANTA_DEFAULT: u32 = 10;
 
-/// Minimum task stack size
+/// Largest time quanta accepted by `os_task_time_quanta_set`
+pub const CFG_TIME_QUANTA_MAX: u32 = 10_000;
+
+/// `OsDelay`: spin out the sub-tick remainder instead of sleeping one
+/// extra tick (`embedded-hal` feature)
+pub const CFG_DELAY_SPIN_REMAINDER: bool = true;
+
+/// Pending `embassy-time` wake-ups (`embassy-time-driver` feature)
+pub const CFG_EMBASSY_ALARM_SIZE: usize = 8;
+
+/// Minimum task stack size in words
+///
+/// Must hold the port's context frame (`OS_CTX_FRAME_WORDS`: 17 on
+/// Cortex-M4, 18 on Cortex-M33, plus 18 FP words on hard-float targets;
+/// 32 on RISC-V) and the words lost to alignment
+/// (`OS_STK_ISR_HEADROOM_WORDS`). Anything above that is the task's own.
 pub const CFG_STK_SIZE_MIN: usize = 64;
 
-/// Number of entries in tick wheel
-pub const CFG_TICK_WHEEL_SIZE: usize = 16;
+/// Clear task stacks at creation so `os_task_info` can report usage
+pub const CFG_STK_CHK_EN: bool = true;
+
+/// Check the outgoing task's stack canary on every context switch
+pub const CFG_TASK_STK_CHK_EN: bool = true;
+
+/// Interrupt (MSP) stack size in bytes, multiple of 8
+pub const CFG_ISR_STK_SIZE: usize = 2048;
+
+/// Check the interrupt stack canary on every `os_int_exit`; an overwritten
+/// canary is reported to the `fault` hook once
+pub const CFG_ISR_STK_CHK_EN: bool = true;
 
-/// Maximum message queue size
+/// Number of records in the trace ring buffer (power of two)
+pub const CFG_TRACE_BUF_SIZE: usize = 256;
+
+/// Number of entries in tick wheel (unused with `tick-list`)
+pub const CFG_TICK_WHEEL_SIZE: usize = if cfg!(feature = "tickwheel-8") {
+    8
+} else if cfg!(feature = "tickwheel-32") {
+    32
+} else if cfg!(feature = "tickwheel-64") {
+    64
+} else {
+    16
+};
+
+const _: () = assert!(
+    count(&[
+        cfg!(feature = "tickwheel-8"),
+        cfg!(feature = "tickwheel-16"),
+        cfg!(feature = "tickwheel-32"),
+        cfg!(feature = "tickwheel-64"),
+    ]) <= 1,
+    "enable at most one tickwheel-* feature"
+);
+
+const _: () = assert!(
+    count(&[cfg!(feature = "tick-wheel"), cfg!(feature = "tick-list")]) <= 1,
+    "enable at most one of tick-wheel and tick-list"
+);
+
+const _: () = assert!(
+    !cfg!(feature = "tick-list")
+        || count(&[
+            cfg!(feature = "tickwheel-8"),
+            cfg!(feature = "tickwheel-16"),
+            cfg!(feature = "tickwheel-32"),
+            cfg!(feature = "tickwheel-64"),
+        ]) == 0,
+    "tickwheel-* sizes the tick wheel, which tick-list replaces"
+);
+
+/// Number of messages in the kernel message pool (`kernel::msg`)
 pub const CFG_MSG_POOL_SIZE: usize = 32;
 
+/// Payload bytes held by each message of the pool
+pub const CFG_MSG_PAYLOAD_SIZE: usize = 16;
+
+/// Number of semaphores in the kernel pool (`obj-pool`, `sem::alloc`)
+pub const CFG_SEM_POOL_SIZE: usize = 8;
+
+/// Number of mutexes in the kernel pool (`obj-pool`, `mutex::alloc`)
+pub const CFG_MUTEX_POOL_SIZE: usize = 8;
+
+/// Bytes of the buffer `OS_DUMP_HOOK` formats into (`kernel-awareness`)
+pub const CFG_DUMP_BUF_SIZE: usize = 1024;
+
+/// Messages the log ring holds while deferred out of critical sections
+pub const CFG_LOG_DEFER_SLOTS: usize = 16;
+
+/// Bytes of arguments, entry function included, a deferred message may
+/// carry; larger ones are dropped (multiple of 8)
+pub const CFG_LOG_DEFER_ARG_BYTES: usize = 32;
+
+/// Number of entries in the kernel object registry
+pub const CFG_OBJ_REGISTRY_SIZE: usize = 32;
+
+/// Number of per-task registers in each TCB
+pub const CFG_TASK_REG_TBL_SIZE: usize = 4;
+
+/// Walk the whole list after every list mutation (`paranoid` feature)
+pub const CFG_PARANOID_WALK_EN: bool = true;
+
+/// Tick task priority (`tick-task` feature), normally the highest
+pub const CFG_TICK_TASK_PRIO: u8 = 0;
+
+/// Tick task stack size in words
+pub const CFG_TICK_TASK_STK_SIZE: usize = 128;
+
+/// Number of tasks the software watchdog can supervise
+pub const CFG_WDOG_TBL_SIZE: usize = 8;
+
+/// Software watchdog supervisor priority
+pub const CFG_WDOG_TASK_PRIO: u8 = 1;
+
+/// Software watchdog supervisor stack size in words
+pub const CFG_WDOG_TASK_STK_SIZE: usize = 128;
+
+/// Ticks between two software watchdog scans
+pub const CFG_WDOG_SCAN_TICKS: u32 = 100;
+
+/// Do not flag suspended tasks as overdue
+pub const CFG_WDOG_IGNORE_SUSPENDED: bool = true;
+
+/// Largest clock rate adjustment in parts per million (`clock` feature)
+pub const CFG_CLOCK_ADJ_MAX_PPM: u32 = 500;
+
+/// Seconds between writes of the clock to the RTC, 0 for none
+pub const CFG_CLOCK_RTC_SYNC_SECS: u32 = 3600;
+
+/// Self-check task priority (`self-check` feature), below the shell
+pub const CFG_SELF_CHECK_TASK_PRIO: u8 = (CFG_PRIO_MAX - 3) as u8;
+
+/// Self-check task stack size in words
+pub const CFG_SELF_CHECK_TASK_STK_SIZE: usize = 128;
+
+/// Ticks between two kernel self-checks
+pub const CFG_SELF_CHECK_PERIOD_TICKS: u32 = 1000;
+
+/// Shell task priority (`shell` feature), just above idle
+pub const CFG_SHELL_TASK_PRIO: u8 = (CFG_PRIO_MAX - 2) as u8;
+
+/// Shell task stack size in words
+pub const CFG_SHELL_TASK_STK_SIZE: usize = 256;
+
+/// Longest command line the shell accepts, in bytes
+pub const CFG_SHELL_LINE_LEN: usize = 64;
+
+/// Number of application commands `shell::register` can add
+pub const CFG_SHELL_CMD_TBL_SIZE: usize = 8;
+
+/// Ticks the shell sleeps when no input is available
+pub const CFG_SHELL_POLL_TICKS: u32 = 10;
+
 /// Enable round-robin scheduling for same-priority tasks
 pub const CFG_SCHED_ROUND_ROBIN_EN: bool = true;
 
 /// Idle task priority
 pub const CFG_PRIO_IDLE: u8 = (CFG_PRIO_MAX - 1) as u8;
+
+/// Idle task stack size in words, unless `OsConfig::idle` provides one
+pub const CFG_IDLE_TASK_STK_SIZE: usize = 128;
+
+/// Priorities application tasks cannot be created at
+pub const CFG_PRIO_RESERVED: &[u8] = &[CFG_PRIO_IDLE];
+
+// ============ Consistency checks ============
+
+/// Build-time checks; a bad value fails the build naming the constant
+mod validate {
+    use super::*;
+    use crate::port::{OS_CTX_FRAME_WORDS, OS_STK_ISR_HEADROOM_WORDS};
+
+    const _: () = assert!(CFG_PRIO_MAX >= 8 && CFG_PRIO_MAX <= 256, "CFG_PRIO_MAX must be 8..=256");
+    const _: () = assert!(CFG_PRIO_IDLE as usize == CFG_PRIO_MAX - 1, "CFG_PRIO_IDLE must be CFG_PRIO_MAX - 1");
+    const _: () = assert!((CFG_WDOG_TASK_PRIO as usize) < CFG_PRIO_MAX - 1, "CFG_WDOG_TASK_PRIO collides with idle");
+    const _: () = assert!((CFG_TICK_TASK_PRIO as usize) < CFG_PRIO_MAX - 1, "CFG_TICK_TASK_PRIO collides with idle");
+    const _: () = assert!((CFG_SHELL_TASK_PRIO as usize) < CFG_PRIO_MAX - 1, "CFG_SHELL_TASK_PRIO collides with idle");
+    const _: () = assert!(
+        (CFG_SELF_CHECK_TASK_PRIO as usize) < CFG_PRIO_MAX - 1,
+        "CFG_SELF_CHECK_TASK_PRIO collides with idle"
+    );
+    const _: () = assert!(
+        CFG_IDLE_TASK_STK_SIZE >= CFG_STK_SIZE_MIN,
+        "CFG_IDLE_TASK_STK_SIZE is smaller than CFG_STK_SIZE_MIN"
+    );
+    const _: () = assert!(
+        CFG_STK_SIZE_MIN >= OS_CTX_FRAME_WORDS + OS_STK_ISR_HEADROOM_WORDS,
+        "CFG_STK_SIZE_MIN is smaller than the port's context frame plus alignment headroom"
+    );
+    const _: () = assert!(
+        CFG_TICK_WHEEL_SIZE.is_power_of_two() && CFG_TICK_WHEEL_SIZE <= 256,
+        "CFG_TICK_WHEEL_SIZE must be a power of two, at least 1 and at most 256"
+    );
+    const _: () = assert!(CFG_TICK_RATE_HZ > 0, "CFG_TICK_RATE_HZ must be nonzero");
+    const _: () = assert!(CFG_CPU_CLK_HZ.is_multiple_of(CFG_TICK_RATE_HZ), "CFG_TICK_RATE_HZ must divide CFG_CPU_CLK_HZ");
+    const _: () = assert!(CFG_CPU_CLK_HZ <= CFG_CPU_CLK_MAX_HZ, "CFG_CPU_CLK_HZ exceeds CFG_CPU_CLK_MAX_HZ");
+    const _: () = assert!(
+        CFG_CPU_CLK_MAX_HZ / CFG_TICK_RATE_HZ <= 1 << 24,
+        "CFG_TICK_RATE_HZ too slow for a 24-bit SysTick at CFG_CPU_CLK_MAX_HZ"
+    );
+    const _: () = assert!(
+        !CFG_SCHED_ROUND_ROBIN_EN || (CFG_TIME_QUANTA_DEFAULT > 0 && CFG_TIME_QUANTA_DEFAULT <= CFG_TIME_QUANTA_MAX),
+        "CFG_TIME_QUANTA_DEFAULT must be 1..=CFG_TIME_QUANTA_MAX with round-robin enabled"
+    );
+}
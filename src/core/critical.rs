@@ -1,18 +1,25 @@
 //! Critical section handling for μC/OS-III
 //!
 //! Provides safe critical section primitives for protecting shared resources.
+//!
+//! Interrupt masking is delegated to the `critical-section` crate, so the
+//! kernel and any other crate using `critical_section::with` go through the
+//! same provider (see `cs_impl` in `lib.rs`). Critical sections nest: only
+//! the outermost guard restores the interrupt state.
+
+use core::sync::atomic::{AtomicU8, Ordering};
 
-use core::sync::atomic::{AtomicBool, Ordering};
+use critical_section::RestoreState;
 
-/// Global flag indicating whether it is in the critical section
-static IN_CRITICAL: AtomicBool = AtomicBool::new(false);
+/// Critical section nesting depth
+static CS_DEPTH: AtomicU8 = AtomicU8::new(0);
 
 /// RAII guard for critical sections
 /// 
 /// When this guard is created, interrupts are disabled.
 /// When it is dropped, interrupts are restored to their previous state.
 pub struct CriticalSection {
-    _private: (),
+    restore: RestoreState,
 }
 
 impl CriticalSection {
@@ -20,28 +27,47 @@ impl CriticalSection {
     /// 
     /// Returns a guard that will restore interrupt state when dropped.
     #[inline(always)]
+    #[cfg_attr(feature = "latency-stats", track_caller)]
     pub fn enter() -> Self {
-        #[cfg(target_arch = "arm")]
-        cortex_m::interrupt::disable();
-        
-        IN_CRITICAL.store(true, Ordering::Release);
-        CriticalSection { _private: () }
+        let restore = unsafe { critical_section::acquire() };
+
+        // Interrupts are masked, a plain load/store cannot race
+        let depth = CS_DEPTH.load(Ordering::Relaxed);
+        CS_DEPTH.store(depth + 1, Ordering::Relaxed);
+
+        #[cfg(feature = "latency-stats")]
+        if depth == 0 {
+            crate::core::kernel::stats::cs_enter(core::panic::Location::caller());
+        }
+
+        CriticalSection { restore }
     }
 
     /// Check if we're currently in a critical section
     #[inline(always)]
     pub fn is_active() -> bool {
-        IN_CRITICAL.load(Ordering::Acquire)
+        CS_DEPTH.load(Ordering::Relaxed) > 0
     }
 }
 
 impl Drop for CriticalSection {
     #[inline(always)]
     fn drop(&mut self) {
-        IN_CRITICAL.store(false, Ordering::Release);
-        
-        #[cfg(target_arch = "arm")]
-        unsafe { cortex_m::interrupt::enable() };
+        let depth = CS_DEPTH.load(Ordering::Relaxed) - 1;
+
+        #[cfg(feature = "latency-stats")]
+        if depth == 0 {
+            crate::core::kernel::stats::cs_exit();
+        }
+
+        CS_DEPTH.store(depth, Ordering::Relaxed);
+
+        unsafe { critical_section::release(self.restore) };
+
+        #[cfg(all(test, not(any(target_arch = "arm", target_arch = "riscv32"))))]
+        if depth == 0 {
+            crate::port::stub::cs_exit();
+        }
     }
 }
 
@@ -50,6 +76,7 @@ impl Drop for CriticalSection {
 /// The closure receives a reference to the critical section guard,
 /// which can be used to access [`CsCell`] protected data.
 #[inline]
+#[cfg_attr(feature = "latency-stats", track_caller)]
 pub fn critical_section<F, R>(f: F) -> R
 where
     F: FnOnce(&CriticalSection) -> R,
@@ -73,8 +100,13 @@ pub fn is_isr_context() -> bool {
         }
         ipsr != 0
     }
+
+    #[cfg(target_arch = "riscv32")]
+    {
+        crate::port::os_cpu_in_trap()
+    }
     
-    #[cfg(not(target_arch = "arm"))]
+    #[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
     {
         false
     }
@@ -6,8 +6,14 @@ use core::cell::UnsafeCell;
 use crate::critical::CriticalSection;
 
 /// A cell that can only be accessed within a critical section.
+///
+/// `#[repr(transparent)]`, so a `static` of it has the layout of `T` and can
+/// be addressed by symbol from assembly.
+#[repr(transparent)]
 pub struct CsCell<T>(UnsafeCell<T>);
 
+// SAFETY: the only safe access needs a `CriticalSection`, which excludes
+// every other task and ISR on the single core.
 unsafe impl<T> Sync for CsCell<T> {}
 
 impl<T> CsCell<T> {
@@ -35,3 +41,29 @@ impl<T> CsCell<T> {
         self.0.get()
     }
 }
+
+/// Static storage accessed only through raw pointers
+///
+/// Stand-in for the unstable `core::cell::SyncUnsafeCell`. It never hands
+/// out references, so task stacks and TCBs owned by the kernel cannot be
+/// aliased by a stray `&mut`.
+#[repr(transparent)]
+pub struct SyncUnsafeCell<T>(UnsafeCell<T>);
+
+// SAFETY: it only hands out raw pointers; dereferencing them is the
+// caller's unsafe contract.
+unsafe impl<T> Sync for SyncUnsafeCell<T> {}
+
+impl<T> SyncUnsafeCell<T> {
+    /// Create a new SyncUnsafeCell
+    #[inline(always)]
+    pub const fn new(value: T) -> Self {
+        Self(UnsafeCell::new(value))
+    }
+
+    /// Get a raw pointer to the inner value
+    #[inline(always)]
+    pub const fn get(&self) -> *mut T {
+        self.0.get()
+    }
+}
@@ -0,0 +1,221 @@
+//! embassy bridge
+//!
+//! With the `embassy-time-driver` feature the kernel tick drives
+//! `embassy-time`: [`OsTimeDriver`] is registered as the embassy time
+//! driver and keeps pending wake-ups in a table of
+//! `CFG_EMBASSY_ALARM_SIZE` entries, checked on every kernel tick (in the
+//! tick interrupt or the tick task).
+//!
+//! [`OsExecutor`] runs an embassy executor inside one μC/OS task. Wakers
+//! post the task semaphore of that task, so `Waker::wake` works from ISRs
+//! and other tasks, and the executor task sleeps in `os_task_sem_pend` while
+//! no future is ready. Other μC/OS tasks preempt it like any other task.
+//!
+//! Resolution is one kernel tick. If the alarm table is full, the waker is
+//! woken at once and the future re-polls until a slot frees up.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Waker;
+
+use embassy_executor::{raw, Spawner};
+use embassy_time_driver::{Driver, TICK_HZ};
+
+use crate::config::CFG_EMBASSY_ALARM_SIZE;
+use crate::core::cs_cell::CsCell;
+use crate::critical::critical_section;
+use crate::kernel;
+use crate::task::{os_task_sem_pend, os_task_sem_post, OsTcb};
+use crate::types::opt;
+
+// ============ Time driver ============
+
+struct Alarms {
+    /// Kernel ticks since `os_init`, without wrap
+    ticks: u64,
+    entries: [Option<(u64, Waker)>; CFG_EMBASSY_ALARM_SIZE],
+}
+
+static ALARMS: CsCell<Alarms> = CsCell::new(Alarms {
+    ticks: 0,
+    entries: [const { None }; CFG_EMBASSY_ALARM_SIZE],
+});
+
+/// embassy time driver backed by the kernel tick
+pub struct OsTimeDriver;
+
+embassy_time_driver::time_driver_impl!(static DRIVER: OsTimeDriver = OsTimeDriver);
+
+/// Kernel ticks to embassy ticks
+fn to_embassy(ticks: u64) -> u64 {
+    let rate = kernel::cfg().tick_rate_hz as u64;
+    if TICK_HZ == rate {
+        ticks
+    } else {
+        (ticks as u128 * TICK_HZ as u128 / rate as u128) as u64
+    }
+}
+
+impl Driver for OsTimeDriver {
+    fn now(&self) -> u64 {
+        to_embassy(critical_section(|cs| ALARMS.get(cs).ticks))
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &Waker) {
+        let fire_now = critical_section(|cs| {
+            let alarms = ALARMS.get(cs);
+            if at <= to_embassy(alarms.ticks) {
+                return true;
+            }
+
+            // One entry per waker, keeping the earliest deadline
+            if let Some((t, _)) = alarms.entries.iter_mut().flatten().find(|(_, w)| w.will_wake(waker)) {
+                *t = (*t).min(at);
+                return false;
+            }
+
+            match alarms.entries.iter_mut().find(|e| e.is_none()) {
+                Some(slot) => {
+                    *slot = Some((at, waker.clone()));
+                    false
+                }
+                None => true,
+            }
+        });
+
+        if fire_now {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+/// Advance the embassy clock by one kernel tick and fire due alarms
+pub(crate) fn on_tick() {
+    let now = critical_section(|cs| {
+        let alarms = ALARMS.get(cs);
+        alarms.ticks += 1;
+        to_embassy(alarms.ticks)
+    });
+
+    // Wake outside the critical section, one entry at a time
+    while let Some(waker) = critical_section(|cs| {
+        ALARMS
+            .get(cs)
+            .entries
+            .iter_mut()
+            .find(|e| e.as_ref().is_some_and(|(at, _)| *at <= now))
+            .and_then(Option::take)
+            .map(|(_, w)| w)
+    }) {
+        waker.wake();
+    }
+}
+
+pub(crate) fn reset() {
+    critical_section(|cs| {
+        let alarms = ALARMS.get(cs);
+        alarms.ticks = 0;
+        alarms.entries = [const { None }; CFG_EMBASSY_ALARM_SIZE];
+    });
+}
+
+// ============ Executor ============
+
+/// embassy executor hosted by a μC/OS task
+///
+/// ```ignore
+/// static EXECUTOR: OsExecutor = OsExecutor::new();
+///
+/// fn async_task(_: *mut ()) -> ! {
+///     EXECUTOR.run(|spawner| spawner.must_spawn(blinky()))
+/// }
+/// ```
+pub struct OsExecutor {
+    started: AtomicBool,
+    inner: UnsafeCell<MaybeUninit<raw::Executor>>,
+}
+
+// SAFETY: `started` lets exactly one task initialize and then own the
+// executor; nothing else touches `inner`.
+unsafe impl Sync for OsExecutor {}
+
+impl OsExecutor {
+    pub const fn new() -> Self {
+        OsExecutor {
+            started: AtomicBool::new(false),
+            inner: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Run the executor in the calling task, never returns
+    ///
+    /// `init` is called once with a spawner for the initial futures.
+    ///
+    /// # Panics
+    /// If called twice or outside a task.
+    pub fn run(&'static self, init: impl FnOnce(Spawner)) -> ! {
+        assert!(!self.started.swap(true, Ordering::AcqRel), "OsExecutor already running");
+
+        let tcb = unsafe { kernel::tcb_cur_ptr() }.expect("OsExecutor::run outside a task");
+        let exec: &'static raw::Executor = unsafe {
+            (*self.inner.get()).write(raw::Executor::new(tcb.as_ptr().cast()))
+        };
+
+        init(exec.spawner());
+
+        loop {
+            unsafe { exec.poll() };
+            let _ = os_task_sem_pend(0, opt::PEND_BLOCKING);
+        }
+    }
+}
+
+impl Default for OsExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Called by embassy when a task of the executor becomes ready
+#[export_name = "__pender"]
+fn __pender(context: *mut ()) {
+    if let Some(tcb) = NonNull::new(context.cast::<OsTcb>()) {
+        let _ = os_task_sem_post(tcb, opt::NONE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::AtomicU32;
+    use core::task::{RawWaker, RawWakerVTable};
+
+    static WOKEN: AtomicU32 = AtomicU32::new(0);
+
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(
+        |p| RawWaker::new(p, &VTABLE),
+        |_| { WOKEN.fetch_add(1, Ordering::Relaxed); },
+        |_| { WOKEN.fetch_add(1, Ordering::Relaxed); },
+        |_| {},
+    );
+
+    #[test]
+    fn test_alarm_fires_on_tick() {
+        reset();
+        let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) };
+        let at = DRIVER.now() + to_embassy(2);
+
+        DRIVER.schedule_wake(at, &waker);
+        DRIVER.schedule_wake(at + 10, &waker);
+        on_tick();
+        assert_eq!(WOKEN.load(Ordering::Relaxed), 0);
+        on_tick();
+        assert_eq!(WOKEN.load(Ordering::Relaxed), 1);
+
+        // Deadline already passed fires at once
+        DRIVER.schedule_wake(0, &waker);
+        assert_eq!(WOKEN.load(Ordering::Relaxed), 2);
+    }
+}
@@ -19,6 +19,10 @@ pub enum OsError {
     /// Cannot flush from ISR
     FlushIsr = 15104,
 
+    // ============ Barrier errors ============
+    /// Barrier needs at least one task
+    BarrierCountInvalid = 14001,
+
     // ============ Fatal errors ============
     /// Fatal return (task returned unexpectedly)
     FatalReturn = 15001,
@@ -52,6 +56,8 @@ pub enum OsError {
     MutexNesting = 22403,
     /// Mutex nesting overflow
     MutexOvf = 22404,
+    /// Blocking would close a lock-order cycle (`deadlock-detect`)
+    MutexWouldDeadlock = 22405,
 
     // ============ Object errors ============
     /// Object already created
@@ -62,6 +68,28 @@ pub enum OsError {
     ObjPtrNull = 24003,
     /// Wrong object type
     ObjType = 24004,
+    /// Every object of the pool is allocated (`obj-pool`)
+    ObjPoolEmpty = 24005,
+
+    // ============ Safety critical errors ============
+    /// Object creation after `os_safety_critical_start`
+    IllegalCreateRunTime = 24501,
+
+    // ============ Config errors ============
+    /// Tick rate is 0 or above `CFG_TICK_RATE_HZ`
+    CfgTickRateInvalid = 24601,
+    /// Round-robin enabled with a default time quanta of 0
+    CfgTimeQuantaInvalid = 24602,
+    /// Round-robin enabled in cooperative scheduling mode
+    CfgSchedModeInvalid = 24603,
+    /// `OsConfig::idle` stack smaller than `CFG_STK_SIZE_MIN`
+    CfgIdleStkInvalid = 24604,
+    /// Kernel task priority at or below idle, or shared by two kernel tasks
+    CfgPrioInvalid = 24605,
+
+    // ============ Id errors ============
+    /// No more ids available
+    NoMoreIdAvail = 24301,
 
     // ============ Option errors ============
     /// Invalid option specified
@@ -76,6 +104,8 @@ pub enum OsError {
     OsNotInit = 24203,
     /// No application task created
     OsNoAppTask = 24204,
+    /// Application tasks exist before the startup task of `os_run`
+    OsAppTaskExists = 24205,
 
     // ============ Pend errors ============
     /// Pend was aborted
@@ -100,6 +130,8 @@ pub enum OsError {
     PrioExist = 25201,
     /// Invalid priority
     PrioInvalid = 25203,
+    /// Preemption threshold below the task's priority
+    PreemptThresholdInvalid = 25204,
 
     // ============ Queue errors ============
     /// Queue is full
@@ -110,6 +142,20 @@ pub enum OsError {
     QMax = 26003,
     /// Message pool is empty (no free messages)
     MsgPoolEmpty = 26004,
+    /// Payload larger than `CFG_MSG_PAYLOAD_SIZE`
+    MsgSizeInvalid = 26005,
+
+    // ============ Register errors ============
+    /// Invalid task register id
+    RegIdInvalid = 27101,
+
+    // ============ Readers-writer lock errors ============
+    /// Write unlock by a task that does not hold the write lock
+    RwLockNotOwner = 27201,
+    /// Read unlock while no read lock is held
+    RwLockNotLocked = 27202,
+    /// Task already holds the write lock
+    RwLockOwner = 27203,
 
     // ============ Scheduler errors ============
     /// Invalid time slice
@@ -126,6 +172,12 @@ pub enum OsError {
     // ============ Semaphore errors ============
     /// Semaphore overflow
     SemOvf = 28101,
+    /// Semaphore maximum is 0 or below the initial count
+    SemMaxInvalid = 28102,
+
+    // ============ Shell errors ============
+    /// Shell command table is full
+    ShellFull = 28151,
 
     // ============ State errors ============
     /// Invalid state
@@ -174,6 +226,16 @@ pub enum OsError {
     TaskSuspendIdle = 29019,
     /// Cannot resume task from ISR
     TaskResumeIsr = 29020,
+    /// Kernel tasks cannot join a task group
+    TaskGroupKernel = 29021,
+    /// Task is not a member of the group
+    TaskNotInGroup = 29022,
+    /// Task used the FPU but was created without `TASK_SAVE_FP`
+    TaskFpUsageUnsaved = 29023,
+    /// Tasks are waiting on the object
+    TaskWaiting = 29030,
+    /// Deletion of the calling task was requested
+    TaskDelRequested = 29031,
 
     // ============ TCB errors ============
     /// Invalid TCB pointer
@@ -182,8 +244,22 @@ pub enum OsError {
     // ============ Time errors ============
     /// Cannot delay from ISR
     TimeDlyIsr = 29301,
+    /// Hours out of range
+    TimeInvalidHours = 29302,
+    /// Minutes out of range
+    TimeInvalidMinutes = 29303,
+    /// Seconds out of range
+    TimeInvalidSeconds = 29304,
+    /// Milliseconds out of range
+    TimeInvalidMilliseconds = 29305,
+    /// Delay does not fit the tick counter
+    TimeDlyTooLong = 29309,
     /// Zero delay specified
     TimeZeroDly = 29310,
+    /// Tick reload value does not fit the tick timer
+    TickReloadInvalid = 29311,
+    /// Clock rate adjustment beyond `CFG_CLOCK_ADJ_MAX_PPM` (`clock`)
+    ClockAdjInvalid = 29312,
 
     // ============ Timeout ============
     /// Operation timed out
@@ -205,6 +281,12 @@ pub enum OsError {
     /// Timer stopped
     TmrStopped = 29513,
 
+    // ============ Watchdog errors ============
+    /// Watchdog table is full
+    WdogFull = 33001,
+    /// Task is not registered with the watchdog
+    WdogNotRegistered = 33002,
+
     // ============ Yield errors ============
     /// Cannot yield from ISR
     YieldIsr = 34001,
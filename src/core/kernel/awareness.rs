@@ -0,0 +1,83 @@
+//! Debugger kernel awareness
+//!
+//! Exports the `OS_KA_DESC` descriptor so a debugger (J-Link/Ozone RTOS
+//! plugin, SystemView, the GDB script in `tools/`) can walk all tasks while
+//! the target is halted, without knowing the Rust layout of the kernel.
+//!
+//! The descriptor holds addresses of the task registry head, the current TCB
+//! pointer and the tick counter, plus byte offsets of the relevant `OsTcb`
+//! fields. `OS_KA_VERSION` is bumped whenever the layout of the descriptor
+//! or the meaning of an exported field changes.
+//!
+//! Exported TCB fields:
+//! * `name` - `&'static str`, stored as a (pointer, length) pair of words;
+//!   absent with `no-names`, where its offset reads `u16::MAX`
+//! * `prio`, `base_prio` - `u8`
+//! * `task_state` - `u8`, see `OsTaskState` for values
+//! * `stk_ptr`, `stk_base` - word pointers, `stk_size` - words
+//! * `dbg_next_ptr` - next TCB in the registry, 0 at the end
+
+use core::mem::{offset_of, size_of};
+
+use crate::task::OsTcb;
+
+use super::{CPU_STATE, KERNEL, SCHED};
+
+/// Layout version of [`OsKaDesc`]
+pub const OS_KA_VERSION: u32 = 1;
+
+/// Kernel awareness descriptor
+#[repr(C)]
+pub struct OsKaDesc {
+    /// `OS_KA_VERSION`
+    pub version: u32,
+    /// Size of this descriptor in bytes
+    pub desc_size: u16,
+    /// Size of `OsTcb` in bytes
+    pub tcb_size: u16,
+    /// Address of the task registry head (`*const OsTcb`, null if empty)
+    pub task_list: *const (),
+    /// Address of the current TCB pointer
+    pub tcb_cur: *const (),
+    /// Address of the tick counter (`u32`)
+    pub tick_ctr: *const (),
+    /// Offset of `OsTcb::name`, `u16::MAX` with `no-names`
+    pub off_name: u16,
+    /// Offset of `OsTcb::prio`
+    pub off_prio: u16,
+    /// Offset of `OsTcb::base_prio`
+    pub off_base_prio: u16,
+    /// Offset of `OsTcb::task_state`
+    pub off_state: u16,
+    /// Offset of `OsTcb::stk_ptr`
+    pub off_stk_ptr: u16,
+    /// Offset of `OsTcb::stk_base`
+    pub off_stk_base: u16,
+    /// Offset of `OsTcb::stk_size`
+    pub off_stk_size: u16,
+    /// Offset of `OsTcb::dbg_next_ptr`
+    pub off_dbg_next: u16,
+}
+
+// SAFETY: the descriptor is immutable; it only holds addresses.
+unsafe impl Sync for OsKaDesc {}
+
+/// Descriptor read by the debugger
+#[no_mangle]
+#[used]
+pub static OS_KA_DESC: OsKaDesc = OsKaDesc {
+    version: OS_KA_VERSION,
+    desc_size: size_of::<OsKaDesc>() as u16,
+    tcb_size: size_of::<OsTcb>() as u16,
+    task_list: unsafe { &raw const (*SCHED.as_ptr()).task_list } as *const (),
+    tcb_cur: unsafe { &raw const (*CPU_STATE.as_ptr()).tcb_cur } as *const (),
+    tick_ctr: &raw const KERNEL.tick_counter as *const (),
+    off_name: if cfg!(feature = "no-names") { u16::MAX } else { offset_of!(OsTcb, name) as u16 },
+    off_prio: offset_of!(OsTcb, prio) as u16,
+    off_base_prio: offset_of!(OsTcb, base_prio) as u16,
+    off_state: offset_of!(OsTcb, task_state) as u16,
+    off_stk_ptr: offset_of!(OsTcb, stk_ptr) as u16,
+    off_stk_base: offset_of!(OsTcb, stk_base) as u16,
+    off_stk_size: offset_of!(OsTcb, stk_size) as u16,
+    off_dbg_next: offset_of!(OsTcb, dbg_next_ptr) as u16,
+};
@@ -0,0 +1,220 @@
+//! Crash record
+//!
+//! The panic and HardFault handlers fill one [`CrashRecord`] with the task
+//! that was running, the kernel tick and where it died. On Cortex-M the
+//! record lives in a `.uninit` section that cortex-m-rt does not clear, so
+//! it survives a warm reset and can be read back with [`os_last_crash`],
+//! by a bootloader or by a debugger through the `OS_CRASH_RECORD` symbol.
+
+use core::mem::MaybeUninit;
+use core::panic::Location;
+
+use crate::core::cs_cell::SyncUnsafeCell;
+use crate::types::{OsPrio, OsTaskId, OsTaskState};
+
+/// Marker of a valid record
+const CRASH_MAGIC: u32 = 0xC0A5_DEAD;
+
+/// Bytes kept of the task name and of the source file path
+const CRASH_NAME_LEN: usize = 16;
+const CRASH_FILE_LEN: usize = 32;
+
+/// What caused the crash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CrashKind {
+    Panic = 1,
+    HardFault = 2,
+}
+
+/// State captured at a panic or HardFault
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct CrashRecord {
+    magic: u32,
+    magic_inv: u32,
+    /// Panic or HardFault
+    pub kind: CrashKind,
+    /// Kernel tick at the crash
+    pub tick: u32,
+    /// Running task name, truncated and zero padded (empty before `os_start`)
+    pub task_name: [u8; CRASH_NAME_LEN],
+    /// Running task priority
+    pub prio: OsPrio,
+    /// Running task state
+    pub state: OsTaskState,
+    /// Stack pointer at the crash
+    pub sp: u32,
+    /// Lowest address of the task stack
+    pub stk_base: u32,
+    /// Stack watermark address of the task
+    pub stk_limit: u32,
+    /// Tail of the panic source file path, zero padded
+    pub file: [u8; CRASH_FILE_LEN],
+    /// Panic source line, 0 if unknown
+    pub line: u32,
+    /// Stacked r0-r3, r12, lr, pc, xpsr (HardFault only)
+    pub frame: [u32; 8],
+    /// Running task ID, `TASK_ID_NONE` before `os_start` (last, so the
+    /// offsets of the fields above hold for older readers)
+    pub task_id: OsTaskId,
+}
+
+impl CrashRecord {
+    /// Task name as a string
+    pub fn task_name(&self) -> &str {
+        str_from(&self.task_name)
+    }
+
+    /// Source file as a string (path may be cut at the front)
+    pub fn file(&self) -> &str {
+        str_from(&self.file)
+    }
+
+    /// Whether `sp` is below the task's stack
+    pub fn stk_overflow(&self) -> bool {
+        self.stk_base != 0 && self.sp < self.stk_base
+    }
+}
+
+fn str_from(bytes: &[u8]) -> &str {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    // Truncation may have split a UTF-8 sequence
+    match core::str::from_utf8(&bytes[..len]) {
+        Ok(s) => s,
+        Err(e) => unsafe { core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) },
+    }
+}
+
+/// Copy as much of `src` as fits into the zeroed `dst`
+fn copy_into(dst: &mut [u8], src: &[u8]) {
+    let len = src.len().min(dst.len());
+    dst[..len].copy_from_slice(&src[..len]);
+}
+
+/// The crash record
+#[no_mangle]
+#[cfg_attr(target_arch = "arm", link_section = ".uninit.OS_CRASH_RECORD")]
+static OS_CRASH_RECORD: SyncUnsafeCell<MaybeUninit<CrashRecord>> =
+    SyncUnsafeCell::new(MaybeUninit::zeroed());
+
+/// Fill the crash record from the running task
+///
+/// Called from the panic and fault handlers with interrupts masked, so the
+/// kernel state is read without locking.
+#[cfg_attr(not(any(target_arch = "arm", target_arch = "riscv32")), allow(dead_code))]
+pub(crate) fn record(
+    kind: CrashKind,
+    sp: u32,
+    location: Option<&Location<'_>>,
+    frame: Option<&[u32; 8]>,
+) -> &'static CrashRecord {
+    let mut rec = CrashRecord {
+        magic: CRASH_MAGIC,
+        magic_inv: !CRASH_MAGIC,
+        kind,
+        tick: super::KERNEL.tick_get(),
+        task_name: [0; CRASH_NAME_LEN],
+        prio: 0,
+        state: OsTaskState::Ready,
+        sp,
+        stk_base: 0,
+        stk_limit: 0,
+        file: [0; CRASH_FILE_LEN],
+        line: 0,
+        frame: frame.copied().unwrap_or_default(),
+        task_id: crate::task::TASK_ID_NONE,
+    };
+
+    let tcb = unsafe { (*super::CPU_STATE.as_ptr()).tcb_cur };
+    if let Some(tcb) = unsafe { tcb.as_ref() } {
+        copy_into(&mut rec.task_name, tcb.name().as_bytes());
+        rec.task_id = tcb.id;
+        rec.prio = tcb.prio;
+        rec.state = tcb.task_state;
+        rec.stk_base = tcb.stk_base as u32;
+        rec.stk_limit = tcb.stk_limit as u32;
+    }
+
+    if let Some(loc) = location {
+        // Keep the end of the path, it holds the file name
+        let file = loc.file().as_bytes();
+        copy_into(&mut rec.file, &file[file.len().saturating_sub(CRASH_FILE_LEN)..]);
+        rec.line = loc.line();
+    }
+
+    unsafe {
+        OS_CRASH_RECORD.get().write(MaybeUninit::new(rec));
+        (*OS_CRASH_RECORD.get()).assume_init_ref()
+    }
+}
+
+/// Log a crash record in place, whatever the log policy (no-op without
+/// `defmt`)
+#[cfg_attr(not(target_arch = "arm"), allow(dead_code))]
+pub(crate) fn report(rec: &CrashRecord) {
+    crate::__os_log_now!(
+        error,
+        "{} in task '{}' (#{}) prio {} at tick {}",
+        if rec.kind == CrashKind::Panic { "panic" } else { "HardFault" },
+        rec.task_name(),
+        rec.task_id,
+        rec.prio,
+        rec.tick,
+    );
+    crate::__os_log_now!(
+        error,
+        "sp {=u32:#x}, stack {=u32:#x}..limit {=u32:#x}{}",
+        rec.sp,
+        rec.stk_base,
+        rec.stk_limit,
+        if rec.stk_overflow() { " (overflow)" } else { "" },
+    );
+    if rec.kind == CrashKind::HardFault {
+        crate::__os_log_now!(error, "pc {=u32:#x} lr {=u32:#x} xpsr {=u32:#x}", rec.frame[6], rec.frame[5], rec.frame[7]);
+    }
+    let _ = rec;
+}
+
+/// Record left by the last panic or HardFault, possibly before a reset
+pub fn os_last_crash() -> Option<&'static CrashRecord> {
+    let ptr = OS_CRASH_RECORD.get().cast::<u32>();
+    // The section is not initialized at reset; check the markers first
+    let valid = unsafe { ptr.read_volatile() == CRASH_MAGIC && ptr.add(1).read_volatile() == !CRASH_MAGIC };
+    valid.then(|| unsafe { (*OS_CRASH_RECORD.get()).assume_init_ref() })
+}
+
+/// Invalidate the crash record, e.g. after it has been reported
+pub fn os_crash_clear() {
+    unsafe { OS_CRASH_RECORD.get().cast::<u32>().write_volatile(0) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_round_trip() {
+        let frame = [1, 2, 3, 4, 12, 0x0800_0101, 0x0800_0200, 0x0100_0000];
+        let loc = Location::caller();
+        let rec = record(CrashKind::HardFault, 0x2000_0100, Some(loc), Some(&frame));
+        report(rec);
+
+        let last = os_last_crash().unwrap();
+        assert_eq!(last.kind, CrashKind::HardFault);
+        assert_eq!(last.frame, frame);
+        assert_eq!(last.line, loc.line());
+        assert!(loc.file().ends_with(last.file()));
+        assert!(!last.stk_overflow());
+
+        os_crash_clear();
+        assert!(os_last_crash().is_none());
+    }
+
+    #[test]
+    fn test_truncated_name_stays_valid_utf8() {
+        let mut buf = [0u8; 4];
+        copy_into(&mut buf, "abcé".as_bytes());
+        assert_eq!(str_from(&buf), "abc");
+    }
+}
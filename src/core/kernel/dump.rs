@@ -0,0 +1,224 @@
+//! Scheduler state dump
+//!
+//! [`os_dump`] writes a text snapshot of the scheduler for post-mortem
+//! analysis: kernel counters, the running and highest ready task, the ready
+//! bitmap, every non-empty ready list and every task with what it waits for
+//! and the lock it inherited its priority from.
+//!
+//! The whole walk runs inside one critical section so the snapshot is
+//! consistent; the writer must not block or call into the kernel. Nothing is
+//! allocated.
+//!
+//! With `kernel-awareness` the exported `OS_DUMP_HOOK` function pointer lets
+//! a halted debugger produce the same text, e.g. from GDB:
+//!
+//! ```text
+//! (gdb) call OS_DUMP_HOOK()
+//! (gdb) printf "%s", OS_DUMP_BUF
+//! ```
+
+use core::fmt::{self, Write};
+use core::ptr::NonNull;
+
+use crate::config::CFG_PRIO_MAX;
+use crate::critical::critical_section;
+use crate::task::OsTcb;
+use crate::types::{OsPendOn, OsPrio};
+
+use super::{registry, CPU_STATE, KERNEL, SCHED};
+
+/// Write a snapshot of the scheduler state to `out`
+///
+/// Interrupts stay masked for the whole dump, so use a fast writer (a RAM
+/// buffer, not a blocking UART) on a running system.
+pub fn os_dump(out: &mut impl Write) -> fmt::Result {
+    critical_section(|cs| {
+        let sched = SCHED.get(cs);
+        let cpu = CPU_STATE.get(cs);
+        let now = KERNEL.tick_get();
+
+        writeln!(
+            out,
+            "tick {} sched_lock {} int_nesting {}",
+            now,
+            KERNEL.sched_lock_nesting(),
+            KERNEL.int_nesting()
+        )?;
+        writeln!(out, "cur {:p} prio {} ({})", cpu.tcb_cur, cpu.prio_cur, tcb_name(cpu.tcb_cur))?;
+        writeln!(
+            out,
+            "high_rdy {:p} prio {} ({})",
+            cpu.tcb_high_rdy,
+            cpu.prio_high_rdy,
+            tcb_name(cpu.tcb_high_rdy)
+        )?;
+
+        write!(out, "bitmap")?;
+        for word in sched.rdy.prio_tbl().words() {
+            write!(out, " {:08x}", word)?;
+        }
+        writeln!(out)?;
+
+        for prio in 0..CFG_PRIO_MAX {
+            let list = sched.rdy.list(prio as OsPrio);
+            if list.is_empty() {
+                continue;
+            }
+            write!(out, "rdy {:3}:", prio)?;
+            let mut node = list.head();
+            while let Some(tcb) = node {
+                write!(out, " {}", tcb_name(tcb.as_ptr()))?;
+                node = unsafe { tcb.as_ref() }.next_ptr;
+            }
+            writeln!(out)?;
+        }
+
+        let mut node = sched.task_list;
+        while let Some(tcb) = node {
+            let t = unsafe { tcb.as_ref() };
+            write!(
+                out,
+                "task {} prio {}/{} {:?}",
+                tcb_name(tcb.as_ptr()),
+                t.prio(),
+                t.base_prio(),
+                t.state()
+            )?;
+            if let Some(by) = t.boosted_by() {
+                write!(out, " boosted by {}", by)?;
+            }
+            if t.pend_on() != OsPendOn::Nothing {
+                write!(out, " on {:?}", t.pend_on())?;
+                match registry::lookup_cs(cs, t.pend_obj_ptr).map(|e| e.name()) {
+                    Some(name) if !name.is_empty() => write!(out, " {}", name)?,
+                    _ => write!(out, " {:p}", t.pend_obj_ptr)?,
+                }
+            }
+            let left = t.ticks_left(now);
+            if left > 0 {
+                write!(out, " ticks {}", left)?;
+            }
+            writeln!(out)?;
+            node = t.dbg_next_ptr;
+        }
+        Ok(())
+    })
+}
+
+/// Task name, or its address when it has none
+fn tcb_name(tcb: *const OsTcb) -> TcbName {
+    TcbName(NonNull::new(tcb as *mut OsTcb))
+}
+
+struct TcbName(Option<NonNull<OsTcb>>);
+
+impl fmt::Display for TcbName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => f.write_str("-"),
+            Some(tcb) => match unsafe { tcb.as_ref() }.name() {
+                "" => write!(f, "{:p}", tcb),
+                name => f.write_str(name),
+            },
+        }
+    }
+}
+
+/// [`os_dump`] to the `defmt` log, one message per line
+///
+/// Lines longer than 128 bytes are cut. They are written in place, whatever
+/// the [log policy](crate::log::set_policy), as `os_dump` holds a critical
+/// section while it writes.
+#[cfg(feature = "defmt")]
+pub fn os_dump_defmt() {
+    let mut out = DefmtLines { buf: [0; 128], len: 0 };
+    let _ = os_dump(&mut out);
+}
+
+/// Collects a line and logs it at each newline
+#[cfg(feature = "defmt")]
+struct DefmtLines {
+    buf: [u8; 128],
+    len: usize,
+}
+
+#[cfg(feature = "defmt")]
+impl Write for DefmtLines {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &b in s.as_bytes() {
+            if b == b'\n' {
+                // Cut lines may end inside a UTF-8 sequence
+                let line = &self.buf[..self.len];
+                let line = match core::str::from_utf8(line) {
+                    Ok(line) => line,
+                    Err(e) => unsafe { core::str::from_utf8_unchecked(&line[..e.valid_up_to()]) },
+                };
+                crate::__os_log_now!(info, "{=str}", line);
+                self.len = 0;
+            } else if self.len < self.buf.len() {
+                self.buf[self.len] = b;
+                self.len += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kernel-awareness")]
+mod hook {
+    use core::fmt::{self, Write};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::config::CFG_DUMP_BUF_SIZE;
+    use crate::core::cs_cell::SyncUnsafeCell;
+
+    /// Text of the last hook call, NUL-terminated
+    #[no_mangle]
+    #[used]
+    static OS_DUMP_BUF: SyncUnsafeCell<[u8; CFG_DUMP_BUF_SIZE]> = SyncUnsafeCell::new([0; CFG_DUMP_BUF_SIZE]);
+
+    /// Bytes of text in `OS_DUMP_BUF`
+    #[no_mangle]
+    #[used]
+    static OS_DUMP_LEN: AtomicUsize = AtomicUsize::new(0);
+
+    /// Dump entry point for a debugger, kept through LTO
+    ///
+    /// Formats [`os_dump`](super::os_dump) into `OS_DUMP_BUF`, cutting it
+    /// at `CFG_DUMP_BUF_SIZE - 1` bytes, and to `defmt` when enabled.
+    #[no_mangle]
+    #[used]
+    pub static OS_DUMP_HOOK: extern "C" fn() = os_dump_hook;
+
+    extern "C" fn os_dump_hook() {
+        // SAFETY: only this function writes the buffer; a debugger calls it
+        // with the target halted, never concurrently.
+        let buf = unsafe { &mut *OS_DUMP_BUF.get() };
+        let mut out = BufWriter { buf, len: 0 };
+        let _ = super::os_dump(&mut out);
+        let len = out.len;
+        out.buf[len] = 0;
+        OS_DUMP_LEN.store(len, Ordering::Relaxed);
+
+        #[cfg(feature = "defmt")]
+        super::os_dump_defmt();
+    }
+
+    struct BufWriter<'a> {
+        buf: &'a mut [u8; CFG_DUMP_BUF_SIZE],
+        len: usize,
+    }
+
+    impl Write for BufWriter<'_> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let room = CFG_DUMP_BUF_SIZE - 1 - self.len;
+            let n = s.len().min(room);
+            self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+            self.len += n;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "kernel-awareness")]
+pub use hook::OS_DUMP_HOOK;
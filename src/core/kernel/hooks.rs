@@ -0,0 +1,288 @@
+//! Application hooks
+//!
+//! Lets an application attach behavior to kernel events (task creation and
+//! deletion, context switches, the idle loop and the tick) without modifying
+//! the kernel.
+//!
+//! Hooks are called from privileged code, usually with interrupts disabled
+//! and often from exception context (PendSV, SysTick). A hook must be short,
+//! must not block and must not call any kernel service that can pend or
+//! reschedule (`os_time_dly`, `pend`, `os_task_create`, ...).
+//!
+//! The tick hook set with [`os_tick_hook_set`] is kept apart from the set so
+//! a periodic job (sampling an ADC, kicking a watchdog) can run at exactly
+//! the tick rate without a task of its own, under an optional cycle budget.
+
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::core::cs_cell::CsCell;
+use crate::critical::critical_section;
+use crate::error::OsError;
+use crate::task::OsTcb;
+
+/// Hook receiving the TCB of a task being created or deleted
+pub type OsAppHookTcb = fn(NonNull<OsTcb>);
+
+/// Hook receiving the outgoing and incoming TCBs of a context switch
+///
+/// The outgoing TCB is `None` for the very first switch after `os_start`.
+pub type OsAppHookSw = fn(Option<NonNull<OsTcb>>, Option<NonNull<OsTcb>>);
+
+/// Hook without arguments (idle, tick)
+pub type OsAppHookVoid = fn();
+
+/// Hook receiving the error describing a detected fault
+pub type OsAppHookFault = fn(OsError);
+
+/// Hook receiving the task name and the list operation that corrupted it
+pub type OsAppHookList = fn(&'static str, &'static str);
+
+/// Hook receiving a task removed by fault recovery and its fault report
+#[cfg(feature = "fault-recovery")]
+pub type OsAppHookTaskFault = fn(NonNull<OsTcb>, &crate::task::OsFaultReport);
+
+/// Hook receiving a task that broke its budget and the limit it broke
+#[cfg(feature = "budget")]
+pub type OsAppHookBudget = fn(crate::task::TaskHandle, crate::task::OsBudgetLimit);
+
+/// Hook receiving the report of a failed kernel self-check
+#[cfg(feature = "self-check")]
+pub type OsAppHookSelfCheck = fn(&crate::core::selfcheck::OsSelfCheckReport);
+
+/// Set of application hooks
+///
+/// Every field is optional; unset hooks cost a single branch.
+#[derive(Clone, Copy)]
+pub struct OsAppHooks {
+    /// Called after a task has been created and made ready
+    pub task_create: Option<OsAppHookTcb>,
+    /// Called after a task has been deleted
+    pub task_del: Option<OsAppHookTcb>,
+    /// Called by PendSV just before switching to the new task
+    pub task_switch: Option<OsAppHookSw>,
+    /// Called on every pass of the idle loop
+    pub idle: Option<OsAppHookVoid>,
+    /// Called on every system tick, from the tick interrupt
+    pub tick: Option<OsAppHookVoid>,
+    /// Called when the kernel detects a fault (e.g. interrupt stack overflow)
+    pub fault: Option<OsAppHookFault>,
+    /// Called on intrusive list corruption before the kernel panics
+    /// (`paranoid` feature only)
+    pub list_fault: Option<OsAppHookList>,
+    /// Called in the context of a faulted task just before it is removed
+    /// (`fault-recovery` feature only)
+    #[cfg(feature = "fault-recovery")]
+    pub task_fault: Option<OsAppHookTaskFault>,
+    /// Called with the report of a failed self-check before the kernel
+    /// panics (`self-check` feature only)
+    #[cfg(feature = "self-check")]
+    pub self_check: Option<OsAppHookSelfCheck>,
+    /// Called from the context switch or the tick interrupt when a task
+    /// overruns its run budget or misses its period (`budget` feature only)
+    #[cfg(feature = "budget")]
+    pub budget_overrun: Option<OsAppHookBudget>,
+}
+
+impl OsAppHooks {
+    /// Create an empty hook set
+    pub const fn new() -> Self {
+        Self {
+            task_create: None,
+            task_del: None,
+            task_switch: None,
+            idle: None,
+            tick: None,
+            fault: None,
+            list_fault: None,
+            #[cfg(feature = "fault-recovery")]
+            task_fault: None,
+            #[cfg(feature = "self-check")]
+            self_check: None,
+            #[cfg(feature = "budget")]
+            budget_overrun: None,
+        }
+    }
+}
+
+impl Default for OsAppHooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Registered application hooks
+static APP_HOOKS: CsCell<OsAppHooks> = CsCell::new(OsAppHooks::new());
+
+/// Register the application hooks
+///
+/// Replaces any previously registered set. May be called before or after
+/// `os_start`; `os_init` clears all hooks.
+pub fn os_app_hooks_set(hooks: OsAppHooks) {
+    critical_section(|cs| {
+        *APP_HOOKS.get(cs) = hooks;
+    });
+}
+
+/// Remove all application hooks
+pub fn os_app_hooks_clr() {
+    os_app_hooks_set(OsAppHooks::new());
+}
+
+/// Get a copy of the registered hooks
+#[inline(always)]
+fn hooks() -> OsAppHooks {
+    // Hooks are only written under a critical section and the struct is
+    // copied out, so a reader never observes a torn set on a single core.
+    unsafe { *APP_HOOKS.get_unchecked() }
+}
+
+pub(crate) fn reset() {
+    os_app_hooks_clr();
+    os_tick_hook_clr();
+    TICK_HOOK_BUDGET.store(0, Ordering::Relaxed);
+    TICK_HOOK_OVERRUNS.store(0, Ordering::Relaxed);
+}
+
+// ============ Tick hook ============
+
+/// Registered tick hook
+static TICK_HOOK: CsCell<Option<OsAppHookVoid>> = CsCell::new(None);
+
+/// Cycle budget of the tick hook, 0 when unchecked
+static TICK_HOOK_BUDGET: AtomicU32 = AtomicU32::new(0);
+
+/// Tick hook runs that exceeded the budget
+static TICK_HOOK_OVERRUNS: AtomicU32 = AtomicU32::new(0);
+
+/// Run `hook` on every tick interrupt
+///
+/// Called after the tick counter advances and before delayed tasks are
+/// expired, so `os_time_get` already reads the new tick. It runs in the
+/// tick interrupt (or `os_tick_handler`'s caller with `no-systick`), also
+/// with `tick-task`. Only ISR-safe services may be used: `Semaphore::post`,
+/// `os_task_sem_post`, `SpscRing::push`, `os_msg_alloc`/`os_msg_free` and
+/// the time and timestamp getters. Anything that pends, delays, creates or
+/// deletes a task, or takes a mutex fails or corrupts state.
+///
+/// May be called at any time; replaces the previous hook. `os_init` clears
+/// it. An unset hook costs the tick interrupt one load and branch.
+pub fn os_tick_hook_set(hook: OsAppHookVoid) {
+    critical_section(|cs| *TICK_HOOK.get(cs) = Some(hook));
+}
+
+/// Remove the tick hook
+pub fn os_tick_hook_clr() {
+    critical_section(|cs| *TICK_HOOK.get(cs) = None);
+}
+
+/// Log and count tick hook runs longer than `cycles`
+///
+/// Measured with the timestamp counter (`time::os_ts_get`, DWT `CYCCNT` on
+/// Cortex-M). 0 turns the check off, which is the default.
+pub fn os_tick_hook_budget_set(cycles: u32) {
+    TICK_HOOK_BUDGET.store(cycles, Ordering::Relaxed);
+}
+
+/// Tick hook runs that exceeded the budget since `os_init`
+pub fn os_tick_hook_overruns() -> u32 {
+    TICK_HOOK_OVERRUNS.load(Ordering::Relaxed)
+}
+
+// ============ Kernel call sites ============
+
+#[inline]
+pub(crate) fn task_create(tcb: NonNull<OsTcb>) {
+    if let Some(hook) = hooks().task_create {
+        hook(tcb);
+    }
+}
+
+#[inline]
+pub(crate) fn task_del(tcb: NonNull<OsTcb>) {
+    if let Some(hook) = hooks().task_del {
+        hook(tcb);
+    }
+}
+
+#[inline]
+#[allow(dead_code)]
+pub(crate) fn task_switch(old: Option<NonNull<OsTcb>>, new: Option<NonNull<OsTcb>>) {
+    if let Some(hook) = hooks().task_switch {
+        hook(old, new);
+    }
+}
+
+#[inline]
+pub(crate) fn idle() {
+    if let Some(hook) = hooks().idle {
+        hook();
+    }
+}
+
+#[inline]
+pub(crate) fn tick() {
+    if let Some(hook) = hooks().tick {
+        hook();
+    }
+}
+
+#[inline]
+pub(crate) fn tick_hook() {
+    // A single word, written only under a critical section
+    let Some(hook) = (unsafe { *TICK_HOOK.get_unchecked() }) else { return };
+
+    let budget = TICK_HOOK_BUDGET.load(Ordering::Relaxed);
+    if budget == 0 {
+        hook();
+        return;
+    }
+
+    let start = crate::time::os_ts_get();
+    hook();
+    let cycles = crate::time::os_ts_diff(start, crate::time::os_ts_get());
+    if cycles > budget {
+        TICK_HOOK_OVERRUNS.fetch_add(1, Ordering::Relaxed);
+        crate::__os_log_hot!(warn, "tick hook overran: {=u32} > {=u32} cycles", cycles, budget);
+    }
+}
+
+#[inline]
+pub(crate) fn fault(err: OsError) {
+    if let Some(hook) = hooks().fault {
+        hook(err);
+    }
+}
+
+#[inline]
+#[cfg(feature = "paranoid")]
+pub(crate) fn list_fault(task: &'static str, op: &'static str) {
+    if let Some(hook) = hooks().list_fault {
+        hook(task, op);
+    }
+}
+
+#[inline]
+#[cfg(feature = "fault-recovery")]
+#[cfg_attr(not(target_arch = "arm"), allow(dead_code))]
+pub(crate) fn task_fault(tcb: NonNull<OsTcb>, report: &crate::task::OsFaultReport) {
+    if let Some(hook) = hooks().task_fault {
+        hook(tcb, report);
+    }
+}
+
+#[inline]
+#[cfg(feature = "self-check")]
+pub(crate) fn self_check(report: &crate::core::selfcheck::OsSelfCheckReport) {
+    if let Some(hook) = hooks().self_check {
+        hook(report);
+    }
+}
+
+#[inline]
+#[cfg(feature = "budget")]
+pub(crate) fn budget_overrun(task: crate::task::TaskHandle, limit: crate::task::OsBudgetLimit) {
+    if let Some(hook) = hooks().budget_overrun {
+        hook(task, limit);
+    }
+}
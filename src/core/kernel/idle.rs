@@ -0,0 +1,165 @@
+//! Idle task and idle power policy
+//!
+//! The idle task runs whenever no other task is ready. On every pass it
+//! bumps the kernel idle counter (used for CPU-usage statistics), calls the
+//! idle hook and then hands control to the registered [`IdlePolicy`].
+//!
+//! The default policy executes `wfi` on ARM and RISC-V when
+//! `OsConfig::idle_wfi` is set. Some debug probes stop draining the RTT
+//! buffer used by `defmt` while the core sleeps (on STM32 the
+//! `DBGMCU_CR.DBG_SLEEP` bit must be set to keep the debug clock running),
+//! so `idle_wfi` defaults to off with the `defmt` feature. The `idle-nop`
+//! feature removes `wfi` from the build altogether.
+//!
+//! The idle counter is bumped in either mode. Sampling
+//! [`idle_counter`](super::idle_counter) at a fixed period and comparing the
+//! difference with that of an unloaded system gives the duty cycle without
+//! the statistics task.
+//!
+//! The idle task's stack is `CFG_IDLE_TASK_STK_SIZE` words. A hook or
+//! policy that needs more can bring its own TCB and stack, declared with
+//! [`os_idle_storage!`](crate::os_idle_storage) and passed in
+//! [`OsConfig::idle`](super::OsConfig::idle).
+
+use crate::core::cs_cell::{CsCell, SyncUnsafeCell};
+use crate::critical::critical_section;
+use crate::task::OsTcb;
+use crate::types::OsStkElement;
+
+use super::{hooks, os_cfg, KERNEL};
+
+/// Power policy executed by the idle task
+///
+/// `idle` is called repeatedly with interrupts enabled. It may sleep the
+/// core, enter a low-power mode or kick a watchdog, but must return once an
+/// interrupt has been serviced so the scheduler can run.
+pub trait IdlePolicy: Sync {
+    /// Execute one idle step
+    fn idle(&self);
+}
+
+/// Default idle policy: `wfi` on ARM and RISC-V, a spin hint elsewhere
+pub struct DefaultIdle;
+
+impl IdlePolicy for DefaultIdle {
+    #[inline(always)]
+    fn idle(&self) {
+        if !os_cfg::cfg().idle_wfi {
+            core::hint::spin_loop();
+            return;
+        }
+
+        #[cfg(all(target_arch = "arm", not(feature = "idle-nop")))]
+        cortex_m::asm::wfi();
+
+        #[cfg(all(target_arch = "riscv32", not(feature = "idle-nop")))]
+        unsafe { core::arch::asm!("wfi") };
+
+        #[cfg(any(not(any(target_arch = "arm", target_arch = "riscv32")), feature = "idle-nop"))]
+        core::hint::spin_loop();
+    }
+}
+
+static DEFAULT_IDLE: DefaultIdle = DefaultIdle;
+
+/// Idle task TCB and stack provided by the application, built by
+/// `os_idle_storage!`
+pub struct IdleStorage {
+    tcb: &'static SyncUnsafeCell<OsTcb>,
+    stk: *mut OsStkElement,
+    stk_size: usize,
+}
+
+// SAFETY: the TCB and stack are only written by `os_init` before any task
+// runs, then by the idle task and the kernel like any other task's
+unsafe impl Sync for IdleStorage {}
+
+impl IdleStorage {
+    #[doc(hidden)]
+    pub const fn new(tcb: &'static SyncUnsafeCell<OsTcb>, stk: *mut OsStkElement, stk_size: usize) -> Self {
+        IdleStorage { tcb, stk, stk_size }
+    }
+
+    /// Stack size in words
+    #[inline]
+    pub const fn stk_size(&self) -> usize {
+        self.stk_size
+    }
+
+    #[inline(always)]
+    pub(crate) fn tcb(&self) -> *mut OsTcb {
+        self.tcb.get()
+    }
+
+    #[inline]
+    pub(crate) fn stk(&self) -> *mut OsStkElement {
+        self.stk
+    }
+}
+
+impl core::fmt::Debug for IdleStorage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IdleStorage").field("stk_size", &self.stk_size).finish()
+    }
+}
+
+impl PartialEq for IdleStorage {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self, other)
+    }
+}
+
+impl Eq for IdleStorage {}
+
+/// Declare an [`IdleStorage`](crate::kernel::IdleStorage) with a stack of
+/// the given number of words
+///
+/// ```ignore
+/// ucosiii::os_idle_storage!(static IDLE = 512;);
+///
+/// os_init_with(&OsConfig { idle: Some(&IDLE), ..OsConfig::default() })?;
+/// ```
+#[macro_export]
+macro_rules! os_idle_storage {
+    ($vis:vis static $name:ident = $words:expr;) => {
+        $vis static $name: $crate::kernel::IdleStorage = {
+            use $crate::core::cs_cell::SyncUnsafeCell;
+            use $crate::task::{OsTcb, Stack};
+
+            const __WORDS: usize = $words;
+            static __TCB: SyncUnsafeCell<OsTcb> = SyncUnsafeCell::new(OsTcb::new());
+            static __STK: SyncUnsafeCell<Stack<__WORDS>> = SyncUnsafeCell::new(Stack::new());
+
+            $crate::kernel::IdleStorage::new(&__TCB, __STK.get().cast(), __WORDS)
+        };
+    };
+}
+
+/// Registered idle policy
+static IDLE_POLICY: CsCell<&'static dyn IdlePolicy> = CsCell::new(&DEFAULT_IDLE);
+
+/// Register the idle power policy
+///
+/// `os_init` restores [`DefaultIdle`].
+pub fn os_idle_policy_set(policy: &'static dyn IdlePolicy) {
+    critical_section(|cs| {
+        *IDLE_POLICY.get(cs) = policy;
+    });
+}
+
+pub(crate) fn reset() {
+    os_idle_policy_set(&DEFAULT_IDLE);
+}
+
+/// Internal IDLE task function
+pub(crate) fn os_idle_task(_: *mut ()) -> ! {
+    loop {
+        KERNEL.idle_ctr_increment();
+        hooks::idle();
+        #[cfg(feature = "defmt")]
+        crate::log::idle_flush();
+
+        let policy = unsafe { *IDLE_POLICY.get_unchecked() };
+        policy.idle();
+    }
+}
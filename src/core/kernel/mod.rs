@@ -0,0 +1,2713 @@
+//! Global kernel state and initialization
+//!
+//! This module manages the global OS state including initialization,
+//! starting the scheduler, and tracking kernel status.
+//!
+//! # Before `os_start`
+//!
+//! Driver bring-up in `main` may signal tasks that do not run yet. Until
+//! `os_start` the kernel services behave as follows:
+//!
+//! - Creating a semaphore, mutex, readers-writer lock or barrier works at
+//!   any time; `os_init` only drops objects created before it from the
+//!   object registry.
+//! - Creating a task needs `os_init` first and fails with
+//!   `OsError::OsNotInit` before it.
+//! - Posting a semaphore or task semaphore, `OsSem::set`, `accept` and
+//!   `Barrier::reset` work; posts count up for the tasks to take later.
+//! - Pending, locking a mutex or readers-writer lock, unlocking a mutex or
+//!   write lock, delays, suspending, resuming and deleting tasks, the
+//!   scheduler lock and `os_tick_rate_set` fail with
+//!   `OsError::OsNotRunning` instead of blocking.
+//! - Services taking `None` for the calling task fail with
+//!   `OsError::TcbInvalid`: `main` is not a task.
+//!
+//! ```no_run
+//! use core::ptr::{addr_of_mut, NonNull};
+//! use ucosiii::task::{os_task_sem_pend, os_task_sem_post, OsTcb, Stack};
+//! use ucosiii::types::opt;
+//!
+//! static mut RX_TCB: OsTcb = OsTcb::new();
+//! static mut RX_STK: Stack<256> = Stack::new();
+//!
+//! fn rx_task(_: *mut ()) -> ! {
+//!     loop {
+//!         // Takes the post made during bring-up first
+//!         let _ = os_task_sem_pend(0, opt::PEND_BLOCKING);
+//!     }
+//! }
+//!
+//! ucosiii::os_init().unwrap();
+//! let tcb = unsafe { &mut *addr_of_mut!(RX_TCB) };
+//! let rx = NonNull::from(&mut *tcb);
+//! ucosiii::os_task_create_stk(tcb, unsafe { &mut *addr_of_mut!(RX_STK) }, "Rx", rx_task, 5).unwrap();
+//!
+//! // A byte arrived during driver bring-up
+//! assert_eq!(os_task_sem_post(rx, opt::POST_NO_SCHED), Ok(1));
+//! assert_eq!(os_task_sem_pend(0, opt::PEND_BLOCKING), Err(ucosiii::OsError::OsNotRunning));
+//!
+//! ucosiii::os_start().unwrap();
+//! ```
+
+pub(crate) mod crash;
+mod dump;
+pub mod hooks;
+mod idle;
+pub mod msg;
+mod os_cfg;
+pub(crate) mod registry;
+pub mod tick_q;
+pub mod version;
+#[cfg(feature = "kernel-awareness")]
+pub mod awareness;
+#[cfg(feature = "latency-stats")]
+pub(crate) mod stats;
+
+pub use crash::{os_crash_clear, os_last_crash, CrashKind, CrashRecord};
+#[cfg(feature = "defmt")]
+pub use dump::os_dump_defmt;
+pub use dump::os_dump;
+#[cfg(feature = "kernel-awareness")]
+pub use dump::OS_DUMP_HOOK;
+pub use hooks::{os_tick_hook_budget_set, os_tick_hook_clr, os_tick_hook_overruns, os_tick_hook_set};
+pub use idle::{os_idle_policy_set, DefaultIdle, IdlePolicy, IdleStorage};
+pub use msg::{os_msg_alloc, os_msg_free, os_msg_pool_stats, OsMsg, OsMsgPoolStats};
+pub use os_cfg::{os_config, OsConfig, SchedMode};
+pub(crate) use os_cfg::cfg;
+#[cfg(feature = "latency-stats")]
+pub use stats::{os_stats, os_stats_reset, OsCycleStats, OsKernelStats};
+pub use tick_q::{TickList, TickQueue, TickWheel};
+pub use version::{os_features, os_version, OS_VERSION};
+pub use registry::{os_obj_registry_dropped, os_obj_registry_dump, os_obj_registry_iter, OsObjEntry, OsObjRegistryIter};
+
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
+
+use crate::config::{CFG_IDLE_TASK_STK_SIZE, CFG_PRIO_MAX};
+use crate::critical::{critical_section, CriticalSection};
+use crate::core::cs_cell::{CsCell, SyncUnsafeCell};
+use crate::error::{OsError, OsResult};
+use crate::prio::PrioTable;
+use crate::sched::ReadyQueues;
+use crate::task::{OsTcb, Stack, TASK_ID_IDLE};
+use crate::types::{obj_name_str, OsNestingCtr, OsPrio, OsTaskId, OsTick};
+
+// ============ Kernel State Structures ============
+
+/// Atomic kernel flags
+pub struct KernelFlags {
+    initialized: AtomicBool,
+    running: AtomicBool,
+    int_nesting: AtomicU8,
+    sched_lock_nesting: AtomicU8,
+    tick_counter: AtomicU32,
+    tick_step: AtomicU32,
+    tick_step_next: AtomicU32,
+    tick_step_later: AtomicU32,
+    time: AtomicU32,
+    idle_ctr: AtomicU32,
+    safety_critical: AtomicBool,
+    int_stk_ovf: AtomicBool,
+}
+
+impl KernelFlags {
+    const fn new() -> Self {
+        Self {
+            initialized: AtomicBool::new(false),
+            running: AtomicBool::new(false),
+            int_nesting: AtomicU8::new(0),
+            sched_lock_nesting: AtomicU8::new(0),
+            tick_counter: AtomicU32::new(0),
+            tick_step: AtomicU32::new(1),
+            tick_step_next: AtomicU32::new(1),
+            tick_step_later: AtomicU32::new(1),
+            time: AtomicU32::new(0),
+            idle_ctr: AtomicU32::new(0),
+            safety_critical: AtomicBool::new(false),
+            int_stk_ovf: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.initialized.store(false, Ordering::SeqCst);
+        self.running.store(false, Ordering::SeqCst);
+        self.int_nesting.store(0, Ordering::SeqCst);
+        self.sched_lock_nesting.store(0, Ordering::SeqCst);
+        self.tick_counter.store(0, Ordering::SeqCst);
+        self.tick_step.store(1, Ordering::SeqCst);
+        self.tick_step_next.store(1, Ordering::SeqCst);
+        self.tick_step_later.store(1, Ordering::SeqCst);
+        self.idle_ctr.store(0, Ordering::SeqCst);
+        self.safety_critical.store(false, Ordering::SeqCst);
+        self.int_stk_ovf.store(false, Ordering::SeqCst);
+    }
+
+    /// Check if the OS is running
+    #[inline(always)]
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Check if OS is initialized
+    #[inline(always)]
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::Acquire)
+    }
+
+    /// Check if object creation is locked by `os_safety_critical_start`
+    #[inline(always)]
+    pub fn is_safety_critical(&self) -> bool {
+        self.safety_critical.load(Ordering::Acquire)
+    }
+
+    /// Get current tick count
+    #[inline(always)]
+    pub fn tick_get(&self) -> OsTick {
+        self.tick_counter.load(Ordering::Relaxed)
+    }
+
+    /// Get idle loop counter
+    ///
+    /// Incremented once per pass of the idle task; wraps on overflow.
+    #[inline(always)]
+    pub fn idle_ctr(&self) -> u32 {
+        self.idle_ctr.load(Ordering::Relaxed)
+    }
+
+    /// Increment idle loop counter
+    #[inline(always)]
+    pub(crate) fn idle_ctr_increment(&self) {
+        self.idle_ctr.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Get interrupt nesting level
+    #[inline(always)]
+    pub fn int_nesting(&self) -> OsNestingCtr {
+        self.int_nesting.load(Ordering::Relaxed)
+    }
+
+    /// Get scheduler lock nesting level
+    #[inline(always)]
+    pub fn sched_lock_nesting(&self) -> OsNestingCtr {
+        self.sched_lock_nesting.load(Ordering::SeqCst)
+    }
+
+    /// Ticks the tick period in progress will count for
+    #[inline(always)]
+    pub fn tick_step(&self) -> OsTick {
+        self.tick_step.load(Ordering::Relaxed)
+    }
+
+    /// Step for the tick timer period just programmed
+    ///
+    /// It applies from the period after the one in progress, or one later
+    /// when `late`: the timer wrapped before the new period was programmed
+    /// and its interrupt is still pending. Called with interrupts masked.
+    #[inline(always)]
+    pub(crate) fn tick_step_set(&self, step: OsTick, late: bool) {
+        if !late {
+            self.tick_step_next.store(step, Ordering::Relaxed);
+        }
+        self.tick_step_later.store(step, Ordering::Relaxed);
+    }
+
+    /// Advance the tick count for the period that just ended
+    ///
+    /// Returns the new count and the step taken, and moves the steps set
+    /// with `tick_step_set` one period closer.
+    #[inline(always)]
+    pub(crate) fn tick_increment(&self) -> (OsTick, OsTick) {
+        let step = self.tick_step.swap(self.tick_step_next.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.tick_step_next.store(self.tick_step_later.load(Ordering::Relaxed), Ordering::Relaxed);
+        (self.tick_counter.fetch_add(step, Ordering::Relaxed).wrapping_add(step), step)
+    }
+
+    /// Enter ISR
+    #[inline(always)]
+    pub(crate) fn int_enter(&self) {
+        if self.is_running() {
+            let nesting = self.int_nesting.fetch_add(1, Ordering::Relaxed);
+            if nesting == 254 {
+                self.int_nesting.store(254, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Set initialized flag
+    #[inline(always)]
+    pub(crate) fn set_initialized(&self, val: bool) {
+        self.initialized.store(val, Ordering::SeqCst);
+    }
+
+    /// Set running flag
+    #[inline(always)]
+    pub(crate) fn set_running(&self, val: bool) {
+        self.running.store(val, Ordering::SeqCst);
+    }
+
+    /// Decrement int nesting
+    #[inline(always)]
+    pub(crate) fn int_nesting_dec(&self) -> OsNestingCtr {
+        let nesting = self.int_nesting.load(Ordering::Relaxed);
+        if nesting > 0 {
+            self.int_nesting.store(nesting - 1, Ordering::Relaxed);
+        }
+        nesting.saturating_sub(1)
+    }
+
+    /// Latch an interrupt stack overflow
+    ///
+    /// Returns true only the first time, so the overflow is reported once.
+    #[inline(always)]
+    pub(crate) fn int_stk_ovf_latch(&self) -> bool {
+        !self.int_stk_ovf.swap(true, Ordering::Relaxed)
+    }
+
+    /// Lock scheduler 
+    pub(crate) fn try_sched_lock(&self) -> OsResult<()> {
+        let nesting = self.sched_lock_nesting.load(Ordering::SeqCst);
+        if nesting == 255 {
+            return Err(OsError::LockNestingOvf);
+        }
+        self.sched_lock_nesting.store(nesting + 1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Unlock scheduler
+    pub(crate) fn try_sched_unlock(&self) -> OsResult<OsNestingCtr> {
+        let nesting = self.sched_lock_nesting.load(Ordering::SeqCst);
+        if nesting == 0 {
+            return Err(OsError::SchedNotLocked);
+        }
+        self.sched_lock_nesting.store(nesting - 1, Ordering::SeqCst);
+        Ok(nesting - 1)
+    }
+}
+
+// ============ Global Instances ============
+
+/// Global kernel state instance
+pub(crate) static KERNEL: KernelFlags = KernelFlags::new();
+
+/// Scheduler state
+pub struct SchedState {
+    pub(crate) rdy: ReadyQueues,
+    /// Delayed tasks and pend timeouts
+    pub(crate) tick_q: TickQueue,
+    /// Head of the list of all created tasks
+    pub(crate) task_list: Option<NonNull<OsTcb>>,
+    /// Priorities whose tasks are rotated on quanta expiry
+    pub(crate) rr_prio: PrioTable,
+    /// Next task ID to try
+    pub(crate) next_task_id: OsTaskId,
+    /// Task IDs have run past `OsTaskId::MAX` since `os_init`
+    pub(crate) task_id_wrapped: bool,
+}
+
+impl SchedState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            rdy: ReadyQueues::new(),
+            tick_q: TickQueue::new(),
+            task_list: None,
+            rr_prio: PrioTable::new(),
+            next_task_id: TASK_ID_IDLE + 1,
+            task_id_wrapped: false,
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.rdy = ReadyQueues::new();
+        self.tick_q = TickQueue::new();
+        self.task_list = None;
+        self.rr_prio.init();
+        self.next_task_id = TASK_ID_IDLE + 1;
+        self.task_id_wrapped = false;
+    }
+
+    /// Give out the next task ID
+    ///
+    /// IDs count up from the one after `TASK_ID_IDLE` and are not reused
+    /// until they run out; a deleted and recreated task gets a new one.
+    /// After `OsTaskId::MAX` they start over, skipping IDs still held by a
+    /// live task, so two live tasks never share an ID. A trace spanning the
+    /// wrap may see an ID reused by a later task.
+    pub(crate) fn task_id_alloc(&mut self) -> OsTaskId {
+        loop {
+            let id = self.next_task_id;
+            self.next_task_id = match id.checked_add(1) {
+                Some(next) => next,
+                None => {
+                    self.task_id_wrapped = true;
+                    TASK_ID_IDLE + 1
+                }
+            };
+            if !self.task_id_wrapped || !self.task_id_used(id) {
+                return id;
+            }
+        }
+    }
+
+    /// Whether a created task holds `id`
+    fn task_id_used(&self, id: OsTaskId) -> bool {
+        let mut cur = self.task_list;
+        while let Some(tcb) = cur {
+            let tcb = unsafe { tcb.as_ref() };
+            if tcb.id == id {
+                return true;
+            }
+            cur = tcb.dbg_next_ptr;
+        }
+        false
+    }
+
+    /// Get mutable reference to the ready queues
+    #[inline(always)]
+    pub fn rdy_queues(&mut self) -> &mut ReadyQueues {
+        &mut self.rdy
+    }
+
+    /// Ticks from `now` to the nearest delay expiry, 0 if overdue
+    #[inline]
+    pub fn next_expiry(&self, now: OsTick) -> Option<OsTick> {
+        self.tick_q.next_expiry(now)
+    }
+
+    /// Number of ready tasks, not counting `exclude` if it is ready
+    pub fn ready_count(&self, exclude: Option<NonNull<OsTcb>>) -> usize {
+        let total: usize = (0..CFG_PRIO_MAX).map(|p| self.rdy.list(p as OsPrio).count()).sum();
+        let excluded = exclude.is_some_and(|tcb| unsafe { tcb.as_ref() }.is_ready());
+        total.saturating_sub(excluded as usize)
+    }
+}
+
+/// Global scheduler state instance  
+pub(crate) static SCHED: CsCell<SchedState> = CsCell::new(SchedState::new());
+
+/// IDLE task TCB, unless `OsConfig::idle` provides one
+static IDLE_TCB: SyncUnsafeCell<OsTcb> = SyncUnsafeCell::new(OsTcb::new());
+
+/// IDLE task stack, unless `OsConfig::idle` provides one
+static IDLE_STK: SyncUnsafeCell<Stack<CFG_IDLE_TASK_STK_SIZE>> = SyncUnsafeCell::new(Stack::new());
+
+/// The idle task's TCB
+#[inline(always)]
+pub(crate) fn idle_tcb_ptr() -> NonNull<OsTcb> {
+    let tcb = cfg().idle.map_or(IDLE_TCB.get(), |idle| idle.tcb());
+    unsafe { NonNull::new_unchecked(tcb) }
+}
+
+// ============ CPU/Context Switch State ============
+
+/// CPU context switch state
+///
+/// A scheduling point only asks for a switch, through [`ctx_sw_request`],
+/// when the highest ready task is not the running one. It names no task:
+/// interrupts that run between the request and the port's switch handler
+/// may ready a higher task or take the chosen one off the ready list. The
+/// handler picks the task itself with [`high_rdy_resolve`], under its own
+/// mask, and writes `tcb_high_rdy`/`prio_high_rdy` just before switching
+/// to them, so the pair always describes the switch in progress or the
+/// last one taken.
+///
+/// Fields read by port assembly come first; keep their offsets.
+#[repr(C)]
+pub struct CpuState {
+    /// Current running task's TCB pointer
+    pub tcb_cur: *mut OsTcb,
+    /// Highest priority ready task's TCB pointer
+    pub tcb_high_rdy: *mut OsTcb,
+    /// Current running task's priority
+    pub prio_cur: OsPrio,
+    /// Highest ready priority
+    pub prio_high_rdy: OsPrio,
+    /// Exception stack base
+    pub except_stk_base: u32,
+    /// Timestamp of the running PendSV entry
+    #[cfg(feature = "latency-stats")]
+    pub sw_start: u32,
+    /// Cycles of the last context switch, 0 once folded into the stats
+    #[cfg(feature = "latency-stats")]
+    pub sw_cycles: u32,
+    /// A switch to `tcb_high_rdy` is pended and has not run yet
+    pub sw_pending: bool,
+}
+
+impl CpuState {
+    pub const fn new() -> Self {
+        Self {
+            tcb_cur: core::ptr::null_mut(),
+            tcb_high_rdy: core::ptr::null_mut(),
+            prio_cur: 0,
+            prio_high_rdy: 0,
+            except_stk_base: 0,
+            #[cfg(feature = "latency-stats")]
+            sw_start: 0,
+            #[cfg(feature = "latency-stats")]
+            sw_cycles: 0,
+            sw_pending: false,
+        }
+    }
+    
+    pub fn reset(&mut self) {
+        self.tcb_cur = core::ptr::null_mut();
+        self.tcb_high_rdy = core::ptr::null_mut();
+        self.prio_cur = 0;
+        self.prio_high_rdy = 0;
+        #[cfg(feature = "latency-stats")]
+        {
+            self.sw_start = 0;
+            self.sw_cycles = 0;
+        }
+        self.sw_pending = false;
+    }
+
+    // ============ TCB Accessor Methods ============
+
+    /// Get current TCB pointer
+    #[inline(always)]
+    pub unsafe fn tcb_cur_ptr(&self) -> Option<NonNull<OsTcb>> {
+        NonNull::new(self.tcb_cur)
+    }
+
+    /// Set current TCB pointer
+    #[inline(always)]
+    pub unsafe fn set_tcb_cur(&mut self, tcb: Option<NonNull<OsTcb>>) {
+        self.tcb_cur = tcb.map_or(core::ptr::null_mut(), |p| p.as_ptr());
+    }
+
+    /// Get high ready TCB pointer
+    #[inline(always)]
+    pub unsafe fn tcb_high_rdy_ptr(&self) -> Option<NonNull<OsTcb>> {
+        NonNull::new(self.tcb_high_rdy)
+    }
+
+    /// Name the task the switch runs, and its priority
+    ///
+    /// Called with interrupts masked, by the switch handler and `os_start`.
+    #[inline(always)]
+    pub(crate) fn high_rdy_set(&mut self, prio: OsPrio, tcb: *mut OsTcb) {
+        self.prio_high_rdy = prio;
+        self.tcb_high_rdy = tcb;
+    }
+
+    // ============ Priority Accessor Methods ============
+
+    /// Get current priority
+    #[inline(always)]
+    pub unsafe fn get_prio_cur(&self) -> OsPrio {
+        self.prio_cur
+    }
+
+    /// Set current priority
+    #[inline(always)]
+    pub unsafe fn set_prio_cur(&mut self, prio: OsPrio) {
+        self.prio_cur = prio;
+    }
+
+    /// Get high ready priority
+    #[inline(always)]
+    pub unsafe fn get_prio_high_rdy(&self) -> OsPrio {
+        self.prio_high_rdy
+    }
+
+    /// The task to run given `rdy`, and its priority
+    ///
+    /// The head of `rdy`, except that a running task that is still ready
+    /// keeps the CPU unless the head beats its preemption threshold. `None`
+    /// with nothing ready.
+    pub(crate) fn high_rdy_pick(&self, rdy: &ReadyQueues) -> Option<(OsPrio, *mut OsTcb)> {
+        let (prio, head) = rdy.highest_tcb()?;
+        if let Some(cur) = unsafe { self.tcb_cur.as_ref() } {
+            if cur.is_ready() && prio < cur.prio && !cur.preempted_by(prio) {
+                return Some((cur.prio, self.tcb_cur));
+            }
+        }
+        Some((prio, head.as_ptr()))
+    }
+
+    /// Whether the task to run is not the running one
+    ///
+    /// Compares tasks rather than `prio_cur`, which still holds the
+    /// priority of a task that blocked while its switch is pending.
+    pub(crate) fn switch_due(&self, rdy: &ReadyQueues) -> bool {
+        self.high_rdy_pick(rdy).is_some_and(|(_, tcb)| tcb != self.tcb_cur)
+    }
+
+    /// Point the high ready pair at the task to run
+    ///
+    /// Returns whether a switch is needed.
+    #[cfg(any(test, target_arch = "arm", target_arch = "riscv32"))]
+    pub(crate) fn high_rdy_refresh(&mut self, rdy: &ReadyQueues) -> bool {
+        let Some((prio, tcb)) = self.high_rdy_pick(rdy) else {
+            return false;
+        };
+        self.high_rdy_set(prio, tcb);
+        self.tcb_high_rdy != self.tcb_cur
+    }
+
+    /// Clear the pending switch and point the pair at the task it runs
+    ///
+    /// Decided from `rdy` as it is now, not when the switch was requested.
+    /// With nothing ready the running task stays.
+    #[cfg(any(test, target_arch = "arm", target_arch = "riscv32"))]
+    pub(crate) fn high_rdy_resolve(&mut self, rdy: &ReadyQueues) {
+        self.sw_pending = false;
+        if !self.high_rdy_refresh(rdy) && self.tcb_high_rdy.is_null() {
+            self.high_rdy_set(self.prio_cur, self.tcb_cur);
+        }
+    }
+}
+
+/// Global CPU state instance
+///
+/// The port's PendSV assembly loads `CPU_STATE` by symbol and reads
+/// `tcb_cur` at offset 0; `CsCell` is `#[repr(transparent)]`, so the symbol
+/// keeps the `CpuState` layout.
+#[no_mangle]
+#[used]
+pub static CPU_STATE: CsCell<CpuState> = CsCell::new(CpuState::new());
+
+const _: () = assert!(core::mem::offset_of!(CpuState, tcb_cur) == 0);
+const _: () = assert!(core::mem::size_of::<CsCell<CpuState>>() == core::mem::size_of::<CpuState>());
+// The Cortex-M PendSV stores its timestamps at these offsets
+#[cfg(all(feature = "latency-stats", target_arch = "arm"))]
+const _: () = assert!(
+    core::mem::offset_of!(CpuState, sw_start) == 16 && core::mem::offset_of!(CpuState, sw_cycles) == 20
+);
+
+/// BASEPRI boundary
+///
+/// Interrupts with a priority value below this boundary are never masked by
+/// the kernel and must not call kernel services. When non-zero it must be
+/// numerically lower than the PendSV/SysTick priority.
+#[no_mangle]
+pub static OS_KA_BASEPRI_Boundary: u32 = 0;
+
+// ============ Initialization ============
+
+/// Reset global kernel state
+unsafe fn os_reset_globals() {
+    KERNEL.reset();
+    hooks::reset();
+    idle::reset();
+    msg::reset();
+    registry::reset();
+    #[cfg(feature = "obj-pool")]
+    {
+        crate::sync::sem::pool_reset();
+        crate::sync::mutex::pool_reset();
+    }
+    #[cfg(feature = "embassy-time-driver")]
+    crate::core::embassy::reset();
+    #[cfg(feature = "clock")]
+    crate::time::clock::reset();
+    #[cfg(feature = "budget")]
+    crate::task::budget::reset();
+    
+    unsafe {
+        CPU_STATE.get_unchecked().reset();
+        IDLE_TCB.get().write(OsTcb::new());
+        IDLE_STK.get().write(Stack::new());
+    }
+    
+    unsafe {
+        SCHED.get_unchecked().reset();
+    }
+}
+
+// ============ Public API ============
+
+/// Initialize the RTOS kernel
+///
+/// This must be called before any other OS function.
+/// It initializes the priority table, ready lists, and internal state.
+/// IDLE task is automatically created.
+///
+/// Equivalent to `os_init_with(&OsConfig::default())`.
+///
+/// # Returns
+/// * `Ok(())` - Initialization successful
+/// * `Err(OsError::OsRunning)` - OS is already running
+pub fn os_init() -> OsResult<()> {
+    os_init_with(&OsConfig::default())
+}
+
+/// Initialize the RTOS kernel with a runtime configuration
+///
+/// Like [`os_init`], but the tick rate, round-robin settings, idle behavior
+/// and optional kernel tasks come from `cfg`.
+///
+/// # Returns
+/// * `Ok(())` - Initialization successful
+/// * `Err(OsError::OsRunning)` - OS is already running
+/// * `Err(OsError::CfgTickRateInvalid)` - Tick rate is 0 or above `CFG_TICK_RATE_HZ`
+/// * `Err(OsError::CfgTimeQuantaInvalid)` - Round-robin enabled with a quanta of 0
+/// * `Err(OsError::CfgIdleStkInvalid)` - `idle` stack below `CFG_STK_SIZE_MIN`
+/// * `Err(OsError::CfgPrioInvalid)` - Two kernel tasks share a priority, or one
+///   is at the idle priority or below
+/// * `Err(_)` - A kernel task or a `task_table` entry could not be created;
+///   see `TaskTable::error` for the entry
+pub fn os_init_with(cfg: &OsConfig) -> OsResult<()> {
+    cfg.validate()?;
+
+    unsafe { os_reset_globals(); }
+    
+    if KERNEL.is_running() {
+        return Err(OsError::OsRunning);
+    }
+
+    os_cfg::set(cfg);
+    
+    critical_section(|cs| {
+        let sched = SCHED.get(cs);
+        
+        // Initialize ready queues
+        sched.rdy.init();
+
+        // Round-robin starts out on every level or none
+        if cfg.round_robin_en {
+            for prio in 0..CFG_PRIO_MAX {
+                sched.rr_prio.insert(prio as OsPrio);
+            }
+        }
+
+        // Create IDLE task, on the application's storage if it gave one
+        let (idle_stk, idle_stk_size) = match cfg.idle {
+            Some(idle) => (idle.stk(), idle.stk_size()),
+            None => (IDLE_STK.get().cast(), CFG_IDLE_TASK_STK_SIZE),
+        };
+        unsafe {
+            crate::task::os_task_create_internal(
+                idle_tcb_ptr().as_ptr(),
+                "Idle",
+                idle::os_idle_task,
+                core::ptr::null_mut(),
+                crate::config::CFG_PRIO_IDLE,
+                idle_stk,
+                idle_stk_size,
+                0,
+                0,
+            )?;
+        }
+
+        // Create tick task
+        #[cfg(feature = "tick-task")]
+        if cfg.tick_task_en {
+            unsafe { crate::time::tick_task::os_tick_task_init() }?;
+        }
+
+        // Create software watchdog supervisor
+        #[cfg(feature = "wdog")]
+        if cfg.wdog_en {
+            unsafe { crate::wdog::os_wdog_init() }?;
+        }
+
+        // Create the kernel self-check task
+        #[cfg(feature = "self-check")]
+        if cfg.self_check_en {
+            unsafe { crate::core::selfcheck::os_self_check_init() }?;
+        }
+
+        KERNEL.set_initialized(true);
+
+        // Create the application's declared tasks, which need the kernel
+        // initialized like any other task
+        #[cfg(feature = "task-table")]
+        if let Some(table) = cfg.task_table {
+            table.create_all().inspect_err(|_| KERNEL.set_initialized(false))?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Tear the kernel down so `os_init` can run again
+///
+/// Stops the tick source, clears the running and initialized flags and
+/// resets the scheduler, CPU state and object registry. Every task's TCB
+/// may be passed to `os_task_create` again. Meant for host tests
+/// and simulation; it does not return to a pre-`os_start` stack on target.
+#[cfg(any(test, feature = "deinit"))]
+pub fn os_deinit() {
+    crate::port::os_cpu_systick_stop();
+
+    critical_section(|_cs| unsafe {
+        // Let the application create its tasks again on the same TCBs
+        let mut next = *task_list();
+        while let Some(tcb) = next {
+            let tcb = &mut *tcb.as_ptr();
+            tcb.obj_type = crate::types::OsObjType::None;
+            tcb.id = crate::task::TASK_ID_NONE;
+            next = tcb.dbg_next_ptr;
+        }
+        os_reset_globals()
+    });
+}
+
+/// Lock object creation for safety-critical builds
+///
+/// After this call every `create` of a task, semaphore or mutex fails with
+/// `OsError::IllegalCreateRunTime`. Call it once all objects exist,
+/// typically from the first task after `os_start`. `os_init` clears it.
+pub fn os_safety_critical_start() {
+    KERNEL.safety_critical.store(true, Ordering::Release);
+}
+
+/// Start multitasking
+///
+/// Equivalent to `os_start_with_clock(cfg.cpu_clk_hz)` with the
+/// configuration given to `os_init_with`, which defaults to the 16 MHz
+/// reset clock.
+pub fn os_start() -> OsResult<()> {
+    os_start_with_clock(os_config().cpu_clk_hz)
+}
+
+/// Start multitasking with an explicit core clock frequency
+///
+/// This function starts the highest priority ready task. It never returns.
+/// Before calling this, at least one application task must be created.
+///
+/// `core_hz` is used to program the SysTick reload value for the configured
+/// tick rate. With the `no-systick` feature SysTick is left
+/// untouched and the application must call `os_tick_handler` from its own
+/// timer interrupt.
+///
+/// # Returns
+/// This function does not return under normal operation.
+/// * `Err(OsError::OsNotInit)` - OS not initialized
+/// * `Err(OsError::OsRunning)` - OS is already running
+/// * `Err(OsError::OsNoAppTask)` - No application task created
+/// * `Err(OsError::TickReloadInvalid)` - `core_hz` cannot produce the tick rate
+pub fn os_start_with_clock(core_hz: u32) -> OsResult<()> {
+    if !KERNEL.is_initialized() {
+        return Err(OsError::OsNotInit);
+    }
+    
+    if KERNEL.is_running() {
+        return Err(OsError::OsRunning);
+    }
+
+    // Initialize SysTick
+    #[cfg(not(feature = "no-systick"))]
+    crate::port::os_cpu_systick_init(core_hz / os_config().tick_rate_hz)?;
+    #[cfg(feature = "no-systick")]
+    let _ = core_hz;
+
+    crate::time::ts::core_clk_set(core_hz);
+    crate::port::os_cpu_ts_init();
+    crate::port::os_cpu_fpu_init();
+    
+    critical_section(|cs| {
+        let sched = SCHED.get(cs);
+        
+        let Some((high_prio, head)) = sched.rdy.highest_tcb() else {
+            return;
+        };
+
+        let cpu = CPU_STATE.get(cs);
+        cpu.high_rdy_set(high_prio, head.as_ptr());
+        cpu.prio_cur = high_prio;
+        cpu.tcb_cur = head.as_ptr();
+        #[cfg(feature = "budget")]
+        unsafe { crate::task::budget::budget_switch(core::ptr::null_mut(), head.as_ptr()) };
+
+        KERNEL.set_running(true);
+    });
+
+    unsafe { crate::port::os_start_high_rdy() };
+    
+    Ok(())
+}
+
+/// Create a startup task and start multitasking
+///
+/// Board bring-up that needs delays or blocking driver calls can run in
+/// `task_fn` instead of spinning before the scheduler starts. Tasks created
+/// before this call run as usual; see [`os_run`] for a startup task that
+/// creates every other task itself.
+///
+/// # Returns
+/// This function does not return under normal operation.
+/// * Errors of `os_task_create_stk` and `os_start`
+///
+/// # Example
+/// ```ignore
+/// static mut INIT_TCB: OsTcb = OsTcb::new();
+/// static mut INIT_STK: Stack<512> = Stack::new();
+///
+/// fn init_task(_: *mut ()) -> ! {
+///     pmic_init_over_i2c(); // may block and delay
+///     // create the application tasks, then carry on as one of them
+///     loop { /* ... */ }
+/// }
+///
+/// os_init().unwrap();
+/// os_start_with(unsafe { &mut INIT_TCB }, unsafe { &mut INIT_STK }, init_task, 1).unwrap();
+/// ```
+pub fn os_start_with<const N: usize>(
+    tcb: &'static mut OsTcb,
+    stack: &'static mut Stack<N>,
+    task_fn: crate::task::OsTaskFn,
+    prio: OsPrio,
+) -> OsResult<()> {
+    if !KERNEL.is_initialized() {
+        return Err(OsError::OsNotInit);
+    }
+    crate::task::os_task_create_stk(tcb, stack, "Startup", task_fn, prio)?;
+    os_start()
+}
+
+/// Start multitasking with a startup task that creates all other tasks
+///
+/// Same as [`os_start_with`], but fails before creating anything if an
+/// application task already exists: with this style every task is
+/// created from the startup task.
+///
+/// # Returns
+/// * `Err(OsError::OsAppTaskExists)` - A task other than the kernel's exists
+/// * Errors of [`os_start_with`]
+pub fn os_run<const N: usize>(
+    tcb: &'static mut OsTcb,
+    stack: &'static mut Stack<N>,
+    task_fn: crate::task::OsTaskFn,
+    prio: OsPrio,
+) -> OsResult<()> {
+    let app_tasks = critical_section(|cs| {
+        let mut node = SCHED.get(cs).task_list;
+        let mut n = 0;
+        while let Some(tcb) = node {
+            let t = unsafe { tcb.as_ref() };
+            n += (t.opt & crate::types::opt::TASK_KERNEL == 0) as usize;
+            node = t.dbg_next_ptr;
+        }
+        n
+    });
+    if app_tasks > 0 {
+        return Err(OsError::OsAppTaskExists);
+    }
+    os_start_with(tcb, stack, task_fn, prio)
+}
+
+/// Enter ISR
+///
+/// Call first in every interrupt handler that uses kernel services, paired
+/// with [`os_int_exit`] last, or wrap the handler body in [`os_isr!`]. A
+/// task woken by the handler then runs as soon as the outermost handler
+/// returns.
+///
+/// [`os_isr!`]: crate::os_isr
+#[inline]
+pub fn os_int_enter() {
+    KERNEL.int_enter();
+}
+
+/// Exit ISR
+///
+/// Pends a switch to the highest priority ready task when the outermost
+/// handler exits.
+pub fn os_int_exit() {
+    if !KERNEL.is_running() {
+        return;
+    }
+
+    let cs = CriticalSection::enter();
+
+    let old_nesting = KERNEL.int_nesting();
+    if old_nesting == 0 {
+        return;
+    }
+
+    let new_nesting = KERNEL.int_nesting_dec();
+
+    if crate::config::CFG_ISR_STK_CHK_EN && !crate::port::os_cpu_int_stk_canary_ok() && KERNEL.int_stk_ovf_latch() {
+        hooks::fault(OsError::StkOvf);
+    }
+
+    if new_nesting == 0 {
+        os_int_sched(&cs);
+    }
+}
+
+/// Pend a switch from interrupt level if a higher priority task is ready
+///
+/// The switch happens once the last active handler returns.
+pub(crate) fn os_int_sched(cs: &CriticalSection) {
+    if KERNEL.sched_lock_nesting() > 0 {
+        return;
+    }
+
+    ctx_sw_request(cs, true);
+}
+
+/// Pend a context switch if a task other than the running one should run
+///
+/// The single path from a scheduling point to the port. Only the need for
+/// a switch is decided here; the switch handler picks the task when it
+/// runs. From interrupt level the switch is left out in cooperative mode.
+pub(crate) fn ctx_sw_request(cs: &CriticalSection, from_isr: bool) {
+    let cpu = CPU_STATE.get(cs);
+    let rdy = &SCHED.get(cs).rdy;
+    let switch = if from_isr {
+        int_switch(cpu, rdy, cfg().sched_mode)
+    } else {
+        cpu.switch_due(rdy)
+    };
+    if switch {
+        ctx_sw_pend(cs, from_isr);
+    }
+}
+
+/// Pick the task the running switch handler switches to
+///
+/// Clears `sw_pending` and points `tcb_high_rdy`/`prio_high_rdy` at the
+/// task to run given the ready lists as they are now. Costs a priority
+/// table lookup and a list head load over taking a stored target.
+///
+/// # Safety
+/// Called by the port's switch handler with interrupts masked; `cpu` is
+/// `CPU_STATE`.
+#[cfg(any(test, target_arch = "arm", target_arch = "riscv32"))]
+#[inline(always)]
+pub(crate) unsafe fn high_rdy_resolve(cpu: &mut CpuState) {
+    cpu.high_rdy_resolve(unsafe { &SCHED.get_unchecked().rdy });
+}
+
+/// The task a switch taken now would run
+#[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
+pub(crate) fn high_rdy_next(cs: &CriticalSection) -> *mut OsTcb {
+    let cpu = CPU_STATE.get(cs);
+    cpu.high_rdy_pick(&SCHED.get(cs).rdy).map_or(cpu.tcb_cur, |(_, tcb)| tcb)
+}
+
+/// Pend a context switch
+///
+/// Asks the port only when no switch is pending; the pending one picks
+/// its task when it runs.
+fn ctx_sw_pend(cs: &CriticalSection, from_isr: bool) {
+    let cpu = CPU_STATE.get(cs);
+    if cpu.sw_pending {
+        return;
+    }
+    cpu.sw_pending = true;
+
+    if from_isr {
+        crate::port::os_int_ctx_sw();
+    } else {
+        crate::port::os_ctx_sw();
+    }
+}
+
+/// Whether an interrupt may switch away from the running task
+///
+/// In cooperative mode the switch waits for the next `os_sched` from the
+/// running task.
+fn int_switch(cpu: &CpuState, rdy: &ReadyQueues, mode: SchedMode) -> bool {
+    cpu.switch_due(rdy) && mode == SchedMode::Preemptive
+}
+
+/// Run an interrupt handler body between [`os_int_enter`] and [`os_int_exit`]
+///
+/// Evaluates to the value of the body. The body runs in a closure, so
+/// `return` leaves the body only.
+///
+/// ```ignore
+/// #[interrupt]
+/// fn EXTI0() {
+///     ucosiii::os_isr! {
+///         clear_exti0_pending();
+///         let _ = BUTTON.signal();
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! os_isr {
+    ($($body:tt)*) => {{
+        $crate::kernel::os_int_enter();
+        #[allow(clippy::redundant_closure_call)]
+        let r = (|| { $($body)* })();
+        $crate::kernel::os_int_exit();
+        r
+    }};
+}
+
+/// Get interrupt (MSP) stack usage in bytes as `(used, total)`
+///
+/// `used` is the high watermark since reset, found by scanning for the
+/// stack fill pattern.
+pub fn os_int_stack_usage() -> (usize, usize) {
+    crate::port::os_cpu_int_stk_usage()
+}
+
+/// Idle loop passes since `os_init`, wrapping
+///
+/// Diff two reads taken a fixed period apart for a cheap duty cycle.
+#[inline]
+pub fn idle_counter() -> u32 {
+    KERNEL.idle_ctr()
+}
+
+/// Lock the scheduler
+pub fn os_sched_lock() -> OsResult<()> {
+    if !KERNEL.is_running() {
+        return Err(OsError::OsNotRunning);
+    }
+
+    if KERNEL.int_nesting() > 0 {
+        return Err(OsError::SchedLockIsr);
+    }
+
+    critical_section(|_cs| {
+        KERNEL.try_sched_lock()
+    })
+}
+
+/// Unlock the scheduler
+pub fn os_sched_unlock() -> OsResult<()> {
+    if !KERNEL.is_running() {
+        return Err(OsError::OsNotRunning);
+    }
+
+    if KERNEL.int_nesting() > 0 {
+        return Err(OsError::SchedUnlockIsr);
+    }
+
+    critical_section(|_cs| {
+        let remaining = KERNEL.try_sched_unlock()?;
+        if remaining == 0 {
+            crate::sched::os_sched();
+        }
+        Ok(())
+    })
+}
+
+/// Check whether the scheduler is locked
+///
+/// While it is, services that would block the caller fail with
+/// `OsError::SchedLocked` instead.
+#[inline]
+pub fn os_sched_is_locked() -> bool {
+    KERNEL.sched_lock_nesting() > 0
+}
+
+/// Scheduler lock nesting depth, 0 when unlocked
+#[inline]
+pub fn os_sched_lock_nesting() -> OsNestingCtr {
+    KERNEL.sched_lock_nesting()
+}
+
+/// Reject a call that would take the running task off the ready list
+///
+/// With the scheduler locked nothing else may run, so the caller would
+/// never be switched out and the system would wedge.
+#[inline]
+pub(crate) fn sched_lock_chk() -> OsResult<()> {
+    if os_sched_is_locked() {
+        return Err(OsError::SchedLocked);
+    }
+    Ok(())
+}
+
+// ============ Running task ============
+//
+// Safe to call from tasks and ISRs without a critical section. `tcb_cur` is
+// read once and the TCB fields read through that copy, so a switch in
+// between yields a consistent view of one task. TCBs are `'static`, so the
+// copy never dangles.
+
+/// Running task, read without a critical section
+#[inline]
+pub(crate) fn tcb_cur_snapshot() -> Option<NonNull<OsTcb>> {
+    let cur = unsafe { core::ptr::read_volatile(core::ptr::addr_of!((*CPU_STATE.as_ptr()).tcb_cur)) };
+    NonNull::new(cur)
+}
+
+/// Name of the running task
+///
+/// From an ISR, the task it interrupted; `"main"` before `os_start`.
+pub fn current_task_name() -> &'static str {
+    match tcb_cur_snapshot() {
+        Some(tcb) => obj_name_str(unsafe { core::ptr::read_volatile(core::ptr::addr_of!((*tcb.as_ptr()).name)) }),
+        None => "main",
+    }
+}
+
+/// Current (possibly inherited) priority of the running task
+///
+/// From an ISR, the task it interrupted; `CFG_PRIO_IDLE` before `os_start`.
+pub fn current_prio() -> OsPrio {
+    match tcb_cur_snapshot() {
+        Some(tcb) => unsafe { core::ptr::read_volatile(core::ptr::addr_of!((*tcb.as_ptr()).prio)) },
+        None => crate::config::CFG_PRIO_IDLE,
+    }
+}
+
+// ============ Internal accessors for other modules ============
+
+/// Get mutable reference to the ready queues
+#[inline(always)]
+pub(crate) unsafe fn rdy_queues() -> &'static mut ReadyQueues {
+    unsafe { &mut SCHED.get_unchecked().rdy }
+}
+
+/// Get head of the task registry
+#[inline(always)]
+pub(crate) unsafe fn task_list() -> &'static mut Option<NonNull<OsTcb>> {
+    unsafe { &mut SCHED.get_unchecked().task_list }
+}
+
+/// Give out the next task ID (caller must hold critical section)
+#[inline]
+pub(crate) unsafe fn task_id_alloc() -> OsTaskId {
+    unsafe { SCHED.get_unchecked().task_id_alloc() }
+}
+
+/// Get current TCB pointer as Option<NonNull>
+#[inline]
+pub(crate) unsafe fn tcb_cur_ptr() -> Option<NonNull<OsTcb>> {
+    unsafe { CPU_STATE.get_unchecked().tcb_cur_ptr() }
+}
+
+/// Set the ticks left of the running task's wait when it did not block
+#[inline]
+pub(crate) unsafe fn tick_remain_set(ticks: OsTick) {
+    if let Some(mut tcb) = unsafe { tcb_cur_ptr() } {
+        unsafe { tcb.as_mut().tick_remain = ticks };
+    }
+}
+
+/// Set current TCB pointer
+#[inline]
+#[allow(dead_code)]
+pub(crate) unsafe fn set_tcb_cur_ptr(tcb: Option<NonNull<OsTcb>>) {
+    unsafe { CPU_STATE.get_unchecked().set_tcb_cur(tcb) }
+}
+
+/// Get high ready TCB pointer as Option<NonNull>
+#[inline]
+#[allow(dead_code)]
+pub(crate) unsafe fn tcb_high_rdy_ptr() -> Option<NonNull<OsTcb>> {
+    unsafe { CPU_STATE.get_unchecked().tcb_high_rdy_ptr() }
+}
+
+/// Get current priority
+#[inline]
+#[allow(dead_code)]
+pub(crate) unsafe fn prio_cur() -> OsPrio {
+    unsafe { CPU_STATE.get_unchecked().get_prio_cur() }
+}
+
+/// Set current priority
+#[inline]
+#[allow(dead_code)]
+pub(crate) unsafe fn set_prio_cur(prio: OsPrio) {
+    unsafe { CPU_STATE.get_unchecked().set_prio_cur(prio) }
+}
+
+/// Get high ready priority
+#[inline]
+#[allow(dead_code)]
+pub(crate) unsafe fn prio_high_rdy() -> OsPrio {
+    unsafe { CPU_STATE.get_unchecked().get_prio_high_rdy() }
+}
+
+// ============ Tick Queue Management ============
+//
+// Delays and pend timeouts go through these, whichever `TickQueue`
+// backend is compiled.
+
+/// Add task to the tick queue based on expiry tick
+pub(crate) unsafe fn tick_insert(tcb: NonNull<OsTcb>, expiry_tick: OsTick) {
+    unsafe {
+        SCHED.get_unchecked().tick_q.insert(tcb, expiry_tick);
+    }
+}
+
+/// Remove task from the tick queue
+pub(crate) unsafe fn tick_remove(tcb: NonNull<OsTcb>) {
+    unsafe {
+        SCHED.get_unchecked().tick_q.remove(tcb);
+    }
+}
+
+/// Take every task due by `now` off the tick queue, passing each to `f`
+///
+/// `span` is the number of ticks the count just advanced by, so a coarse
+/// tick also expires the slots it skipped. `f` runs with the queue
+/// released, so it may delay or ready tasks.
+pub(crate) unsafe fn tick_expire(now: OsTick, span: OsTick, mut f: impl FnMut(NonNull<OsTcb>)) {
+    while let Some(tcb) = unsafe { SCHED.get_unchecked().tick_q.pop_expired_span(now, span) } {
+        f(tcb);
+    }
+}
+
+/// Serializes the host tests that use the kernel globals
+///
+/// Test threads run in parallel and would otherwise see each other's
+/// tasks, ticks and trace events. Recovers from a failed holder, whose
+/// state the next `os_init` resets.
+#[cfg(test)]
+pub(crate) fn test_lock() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    LOCK.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CFG_STK_SIZE_MIN;
+    use crate::types::{opt, OsOpt};
+
+    fn spin(_: *mut ()) -> ! {
+        loop {}
+    }
+
+    /// Storage for a task that runs `spin`
+    struct TestTask {
+        tcb: SyncUnsafeCell<OsTcb>,
+        stk: SyncUnsafeCell<Stack<CFG_STK_SIZE_MIN>>,
+    }
+
+    impl TestTask {
+        const fn new() -> Self {
+            Self { tcb: SyncUnsafeCell::new(OsTcb::new()), stk: SyncUnsafeCell::new(Stack::new()) }
+        }
+
+        fn ptr(&self) -> NonNull<OsTcb> {
+            NonNull::new(self.tcb.get()).unwrap()
+        }
+
+        fn create(&'static self, name: &'static str, prio: OsPrio) -> NonNull<OsTcb> {
+            self.create_opt(name, prio, opt::TASK_NONE)
+        }
+
+        fn create_opt(&'static self, name: &'static str, prio: OsPrio, opt: OsOpt) -> NonNull<OsTcb> {
+            let (tcb, stk) = unsafe { (&mut *self.tcb.get(), &mut *self.stk.get()) };
+            crate::task::os_task_create_opt(tcb, stk, name, spin, prio, opt).unwrap();
+            self.ptr()
+        }
+    }
+
+    /// Take the pending switch as the port's handler would; the stub port
+    /// only records the request
+    fn fake_switch() {
+        critical_section(|cs| {
+            let cpu = CPU_STATE.get(cs);
+            unsafe { high_rdy_resolve(cpu) };
+            cpu.tcb_cur = cpu.tcb_high_rdy;
+            cpu.prio_cur = cpu.prio_high_rdy;
+        });
+    }
+
+    #[test]
+    fn test_init_deinit_cycles() {
+        let _lock = test_lock();
+        for _ in 0..3 {
+            os_init().unwrap();
+            assert!(KERNEL.is_initialized());
+            assert!(registry::os_obj_registry_iter().any(|e| e.name() == "Idle"));
+
+            os_deinit();
+            assert!(!KERNEL.is_initialized());
+            assert!(unsafe { rdy_queues() }.highest().is_none());
+            assert_eq!(registry::os_obj_registry_iter().count(), 0);
+        }
+    }
+
+    /// A task table is created by `os_init_with`, and deinit frees its
+    /// TCBs for the next init; a bad entry fails the init and is reported
+    #[test]
+    #[cfg(feature = "task-table")]
+    fn test_task_table() {
+        use crate::task::TaskSpec;
+
+        crate::os_task_table! {
+            static GOOD = [
+                TaskSpec::new("Low", spin, 9, CFG_STK_SIZE_MIN),
+                TaskSpec::new("High", spin, 3, CFG_STK_SIZE_MIN + 1),
+            ];
+        }
+        crate::os_task_table! {
+            static BAD = [
+                TaskSpec::new("Ok", spin, 9, CFG_STK_SIZE_MIN),
+                TaskSpec::new("Tiny", spin, 3, CFG_STK_SIZE_MIN - 1),
+            ];
+        }
+
+        let _lock = test_lock();
+        for _ in 0..2 {
+            os_init_with(&OsConfig { task_table: Some(&GOOD), ..OsConfig::default() }).unwrap();
+            let high = GOOD.tcb_by_name("High").unwrap();
+            assert_eq!(unsafe { high.as_ref() }.prio(), 3);
+            assert_eq!(unsafe { rdy_queues() }.highest_tcb(), Some((3, high)));
+            assert_eq!(GOOD.error(), None);
+            os_deinit();
+        }
+
+        let err = os_init_with(&OsConfig { task_table: Some(&BAD), ..OsConfig::default() });
+        assert_eq!(err, Err(OsError::StkSizeInvalid));
+        let entry = BAD.error().unwrap();
+        assert_eq!((entry.index, entry.name), (1, "Tiny"));
+        assert!(!KERNEL.is_initialized());
+        os_deinit();
+    }
+
+    /// A ready mutex owner being boosted moves ready lists
+    #[test]
+    #[cfg(feature = "mutex")]
+    fn test_mutex_boost_requeues() {
+        static OWNER: TestTask = TestTask::new();
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        let owner = OWNER.create("Owner", 20);
+        critical_section(|_cs| unsafe { crate::sync::prio_change(owner, 3) });
+        assert_eq!(unsafe { rdy_queues() }.highest_tcb(), Some((3, owner)));
+        os_deinit();
+    }
+
+    /// Nothing takes the running task off the ready list under a lock
+    #[test]
+    fn test_sched_lock_keeps_running_task() {
+        static CUR: TestTask = TestTask::new();
+        static OTHER: TestTask = TestTask::new();
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        let cur = CUR.create("Cur", 5);
+        let other = OTHER.create("Other", 6);
+        fake_switch();
+        KERNEL.set_running(true);
+
+        os_sched_lock().unwrap();
+        os_sched_lock().unwrap();
+        assert!(os_sched_is_locked());
+        assert_eq!(os_sched_lock_nesting(), 2);
+
+        assert_eq!(crate::time::os_time_dly(5), Err(OsError::SchedLocked));
+        assert_eq!(crate::task::os_task_suspend(None), Err(OsError::SchedLocked));
+        assert_eq!(crate::task::os_task_del(None), Err(OsError::SchedLocked));
+        assert_eq!(crate::task::os_task_sem_pend(0, opt::PEND_BLOCKING), Err(OsError::SchedLocked));
+        assert_eq!(crate::task::os_task_sem_pend(0, opt::PEND_NON_BLOCKING), Err(OsError::PendWouldBlock));
+
+        #[cfg(feature = "sem")]
+        {
+            let mut sem = crate::sync::sem::OsSem::new(0);
+            sem.create(0, "Locked").unwrap();
+            assert_eq!(sem.pend(0, opt::PEND_BLOCKING), Err(OsError::SchedLocked));
+            sem.post(opt::POST_FIFO).unwrap();
+            assert_eq!(sem.pend(0, opt::PEND_BLOCKING), Ok(0));
+            for _ in 0..3 {
+                sem.post(opt::POST_FIFO).unwrap();
+            }
+            assert_eq!(sem.pend(0, opt::PEND_BLOCKING | opt::PEND_CONSUME_ALL), Ok(3));
+            assert_eq!(sem.pend(0, opt::PEND_NON_BLOCKING | opt::PEND_CONSUME_ALL), Err(OsError::PendWouldBlock));
+            assert_eq!(crate::task::os_task_sem_pend(0, opt::PEND_CONSUME_ALL), Err(OsError::OptInvalid));
+        }
+
+        #[cfg(feature = "mutex")]
+        {
+            let mut mutex = crate::sync::mutex::OsMutex::new();
+            mutex.create("Locked").unwrap();
+            mutex.pend(0, opt::PEND_BLOCKING).unwrap();
+            critical_section(|cs| unsafe { CPU_STATE.get(cs).set_tcb_cur(Some(other)) });
+            assert_eq!(mutex.pend(0, opt::PEND_BLOCKING), Err(OsError::SchedLocked));
+            critical_section(|cs| unsafe { CPU_STATE.get(cs).set_tcb_cur(Some(cur)) });
+        }
+        let _ = other;
+
+        assert!(unsafe { cur.as_ref() }.is_ready());
+        os_sched_unlock().unwrap();
+        os_sched_unlock().unwrap();
+        assert!(!os_sched_is_locked());
+        os_deinit();
+    }
+
+    /// A coarse tick expires every deadline it passed, at most one coarse
+    /// period late, and dropping back to 1 loses no ticks
+    #[test]
+    #[cfg(not(feature = "tick-task"))]
+    fn test_tick_divider() {
+        use crate::time::{os_tick_handler, os_tick_rate_get, os_tick_rate_set, os_time_dly, os_time_get};
+
+        static TASKS: [TestTask; 4] = [const { TestTask::new() }; 4];
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        assert_eq!(os_tick_rate_set(10), Err(OsError::OsNotRunning));
+
+        let dlys: [OsTick; 4] = [3, 15, 27, 45];
+        let tasks = TASKS.each_ref().map(|task| task.create("Dly", 10));
+        KERNEL.set_running(true);
+        for (&t, &dly) in tasks.iter().zip(&dlys) {
+            fake_switch();
+            assert_eq!(unsafe { tcb_cur_ptr() }, Some(t));
+            os_time_dly(dly).unwrap();
+        }
+        fake_switch();
+
+        let mut fired = [None; 4];
+        let tick = |fired: &mut [Option<OsTick>; 4]| {
+            os_tick_handler();
+            for (f, t) in fired.iter_mut().zip(tasks) {
+                if f.is_none() && unsafe { t.as_ref() }.is_ready() {
+                    *f = Some(os_time_get());
+                }
+            }
+            os_time_get()
+        };
+
+        assert_eq!(os_tick_rate_set(0), Err(OsError::TickReloadInvalid));
+        os_tick_rate_set(10).unwrap();
+        // The period in progress still counts one tick
+        assert_eq!(os_tick_rate_get(), 1);
+        assert_eq!(tick(&mut fired), 1);
+        assert_eq!(os_tick_rate_get(), 10);
+        assert_eq!(tick(&mut fired), 11);
+        assert_eq!(tick(&mut fired), 21);
+
+        os_tick_rate_set(1).unwrap();
+        assert_eq!(tick(&mut fired), 31);
+        for expect in 32..=45 {
+            assert_eq!(tick(&mut fired), expect);
+        }
+
+        assert_eq!(fired, [Some(11), Some(21), Some(31), Some(45)]);
+        for (&dly, f) in dlys.iter().zip(fired) {
+            assert!(f.unwrap() - dly < 10);
+        }
+        os_deinit();
+    }
+
+    /// The tick hook sees the new tick and overruns are counted
+    #[test]
+    fn test_tick_hook() {
+        static CALLS: AtomicU32 = AtomicU32::new(0);
+        static SEEN: AtomicU32 = AtomicU32::new(0);
+
+        fn hook() {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            SEEN.store(crate::time::os_time_get(), Ordering::Relaxed);
+            // The timestamp advances by at least one per read
+            for _ in 0..10 {
+                core::hint::black_box(crate::time::os_ts_get());
+            }
+        }
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        os_tick_hook_set(hook);
+        crate::time::os_tick_handler();
+        assert_eq!((CALLS.load(Ordering::Relaxed), SEEN.load(Ordering::Relaxed)), (1, 1));
+        assert_eq!(os_tick_hook_overruns(), 0);
+
+        os_tick_hook_budget_set(3);
+        crate::time::os_tick_handler();
+        assert_eq!(os_tick_hook_overruns(), 1);
+
+        os_tick_hook_clr();
+        crate::time::os_tick_handler();
+        assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+        os_deinit();
+        assert_eq!(os_tick_hook_overruns(), 0);
+    }
+
+    /// The startup task helpers fail before starting anything
+    #[test]
+    fn test_start_helpers_fail_early() {
+        static APP: TestTask = TestTask::new();
+        static INIT: TestTask = TestTask::new();
+
+        let init = || unsafe { (&mut *INIT.tcb.get(), &mut *INIT.stk.get()) };
+        let _lock = test_lock();
+        let (tcb, stk) = init();
+        assert_eq!(os_start_with(tcb, stk, spin, 8), Err(OsError::OsNotInit));
+
+        os_init().unwrap();
+        let (tcb, stk) = init();
+        assert_eq!(os_start_with(tcb, stk, spin, crate::config::CFG_PRIO_IDLE), Err(OsError::PrioInvalid));
+        APP.create("App", 9);
+        let (tcb, stk) = init();
+        assert_eq!(os_run(tcb, stk, spin, 8), Err(OsError::OsAppTaskExists));
+        assert_eq!(unsafe { INIT.ptr().as_ref() }.obj_type, crate::types::OsObjType::None);
+        os_deinit();
+    }
+
+    /// The dump lists the idle task as current, ready and registered
+    #[test]
+    fn test_dump() {
+        struct Buf([u8; 1024], usize);
+
+        impl core::fmt::Write for Buf {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                let end = self.1 + s.len();
+                self.0.get_mut(self.1..end).ok_or(core::fmt::Error)?.copy_from_slice(s.as_bytes());
+                self.1 = end;
+                Ok(())
+            }
+        }
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        let mut buf = Buf([0; 1024], 0);
+        os_dump(&mut buf).unwrap();
+        let text = core::str::from_utf8(&buf.0[..buf.1]).unwrap();
+        assert!(text.starts_with("tick 0 sched_lock 0 int_nesting 0\n"));
+        assert!(text.lines().any(|l| l.starts_with("rdy") && l.ends_with(": Idle")));
+        assert!(text.lines().any(|l| l.starts_with("task Idle prio") && l.ends_with(" Ready")));
+        os_deinit();
+    }
+
+    /// The idle task runs on the application's storage when given one
+    #[test]
+    fn test_idle_storage() {
+        crate::os_idle_storage!(static BIG = 2 * CFG_IDLE_TASK_STK_SIZE;);
+
+        let _lock = test_lock();
+        os_init_with(&OsConfig { idle: Some(&BIG), ..OsConfig::default() }).unwrap();
+        let idle = idle_tcb_ptr();
+        assert_eq!(idle.as_ptr(), BIG.tcb());
+        assert_eq!(unsafe { idle.as_ref() }.stk_size, 2 * CFG_IDLE_TASK_STK_SIZE);
+        assert_eq!(unsafe { rdy_queues() }.list(crate::config::CFG_PRIO_IDLE).head(), Some(idle));
+        os_deinit();
+
+        // And on the kernel's again without
+        os_init().unwrap();
+        assert_eq!(idle_tcb_ptr().as_ptr(), IDLE_TCB.get());
+        assert_eq!(unsafe { idle_tcb_ptr().as_ref() }.stk_size, CFG_IDLE_TASK_STK_SIZE);
+        os_deinit();
+    }
+
+    /// Task IDs: idle has its own, created tasks count up, a recreated
+    /// task gets a new one and after a wrap live IDs are skipped
+    #[test]
+    #[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
+    fn test_task_ids() {
+        use crate::task::{current, os_task_del, os_task_info, TASK_ID_IDLE, TASK_ID_NONE};
+
+        static A: TestTask = TestTask::new();
+        static B: TestTask = TestTask::new();
+
+        let id = |tcb: NonNull<OsTcb>| unsafe { tcb.as_ref() }.id();
+        let (a, b) = (A.ptr(), B.ptr());
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        assert_eq!(id(idle_tcb_ptr()), TASK_ID_IDLE);
+        A.create("A", 5);
+        B.create("B", 6);
+        assert_eq!((id(a), id(b)), (2, 3));
+        assert_eq!(os_task_info(Some(b)).map(|info| info.id), Ok(3));
+        let entry = registry::os_obj_registry_iter().find(|e| e.name() == "A").unwrap();
+        assert_eq!(entry.task_id(), Some(2));
+
+        KERNEL.set_running(true);
+        fake_switch();
+        assert_eq!(current().map(|task| task.id()), Some(2));
+
+        os_task_del(Some(b)).unwrap();
+        assert_eq!(id(b), TASK_ID_NONE);
+        B.create("B", 6);
+        assert_eq!(id(b), 4);
+
+        // The last ID, then 2 is still A's
+        critical_section(|cs| SCHED.get(cs).next_task_id = OsTaskId::MAX);
+        os_task_del(Some(b)).unwrap();
+        B.create("B", 6);
+        assert_eq!(id(b), OsTaskId::MAX);
+        os_task_del(Some(b)).unwrap();
+        B.create("B", 6);
+        assert_eq!(id(b), 3);
+        os_deinit();
+
+        // `os_init` starts over
+        os_init().unwrap();
+        B.create("B", 6);
+        assert_eq!(id(b), 2);
+        os_deinit();
+    }
+
+    /// `os_post` reaches every object kind through its tag and checks the
+    /// tag against the object
+    #[test]
+    fn test_os_post() {
+        use crate::sync::{os_post, ObjRef, PostValue};
+        use crate::types::OsObjType;
+
+        static TASK: TestTask = TestTask::new();
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        let cur = TASK.create("Task", 5);
+        fake_switch();
+        KERNEL.set_running(true);
+
+        os_post(ObjRef::Task(cur), PostValue::Count(2), opt::POST_FIFO).unwrap();
+        assert_eq!(unsafe { cur.as_ref() }.sem_ctr, 2);
+        assert_eq!(os_post(ObjRef::Task(cur), PostValue::Signal, opt::POST_LIFO), Err(OsError::OptInvalid));
+
+        #[cfg(feature = "sem")]
+        {
+            let mut sem = crate::sync::sem::OsSem::new(0);
+            sem.create(0, "Post").unwrap();
+            let obj = ObjRef::Sem(NonNull::from(&mut sem));
+            os_post(obj, PostValue::Signal, opt::POST_FIFO).unwrap();
+            os_post(obj, PostValue::Count(3), opt::POST_NO_SCHED).unwrap();
+            assert_eq!(sem.count(), 4);
+
+            // A semaphore tagged as a task or a barrier tag is refused
+            let wrong = unsafe { ObjRef::from_raw(OsObjType::Task, &sem as *const _ as *const ()) }.unwrap();
+            assert_eq!(os_post(wrong, PostValue::Signal, opt::POST_FIFO), Err(OsError::ObjType));
+            let barrier = unsafe { ObjRef::from_raw(OsObjType::Barrier, &sem as *const _ as *const ()) };
+            assert_eq!(barrier, Err(OsError::ObjType));
+            assert_eq!(unsafe { ObjRef::from_raw(OsObjType::Sem, core::ptr::null()) }, Err(OsError::ObjPtrNull));
+        }
+
+        #[cfg(feature = "mutex")]
+        {
+            let mut mutex = crate::sync::mutex::OsMutex::new();
+            mutex.create("Post").unwrap();
+            mutex.pend(0, opt::PEND_BLOCKING).unwrap();
+            let obj = ObjRef::Mutex(NonNull::from(&mut mutex));
+            assert_eq!(os_post(obj, PostValue::Count(2), opt::POST_FIFO), Err(OsError::ObjType));
+            os_post(obj, PostValue::Signal, opt::POST_FIFO).unwrap();
+            assert!(!mutex.is_owned());
+            assert_eq!(os_post(obj, PostValue::Signal, opt::POST_FIFO), Err(OsError::MutexNotOwner));
+
+            #[cfg(feature = "sem")]
+            {
+                let wrong = unsafe { ObjRef::from_raw(OsObjType::Sem, &mutex as *const _ as *const ()) }.unwrap();
+                assert_eq!(os_post(wrong, PostValue::Signal, opt::POST_FIFO), Err(OsError::ObjType));
+            }
+        }
+
+        #[cfg(feature = "rwlock")]
+        {
+            let mut lock = crate::sync::rwlock::OsRwLock::new();
+            lock.create("Post").unwrap();
+            let obj = ObjRef::RwLock(NonNull::from(&mut lock));
+            lock.write_lock(0, opt::PEND_BLOCKING).unwrap();
+            os_post(obj, PostValue::Signal, opt::POST_FIFO).unwrap();
+            assert!(!lock.is_write_locked());
+            lock.read_lock(0, opt::PEND_BLOCKING).unwrap();
+            os_post(obj, PostValue::Signal, opt::POST_FIFO).unwrap();
+            assert_eq!(lock.readers(), 0);
+        }
+
+        // A deleted task no longer takes posts
+        crate::task::os_task_del(Some(cur)).unwrap();
+        assert_eq!(unsafe { cur.as_ref() }.obj_type, OsObjType::None);
+        assert_eq!(os_post(ObjRef::Task(cur), PostValue::Signal, opt::POST_FIFO), Err(OsError::ObjType));
+        os_deinit();
+    }
+
+    /// Budgets: a run past its budget is reported by the tick or at
+    /// switch-out, a gap past the period by the tick, each once; only
+    /// tasks with a period are scanned
+    #[test]
+    #[cfg(feature = "budget")]
+    fn test_budget() {
+        use crate::task::budget::{budget_switch_at, budget_tick_at};
+        use crate::task::{os_task_set_budget, OsBudgetLimit, TaskHandle};
+
+        static A: TestTask = TestTask::new();
+        static B: TestTask = TestTask::new();
+        static TRIPS: CsCell<([(*const OsTcb, OsBudgetLimit); 4], usize)> =
+            CsCell::new(([(core::ptr::null(), OsBudgetLimit::Run); 4], 0));
+
+        fn record(task: TaskHandle, limit: OsBudgetLimit) {
+            critical_section(|cs| {
+                let (log, n) = TRIPS.get(cs);
+                log[*n] = (task.as_ptr().as_ptr(), limit);
+                *n += 1;
+            });
+        }
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        hooks::os_app_hooks_set(hooks::OsAppHooks { budget_overrun: Some(record), ..hooks::OsAppHooks::new() });
+        let (a, b) = (A.create("A", 5).as_ptr(), B.create("B", 6).as_ptr());
+        let per_tick = crate::time::os_ts_freq_hz() / cfg().tick_rate_hz;
+
+        assert_eq!(os_task_set_budget(None, 2, 0), Err(OsError::TcbInvalid));
+        os_task_set_budget(NonNull::new(a), 2, 0).unwrap();
+        os_task_set_budget(NonNull::new(b), 0, 10).unwrap();
+        assert_eq!(unsafe { (*b).budget_next_ptr }, None);
+
+        critical_section(|cs| unsafe {
+            CPU_STATE.get(cs).tcb_cur = a;
+            budget_switch_at(core::ptr::null_mut(), a, 0, 1);
+            // Two ticks of run is within A's budget
+            budget_switch_at(a, b, 2 * per_tick, 3);
+            budget_switch_at(b, a, 2 * per_tick + 10, 4);
+            // A runs on through the tick past its budget
+            budget_tick_at(5 * per_tick + 10, 7);
+            budget_tick_at(6 * per_tick + 10, 8);
+            // B has not run for its period
+            budget_tick_at(13 * per_tick, 15);
+            budget_tick_at(14 * per_tick, 16);
+            budget_switch_at(a, b, 14 * per_tick, 16);
+        });
+        critical_section(|cs| {
+            let (log, n) = *TRIPS.get(cs);
+            assert_eq!(log[..n], [(a as *const OsTcb, OsBudgetLimit::Run), (b as *const OsTcb, OsBudgetLimit::Period)]);
+        });
+
+        // A new run and a new gap are reported again
+        critical_section(|cs| unsafe {
+            CPU_STATE.get(cs).tcb_cur = b;
+            budget_switch_at(b, a, 15 * per_tick, 17);
+            CPU_STATE.get(cs).tcb_cur = a;
+            budget_tick_at(20 * per_tick, 27);
+            budget_tick_at(21 * per_tick, 28);
+        });
+        critical_section(|cs| {
+            let (log, n) = *TRIPS.get(cs);
+            assert_eq!(log[2..n], [(a as *const OsTcb, OsBudgetLimit::Run), (b as *const OsTcb, OsBudgetLimit::Period)]);
+        });
+
+        // A task leaves the scan when it is deleted or its period is
+        // cleared
+        os_task_set_budget(NonNull::new(a), 0, 5).unwrap();
+        assert_eq!(unsafe { (*a).budget_next_ptr }, NonNull::new(b));
+        crate::task::os_task_del(NonNull::new(b)).unwrap();
+        assert_eq!(unsafe { (*a).budget_next_ptr }, None);
+        os_task_set_budget(NonNull::new(a), 0, 0).unwrap();
+        critical_section(|cs| unsafe {
+            budget_tick_at(40 * per_tick, 60);
+            assert_eq!(TRIPS.get(cs).1, 4);
+        });
+        os_deinit();
+    }
+
+    /// The calendar clock follows the ticks at any tick divider, slews
+    /// with the rate adjustment and keeps the RTC in step
+    #[test]
+    #[cfg(all(feature = "clock", not(any(target_arch = "arm", target_arch = "riscv32"))))]
+    fn test_clock() {
+        use core::sync::atomic::AtomicU64;
+
+        use crate::config::{CFG_CLOCK_ADJ_MAX_PPM, CFG_CLOCK_RTC_SYNC_SECS};
+        use crate::time::{clock, os_tick_handler, os_tick_rate_set, os_time_get};
+
+        struct MockRtc(AtomicU64);
+
+        impl clock::Rtc for MockRtc {
+            fn read(&self) -> Option<u64> {
+                Some(self.0.load(Ordering::Relaxed))
+            }
+
+            fn write(&self, unix_secs: u64) {
+                self.0.store(unix_secs, Ordering::Relaxed);
+            }
+        }
+
+        static RTC: MockRtc = MockRtc(AtomicU64::new(1_700_000_000));
+
+        let _lock = test_lock();
+        clock::rtc_set(Some(&RTC));
+        os_init().unwrap();
+        assert_eq!(clock::now(), (1_700_000_000, 0));
+        KERNEL.set_running(true);
+
+        let hz = cfg().tick_rate_hz;
+        let expect = |ticks: OsTick| {
+            let ms = ticks as u64 * 1000 / hz as u64;
+            (1_700_000_000 + ms / 1000, (ms % 1000) as u16)
+        };
+        for _ in 0..hz * 3 / 2 {
+            os_tick_handler();
+        }
+        assert_eq!(clock::now(), expect(os_time_get()));
+
+        // Ten ticks per interrupt
+        os_tick_rate_set(10).unwrap();
+        for _ in 0..7 {
+            os_tick_handler();
+        }
+        assert_eq!(clock::now(), expect(os_time_get()));
+        os_tick_rate_set(1).unwrap();
+        os_tick_handler();
+        assert_eq!(clock::now(), expect(os_time_get()));
+
+        // Written back on crossing a multiple of the sync period
+        if CFG_CLOCK_RTC_SYNC_SECS != 0 {
+            let sync = CFG_CLOCK_RTC_SYNC_SECS as u64 * 500_000;
+            clock::set(sync - 1);
+            assert_eq!(RTC.0.load(Ordering::Relaxed), sync - 1);
+            for _ in 0..hz {
+                os_tick_handler();
+            }
+            assert_eq!(RTC.0.load(Ordering::Relaxed), sync);
+        }
+
+        // 100 ppm slow loses a millisecond in ten seconds
+        assert_eq!(clock::adjust_ppm(CFG_CLOCK_ADJ_MAX_PPM as i32 + 1), Err(OsError::ClockAdjInvalid));
+        clock::adjust_ppm(-100).unwrap();
+        clock::set(1_800_000_000);
+        for _ in 0..hz * 10 {
+            os_tick_handler();
+        }
+        assert_eq!(clock::now(), (1_800_000_009, 999));
+        os_deinit();
+
+        // Without an RTC the clock starts from the epoch
+        clock::rtc_set(None);
+        os_init().unwrap();
+        assert_eq!(clock::now(), (0, 0));
+        os_deinit();
+    }
+
+    /// The services keep the documented pre-start contract before
+    /// `os_init`, between `os_init` and `os_start`, and after start
+    #[test]
+    #[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
+    fn test_pre_start_contract() {
+        use crate::sched::os_sched_yield;
+        use crate::task::{
+            os_task_del, os_task_info_self, os_task_resume, os_task_sem_pend, os_task_sem_post, os_task_suspend,
+            os_task_time_quanta_set,
+        };
+        use crate::time::{os_tick_rate_set, os_time_dly, os_time_dly_resume};
+        use crate::types::OsObjType;
+
+        static APP: TestTask = TestTask::new();
+
+        let create = || {
+            crate::task::os_task_create_stk(unsafe { &mut *APP.tcb.get() }, unsafe { &mut *APP.stk.get() }, "App", spin, 5)
+        };
+        let app = APP.ptr();
+
+        // Services that need a running kernel, checked in both phases
+        // before the start
+        let not_running = || {
+            assert_eq!(os_time_dly(1), Err(OsError::OsNotRunning));
+            assert_eq!(os_sched_lock(), Err(OsError::OsNotRunning));
+            assert_eq!(os_sched_unlock(), Err(OsError::OsNotRunning));
+            assert_eq!(os_sched_yield(), Err(OsError::OsNotRunning));
+            assert_eq!(os_tick_rate_set(2), Err(OsError::OsNotRunning));
+            assert_eq!(os_task_sem_pend(0, opt::PEND_BLOCKING), Err(OsError::OsNotRunning));
+            assert_eq!(os_task_suspend(Some(app)), Err(OsError::OsNotRunning));
+            assert_eq!(os_task_resume(app), Err(OsError::OsNotRunning));
+            assert_eq!(os_task_del(Some(app)), Err(OsError::OsNotRunning));
+            assert_eq!(os_time_dly_resume(app), Err(OsError::OsNotRunning));
+            assert_eq!(os_task_info_self().err(), Some(OsError::TcbInvalid));
+            assert_eq!(os_task_time_quanta_set(None, 5), Err(OsError::TcbInvalid));
+
+            #[cfg(feature = "sem")]
+            {
+                let mut sem = crate::sync::sem::OsSem::new(0);
+                sem.create(0, "Early").unwrap();
+                assert_eq!(sem.post(opt::POST_FIFO), Ok(crate::sync::sem::PostResult::Count(1)));
+                assert_eq!(sem.post(opt::POST_FIFO), Ok(crate::sync::sem::PostResult::Count(2)));
+                assert_eq!(sem.pend(0, opt::PEND_NON_BLOCKING), Err(OsError::OsNotRunning));
+                sem.set(4).unwrap();
+                assert_eq!(sem.accept(), Ok(4));
+            }
+
+            #[cfg(feature = "mutex")]
+            {
+                let mut mutex = crate::sync::mutex::OsMutex::new();
+                mutex.create("Early").unwrap();
+                assert_eq!(mutex.pend(0, opt::PEND_BLOCKING), Err(OsError::OsNotRunning));
+                assert_eq!(mutex.post(opt::POST_FIFO), Err(OsError::OsNotRunning));
+                assert!(!mutex.is_owned());
+            }
+
+            #[cfg(feature = "rwlock")]
+            {
+                let mut lock = crate::sync::rwlock::OsRwLock::new();
+                lock.create("Early").unwrap();
+                assert_eq!(lock.read_lock(0, opt::PEND_BLOCKING), Err(OsError::OsNotRunning));
+                assert_eq!(lock.write_lock(0, opt::PEND_BLOCKING), Err(OsError::OsNotRunning));
+                assert_eq!(lock.read_unlock(opt::POST_FIFO), Err(OsError::RwLockNotLocked));
+                assert_eq!(lock.write_unlock(opt::POST_FIFO), Err(OsError::OsNotRunning));
+            }
+
+            #[cfg(feature = "barrier")]
+            {
+                let mut barrier = crate::sync::barrier::OsBarrier::new(2);
+                barrier.create(2, "Early").unwrap();
+                assert_eq!(barrier.wait(0, opt::PEND_BLOCKING), Err(OsError::OsNotRunning));
+                assert_eq!(barrier.reset(), Ok(0));
+            }
+        };
+
+        let _lock = test_lock();
+
+        // Before `os_init`, which would drop a task created now
+        assert!(!KERNEL.is_initialized());
+        assert_eq!(create(), Err(OsError::OsNotInit));
+        assert_eq!(unsafe { app.as_ref() }.obj_type, OsObjType::None);
+        assert_eq!(os_start(), Err(OsError::OsNotInit));
+        not_running();
+
+        // Between `os_init` and `os_start`: posts to the task count up
+        os_init().unwrap();
+        create().unwrap();
+        not_running();
+        assert_eq!(os_task_sem_post(app, opt::POST_FIFO), Ok(1));
+        assert_eq!(os_task_sem_post(app, opt::POST_NO_SCHED), Ok(2));
+        os_task_time_quanta_set(Some(app), 5).unwrap();
+        assert!(unsafe { app.as_ref() }.is_ready());
+
+        // After the start, with the task running
+        KERNEL.set_running(true);
+        fake_switch();
+        assert_eq!(os_task_sem_pend(0, opt::PEND_NON_BLOCKING), Ok(1));
+        assert_eq!(os_task_sem_pend(0, opt::PEND_NON_BLOCKING), Ok(0));
+        assert_eq!(os_task_sem_pend(0, opt::PEND_NON_BLOCKING), Err(OsError::PendWouldBlock));
+        assert_eq!(os_start(), Err(OsError::OsRunning));
+        os_sched_lock().unwrap();
+        os_sched_unlock().unwrap();
+        assert_eq!(os_task_info_self().map(|info| info.prio), Ok(5));
+
+        #[cfg(feature = "mutex")]
+        {
+            let mut mutex = crate::sync::mutex::OsMutex::new();
+            mutex.create("Late").unwrap();
+            mutex.pend(0, opt::PEND_BLOCKING).unwrap();
+            mutex.post(opt::POST_FIFO).unwrap();
+        }
+        os_deinit();
+    }
+
+    /// The stub port logs the switches asked for; the test performs them
+    #[test]
+    #[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
+    fn test_stub_port_switches() {
+        use crate::config::CFG_STK_CHK_EN;
+        use crate::port::stub::{take_events, PortEvent, OS_CTX_FRAME_WORDS, OS_STK_ALIGN};
+        use crate::types::OsStkElement;
+
+        static A: TestTask = TestTask::new();
+        static B: TestTask = TestTask::new();
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        let idle = idle_tcb_ptr().as_ptr() as *const OsTcb;
+        let (a, b) = (A.ptr().as_ptr() as *const OsTcb, B.ptr().as_ptr() as *const OsTcb);
+        let _ = take_events();
+
+        // Creating a higher priority task asks for a switch to it
+        A.create("A", 5);
+        assert_eq!(*take_events(), [PortEvent::CtxSw { to: a }]);
+        fake_switch();
+
+        // The frame ends at the aligned top with the entry point as PC
+        let top = unsafe { (*A.stk.get()).as_ptr_range().end };
+        let stk_ptr = unsafe { (*a).stk_ptr } as *const OsStkElement;
+        assert_eq!(top as usize % OS_STK_ALIGN, 0);
+        assert_eq!(unsafe { top.offset_from(stk_ptr) }, OS_CTX_FRAME_WORDS as isize);
+        assert_eq!(unsafe { *stk_ptr.add(15) }, spin as crate::task::OsTaskFn as usize as OsStkElement | 1);
+        if CFG_STK_CHK_EN {
+            let (_, used) = crate::task::os_task_stk_chk(Some(A.ptr())).unwrap();
+            assert_eq!(used, OS_CTX_FRAME_WORDS + 1);
+        }
+
+        // A delay hands over to idle and expires from the tick interrupt
+        crate::time::os_time_dly(2).unwrap();
+        assert_eq!(*take_events(), [PortEvent::CtxSw { to: idle }]);
+        fake_switch();
+        crate::time::os_tick_handler();
+        assert!(take_events().is_empty());
+        crate::time::os_tick_handler();
+        assert_eq!(*take_events(), [PortEvent::IntCtxSw { to: a }]);
+        fake_switch();
+
+        // A peer waits for the slice to run out, then the tick rotates
+        B.create("B", 5);
+        assert!(take_events().is_empty());
+        crate::sched::os_sched_rr_enable_prio(5, true).unwrap();
+        crate::task::os_task_time_quanta_set(None, 1).unwrap();
+        crate::time::os_tick_handler();
+        let events = take_events();
+        assert_eq!(events.last(), Some(&PortEvent::IntCtxSw { to: b }));
+        assert!(events.iter().all(|e| matches!(e, PortEvent::CtxSw { to } | PortEvent::IntCtxSw { to } if *to == b)));
+        fake_switch();
+        assert_eq!(unsafe { rdy_queues() }.highest_tcb().map(|(_, t)| t.as_ptr() as *const OsTcb), Some(b));
+        os_deinit();
+    }
+
+    /// Waiters leave through the shared pend path: post, timeout, suspension
+    #[test]
+    #[cfg(all(feature = "sem", not(any(target_arch = "arm", target_arch = "riscv32"))))]
+    fn test_pend_path_exits() {
+        use crate::config::CFG_PRIO_IDLE;
+        use crate::port::stub::{take_events, PortEvent};
+        use crate::sync::sem::OsSem;
+        use crate::time::os_tick_handler;
+        use crate::types::OsTaskState;
+
+        static T: TestTask = TestTask::new();
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        let idle = idle_tcb_ptr().as_ptr() as *const OsTcb;
+        let t = T.create("T", 5);
+        let to_t = t.as_ptr() as *const OsTcb;
+        fake_switch();
+        let mut sem = OsSem::new(0);
+        sem.create(0, "Pend").unwrap();
+        let state = || unsafe { t.as_ref() }.state();
+        let _ = take_events();
+
+        // The stub does not switch, so `pend` returns at once; the
+        // waiter's state shows where it is
+        let _ = sem.pend(3, opt::PEND_BLOCKING);
+        assert_eq!((state(), sem.waiters()), (OsTaskState::PendTimeout, 1));
+        assert_eq!(*take_events(), [PortEvent::CtxSw { to: idle }]);
+        fake_switch();
+
+        // A post readies the waiter and takes it off the tick queue
+        sem.post(opt::POST_FIFO).unwrap();
+        assert_eq!((state(), sem.waiters()), (OsTaskState::Ready, 0));
+        assert_eq!(*take_events(), [PortEvent::CtxSw { to: to_t }]);
+        fake_switch();
+        for _ in 0..4 {
+            os_tick_handler();
+        }
+        assert!(take_events().is_empty());
+        assert_eq!(unsafe { t.as_ref() }.pend_result(), Ok(()));
+
+        // A timeout unlinks the waiter from the semaphore
+        let _ = sem.pend(2, opt::PEND_BLOCKING);
+        fake_switch();
+        os_tick_handler();
+        os_tick_handler();
+        assert_eq!((state(), sem.waiters()), (OsTaskState::Ready, 0));
+        assert_eq!(unsafe { t.as_ref() }.pend_result(), Err(OsError::Timeout));
+        assert_eq!(take_events().last(), Some(&PortEvent::IntCtxSw { to: to_t }));
+        fake_switch();
+
+        // A suspended waiter that is posted or times out stays suspended
+        for timeout in [0, 2] {
+            let _ = sem.pend(timeout, opt::PEND_BLOCKING);
+            fake_switch();
+            crate::task::os_task_suspend(Some(t)).unwrap();
+            if timeout == 0 {
+                sem.post(opt::POST_FIFO).unwrap();
+            } else {
+                os_tick_handler();
+                os_tick_handler();
+            }
+            assert_eq!((state(), sem.waiters()), (OsTaskState::Suspended, 0));
+            assert_eq!(unsafe { rdy_queues() }.highest_tcb().map(|(prio, _)| prio), Some(CFG_PRIO_IDLE));
+
+            crate::task::os_task_resume(t).unwrap();
+            assert_eq!(state(), OsTaskState::Ready);
+            assert_eq!(take_events().last(), Some(&PortEvent::CtxSw { to: to_t }));
+            fake_switch();
+        }
+        os_deinit();
+    }
+
+    /// A pend reads its result only once the switch it asked for has run
+    /// and switched back to it, as on a port where the switch is taken
+    /// when interrupts are unmasked
+    #[test]
+    #[cfg(all(feature = "sem", not(any(target_arch = "arm", target_arch = "riscv32"))))]
+    fn test_pend_waits_for_switch() {
+        use crate::port::stub::{switch_handler_set, take_events, PortEvent};
+        use crate::sync::sem::OsSem;
+        use crate::time::os_tick_handler;
+
+        static T: TestTask = TestTask::new();
+
+        // Away from the waiter, ticks run until its timeout readies it
+        // and asks to switch back
+        fn pend_sv() {
+            fake_switch();
+            while !unsafe { T.ptr().as_ref() }.is_ready() {
+                os_tick_handler();
+            }
+        }
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        let idle = idle_tcb_ptr().as_ptr() as *const OsTcb;
+        let t = T.create("T", 5).as_ptr();
+        fake_switch();
+        let mut sem = OsSem::new(0);
+        sem.create(0, "Deferred").unwrap();
+        let _ = take_events();
+
+        switch_handler_set(Some(pend_sv));
+        assert_eq!(sem.pend(2, opt::PEND_BLOCKING), Err(OsError::Timeout));
+        assert_eq!(*take_events(), [PortEvent::CtxSw { to: idle }, PortEvent::IntCtxSw { to: t }]);
+        switch_handler_set(None);
+        assert_eq!((KERNEL.tick_get(), unsafe { CPU_STATE.get_unchecked() }.tcb_cur), (2, t));
+        os_deinit();
+    }
+
+    /// Freeing a pooled object readies its waiters with `ObjDel` and stales
+    /// every copy of its handle
+    #[test]
+    #[cfg(all(feature = "obj-pool", not(any(target_arch = "arm", target_arch = "riscv32"))))]
+    fn test_pool_free_wakes_waiters() {
+        use crate::config::CFG_SEM_POOL_SIZE;
+        use crate::sync::{mutex, sem};
+        use crate::time::os_tick_handler;
+        use crate::types::{OsTaskState, Timeout};
+
+        static LOW: TestTask = TestTask::new();
+        static HIGH: TestTask = TestTask::new();
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        let tcb = |t: NonNull<OsTcb>| unsafe { &*t.as_ptr() };
+        let (low, high) = (LOW.ptr(), HIGH.ptr());
+        LOW.create("Low", 5);
+        fake_switch();
+
+        // The owner loses the boost its waiter gave it
+        let m = mutex::alloc().unwrap();
+        m.lock(Timeout::Forever).unwrap();
+        HIGH.create("High", 3);
+        fake_switch();
+        let _ = m.lock(Timeout::Forever);
+        assert_eq!((tcb(high).state(), tcb(low).prio()), (OsTaskState::Pend, 3));
+        fake_switch();
+        assert_eq!(mutex::free(m), Ok(1));
+        assert_eq!((tcb(high).state(), tcb(low).prio()), (OsTaskState::Ready, 5));
+        assert_eq!(tcb(high).pend_result(), Err(OsError::ObjDel));
+        fake_switch();
+        assert_eq!(m.lock(Timeout::Forever), Err(OsError::ObjDel));
+        assert_eq!(mutex::free(m), Err(OsError::ObjDel));
+
+        // A timed waiter leaves the tick queue too
+        let s = sem::alloc().unwrap();
+        let _ = s.wait(Timeout::Ticks(4));
+        assert_eq!(tcb(high).state(), OsTaskState::PendTimeout);
+        fake_switch();
+        assert_eq!(sem::free(s), Ok(1));
+        fake_switch();
+        for _ in 0..5 {
+            os_tick_handler();
+        }
+        assert_eq!((tcb(high).state(), tcb(high).pend_result()), (OsTaskState::Ready, Err(OsError::ObjDel)));
+        assert_eq!(s.signal(), Err(OsError::ObjDel));
+
+        let again = sem::alloc().unwrap();
+        assert_ne!(again, s);
+        assert_eq!(again.signal(), Ok(sem::PostResult::Count(1)));
+        let mut allocated = 1;
+        while sem::alloc().is_ok() {
+            allocated += 1;
+        }
+        assert_eq!(allocated, CFG_SEM_POOL_SIZE);
+        assert_eq!(sem::alloc(), Err(OsError::ObjPoolEmpty));
+        os_deinit();
+
+        os_init().unwrap();
+        assert_eq!(again.count(), Err(OsError::ObjDel));
+        os_deinit();
+    }
+
+    /// The self-check passes on a healthy kernel and names each broken
+    /// invariant it is given
+    #[test]
+    #[cfg(all(feature = "self-check", not(any(target_arch = "arm", target_arch = "riscv32"))))]
+    fn test_self_check_invariants() {
+        use crate::selfcheck::{os_self_check, Invariant};
+        use crate::types::OsTaskState;
+
+        static A: TestTask = TestTask::new();
+        static B: TestTask = TestTask::new();
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        let a = A.create("A", 5).as_ptr();
+        let b = B.create("B", 5).as_ptr();
+        fake_switch();
+        assert!(os_self_check().is_ok());
+
+        let invariant = || os_self_check().unwrap_err().invariant;
+
+        // A broken back link
+        let prev = unsafe { (*b).prev_ptr.take() };
+        let report = os_self_check().unwrap_err();
+        assert_eq!((report.invariant, report.tcb, report.prio), (Invariant::ReadyList, NonNull::new(b), Some(5)));
+        unsafe { (*b).prev_ptr = prev };
+
+        // A task queued under a priority it no longer has
+        unsafe { (*a).prio = 6 };
+        assert_eq!(invariant(), Invariant::ReadyMember);
+        unsafe { (*a).prio = 5 };
+
+        // A delayed task the tick queue does not hold
+        crate::task::os_task_suspend(NonNull::new(b)).unwrap();
+        assert!(os_self_check().is_ok());
+        unsafe { (*b).task_state = OsTaskState::Delayed };
+        assert_eq!(invariant(), Invariant::TickMember);
+        unsafe { (*b).task_state = OsTaskState::Suspended };
+
+        // A running task that was never created
+        let mut stray = OsTcb::new();
+        let cur = critical_section(|cs| core::mem::replace(&mut CPU_STATE.get(cs).tcb_cur, &mut stray));
+        assert_eq!(invariant(), Invariant::TcbCur);
+        critical_section(|cs| CPU_STATE.get(cs).tcb_cur = cur);
+        assert!(os_self_check().is_ok());
+        os_deinit();
+    }
+
+    /// Switch requests: a decision made while a switch is pending does not
+    /// pend another, and a round-robin rotation never overrides a higher
+    /// priority task readied first
+    #[test]
+    #[cfg(all(feature = "sem", not(any(target_arch = "arm", target_arch = "riscv32"))))]
+    fn test_switch_requests() {
+        use crate::port::stub::{take_events, PortEvent};
+        use crate::sync::sem::OsSem;
+        use crate::time::os_tick_handler;
+
+        static A: TestTask = TestTask::new();
+        static B: TestTask = TestTask::new();
+        static H: TestTask = TestTask::new();
+
+        // Every switch taken here was asked for
+        let switch = || {
+            assert!(critical_section(|cs| CPU_STATE.get(cs).sw_pending));
+            fake_switch();
+        };
+        let high_rdy = || critical_section(|cs| high_rdy_next(cs)) as *const OsTcb;
+        let [a, b, h] = [&A, &B, &H].map(|t| t.ptr().as_ptr() as *const OsTcb);
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        crate::sched::os_sched_rr_enable_prio(5, true).unwrap();
+        for (task, name) in [(&A, "A"), (&B, "B")] {
+            let tcb = task.create(name, 5);
+            crate::task::os_task_time_quanta_set(Some(tcb), 1).unwrap();
+        }
+        H.create("H", 4);
+        let mut sem = OsSem::new(0);
+        sem.create(0, "Switch").unwrap();
+        switch();
+        let _ = take_events();
+
+        // H blocks; the switch to A stays pending while H is posted
+        // from an interrupt and runs H without a new request
+        let _ = sem.pend(0, opt::PEND_BLOCKING);
+        assert_eq!(high_rdy(), a);
+        os_int_enter();
+        sem.post(opt::POST_FIFO).unwrap();
+        os_int_exit();
+        assert_eq!(*take_events(), [PortEvent::CtxSw { to: a }]);
+        assert_eq!(high_rdy(), h);
+        switch();
+        assert_eq!(unsafe { CPU_STATE.get_unchecked() }.prio_cur, 4);
+
+        // A runs and is rotated by the tick; the rotation is one request
+        let _ = sem.pend(0, opt::PEND_BLOCKING);
+        switch();
+        assert_eq!(take_events().len(), 1);
+        os_tick_handler();
+        assert_eq!(*take_events(), [PortEvent::IntCtxSw { to: b }]);
+        switch();
+
+        // B posts H, then a tick rotates B before the switch runs: the
+        // rotation keeps H as the target and asks for nothing more
+        sem.post(opt::POST_FIFO).unwrap();
+        assert_eq!(*take_events(), [PortEvent::CtxSw { to: h }]);
+        os_tick_handler();
+        assert!(take_events().is_empty());
+        assert_eq!(high_rdy(), h);
+        assert_eq!(unsafe { rdy_queues() }.list(5).head().map(|t| t.as_ptr() as *const OsTcb), Some(a));
+        switch();
+        assert_eq!(unsafe { CPU_STATE.get_unchecked() }.tcb_cur as *const OsTcb, h);
+        os_deinit();
+    }
+
+    /// A post racing a timeout: before the timed-out waiter runs the post
+    /// wins and the pend returns `Ok`; once it ran, the timeout stands
+    #[test]
+    #[cfg(all(feature = "sem", not(any(target_arch = "arm", target_arch = "riscv32"))))]
+    fn test_post_races_timeout() {
+        use crate::port::stub::switch_handler_set;
+        use crate::sync::sem::{OsSem, PostResult};
+        use crate::time::os_tick_handler;
+
+        static T: TestTask = TestTask::new();
+        static U: TestTask = TestTask::new();
+        static SEM: SyncUnsafeCell<OsSem> = SyncUnsafeCell::new(OsSem::new(0));
+        static POST_LATE: AtomicBool = AtomicBool::new(false);
+
+        // Away from the waiter its timeout runs out; a post may land
+        // before the switch back to it
+        fn pend_sv() {
+            fake_switch();
+            let t = unsafe { T.ptr().as_ref() };
+            if !t.is_ready() {
+                while !t.is_ready() {
+                    os_tick_handler();
+                }
+                if POST_LATE.load(Ordering::Relaxed) {
+                    assert_eq!(unsafe { &mut *SEM.get() }.post(opt::POST_FIFO), Ok(PostResult::WokeTask(5)));
+                }
+            }
+        }
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        let t = T.create("T", 5);
+        fake_switch();
+        let sem = unsafe { &mut *SEM.get() };
+        sem.create(0, "Race").unwrap();
+
+        // The timeout readies the waiter, a post lands before it runs
+        switch_handler_set(Some(pend_sv));
+        POST_LATE.store(true, Ordering::Relaxed);
+        assert_eq!(sem.pend(2, opt::PEND_BLOCKING), Ok(0));
+        assert_eq!(sem.count(), 0);
+
+        // The waiter runs first: it keeps its timeout and the post counts
+        POST_LATE.store(false, Ordering::Relaxed);
+        assert_eq!(sem.pend(2, opt::PEND_BLOCKING), Err(OsError::Timeout));
+        switch_handler_set(None);
+        assert_eq!(sem.post(opt::POST_FIFO), Ok(PostResult::Count(1)));
+        assert_eq!(sem.accept(), Ok(1));
+
+        // A waiting task comes before a late one; the late one keeps
+        // its timeout if the only post went to the other
+        let _ = sem.pend(1, opt::PEND_BLOCKING);
+        fake_switch();
+        os_tick_handler();
+        let u = U.create("U", 4);
+        fake_switch();
+        let _ = sem.pend(0, opt::PEND_BLOCKING);
+        assert_eq!(sem.post(opt::POST_FIFO), Ok(PostResult::WokeTask(4)));
+        assert_eq!(unsafe { u.as_ref() }.pend_result(), Ok(()));
+        assert_eq!(unsafe { t.as_ref() }.pend_result(), Err(OsError::Timeout));
+
+        // Deleting a late waiter takes it out of reach
+        crate::task::os_task_del(Some(t)).unwrap();
+        assert_eq!(sem.post(opt::POST_FIFO), Ok(PostResult::Count(1)));
+        os_deinit();
+    }
+
+    /// A deletion request wakes a `TASK_DEL_INTERRUPT` task, which hands its
+    /// mutex on and exits; without the option it only sets the flag
+    #[test]
+    #[cfg(all(feature = "sem", feature = "mutex", not(any(target_arch = "arm", target_arch = "riscv32"))))]
+    fn test_del_request() {
+        use crate::sync::mutex::OsMutex;
+        use crate::sync::pend_resume;
+        use crate::sync::sem::OsSem;
+        use crate::task::{delete_requested, os_task_del, os_task_del_request};
+
+        static T: TestTask = TestTask::new();
+        static W: TestTask = TestTask::new();
+
+        let cur = || unsafe { tcb_cur_ptr() };
+        let resume = |t: NonNull<OsTcb>| critical_section(|_cs| unsafe { pend_resume(t) });
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        let t = T.create_opt("T", 5, opt::TASK_DEL_INTERRUPT);
+        let w = W.create("W", 6);
+        let mut mutex = OsMutex::new();
+        mutex.create("Held").unwrap();
+        let mut sem = OsSem::new(0);
+        sem.create(0, "Work").unwrap();
+
+        // T takes the mutex and waits for work, W waits for the mutex
+        fake_switch();
+        assert_eq!(cur(), Some(t));
+        mutex.pend(0, opt::PEND_BLOCKING).unwrap();
+        let _ = sem.pend(5, opt::PEND_BLOCKING);
+        fake_switch();
+        assert_eq!(cur(), Some(w));
+        let _ = mutex.pend(0, opt::PEND_BLOCKING);
+        fake_switch();
+        assert_eq!(cur(), Some(idle_tcb_ptr()));
+        assert!(!delete_requested());
+        assert_eq!(os_task_del_request(idle_tcb_ptr()), Err(OsError::TaskDelIdle));
+
+        // The request takes T off the semaphore and the tick queue
+        os_task_del_request(t).unwrap();
+        assert!(unsafe { t.as_ref() }.is_ready());
+        assert_eq!(sem.waiters(), 0);
+        assert_eq!(resume(t), Err(OsError::TaskDelRequested));
+        fake_switch();
+        assert_eq!(cur(), Some(t));
+        assert!(delete_requested());
+
+        // Blocking calls fail from now on, non-blocking ones still work
+        assert_eq!(sem.pend(0, opt::PEND_BLOCKING), Err(OsError::TaskDelRequested));
+        assert_eq!(crate::task::os_task_sem_pend(0, opt::PEND_BLOCKING), Err(OsError::TaskDelRequested));
+        assert_eq!(crate::time::os_time_dly(3), Err(OsError::TaskDelRequested));
+        assert_eq!(sem.pend(0, opt::PEND_NON_BLOCKING), Err(OsError::PendWouldBlock));
+        assert!(unsafe { t.as_ref() }.is_ready());
+
+        // T hands the mutex on, then exits
+        mutex.post(opt::POST_FIFO).unwrap();
+        os_task_del(None).unwrap();
+        fake_switch();
+        assert_eq!(cur(), Some(w));
+        assert_eq!(mutex.owner_prio(), Some(6));
+        assert_eq!(os_task_del_request(t), Err(OsError::TcbInvalid));
+
+        // Without `TASK_DEL_INTERRUPT` the pend goes on
+        mutex.post(opt::POST_FIFO).unwrap();
+        let _ = sem.pend(0, opt::PEND_BLOCKING);
+        fake_switch();
+        os_task_del_request(w).unwrap();
+        assert!(unsafe { w.as_ref() }.is_pending());
+        assert!(unsafe { w.as_ref() }.del_req);
+        os_task_del(Some(w)).unwrap();
+        os_deinit();
+    }
+
+    /// An overwritten interrupt stack canary is reported once, however
+    /// many handlers exit after it
+    #[test]
+    #[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
+    fn test_int_stk_ovf_reported_once() {
+        use crate::port::stub::{int_stk_canary_set, take_events};
+
+        static FAULTS: AtomicU32 = AtomicU32::new(0);
+
+        fn fault(err: OsError) {
+            assert_eq!(err, OsError::StkOvf);
+            FAULTS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let _lock = test_lock();
+        os_init().unwrap();
+        KERNEL.set_running(true);
+        hooks::os_app_hooks_set(hooks::OsAppHooks { fault: Some(fault), ..hooks::OsAppHooks::new() });
+        FAULTS.store(0, Ordering::Relaxed);
+        for canary_ok in [true, false, false] {
+            int_stk_canary_set(canary_ok);
+            os_int_enter();
+            os_int_exit();
+        }
+        int_stk_canary_set(true);
+        assert_eq!(FAULTS.load(Ordering::Relaxed), 1);
+        let _ = take_events();
+        os_deinit();
+    }
+
+    #[test]
+    fn test_ready_count_excludes() {
+        let mut tcbs = [OsTcb::new(), OsTcb::new(), OsTcb::new()];
+        for (t, prio) in tcbs.iter_mut().zip([1, 1, crate::config::CFG_PRIO_IDLE]) {
+            t.prio = prio;
+        }
+        let [a, b, idle] = tcbs.each_mut().map(NonNull::from);
+        let mut sched = SchedState::new();
+        for t in [a, b, idle] {
+            sched.rdy.insert(t);
+        }
+
+        assert_eq!(sched.ready_count(None), 3);
+        assert_eq!(sched.ready_count(Some(idle)), 2);
+        assert_eq!(sched.rdy.highest(), Some(1));
+    }
+
+    /// Interrupts ready and suspend tasks between a switch request and
+    /// PendSV running; PendSV still runs the task that should run then
+    #[test]
+    fn test_isr_in_switch_window() {
+        const PRIOS: [OsPrio; 6] = [2, 4, 4, 7, 9, crate::config::CFG_PRIO_IDLE];
+        let mut tcbs = PRIOS.map(|prio| {
+            let mut t = OsTcb::new();
+            t.prio = prio;
+            t
+        });
+        let tasks = tcbs.each_mut().map(NonNull::from);
+        let idle = tasks[PRIOS.len() - 1];
+        let mut ready = [true; PRIOS.len()];
+        let mut sched = SchedState::new();
+        let mut cpu = CpuState::new();
+        for t in tasks {
+            sched.rdy.insert(t);
+        }
+
+        let pendsv = |cpu: &mut CpuState, rdy: &ReadyQueues| {
+            cpu.high_rdy_resolve(rdy);
+            cpu.tcb_cur = cpu.tcb_high_rdy;
+            cpu.prio_cur = cpu.prio_high_rdy;
+        };
+        assert!(cpu.switch_due(&sched.rdy));
+        pendsv(&mut cpu, &sched.rdy);
+
+        let mut seed = 0x1234_5678u32;
+        let mut rand = |n: usize| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 16) as usize % n
+        };
+
+        for _ in 0..10_000 {
+            // The running task blocks unless it is idle
+            let cur = tasks.iter().position(|t| t.as_ptr() == cpu.tcb_cur).unwrap();
+            let mut pend = false;
+            if tasks[cur] != idle && rand(2) == 0 {
+                sched.rdy.remove(tasks[cur]);
+                ready[cur] = false;
+                pend = cpu.switch_due(&sched.rdy);
+            }
+
+            // Interrupt before PendSV: ready one waiting task, or suspend
+            // one that is ready and not running
+            if rand(2) == 0 {
+                if let Some(i) = (0..PRIOS.len()).map(|_| rand(PRIOS.len())).find(|&i| !ready[i]) {
+                    sched.rdy.insert(tasks[i]);
+                    ready[i] = true;
+                }
+                pend |= cpu.switch_due(&sched.rdy);
+            } else if pend {
+                let cur = cpu.tcb_cur;
+                if let Some(i) = (0..PRIOS.len() - 1).find(|&i| ready[i] && tasks[i].as_ptr() != cur) {
+                    sched.rdy.remove(tasks[i]);
+                    ready[i] = false;
+                }
+            }
+
+            if pend {
+                pendsv(&mut cpu, &sched.rdy);
+            }
+            assert_eq!(Some(cpu.prio_cur), sched.rdy.highest());
+            assert_eq!(cpu.prio_cur, unsafe { (*cpu.tcb_cur).prio });
+        }
+    }
+
+    /// A task readied by an interrupt waits for the running task to block
+    #[test]
+    fn test_cooperative_defers_to_blocking() {
+        for mode in [SchedMode::Preemptive, SchedMode::Cooperative] {
+            let mut tcbs = [OsTcb::new(), OsTcb::new()];
+            tcbs[0].prio = 2;
+            tcbs[1].prio = 20;
+            let [high, low] = tcbs.each_mut().map(NonNull::from);
+            let mut sched = SchedState::new();
+            let mut cpu = CpuState::new();
+
+            // Compute-bound low priority task runs
+            sched.rdy.insert(low);
+            assert!(cpu.high_rdy_refresh(&sched.rdy));
+            cpu.tcb_cur = cpu.tcb_high_rdy;
+
+            // Tick interrupt readies the high priority task
+            sched.rdy.insert(high);
+            let switch = int_switch(&cpu, &sched.rdy, mode);
+            assert_eq!(switch, mode == SchedMode::Preemptive);
+            assert_eq!(cpu.high_rdy_pick(&sched.rdy), Some((2, high.as_ptr())));
+            if switch {
+                continue;
+            }
+
+            // Further ticks change nothing while it computes
+            for _ in 0..100 {
+                assert!(!int_switch(&cpu, &sched.rdy, mode));
+                assert_eq!(cpu.tcb_cur, low.as_ptr());
+            }
+
+            // Blocking reaches `os_sched`, which switches
+            sched.rdy.remove(low);
+            assert!(cpu.high_rdy_refresh(&sched.rdy));
+            assert_eq!(cpu.tcb_high_rdy, high.as_ptr());
+        }
+    }
+
+    /// The switch handler runs the task that should run when it is taken,
+    /// whatever the high ready pair held from before
+    #[test]
+    fn test_switch_resolves_target() {
+        let mut tcbs = [4, 9, 9].map(|prio| {
+            let mut t = OsTcb::new();
+            t.prio = prio;
+            t
+        });
+        let [high, low, peer] = tcbs.each_mut().map(NonNull::from);
+        let mut sched = SchedState::new();
+
+        let switch = |cpu: &mut CpuState, rdy: &ReadyQueues| {
+            cpu.high_rdy_resolve(rdy);
+            cpu.tcb_cur = cpu.tcb_high_rdy;
+            cpu.prio_cur = cpu.prio_high_rdy;
+        };
+        let whole = |cpu: &CpuState| cpu.prio_cur == unsafe { (*cpu.tcb_cur).prio };
+
+        // First switch, nothing named yet
+        let mut cpu = CpuState::new();
+        sched.rdy.insert(low);
+        sched.rdy.insert(peer);
+        cpu.sw_pending = true;
+        switch(&mut cpu, &sched.rdy);
+        assert!(!cpu.sw_pending);
+        assert_eq!(cpu.tcb_cur, low.as_ptr());
+
+        // The pair still names the peer at the high task's priority, as a
+        // decision torn by an interrupt would; the peer since left and the
+        // high task was readied
+        cpu.high_rdy_set(4, peer.as_ptr());
+        sched.rdy.remove(peer);
+        sched.rdy.insert(high);
+        switch(&mut cpu, &sched.rdy);
+        assert_eq!(cpu.tcb_cur, high.as_ptr());
+        assert!(whole(&cpu));
+
+        // The high task blocks and is readied again before the switch
+        sched.rdy.remove(high);
+        assert!(cpu.switch_due(&sched.rdy));
+        sched.rdy.insert(high);
+        assert!(!cpu.switch_due(&sched.rdy));
+        switch(&mut cpu, &sched.rdy);
+        assert_eq!(cpu.tcb_cur, high.as_ptr());
+        assert!(whole(&cpu));
+    }
+
+    /// A running task is only preempted by tasks beating its threshold
+    #[test]
+    fn test_preempt_threshold() {
+        let mut tcbs = [10, 7, 4, 10].map(|prio| {
+            let mut t = OsTcb::new();
+            t.prio = prio;
+            t
+        });
+        tcbs[0].preempt_threshold = 5;
+        let [cur, mid, high, peer] = tcbs.each_mut().map(NonNull::from);
+        let mut sched = SchedState::new();
+        let mut cpu = CpuState::new();
+
+        sched.rdy.insert(cur);
+        assert!(cpu.high_rdy_refresh(&sched.rdy));
+        cpu.tcb_cur = cpu.tcb_high_rdy;
+
+        // Priority 7 waits, priority 4 preempts
+        sched.rdy.insert(mid);
+        assert!(!int_switch(&cpu, &sched.rdy, SchedMode::Preemptive));
+        assert_eq!(cpu.high_rdy_pick(&sched.rdy), Some((10, cur.as_ptr())));
+        sched.rdy.insert(high);
+        assert!(int_switch(&cpu, &sched.rdy, SchedMode::Preemptive));
+        assert_eq!(cpu.high_rdy_pick(&sched.rdy), Some((4, high.as_ptr())));
+        sched.rdy.remove(high);
+
+        // A yield still hands over to a task of equal priority
+        sched.rdy.remove(mid);
+        sched.rdy.insert(peer);
+        sched.rdy.remove(cur);
+        sched.rdy.insert(cur);
+        assert!(cpu.high_rdy_refresh(&sched.rdy));
+        assert_eq!(cpu.tcb_high_rdy, peer.as_ptr());
+
+        // Blocking switches to priority 7 regardless of the threshold
+        sched.rdy.insert(mid);
+        sched.rdy.remove(cur);
+        unsafe { (*cur.as_ptr()).task_state = crate::types::OsTaskState::Pend };
+        assert!(cpu.high_rdy_refresh(&sched.rdy));
+        assert_eq!(cpu.tcb_high_rdy, mid.as_ptr());
+    }
+}
@@ -0,0 +1,186 @@
+//! Kernel message pool
+//!
+//! A fixed pool of `CFG_MSG_POOL_SIZE` message nodes, each carrying up to
+//! `CFG_MSG_PAYLOAD_SIZE` bytes. A producer copies its payload into a node
+//! with [`os_msg_alloc`], so the data outlives the producer's buffer; this
+//! is what lets an ISR post more than a pointer. The consumer returns the
+//! node with [`os_msg_free`].
+//!
+//! Both calls run under a short critical section and are ISR-safe. The pool
+//! is rebuilt by `os_init`; messages held across it are lost.
+
+use core::ptr::NonNull;
+
+use crate::config::{CFG_MSG_PAYLOAD_SIZE, CFG_MSG_POOL_SIZE};
+use crate::core::cs_cell::CsCell;
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::types::OsMsgSize;
+
+/// Message node
+#[derive(Clone, Copy)]
+pub struct OsMsg {
+    /// Next free node
+    next: Option<NonNull<OsMsg>>,
+    /// Payload bytes in use
+    size: OsMsgSize,
+    /// Payload
+    data: [u8; CFG_MSG_PAYLOAD_SIZE],
+}
+
+impl OsMsg {
+    const fn new() -> Self {
+        Self {
+            next: None,
+            size: 0,
+            data: [0; CFG_MSG_PAYLOAD_SIZE],
+        }
+    }
+
+    /// Copied payload
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.size]
+    }
+
+    /// Payload size in bytes
+    #[inline]
+    pub fn size(&self) -> OsMsgSize {
+        self.size
+    }
+}
+
+/// Message pool usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsMsgPoolStats {
+    /// Nodes in the pool
+    pub size: usize,
+    /// Nodes currently allocated
+    pub used: usize,
+    /// Most nodes allocated at once since `os_init`
+    pub used_max: usize,
+}
+
+/// Nodes and their free list
+pub(crate) struct MsgPool {
+    nodes: [OsMsg; CFG_MSG_POOL_SIZE],
+    free: Option<NonNull<OsMsg>>,
+    used: usize,
+    used_max: usize,
+}
+
+impl MsgPool {
+    const fn new() -> Self {
+        Self {
+            nodes: [OsMsg::new(); CFG_MSG_POOL_SIZE],
+            free: None,
+            used: 0,
+            used_max: 0,
+        }
+    }
+
+    /// Link every node into the free list
+    ///
+    /// The list points into `self`, which must not move afterwards.
+    fn init(&mut self) {
+        let mut next = None;
+        for node in self.nodes.iter_mut().rev() {
+            *node = OsMsg::new();
+            node.next = next;
+            next = Some(NonNull::from(&mut *node));
+        }
+        self.free = next;
+        self.used = 0;
+        self.used_max = 0;
+    }
+
+    fn alloc(&mut self, payload: &[u8]) -> OsResult<NonNull<OsMsg>> {
+        if payload.len() > CFG_MSG_PAYLOAD_SIZE {
+            return Err(OsError::MsgSizeInvalid);
+        }
+
+        let mut msg = self.free.ok_or(OsError::MsgPoolEmpty)?;
+        let node = unsafe { msg.as_mut() };
+        self.free = node.next.take();
+        node.size = payload.len();
+        node.data[..payload.len()].copy_from_slice(payload);
+
+        self.used += 1;
+        self.used_max = self.used_max.max(self.used);
+        Ok(msg)
+    }
+
+    unsafe fn free(&mut self, mut msg: NonNull<OsMsg>) {
+        unsafe { msg.as_mut() }.next = self.free;
+        self.free = Some(msg);
+        self.used = self.used.saturating_sub(1);
+    }
+
+    fn stats(&self) -> OsMsgPoolStats {
+        OsMsgPoolStats {
+            size: CFG_MSG_POOL_SIZE,
+            used: self.used,
+            used_max: self.used_max,
+        }
+    }
+}
+
+static MSG_POOL: CsCell<MsgPool> = CsCell::new(MsgPool::new());
+
+pub(crate) fn reset() {
+    critical_section(|cs| MSG_POOL.get(cs).init());
+}
+
+/// Copy `payload` into a free message
+///
+/// # Returns
+/// * `Err(OsError::MsgSizeInvalid)` - `payload` exceeds `CFG_MSG_PAYLOAD_SIZE`
+/// * `Err(OsError::MsgPoolEmpty)` - Every message is allocated
+pub fn os_msg_alloc(payload: &[u8]) -> OsResult<NonNull<OsMsg>> {
+    critical_section(|cs| MSG_POOL.get(cs).alloc(payload))
+}
+
+/// Return a message to the pool
+///
+/// # Safety
+/// `msg` must come from [`os_msg_alloc`] since the last `os_init`, must not
+/// have been freed already and must not be used afterwards.
+pub unsafe fn os_msg_free(msg: NonNull<OsMsg>) {
+    critical_section(|cs| unsafe { MSG_POOL.get(cs).free(msg) });
+}
+
+/// Get the message pool usage
+pub fn os_msg_pool_stats() -> OsMsgPoolStats {
+    critical_section(|cs| MSG_POOL.get(cs).stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exhaust_and_refill() {
+        let mut pool = MsgPool::new();
+        pool.init();
+
+        let too_long = [0u8; CFG_MSG_PAYLOAD_SIZE + 1];
+        assert_eq!(pool.alloc(&too_long).err(), Some(OsError::MsgSizeInvalid));
+
+        let mut held = [None; CFG_MSG_POOL_SIZE];
+        for (i, slot) in held.iter_mut().enumerate() {
+            *slot = Some(pool.alloc(&[i as u8; 3]).unwrap());
+        }
+        assert_eq!(pool.alloc(&[]).err(), Some(OsError::MsgPoolEmpty));
+        assert_eq!(unsafe { held[5].unwrap().as_ref() }.data(), &[5, 5, 5]);
+
+        for msg in held.iter().take(4).flatten() {
+            unsafe { pool.free(*msg) };
+        }
+        let stats = pool.stats();
+        assert_eq!((stats.used, stats.used_max), (CFG_MSG_POOL_SIZE - 4, CFG_MSG_POOL_SIZE));
+
+        // A freed node comes back with the new payload only
+        let msg = pool.alloc(&[9]).unwrap();
+        assert_eq!(unsafe { msg.as_ref() }.data(), &[9]);
+    }
+}
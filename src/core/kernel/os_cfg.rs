@@ -0,0 +1,187 @@
+//! Runtime kernel configuration
+//!
+//! [`OsConfig`] holds the knobs that do not change memory layout and can
+//! therefore be chosen at boot instead of at compile time. It is passed to
+//! [`os_init_with`](super::os_init_with), validated, and stored in kernel
+//! state; `os_init` uses [`OsConfig::default`].
+//!
+//! The kernel tasks' priorities are set here; their stacks are statics
+//! sized by the `CFG_*_TASK_STK_SIZE` constants, except the idle task's,
+//! which the application may provide through [`OsConfig::idle`].
+
+use super::idle::IdleStorage;
+use crate::config::{
+    CFG_CPU_CLK_HZ, CFG_PRIO_IDLE, CFG_SCHED_ROUND_ROBIN_EN, CFG_SELF_CHECK_TASK_PRIO, CFG_STK_SIZE_MIN,
+    CFG_TICK_RATE_HZ, CFG_TICK_TASK_PRIO, CFG_TIME_QUANTA_DEFAULT, CFG_TIME_QUANTA_MAX, CFG_WDOG_TASK_PRIO,
+};
+use crate::core::cs_cell::CsCell;
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::types::{OsPrio, OsTick};
+
+/// When the scheduler may switch tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedMode {
+    /// A task readied by an interrupt preempts at once
+    Preemptive,
+    /// Switches happen only when the running task calls into the kernel
+    /// (delay, pend, post, yield). Interrupts only record that a higher
+    /// priority task is ready. Round-robin must be disabled.
+    Cooperative,
+}
+
+/// Runtime kernel configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsConfig {
+    /// Core clock used by `os_start` to program SysTick
+    pub cpu_clk_hz: u32,
+    /// Tick rate in Hz, at most `CFG_TICK_RATE_HZ`
+    pub tick_rate_hz: u32,
+    /// Time quanta given to tasks created by `os_task_create`
+    pub time_quanta_default: OsTick,
+    /// Rotate same-priority tasks on quanta expiry
+    pub round_robin_en: bool,
+    /// Preemptive or cooperative scheduling
+    pub sched_mode: SchedMode,
+    /// Let the default idle policy sleep with `wfi` instead of spinning;
+    /// off by default with `defmt`, whose RTT draining `wfi` can stall
+    pub idle_wfi: bool,
+    /// Create the tick task (`tick-task` feature); otherwise the tick
+    /// interrupt processes the tick queue itself
+    pub tick_task_en: bool,
+    /// Create the software watchdog supervisor (`wdog` feature)
+    pub wdog_en: bool,
+    /// Create the kernel self-check task (`self-check` feature)
+    pub self_check_en: bool,
+    /// Tick task priority
+    pub tick_task_prio: OsPrio,
+    /// Software watchdog supervisor priority
+    pub wdog_task_prio: OsPrio,
+    /// Self-check task priority
+    pub self_check_task_prio: OsPrio,
+    /// Idle task TCB and stack, from `os_idle_storage!`; `None` uses the
+    /// kernel's, of `CFG_IDLE_TASK_STK_SIZE` words
+    pub idle: Option<&'static IdleStorage>,
+    /// Application tasks for `os_init_with` to create (`task-table` feature)
+    #[cfg(feature = "task-table")]
+    pub task_table: Option<&'static crate::task::TaskTable>,
+}
+
+impl OsConfig {
+    /// Configuration matching the compile-time `CFG_*` defaults
+    #[allow(clippy::should_implement_trait)]
+    pub const fn default() -> Self {
+        Self {
+            cpu_clk_hz: CFG_CPU_CLK_HZ,
+            tick_rate_hz: CFG_TICK_RATE_HZ,
+            time_quanta_default: CFG_TIME_QUANTA_DEFAULT,
+            round_robin_en: CFG_SCHED_ROUND_ROBIN_EN,
+            sched_mode: SchedMode::Preemptive,
+            idle_wfi: !cfg!(any(feature = "idle-nop", feature = "defmt")),
+            tick_task_en: true,
+            wdog_en: true,
+            self_check_en: true,
+            tick_task_prio: CFG_TICK_TASK_PRIO,
+            wdog_task_prio: CFG_WDOG_TASK_PRIO,
+            self_check_task_prio: CFG_SELF_CHECK_TASK_PRIO,
+            idle: None,
+            #[cfg(feature = "task-table")]
+            task_table: None,
+        }
+    }
+
+    /// Check the configuration
+    ///
+    /// # Returns
+    /// * `Err(OsError::CfgTickRateInvalid)` - Tick rate is 0 or above `CFG_TICK_RATE_HZ`
+    /// * `Err(OsError::CfgTimeQuantaInvalid)` - Round-robin enabled with a quanta of 0,
+    ///   or a quanta above `CFG_TIME_QUANTA_MAX`
+    /// * `Err(OsError::CfgSchedModeInvalid)` - Round-robin enabled in cooperative mode
+    /// * `Err(OsError::CfgIdleStkInvalid)` - `idle` stack below `CFG_STK_SIZE_MIN`
+    /// * `Err(OsError::CfgPrioInvalid)` - Two enabled kernel tasks share a
+    ///   priority, or one is at the idle priority or below
+    pub const fn validate(&self) -> OsResult<()> {
+        if self.tick_rate_hz == 0 || self.tick_rate_hz > CFG_TICK_RATE_HZ {
+            return Err(OsError::CfgTickRateInvalid);
+        }
+
+        if self.time_quanta_default > CFG_TIME_QUANTA_MAX
+            || (self.round_robin_en && self.time_quanta_default == 0)
+        {
+            return Err(OsError::CfgTimeQuantaInvalid);
+        }
+
+        if self.round_robin_en && matches!(self.sched_mode, SchedMode::Cooperative) {
+            return Err(OsError::CfgSchedModeInvalid);
+        }
+
+        if let Some(idle) = self.idle {
+            if idle.stk_size() < CFG_STK_SIZE_MIN {
+                return Err(OsError::CfgIdleStkInvalid);
+            }
+        }
+
+        let prios = self.kernel_task_prios();
+        let mut i = 0;
+        while i < prios.len() {
+            if let Some(prio) = prios[i] {
+                if prio >= CFG_PRIO_IDLE {
+                    return Err(OsError::CfgPrioInvalid);
+                }
+                let mut j = i + 1;
+                while j < prios.len() {
+                    if matches!(prios[j], Some(other) if other == prio) {
+                        return Err(OsError::CfgPrioInvalid);
+                    }
+                    j += 1;
+                }
+            }
+            i += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Priorities of the kernel tasks this configuration creates
+    const fn kernel_task_prios(&self) -> [Option<OsPrio>; 3] {
+        const fn prio_if(en: bool, prio: OsPrio) -> Option<OsPrio> {
+            if en {
+                Some(prio)
+            } else {
+                None
+            }
+        }
+        [
+            prio_if(cfg!(feature = "tick-task") && self.tick_task_en, self.tick_task_prio),
+            prio_if(cfg!(feature = "wdog") && self.wdog_en, self.wdog_task_prio),
+            prio_if(cfg!(feature = "self-check") && self.self_check_en, self.self_check_task_prio),
+        ]
+    }
+}
+
+impl Default for OsConfig {
+    fn default() -> Self {
+        Self::default()
+    }
+}
+
+/// Active configuration, written by `os_init_with`
+static OS_CFG: CsCell<OsConfig> = CsCell::new(OsConfig::default());
+
+pub(crate) fn set(cfg: &OsConfig) {
+    critical_section(|cs| *OS_CFG.get(cs) = *cfg);
+}
+
+/// Active configuration, for kernel hot paths
+///
+/// Only written by `os_init_with` before `os_start`, so reading it without a
+/// critical section is sound once the kernel runs.
+#[inline(always)]
+pub(crate) fn cfg() -> &'static OsConfig {
+    unsafe { OS_CFG.get_unchecked() }
+}
+
+/// Get the active kernel configuration
+pub fn os_config() -> OsConfig {
+    critical_section(|cs| *OS_CFG.get(cs))
+}
@@ -0,0 +1,212 @@
+//! Kernel object registry
+//!
+//! Every semaphore, mutex and task records its type, name and address in a
+//! fixed table when it is created, giving debug dumps, trace and kernel
+//! awareness a stable list of object identities. Tasks are dropped from the
+//! table when deleted.
+//!
+//! The table holds `CFG_OBJ_REGISTRY_SIZE` entries. Creating an object while
+//! it is full still succeeds; the object is simply not listed and
+//! [`os_obj_registry_dropped`] is incremented.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::config::CFG_OBJ_REGISTRY_SIZE;
+use crate::core::cs_cell::CsCell;
+use crate::critical::{critical_section, CriticalSection};
+use crate::types::{obj_name, obj_name_str, ObjName, OsObjType};
+
+/// Registry entry
+#[derive(Debug, Clone, Copy)]
+pub struct OsObjEntry {
+    /// Object type
+    pub obj_type: OsObjType,
+    /// Name given at creation
+    pub name: ObjName,
+    /// Address of the object
+    pub addr: *const (),
+}
+
+impl OsObjEntry {
+    /// Name given at creation, `""` with `no-names`
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        obj_name_str(self.name)
+    }
+
+    /// Counters of a registered semaphore, mutex or readers-writer lock
+    ///
+    /// `None` for other object types.
+    #[cfg(feature = "obj-stats")]
+    pub fn stats(&self) -> Option<crate::sync::stats::OsObjStats> {
+        match self.obj_type {
+            #[cfg(feature = "sem")]
+            OsObjType::Sem => Some(unsafe { &*(self.addr as *const crate::sem::OsSem) }.stats()),
+            #[cfg(feature = "mutex")]
+            OsObjType::Mutex => Some(unsafe { &*(self.addr as *const crate::mutex::OsMutex) }.stats()),
+            #[cfg(feature = "rwlock")]
+            OsObjType::RwLock => Some(unsafe { &*(self.addr as *const crate::rwlock::OsRwLock) }.stats()),
+            _ => None,
+        }
+    }
+
+    /// Count and maximum of a registered semaphore
+    ///
+    /// `None` for other object types.
+    #[cfg(feature = "sem")]
+    pub fn sem_count(&self) -> Option<(crate::types::OsSemCtr, crate::types::OsSemCtr)> {
+        match self.obj_type {
+            OsObjType::Sem => {
+                let sem = unsafe { &*(self.addr as *const crate::sem::OsSem) };
+                Some((sem.count(), sem.max()))
+            }
+            _ => None,
+        }
+    }
+
+    /// ID of a registered task
+    ///
+    /// `None` for other object types.
+    pub fn task_id(&self) -> Option<crate::types::OsTaskId> {
+        match self.obj_type {
+            OsObjType::Task => Some(unsafe { &*(self.addr as *const crate::task::OsTcb) }.id),
+            _ => None,
+        }
+    }
+
+    /// Group of a registered task
+    ///
+    /// `None` for other object types and tasks in no group.
+    #[cfg(feature = "task-group")]
+    pub fn task_group(&self) -> Option<&'static str> {
+        match self.obj_type {
+            OsObjType::Task => unsafe { &*(self.addr as *const crate::task::OsTcb) }.group_name(),
+            _ => None,
+        }
+    }
+}
+
+static REGISTRY: CsCell<[Option<OsObjEntry>; CFG_OBJ_REGISTRY_SIZE]> =
+    CsCell::new([None; CFG_OBJ_REGISTRY_SIZE]);
+
+/// Objects that could not be registered
+static DROPPED: AtomicU32 = AtomicU32::new(0);
+
+/// Record an object, inside an existing critical section
+///
+/// Re-creating an object at the same address updates its entry.
+pub(crate) fn register_cs(cs: &CriticalSection, obj_type: OsObjType, name: &'static str, addr: *const ()) {
+    register(REGISTRY.get(cs), obj_type, name, addr);
+}
+
+/// Record an object during `os_init`, before interrupts are relevant
+///
+/// # Safety
+/// Caller must hold a critical section or run before `os_start`.
+pub(crate) unsafe fn register_unchecked(obj_type: OsObjType, name: &'static str, addr: *const ()) {
+    register(unsafe { REGISTRY.get_unchecked() }, obj_type, name, addr);
+}
+
+fn register(
+    table: &mut [Option<OsObjEntry>; CFG_OBJ_REGISTRY_SIZE],
+    obj_type: OsObjType,
+    name: &'static str,
+    addr: *const (),
+) {
+    let slot = match table.iter().position(|e| e.is_some_and(|e| e.addr == addr)) {
+        Some(i) => i,
+        None => match table.iter().position(|e| e.is_none()) {
+            Some(i) => i,
+            None => {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        },
+    };
+
+    table[slot] = Some(OsObjEntry { obj_type, name: obj_name(name), addr });
+}
+
+/// Forget an object, inside an existing critical section
+pub(crate) fn unregister_cs(cs: &CriticalSection, addr: *const ()) {
+    for e in REGISTRY.get(cs).iter_mut() {
+        if e.is_some_and(|e| e.addr == addr) {
+            *e = None;
+        }
+    }
+}
+
+/// Entry of the object at `addr`, inside an existing critical section
+pub(crate) fn lookup_cs(cs: &CriticalSection, addr: *const ()) -> Option<OsObjEntry> {
+    REGISTRY.get(cs).iter().flatten().find(|e| e.addr == addr).copied()
+}
+
+pub(crate) fn reset() {
+    critical_section(|cs| *REGISTRY.get(cs) = [None; CFG_OBJ_REGISTRY_SIZE]);
+    DROPPED.store(0, Ordering::Relaxed);
+}
+
+/// Number of objects created while the registry was full
+pub fn os_obj_registry_dropped() -> u32 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Iterator over registered objects
+///
+/// Each step copies one entry under a short critical section, so objects
+/// created or deleted during the walk may or may not be seen.
+pub struct OsObjRegistryIter {
+    idx: usize,
+}
+
+impl Iterator for OsObjRegistryIter {
+    type Item = OsObjEntry;
+
+    fn next(&mut self) -> Option<OsObjEntry> {
+        while self.idx < CFG_OBJ_REGISTRY_SIZE {
+            let idx = self.idx;
+            self.idx += 1;
+            if let Some(entry) = critical_section(|cs| REGISTRY.get(cs)[idx]) {
+                return Some(entry);
+            }
+        }
+        None
+    }
+}
+
+/// Iterate over all registered kernel objects
+pub fn os_obj_registry_iter() -> OsObjRegistryIter {
+    OsObjRegistryIter { idx: 0 }
+}
+
+/// Log every registered object, with its counters under `obj-stats`
+#[cfg_attr(not(feature = "defmt"), allow(unused_variables))]
+pub fn os_obj_registry_dump() {
+    for e in os_obj_registry_iter() {
+        crate::info!("{=u32:x} {} @ {=usize:x}", e.obj_type as u32, e.name(), e.addr as usize);
+
+        #[cfg(feature = "sem")]
+        if let Some((count, max)) = e.sem_count() {
+            crate::info!("  count {}/{}", count, max);
+        }
+
+        #[cfg(feature = "task-group")]
+        if let Some(group) = e.task_group() {
+            crate::info!("  group {}", group);
+        }
+
+        #[cfg(feature = "obj-stats")]
+        if let Some(st) = e.stats() {
+            crate::info!(
+                "  pends {} blocked {} posts {} timeouts {} aborts {} waiters {}/{} held_max {}",
+                st.pends, st.pends_blocked, st.posts, st.timeouts, st.aborts,
+                st.waiters, st.waiters_max, st.held_max
+            );
+        }
+    }
+
+    let dropped = os_obj_registry_dropped();
+    if dropped > 0 {
+        crate::warn!("{} objects not registered", dropped);
+    }
+}
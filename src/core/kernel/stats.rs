@@ -0,0 +1,131 @@
+//! Latency statistics (`latency-stats` feature)
+//!
+//! Two measurements, both in timestamp-counter cycles (DWT `CYCCNT` on
+//! Cortex-M):
+//!
+//! - Context switch: the PendSV handler stamps its entry and, after the new
+//!   context is restored, stores the elapsed cycles in `CpuState`. They are
+//!   folded into [`OsKernelStats::ctx_sw`] at the next switch or read.
+//! - Interrupts masked: the outermost kernel `CriticalSection` is stamped on
+//!   enter and drop; the longest one is kept with its source location.
+//!
+//! Only kernel critical sections are timed, not other users of the
+//! `critical-section` crate. Context switch timing is Cortex-M only.
+//! [`os_ts_to_ns`](crate::time::os_ts_to_ns) converts cycles to time.
+
+use core::panic::Location;
+
+use crate::core::cs_cell::CsCell;
+use crate::critical::critical_section;
+
+/// Min/max/average of a cycle count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsCycleStats {
+    /// Shortest sample, `u32::MAX` if none
+    pub min: u32,
+    /// Longest sample
+    pub max: u32,
+    /// Sum of all samples
+    pub total: u64,
+    /// Number of samples
+    pub count: u32,
+}
+
+impl OsCycleStats {
+    pub const fn new() -> Self {
+        Self { min: u32::MAX, max: 0, total: 0, count: 0 }
+    }
+
+    /// Average sample, 0 if none
+    pub const fn avg(&self) -> u32 {
+        if self.count == 0 { 0 } else { (self.total / self.count as u64) as u32 }
+    }
+
+    fn add(&mut self, cycles: u32) {
+        self.min = self.min.min(cycles);
+        self.max = self.max.max(cycles);
+        self.total = self.total.wrapping_add(cycles as u64);
+        self.count = self.count.wrapping_add(1);
+    }
+}
+
+impl Default for OsCycleStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Kernel latency statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsKernelStats {
+    /// PendSV entry to exception return
+    pub ctx_sw: OsCycleStats,
+    /// Longest window with interrupts masked by the kernel
+    pub cs_max: u32,
+    /// Where the longest window was entered
+    pub cs_max_at: Option<&'static Location<'static>>,
+}
+
+impl OsKernelStats {
+    const fn new() -> Self {
+        Self { ctx_sw: OsCycleStats::new(), cs_max: 0, cs_max_at: None }
+    }
+}
+
+struct State {
+    stats: OsKernelStats,
+    cs_start: u32,
+    cs_at: Option<&'static Location<'static>>,
+}
+
+static STATE: CsCell<State> = CsCell::new(State {
+    stats: OsKernelStats::new(),
+    cs_start: 0,
+    cs_at: None,
+});
+
+/// Outermost critical section entered, interrupts already masked
+#[inline(always)]
+pub(crate) fn cs_enter(at: &'static Location<'static>) {
+    let state = unsafe { STATE.get_unchecked() };
+    state.cs_at = Some(at);
+    state.cs_start = crate::port::os_cpu_ts_get();
+}
+
+/// Outermost critical section about to be left, interrupts still masked
+#[inline(always)]
+pub(crate) fn cs_exit() {
+    let state = unsafe { STATE.get_unchecked() };
+    let cycles = crate::port::os_cpu_ts_get().wrapping_sub(state.cs_start);
+    if cycles > state.stats.cs_max {
+        state.stats.cs_max = cycles;
+        state.stats.cs_max_at = state.cs_at;
+    }
+}
+
+/// Fold the last context switch time left by PendSV
+///
+/// Called with interrupts masked.
+pub(crate) fn ctx_sw_fold() {
+    let cpu = unsafe { super::CPU_STATE.get_unchecked() };
+    if cpu.sw_cycles != 0 {
+        unsafe { STATE.get_unchecked() }.stats.ctx_sw.add(cpu.sw_cycles);
+        cpu.sw_cycles = 0;
+    }
+}
+
+/// Read the latency statistics
+pub fn os_stats() -> OsKernelStats {
+    critical_section(|cs| {
+        ctx_sw_fold();
+        STATE.get(cs).stats
+    })
+}
+
+/// Clear the latency statistics
+pub fn os_stats_reset() {
+    critical_section(|cs| {
+        unsafe { super::CPU_STATE.get_unchecked() }.sw_cycles = 0;
+        STATE.get(cs).stats = OsKernelStats::new();
+    });
+}
@@ -0,0 +1,424 @@
+//! Delay and timeout queue
+//!
+//! Tasks waiting for a tick sit in one of two backends, chosen at build
+//! time and exposed as [`TickQueue`]:
+//!
+//! - [`TickWheel`] (default, `tick-wheel`): `CFG_TICK_WHEEL_SIZE` slots
+//!   indexed by expiry modulo the wheel size. Insert walks one slot; each
+//!   tick looks at one slot.
+//! - [`TickList`] (`tick-list`): a single list. Insert walks every delayed
+//!   task; each tick looks at the head only. Smaller and simpler when few
+//!   tasks are delayed at once.
+//!
+//! Lists are sorted by absolute expiry, compared wrap-safe, so expiring
+//! never visits an entry that is not due and nothing is decremented per
+//! tick. `examples/tick_bench.rs` compares the two.
+
+use core::ptr::NonNull;
+
+use crate::config::CFG_TICK_WHEEL_SIZE;
+use crate::task::OsTcb;
+use crate::time::tick::{tick_after, tick_reached, tick_until};
+use crate::types::OsTick;
+
+/// Backend selected by the `tick-*` backend features
+#[cfg(not(feature = "tick-list"))]
+pub type TickQueue = TickWheel;
+/// Backend selected by the `tick-*` backend features
+#[cfg(feature = "tick-list")]
+pub type TickQueue = TickList;
+
+/// Link `tcb` into `head`, after every entry expiring no later
+///
+/// Tasks with equal expiry stay in insertion order.
+unsafe fn sorted_insert(head: &mut Option<NonNull<OsTcb>>, tcb: NonNull<OsTcb>, expiry_tick: OsTick) {
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+    tcb_ref.tick_expiry = expiry_tick;
+
+    // Find the first entry expiring strictly later
+    let mut prev: Option<NonNull<OsTcb>> = None;
+    let mut next = *head;
+    while let Some(n) = next {
+        let n_ref = unsafe { n.as_ref() };
+        if tick_after(n_ref.tick_expiry, expiry_tick) {
+            break;
+        }
+        prev = next;
+        next = n_ref.tick_next_ptr;
+    }
+
+    tcb_ref.tick_prev_ptr = prev;
+    tcb_ref.tick_next_ptr = next;
+
+    match prev {
+        Some(mut p) => unsafe { p.as_mut().tick_next_ptr = Some(tcb) },
+        None => *head = Some(tcb),
+    }
+    if let Some(mut n) = next {
+        unsafe { n.as_mut().tick_prev_ptr = Some(tcb) };
+    }
+}
+
+/// Unlink `tcb` from `head`
+unsafe fn unlink(head: &mut Option<NonNull<OsTcb>>, tcb: NonNull<OsTcb>) {
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+
+    match tcb_ref.tick_prev_ptr {
+        Some(mut prev) => unsafe { prev.as_mut().tick_next_ptr = tcb_ref.tick_next_ptr },
+        None => *head = tcb_ref.tick_next_ptr,
+    }
+    if let Some(mut next) = tcb_ref.tick_next_ptr {
+        unsafe { next.as_mut().tick_prev_ptr = tcb_ref.tick_prev_ptr };
+    }
+
+    tcb_ref.tick_next_ptr = None;
+    tcb_ref.tick_prev_ptr = None;
+}
+
+/// Head of `head` if it is due at `now`
+fn due(head: Option<NonNull<OsTcb>>, now: OsTick) -> Option<NonNull<OsTcb>> {
+    head.filter(|h| tick_reached(now, unsafe { h.as_ref() }.tick_expiry))
+}
+
+// ============ Tick Wheel ============
+
+/// Hashed wheel of sorted slots
+pub struct TickWheel {
+    slots: [Option<NonNull<OsTcb>>; CFG_TICK_WHEEL_SIZE],
+}
+
+impl TickWheel {
+    pub const fn new() -> Self {
+        TickWheel { slots: [None; CFG_TICK_WHEEL_SIZE] }
+    }
+
+    /// Slot for an expiry tick
+    #[inline(always)]
+    fn slot(tick: OsTick) -> usize {
+        (tick as usize) % CFG_TICK_WHEEL_SIZE
+    }
+
+    /// Head of a slot
+    #[inline(always)]
+    pub fn slot_head(&self, slot: usize) -> Option<NonNull<OsTcb>> {
+        self.slots[slot]
+    }
+
+    /// Add a task expiring at `expiry_tick`
+    ///
+    /// # Safety
+    /// `tcb` is valid and on no tick queue.
+    pub unsafe fn insert(&mut self, tcb: NonNull<OsTcb>, expiry_tick: OsTick) {
+        let slot = Self::slot(expiry_tick);
+        os_list_chk!(link, tcb, Tick, &self.slots[slot], "tick_insert");
+        unsafe {
+            (*tcb.as_ptr()).tick_wheel_slot = slot as u8;
+            sorted_insert(&mut self.slots[slot], tcb, expiry_tick);
+        }
+        os_list_chk!(walk, self.slots[slot], None, None, Tick, &self.slots[slot], "tick_insert");
+    }
+
+    /// Remove a task
+    ///
+    /// # Safety
+    /// `tcb` is on this wheel.
+    pub unsafe fn remove(&mut self, tcb: NonNull<OsTcb>) {
+        let slot = unsafe { tcb.as_ref() }.tick_wheel_slot as usize;
+        os_list_chk!(unlink, tcb, Tick, &self.slots[slot], "tick_remove");
+        unsafe { unlink(&mut self.slots[slot], tcb) };
+        os_list_chk!(walk, self.slots[slot], None, None, Tick, &self.slots[slot], "tick_remove");
+    }
+
+    /// Remove and return the next task of the slot for `now` that is due
+    pub fn pop_expired(&mut self, now: OsTick) -> Option<NonNull<OsTcb>> {
+        let head = due(self.slots[Self::slot(now)], now)?;
+        unsafe { self.remove(head) };
+        Some(head)
+    }
+
+    /// Remove and return the next due task of the slots for the `span`
+    /// ticks ending at `now`
+    ///
+    /// For a tick that advanced the counter by `span` at once. Slots are
+    /// visited oldest first; a span of the wheel size or more visits all.
+    pub fn pop_expired_span(&mut self, now: OsTick, span: OsTick) -> Option<NonNull<OsTcb>> {
+        let span = span.clamp(1, CFG_TICK_WHEEL_SIZE as OsTick);
+        let head = (0..span)
+            .rev()
+            .find_map(|back| due(self.slots[Self::slot(now.wrapping_sub(back))], now))?;
+        unsafe { self.remove(head) };
+        Some(head)
+    }
+
+    /// Heads of every slot
+    #[inline]
+    pub fn lists(&self) -> &[Option<NonNull<OsTcb>>] {
+        &self.slots
+    }
+
+    /// Ticks from `now` to the nearest expiry, 0 if overdue
+    ///
+    /// Each slot is sorted, so only the slot heads are compared.
+    pub fn next_expiry(&self, now: OsTick) -> Option<OsTick> {
+        self.slots
+            .iter()
+            .flatten()
+            .map(|head| tick_until(now, unsafe { head.as_ref() }.tick_expiry))
+            .min()
+    }
+}
+
+impl Default for TickWheel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============ Tick List ============
+
+/// Single list sorted by expiry
+pub struct TickList {
+    head: Option<NonNull<OsTcb>>,
+}
+
+impl TickList {
+    pub const fn new() -> Self {
+        TickList { head: None }
+    }
+
+    /// First task to expire
+    #[inline(always)]
+    pub fn head(&self) -> Option<NonNull<OsTcb>> {
+        self.head
+    }
+
+    /// Add a task expiring at `expiry_tick`
+    ///
+    /// # Safety
+    /// `tcb` is valid and on no tick queue.
+    pub unsafe fn insert(&mut self, tcb: NonNull<OsTcb>, expiry_tick: OsTick) {
+        os_list_chk!(link, tcb, Tick, &self.head, "tick_insert");
+        unsafe { sorted_insert(&mut self.head, tcb, expiry_tick) };
+        os_list_chk!(walk, self.head, None, None, Tick, &self.head, "tick_insert");
+    }
+
+    /// Remove a task
+    ///
+    /// # Safety
+    /// `tcb` is on this list.
+    pub unsafe fn remove(&mut self, tcb: NonNull<OsTcb>) {
+        os_list_chk!(unlink, tcb, Tick, &self.head, "tick_remove");
+        unsafe { unlink(&mut self.head, tcb) };
+        os_list_chk!(walk, self.head, None, None, Tick, &self.head, "tick_remove");
+    }
+
+    /// Remove and return the head if it is due at `now`
+    pub fn pop_expired(&mut self, now: OsTick) -> Option<NonNull<OsTcb>> {
+        let head = due(self.head, now)?;
+        unsafe { self.remove(head) };
+        Some(head)
+    }
+
+    /// Same as [`pop_expired`](Self::pop_expired); the head is the
+    /// earliest expiry whatever the span
+    pub fn pop_expired_span(&mut self, now: OsTick, _span: OsTick) -> Option<NonNull<OsTcb>> {
+        self.pop_expired(now)
+    }
+
+    /// Head of the list, as the single list of the queue
+    #[inline]
+    pub fn lists(&self) -> &[Option<NonNull<OsTcb>>] {
+        core::slice::from_ref(&self.head)
+    }
+
+    /// Ticks from `now` to the head's expiry, 0 if overdue
+    pub fn next_expiry(&self, now: OsTick) -> Option<OsTick> {
+        self.head.map(|head| tick_until(now, unsafe { head.as_ref() }.tick_expiry))
+    }
+}
+
+impl Default for TickList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Both backends behind one interface, for shared scenarios
+    trait Queue: Default {
+        unsafe fn insert(&mut self, tcb: NonNull<OsTcb>, expiry_tick: OsTick);
+        fn pop_expired(&mut self, now: OsTick) -> Option<NonNull<OsTcb>>;
+        fn pop_expired_span(&mut self, now: OsTick, span: OsTick) -> Option<NonNull<OsTcb>>;
+        fn next_expiry(&self, now: OsTick) -> Option<OsTick>;
+    }
+
+    macro_rules! impl_queue {
+        ($($t:ty),*) => {$(
+            impl Queue for $t {
+                unsafe fn insert(&mut self, tcb: NonNull<OsTcb>, expiry_tick: OsTick) {
+                    unsafe { <$t>::insert(self, tcb, expiry_tick) }
+                }
+                fn pop_expired(&mut self, now: OsTick) -> Option<NonNull<OsTcb>> {
+                    <$t>::pop_expired(self, now)
+                }
+                fn pop_expired_span(&mut self, now: OsTick, span: OsTick) -> Option<NonNull<OsTcb>> {
+                    <$t>::pop_expired_span(self, now, span)
+                }
+                fn next_expiry(&self, now: OsTick) -> Option<OsTick> {
+                    <$t>::next_expiry(self, now)
+                }
+            }
+        )*};
+    }
+    impl_queue!(TickWheel, TickList);
+
+    fn next_expiry_case<Q: Queue>() {
+        let n = CFG_TICK_WHEEL_SIZE as u32;
+        let mut tcbs = [OsTcb::new(), OsTcb::new(), OsTcb::new()];
+        let [a, b, c] = tcbs.each_mut().map(NonNull::from);
+        let mut q = Q::default();
+        assert_eq!(q.next_expiry(0), None);
+
+        // Near the wrap, the soonest expiry sits past it
+        let now = u32::MAX - 1;
+        unsafe {
+            q.insert(a, now.wrapping_add(2 * n));
+            q.insert(b, now.wrapping_add(5));
+            q.insert(c, now.wrapping_add(n + 3));
+        }
+        assert_eq!(q.next_expiry(now), Some(5));
+
+        // Overdue reads as 0
+        assert_eq!(q.next_expiry(now.wrapping_add(7)), Some(0));
+    }
+
+    #[test]
+    fn test_next_expiry_across_slots() {
+        next_expiry_case::<TickWheel>();
+        next_expiry_case::<TickList>();
+    }
+
+    /// Delays started on every tick around the counter wrap expire on
+    /// exactly their tick
+    fn expiry_case<Q: Queue>() {
+        let n = CFG_TICK_WHEEL_SIZE as u32;
+        let dlys = [1, 2, n - 1, n, n + 1, 2 * n + 3, 5 * n];
+        let mut tcbs = dlys.map(|_| OsTcb::new());
+        let tasks = tcbs.each_mut().map(NonNull::from);
+
+        for start in u32::MAX - 6 * n..=u32::MAX {
+            let mut q = Q::default();
+            for (&t, &dly) in tasks.iter().zip(&dlys) {
+                unsafe { q.insert(t, start.wrapping_add(dly)) };
+            }
+
+            let mut fired = dlys.map(|_| None);
+            for step in 1..=6 * n {
+                let now = start.wrapping_add(step);
+                while let Some(t) = q.pop_expired(now) {
+                    let i = tasks.iter().position(|&x| x == t).unwrap();
+                    assert!(fired[i].replace(step).is_none());
+                }
+            }
+            assert_eq!(fired, dlys.map(Some), "start {start:#x}");
+        }
+    }
+
+    #[test]
+    fn test_expiry_across_wrap() {
+        expiry_case::<TickWheel>();
+        expiry_case::<TickList>();
+    }
+
+    /// Ticks advanced several at a time expire every skipped slot at the
+    /// first tick reaching it, in expiry order while the span fits the wheel
+    fn span_case<Q: Queue>(span: u32) {
+        let n = CFG_TICK_WHEEL_SIZE as u32;
+        let dlys = [1, 2, span - 1, span, span + 1, n + 3, 3 * n];
+        let mut tcbs = dlys.map(|_| OsTcb::new());
+        let tasks = tcbs.each_mut().map(NonNull::from);
+
+        let start = u32::MAX - n;
+        let mut q = Q::default();
+        for (&t, &dly) in tasks.iter().zip(&dlys) {
+            unsafe { q.insert(t, start.wrapping_add(dly)) };
+        }
+
+        let mut order = [0; 7];
+        let mut len = 0;
+        let mut fired = dlys.map(|_| None);
+        let mut step = 0;
+        while step < 4 * n + span {
+            step += span;
+            while let Some(t) = q.pop_expired_span(start.wrapping_add(step), span) {
+                let i = tasks.iter().position(|&x| x == t).unwrap();
+                assert!(fired[i].replace(step).is_none());
+                order[len] = dlys[i];
+                len += 1;
+            }
+        }
+        for (&dly, &at) in dlys.iter().zip(&fired) {
+            let at = at.unwrap();
+            assert!(at >= dly && at - dly < span, "span {span}: {dly} fired at {at}");
+        }
+        if span <= n {
+            assert!(order[..len].windows(2).all(|w| w[0] <= w[1]), "span {span}");
+        }
+    }
+
+    #[test]
+    fn test_expiry_span() {
+        let n = CFG_TICK_WHEEL_SIZE as u32;
+        for span in [2, 10, n, n + 5] {
+            span_case::<TickWheel>(span);
+            span_case::<TickList>(span);
+        }
+    }
+
+    #[test]
+    fn test_tick_wheel_slot_sorted() {
+        let n = CFG_TICK_WHEEL_SIZE as u32;
+        let mut tcbs = [OsTcb::new(), OsTcb::new(), OsTcb::new()];
+        let [a, b, c] = tcbs.each_mut().map(NonNull::from);
+
+        // Same slot, inserted out of order, one across the wrap
+        let mut wheel = TickWheel::new();
+        unsafe {
+            wheel.insert(a, 3 * n + 1);
+            wheel.insert(b, (n + 1).wrapping_sub(2 * n));
+            wheel.insert(c, n + 1);
+        }
+
+        let head = wheel.slot_head(TickWheel::slot(n + 1));
+        assert_eq!(head, Some(b));
+        let second = unsafe { b.as_ref() }.tick_next_ptr;
+        assert_eq!(second, Some(c));
+        assert_eq!(unsafe { c.as_ref() }.tick_next_ptr, Some(a));
+    }
+
+    #[test]
+    fn test_tick_list_remove_middle() {
+        let mut tcbs = [OsTcb::new(), OsTcb::new(), OsTcb::new()];
+        let [a, b, c] = tcbs.each_mut().map(NonNull::from);
+
+        let mut list = TickList::new();
+        unsafe {
+            list.insert(c, 30);
+            list.insert(a, 10);
+            list.insert(b, 20);
+            list.remove(b);
+        }
+        assert_eq!(list.head(), Some(a));
+        assert_eq!(unsafe { a.as_ref() }.tick_next_ptr, Some(c));
+        assert_eq!(unsafe { c.as_ref() }.tick_prev_ptr, Some(a));
+
+        // Only the head is checked: nothing due before tick 10
+        assert_eq!(list.pop_expired(9), None);
+        assert_eq!(list.pop_expired(30), Some(a));
+        assert_eq!(list.pop_expired(30), Some(c));
+        assert_eq!(list.next_expiry(30), None);
+    }
+}
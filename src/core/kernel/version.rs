@@ -0,0 +1,141 @@
+//! Kernel version and build features
+//!
+//! [`OS_VERSION`] encodes the crate version as `major * 10000 + minor * 100
+//! + patch`, the scheme of μC/OS-III's `OSVersion`, so `0.1.0` reads `100`.
+//! It is also exported unmangled as `OS_VERSION_SYM`, which a debugger or
+//! post-mortem tool reads from the ELF or a memory dump to tell which kernel
+//! produced it. [`os_features`] returns the kernel features the image was
+//! built with as a bitmask of the `OS_FEATURE_*` bits.
+//!
+//! Bits are never reused: a removed feature leaves its bit clear.
+
+/// Parse a decimal version component
+const fn parse(s: &str) -> u32 {
+    let bytes = s.as_bytes();
+    let mut value = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "version component is not a number");
+        value = value * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    value
+}
+
+const MAJOR: u32 = parse(env!("CARGO_PKG_VERSION_MAJOR"));
+const MINOR: u32 = parse(env!("CARGO_PKG_VERSION_MINOR"));
+const PATCH: u32 = parse(env!("CARGO_PKG_VERSION_PATCH"));
+
+const _: () = assert!(MINOR < 100 && PATCH < 100, "version component does not fit OS_VERSION");
+
+/// Crate version as `major * 10000 + minor * 100 + patch`
+pub const OS_VERSION: u32 = MAJOR * 10_000 + MINOR * 100 + PATCH;
+
+/// [`OS_VERSION`] under a fixed symbol name for debuggers and dump tools
+#[no_mangle]
+#[used]
+pub static OS_VERSION_SYM: u32 = OS_VERSION;
+
+/// `sem`
+pub const OS_FEATURE_SEM: u32 = 1 << 0;
+/// `mutex`
+pub const OS_FEATURE_MUTEX: u32 = 1 << 1;
+/// `rwlock`
+pub const OS_FEATURE_RWLOCK: u32 = 1 << 2;
+/// `barrier`
+pub const OS_FEATURE_BARRIER: u32 = 1 << 3;
+/// `ring`
+pub const OS_FEATURE_RING: u32 = 1 << 4;
+/// `tick-task`
+pub const OS_FEATURE_TICK_TASK: u32 = 1 << 5;
+/// `trace`
+pub const OS_FEATURE_TRACE: u32 = 1 << 6;
+/// `kernel-awareness`
+pub const OS_FEATURE_KERNEL_AWARENESS: u32 = 1 << 7;
+/// `wdog`
+pub const OS_FEATURE_WDOG: u32 = 1 << 8;
+/// `task-group`
+pub const OS_FEATURE_TASK_GROUP: u32 = 1 << 9;
+/// `task-table`
+pub const OS_FEATURE_TASK_TABLE: u32 = 1 << 10;
+/// `self-check`
+pub const OS_FEATURE_SELF_CHECK: u32 = 1 << 11;
+/// `clock`
+pub const OS_FEATURE_CLOCK: u32 = 1 << 12;
+/// `shell`
+pub const OS_FEATURE_SHELL: u32 = 1 << 13;
+/// `obj-stats`
+pub const OS_FEATURE_OBJ_STATS: u32 = 1 << 14;
+/// `obj-pool`
+pub const OS_FEATURE_OBJ_POOL: u32 = 1 << 15;
+/// `fault-recovery`
+pub const OS_FEATURE_FAULT_RECOVERY: u32 = 1 << 16;
+/// `latency-stats`
+pub const OS_FEATURE_LATENCY_STATS: u32 = 1 << 17;
+/// `no-names`
+pub const OS_FEATURE_NO_NAMES: u32 = 1 << 18;
+/// `deadlock-detect`
+pub const OS_FEATURE_DEADLOCK_DETECT: u32 = 1 << 19;
+/// `paranoid`
+pub const OS_FEATURE_PARANOID: u32 = 1 << 20;
+/// `fpu-check`
+pub const OS_FEATURE_FPU_CHECK: u32 = 1 << 21;
+/// `rr-precise`
+pub const OS_FEATURE_RR_PRECISE: u32 = 1 << 22;
+/// `tick-list`
+pub const OS_FEATURE_TICK_LIST: u32 = 1 << 23;
+/// `idle-nop`
+pub const OS_FEATURE_IDLE_NOP: u32 = 1 << 24;
+/// `no-systick`
+pub const OS_FEATURE_NO_SYSTICK: u32 = 1 << 25;
+/// `os3-compat`
+pub const OS_FEATURE_OS3_COMPAT: u32 = 1 << 26;
+/// `deinit`
+pub const OS_FEATURE_DEINIT: u32 = 1 << 27;
+/// `budget`
+pub const OS_FEATURE_BUDGET: u32 = 1 << 28;
+
+/// Bit of each feature enabled in this build
+const FEATURES: u32 = {
+    let mut bits = 0;
+    if cfg!(feature = "sem") { bits |= OS_FEATURE_SEM; }
+    if cfg!(feature = "mutex") { bits |= OS_FEATURE_MUTEX; }
+    if cfg!(feature = "rwlock") { bits |= OS_FEATURE_RWLOCK; }
+    if cfg!(feature = "barrier") { bits |= OS_FEATURE_BARRIER; }
+    if cfg!(feature = "ring") { bits |= OS_FEATURE_RING; }
+    if cfg!(feature = "tick-task") { bits |= OS_FEATURE_TICK_TASK; }
+    if cfg!(feature = "trace") { bits |= OS_FEATURE_TRACE; }
+    if cfg!(feature = "kernel-awareness") { bits |= OS_FEATURE_KERNEL_AWARENESS; }
+    if cfg!(feature = "wdog") { bits |= OS_FEATURE_WDOG; }
+    if cfg!(feature = "task-group") { bits |= OS_FEATURE_TASK_GROUP; }
+    if cfg!(feature = "task-table") { bits |= OS_FEATURE_TASK_TABLE; }
+    if cfg!(feature = "self-check") { bits |= OS_FEATURE_SELF_CHECK; }
+    if cfg!(feature = "clock") { bits |= OS_FEATURE_CLOCK; }
+    if cfg!(feature = "shell") { bits |= OS_FEATURE_SHELL; }
+    if cfg!(feature = "obj-stats") { bits |= OS_FEATURE_OBJ_STATS; }
+    if cfg!(feature = "obj-pool") { bits |= OS_FEATURE_OBJ_POOL; }
+    if cfg!(feature = "fault-recovery") { bits |= OS_FEATURE_FAULT_RECOVERY; }
+    if cfg!(feature = "latency-stats") { bits |= OS_FEATURE_LATENCY_STATS; }
+    if cfg!(feature = "no-names") { bits |= OS_FEATURE_NO_NAMES; }
+    if cfg!(feature = "deadlock-detect") { bits |= OS_FEATURE_DEADLOCK_DETECT; }
+    if cfg!(feature = "paranoid") { bits |= OS_FEATURE_PARANOID; }
+    if cfg!(feature = "fpu-check") { bits |= OS_FEATURE_FPU_CHECK; }
+    if cfg!(feature = "rr-precise") { bits |= OS_FEATURE_RR_PRECISE; }
+    if cfg!(feature = "tick-list") { bits |= OS_FEATURE_TICK_LIST; }
+    if cfg!(feature = "idle-nop") { bits |= OS_FEATURE_IDLE_NOP; }
+    if cfg!(feature = "no-systick") { bits |= OS_FEATURE_NO_SYSTICK; }
+    if cfg!(feature = "os3-compat") { bits |= OS_FEATURE_OS3_COMPAT; }
+    if cfg!(feature = "deinit") { bits |= OS_FEATURE_DEINIT; }
+    if cfg!(feature = "budget") { bits |= OS_FEATURE_BUDGET; }
+    bits
+};
+
+/// Crate version string, e.g. `"0.1.0"`
+pub fn os_version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// Kernel features this image was built with, `OS_FEATURE_*` bits
+pub fn os_features() -> u32 {
+    FEATURES
+}
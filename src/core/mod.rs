@@ -2,6 +2,47 @@
 //!
 //! Contains kernel, scheduler, task management, and time management.
 
+/// Record a trace event; expands to nothing without the `trace` feature
+///
+/// Takes a `TraceEvent` variant, e.g. `os_trace!(SemPost { obj })`.
+macro_rules! os_trace {
+    ($($ev:tt)*) => {
+        #[cfg(feature = "trace")]
+        $crate::core::trace::record($crate::core::trace::TraceEvent::$($ev)*);
+    };
+}
+pub(crate) use os_trace;
+
+/// Run an intrusive list check; expands to nothing without the `paranoid`
+/// feature
+///
+/// `os_list_chk!(link, tcb, Ready, list, "op")` and `unlink` check and update
+/// the TCB tag, `os_list_chk!(walk, head, tail, count, Ready, list, "op")`
+/// validates the whole list.
+macro_rules! os_list_chk {
+    (walk, $head:expr, $tail:expr, $count:expr, $kind:ident, $list:expr, $op:expr) => {
+        #[cfg(feature = "paranoid")]
+        $crate::core::paranoid::walk(
+            $head,
+            $tail,
+            $count,
+            $crate::core::paranoid::ListKind::$kind,
+            $list as *const _ as *const (),
+            $op,
+        );
+    };
+    ($f:ident, $tcb:expr, $kind:ident, $list:expr, $op:expr) => {
+        #[cfg(feature = "paranoid")]
+        $crate::core::paranoid::$f(
+            $tcb,
+            $crate::core::paranoid::ListKind::$kind,
+            $list as *const _ as *const (),
+            $op,
+        );
+    };
+}
+pub(crate) use os_list_chk;
+
 pub mod config;
 pub mod critical;
 pub mod error;
@@ -12,3 +53,17 @@ pub mod task;
 pub mod sched;
 pub mod time;
 pub mod cs_cell;
+#[cfg(feature = "os3-compat")]
+pub mod compat;
+#[cfg(feature = "embassy-time-driver")]
+pub mod embassy;
+#[cfg(feature = "paranoid")]
+pub mod paranoid;
+#[cfg(feature = "self-check")]
+pub mod selfcheck;
+#[cfg(feature = "shell")]
+pub mod shell;
+#[cfg(feature = "trace")]
+pub mod trace;
+#[cfg(feature = "wdog")]
+pub mod wdog;
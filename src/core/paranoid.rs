@@ -0,0 +1,155 @@
+//! Intrusive list corruption checks
+//!
+//! Only compiled with the `paranoid` feature. Every TCB records which ready,
+//! pend and tick-wheel list it is linked into. Inserting an already linked
+//! TCB, or removing a TCB from a list it is not in, is reported at the point
+//! of the mistake instead of surfacing later as a corrupted list.
+//!
+//! With `CFG_PARANOID_WALK_EN` the whole list is also walked after each
+//! mutation to check that the links are consistent with its head and tail.
+//!
+//! A violation calls the `list_fault` application hook with the task name
+//! and the operation, then panics.
+
+use core::ptr::NonNull;
+
+use crate::config::CFG_PARANOID_WALK_EN;
+use crate::kernel::hooks;
+use crate::task::OsTcb;
+
+/// Upper bound on a walk, catches cycles
+const WALK_LIMIT: usize = 1024;
+
+/// Link set of a TCB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListKind {
+    /// `next_ptr`/`prev_ptr`
+    Ready,
+    /// `pend_next_ptr`/`pend_prev_ptr`
+    Pend,
+    /// `tick_next_ptr`/`tick_prev_ptr`
+    Tick,
+}
+
+/// Lists a TCB is currently linked into, by list address (null if unlinked)
+#[derive(Debug, Clone, Copy)]
+pub struct ListTag {
+    /// Ready list
+    pub rdy: *const (),
+    /// Pend list
+    pub pend: *const (),
+    /// Tick wheel slot
+    pub tick: *const (),
+}
+
+impl ListTag {
+    /// Tag of a TCB that is in no list
+    pub const UNLINKED: Self = Self {
+        rdy: core::ptr::null(),
+        pend: core::ptr::null(),
+        tick: core::ptr::null(),
+    };
+
+    fn slot(&mut self, kind: ListKind) -> &mut *const () {
+        match kind {
+            ListKind::Ready => &mut self.rdy,
+            ListKind::Pend => &mut self.pend,
+            ListKind::Tick => &mut self.tick,
+        }
+    }
+}
+
+/// Report a violation and stop
+#[cold]
+fn violation(tcb: NonNull<OsTcb>, op: &'static str) -> ! {
+    let name = unsafe { tcb.as_ref() }.name();
+    hooks::list_fault(name, op);
+    panic!("list corruption: {} on task {}", op, name);
+}
+
+/// Check that `tcb` is unlinked from `kind` lists and tag it with `list`
+pub(crate) fn link(tcb: NonNull<OsTcb>, kind: ListKind, list: *const (), op: &'static str) {
+    let slot = unsafe { (*tcb.as_ptr()).which_list.slot(kind) };
+    if !slot.is_null() {
+        violation(tcb, op);
+    }
+    *slot = list;
+}
+
+/// Check that `tcb` is tagged with `list` and clear the tag
+pub(crate) fn unlink(tcb: NonNull<OsTcb>, kind: ListKind, list: *const (), op: &'static str) {
+    let slot = unsafe { (*tcb.as_ptr()).which_list.slot(kind) };
+    if *slot != list {
+        violation(tcb, op);
+    }
+    *slot = core::ptr::null();
+}
+
+fn links(tcb: &OsTcb, kind: ListKind) -> (Option<NonNull<OsTcb>>, Option<NonNull<OsTcb>>) {
+    match kind {
+        ListKind::Ready => (tcb.next_ptr, tcb.prev_ptr),
+        ListKind::Pend => (tcb.pend_next_ptr, tcb.pend_prev_ptr),
+        ListKind::Tick => (tcb.tick_next_ptr, tcb.tick_prev_ptr),
+    }
+}
+
+/// Walk a list and check its links against `head`, `tail` and `count`
+///
+/// `tail` and `count` are `None` for lists that do not track them.
+pub(crate) fn walk(
+    head: Option<NonNull<OsTcb>>,
+    tail: Option<Option<NonNull<OsTcb>>>,
+    count: Option<usize>,
+    kind: ListKind,
+    list: *const (),
+    op: &'static str,
+) {
+    if !CFG_PARANOID_WALK_EN {
+        return;
+    }
+
+    let mut prev: Option<NonNull<OsTcb>> = None;
+    let mut cur = head;
+    let mut n = 0;
+
+    while let Some(tcb) = cur {
+        let tcb_ref = unsafe { tcb.as_ref() };
+        let (next, back) = links(tcb_ref, kind);
+        let tag = unsafe { *(*tcb.as_ptr()).which_list.slot(kind) };
+
+        n += 1;
+        if back != prev || tag != list || n > WALK_LIMIT {
+            violation(tcb, op);
+        }
+
+        prev = cur;
+        cur = next;
+    }
+
+    let tail_bad = tail.is_some_and(|t| t != prev);
+    let count_bad = count.is_some_and(|c| c != n);
+    if tail_bad || count_bad {
+        // Blame the last linked task, or the head if the list looks empty
+        if let Some(tcb) = prev.or(head).or(tail.flatten()) {
+            violation(tcb, op);
+        }
+        panic!("list corruption: {} on empty list", op);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sched::ReadyList;
+
+    #[test]
+    #[should_panic(expected = "ReadyList::insert_tail")]
+    fn test_double_insert_detected() {
+        let mut tcb = OsTcb::new();
+        let tcb = NonNull::from(&mut tcb);
+
+        let mut list = ReadyList::new();
+        list.insert_tail(tcb);
+        list.insert_tail(tcb);
+    }
+}
@@ -2,7 +2,11 @@
 //!
 //! This module implements the priority table using a bitmap approach.
 //! Leverages the CLZ (Count Leading Zeros) instruction for efficient
-//! highest-priority determination.
+//! highest-priority determination. ARMv6-M has no CLZ, so a de Bruijn
+//! multiply lookup is used there instead.
+//!
+//! Tables of one or two words (up to 64 priorities) skip the word loop; the
+//! path is picked at compile time from `CFG_PRIO_MAX`.
 
 use crate::config::CFG_PRIO_MAX;
 use crate::types::OsPrio;
@@ -54,37 +58,47 @@ impl PrioTable {
         self.bitmap[word_idx] &= !(1 << bit_pos);
     }
 
-    /// Get the highest priority
+    /// Get the highest ready priority, `None` if the table is empty
     #[inline]
-    pub fn get_highest(&self) -> OsPrio {
-        #[cfg(any())]
-        {
-            // Single word optimization (up to 32 priorities)
-            if PRIO_TBL_SIZE == 1 {
-                return Self::clz(self.bitmap[0]);
-            }
-            
-            // Two word optimization (up to 64 priorities)
-            if PRIO_TBL_SIZE == 2 {
-                if self.bitmap[0] != 0 {
-                    return Self::clz(self.bitmap[0]);
-                } else {
-                    return 32 + Self::clz(self.bitmap[1]);
-                }
-            }
+    pub fn get_highest_checked(&self) -> Option<OsPrio> {
+        // Single word optimization (up to 32 priorities)
+        if PRIO_TBL_SIZE == 1 {
+            let word = self.bitmap[0];
+            return if word != 0 { Some(Self::clz(word)) } else { None };
+        }
+
+        // Two word optimization (up to 64 priorities)
+        if PRIO_TBL_SIZE == 2 {
+            let (w0, w1) = (self.bitmap[0], self.bitmap[PRIO_TBL_SIZE - 1]);
+            return if w0 != 0 {
+                Some(Self::clz(w0))
+            } else if w1 != 0 {
+                Some(32 + Self::clz(w1))
+            } else {
+                None
+            };
         }
 
         let mut prio: OsPrio = 0;
         for &word in self.bitmap.iter() {
             if word != 0 {
-                prio += Self::clz(word);
-                return prio;
+                return Some(prio + Self::clz(word));
             }
-            prio += 32;
+            prio = prio.wrapping_add(32);
         }
 
-        // return lowest priority
-        (CFG_PRIO_MAX - 1) as OsPrio
+        None
+    }
+
+    /// Get the highest priority
+    ///
+    /// Returns the lowest priority when the table is empty; use
+    /// [`get_highest_checked`](Self::get_highest_checked) to tell the two
+    /// apart.
+    #[inline]
+    pub fn get_highest(&self) -> OsPrio {
+        self.get_highest_checked()
+            .unwrap_or((CFG_PRIO_MAX - 1) as OsPrio)
     }
 
     /// Check if a specific priority has any ready tasks
@@ -96,6 +110,12 @@ impl PrioTable {
         (self.bitmap[word_idx] & (1 << bit_pos)) != 0
     }
 
+    /// Bitmap words, priority 0 in the top bit of the first
+    #[inline]
+    pub fn words(&self) -> &[u32] {
+        &self.bitmap
+    }
+
     /// Check if the priority table is empty
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -108,11 +128,41 @@ impl PrioTable {
         if value == 0 {
             32
         } else {
-            value.leading_zeros() as OsPrio
+            #[cfg(not(armv6m))]
+            {
+                value.leading_zeros() as OsPrio
+            }
+            #[cfg(armv6m)]
+            {
+                clz_debruijn(value)
+            }
         }
     }
 }
 
+/// Bit position lookup for the `0x07C4ACDD` de Bruijn sequence
+const DEBRUIJN_POS: [u8; 32] = [
+    0, 9, 1, 10, 13, 21, 2, 29, 11, 14, 16, 18, 22, 25, 3, 30,
+    8, 12, 20, 28, 15, 17, 24, 7, 19, 27, 23, 6, 26, 5, 4, 31,
+];
+
+/// Count leading zeros of a non-zero word without a CLZ instruction
+///
+/// Smears the highest set bit downwards, then finds its position with one
+/// multiply and a table lookup.
+#[inline]
+#[cfg_attr(not(armv6m), allow(dead_code))]
+fn clz_debruijn(mut value: u32) -> OsPrio {
+    value |= value >> 1;
+    value |= value >> 2;
+    value |= value >> 4;
+    value |= value >> 8;
+    value |= value >> 16;
+
+    let msb = DEBRUIJN_POS[(value.wrapping_mul(0x07C4_ACDD) >> 27) as usize];
+    31 - msb
+}
+
 impl Default for PrioTable {
     fn default() -> Self {
         Self::new()
@@ -128,6 +178,26 @@ mod tests {
         let table = PrioTable::new();
         assert!(table.is_empty());
         assert_eq!(table.get_highest(), (CFG_PRIO_MAX - 1) as OsPrio);
+        assert_eq!(table.get_highest_checked(), None);
+    }
+
+    #[test]
+    fn test_lowest_prio_checked() {
+        let mut table = PrioTable::new();
+        let lowest = (CFG_PRIO_MAX - 1) as OsPrio;
+
+        table.insert(lowest);
+        assert_eq!(table.get_highest_checked(), Some(lowest));
+    }
+
+    #[test]
+    fn test_clz_debruijn() {
+        for shift in 0..32 {
+            let v = 1u32 << shift;
+            assert_eq!(clz_debruijn(v) as u32, v.leading_zeros());
+            assert_eq!(clz_debruijn(v | 1) as u32, v.leading_zeros());
+        }
+        assert_eq!(clz_debruijn(u32::MAX), 0);
     }
 
     #[test]
@@ -3,17 +3,24 @@
 //! Priority-based preemptive scheduler with round-robin for same priority.
 
 mod rdy_list;
+mod rdy_queues;
+#[cfg(feature = "rr-precise")]
+mod slice;
 
 pub use rdy_list::ReadyList;
+pub use rdy_queues::ReadyQueues;
+#[cfg(feature = "rr-precise")]
+pub(crate) use slice::slice_switch;
 
 use core::ptr::NonNull;
 
-use crate::config::CFG_SCHED_ROUND_ROBIN_EN;
 use crate::critical::{critical_section, CriticalSection, is_isr_context};
 
-use crate::kernel;
+use crate::config::{CFG_PRIO_IDLE, CFG_PRIO_MAX};
+use crate::error::{OsError, OsResult};
+use crate::kernel::{self, SchedMode, SchedState};
 use crate::task::OsTcb;
-use crate::types::OsPrio;
+use crate::types::{OsPrio, OsTick};
 
 /// Main scheduling point
 ///
@@ -29,35 +36,108 @@ pub fn os_sched() {
     }
     
     if is_isr_context() {
+        // Inside `os_int_enter`/`os_int_exit` the switch is left to the
+        // outermost exit; a handler without them pends it now
+        if kernel::KERNEL.int_nesting() == 0 {
+            kernel::os_int_sched(&CriticalSection::enter());
+        }
         return;
     }
 
     if kernel::KERNEL.sched_lock_nesting() > 0 {
+        sched_locked_chk();
         return;
     }
 
-    let _cs = CriticalSection::enter();
+    kernel::ctx_sw_request(&CriticalSection::enter(), false);
+}
 
-    let high_prio = unsafe { kernel::prio_table().get_highest() };
-    
-    unsafe {
-        if let Some(high_rdy) = kernel::rdy_list(high_prio).head() {
-            kernel::set_prio_high_rdy(high_prio);
-            kernel::set_tcb_high_rdy_ptr(Some(high_rdy));
-            
-            if Some(high_rdy) != kernel::tcb_cur_ptr() {
-                crate::port::os_ctx_sw();
-            }
+/// Report a running task that left the ready list under a scheduler lock
+///
+/// Nothing else may run until the lock is released, which the task can no
+/// longer do. The blocking services refuse this with `SchedLocked`; getting
+/// here means a path that does not.
+fn sched_locked_chk() {
+    let stuck = critical_section(|_cs| unsafe { kernel::tcb_cur_ptr() }.is_some_and(|t| !unsafe { t.as_ref() }.is_ready()));
+    if stuck {
+        kernel::hooks::fault(OsError::SchedLocked);
+        debug_assert!(false, "running task blocked with the scheduler locked");
+    }
+}
+
+/// Give the CPU to another ready task of the same or higher priority
+///
+/// The calling task moves to the tail of its priority's ready list. In
+/// cooperative mode this is also the point where a higher priority task
+/// readied by an interrupt gets to run.
+///
+/// # Returns
+/// * `Err(OsError::YieldIsr)` - Called from an ISR
+/// * `Err(OsError::SchedLocked)` - Scheduler is locked
+pub fn os_sched_yield() -> OsResult<()> {
+    if is_isr_context() {
+        return Err(OsError::YieldIsr);
+    }
+
+    if !kernel::KERNEL.is_running() {
+        return Err(OsError::OsNotRunning);
+    }
+
+    if kernel::KERNEL.sched_lock_nesting() > 0 {
+        return Err(OsError::SchedLocked);
+    }
+
+    critical_section(|_cs| unsafe {
+        if let Some(cur) = kernel::tcb_cur_ptr() {
+            let rdy = kernel::rdy_queues();
+            rdy.remove(cur);
+            rdy.insert(cur);
         }
+        os_sched();
+    });
+    Ok(())
+}
+
+/// Enable or disable round-robin for one priority level
+///
+/// `os_init` enables every level when `OsConfig::round_robin_en` is set and
+/// none otherwise. A level with round-robin off runs each task until it
+/// blocks or yields, whatever its time quanta.
+///
+/// # Returns
+/// * `Err(OsError::PrioInvalid)` - `prio` is not below `CFG_PRIO_MAX`
+/// * `Err(OsError::CfgSchedModeInvalid)` - Enabling in cooperative mode
+pub fn os_sched_rr_enable_prio(prio: OsPrio, en: bool) -> OsResult<()> {
+    if prio as usize >= CFG_PRIO_MAX {
+        return Err(OsError::PrioInvalid);
+    }
+
+    if en && kernel::cfg().sched_mode == SchedMode::Cooperative {
+        return Err(OsError::CfgSchedModeInvalid);
     }
+
+    critical_section(|cs| {
+        let rr_prio = &mut kernel::SCHED.get(cs).rr_prio;
+        if en {
+            rr_prio.insert(prio);
+        } else {
+            rr_prio.remove(prio);
+        }
+    });
+    Ok(())
+}
+
+/// Check whether round-robin is enabled for a priority level
+pub fn os_sched_rr_prio_enabled(prio: OsPrio) -> bool {
+    (prio as usize) < CFG_PRIO_MAX && critical_section(|cs| kernel::SCHED.get(cs).rr_prio.is_set(prio))
 }
 
 /// Round-robin scheduling for tasks at the same priority
+///
+/// Called from the tick interrupt. A rotation is scheduled like any other
+/// readiness change, so a higher priority task readied by the same tick
+/// still wins.
 pub fn os_sched_round_robin() {
-    if !CFG_SCHED_ROUND_ROBIN_EN {
-        return;
-    }
-
     if !kernel::KERNEL.is_running() {
         return;
     }
@@ -66,63 +146,121 @@ pub fn os_sched_round_robin() {
         return;
     }
 
-    critical_section(|_cs| {
+    critical_section(|cs| {
         unsafe {
             if let Some(cur_tcb_ptr) = kernel::tcb_cur_ptr() {
-                let cur_tcb = &mut *cur_tcb_ptr.as_ptr();
-                
-                if cur_tcb.time_quanta_ctr > 0 {
-                    cur_tcb.time_quanta_ctr -= 1;
-                }
-                
-                if cur_tcb.time_quanta_ctr == 0 {
-                    cur_tcb.time_quanta_ctr = cur_tcb.time_quanta;
-                    
-                    let prio = cur_tcb.prio;
-                    let rdy_list = kernel::rdy_list(prio);
-                    
-                    // Only rotate if more than one task at this priority
-                    if rdy_list.head() != rdy_list.tail() {
-                        rdy_list.remove(cur_tcb_ptr);
-                        rdy_list.insert_tail(cur_tcb_ptr);
-                        
-                        if let Some(new_head) = rdy_list.head() {
-                            kernel::set_tcb_high_rdy_ptr(Some(new_head));
-                        }
-                        
-                        crate::port::os_ctx_sw();
-                    }
+                if os_sched_round_robin_charge(cur_tcb_ptr).is_some() {
+                    kernel::os_int_sched(cs);
                 }
             }
         }
     });
 }
 
-/// Make a task ready
-pub(crate) unsafe fn os_rdy_list_insert(tcb: NonNull<OsTcb>) {
-    let tcb_ref = unsafe { tcb.as_ref() };
+/// Charge one tick to a running task's time quanta
+///
+/// With `rr-precise` the task's measured run time is checked against its
+/// slice instead. When the quanta is used up and other tasks share its
+/// priority, the task is moved to the tail of its ready list and the new
+/// head is returned.
+/// Tasks with a quanta of 0, a preemption threshold below their priority or
+/// a priority with round-robin disabled are never rotated. Must be called
+/// inside a critical section.
+pub(crate) unsafe fn os_sched_round_robin_charge(tcb: NonNull<OsTcb>) -> Option<NonNull<OsTcb>> {
+    unsafe { round_robin_charge(kernel::SCHED.get_unchecked(), tcb) }
+}
+
+unsafe fn round_robin_charge(sched: &mut SchedState, tcb: NonNull<OsTcb>) -> Option<NonNull<OsTcb>> {
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+
+    if tcb_ref.time_quanta == 0 || tcb_ref.preempted_by(tcb_ref.prio) || !sched.rr_prio.is_set(tcb_ref.prio) {
+        return None;
+    }
+
+    if !slice_charge(tcb_ref) {
+        return None;
+    }
+
     let prio = tcb_ref.prio;
-    
-    unsafe {
-        let rdy_list = kernel::rdy_list(prio);
-        rdy_list.insert_tail(tcb);
-        kernel::prio_table().insert(prio);
+    let rdy = &mut sched.rdy;
+
+    // Only rotate if more than one task at this priority
+    if rdy.list(prio).head() == rdy.list(prio).tail() {
+        return None;
+    }
+
+    rdy.remove(tcb);
+    rdy.insert(tcb);
+    rdy.list(prio).head()
+}
+
+/// Charge the running task one tick; returns whether its slice ran out
+#[cfg(not(feature = "rr-precise"))]
+fn slice_charge(tcb: &mut OsTcb) -> bool {
+    if tcb.time_quanta_ctr > 0 {
+        tcb.time_quanta_ctr -= 1;
     }
+
+    if tcb.time_quanta_ctr != 0 {
+        return false;
+    }
+    tcb.time_quanta_ctr = tcb.time_quanta;
+    true
+}
+
+/// Check the running task's measured run time; returns whether its slice ran out
+#[cfg(feature = "rr-precise")]
+fn slice_charge(tcb: &mut OsTcb) -> bool {
+    slice::slice_expired(tcb, crate::time::os_ts_get())
+}
+
+// ============ Introspection ============
+//
+// Each call takes one snapshot under a critical section. An interrupt may
+// ready or delay a task right after it returns, so callers deciding to
+// sleep (e.g. an idle policy) must re-check with interrupts masked.
+
+/// Ticks until the nearest delay expiry, `None` if no task is delayed
+///
+/// 0 means an expiry is due and will be processed on the next tick.
+pub fn os_next_wake_ticks() -> Option<OsTick> {
+    critical_section(|_cs| unsafe {
+        kernel::SCHED.get_unchecked().next_expiry(kernel::KERNEL.tick_get())
+    })
+}
+
+/// Number of ready tasks, including the running one
+///
+/// # Arguments
+/// * `exclude_idle` - Do not count the idle task
+pub fn os_ready_task_count(exclude_idle: bool) -> usize {
+    let idle = exclude_idle.then(kernel::idle_tcb_ptr);
+    critical_section(|_cs| unsafe { kernel::SCHED.get_unchecked().ready_count(idle) })
+}
+
+/// Highest priority with a ready task
+///
+/// `CFG_PRIO_IDLE` when nothing else is ready, including before `os_init`.
+pub fn os_highest_ready_prio() -> OsPrio {
+    critical_section(|_cs| unsafe { kernel::rdy_queues().highest() }).unwrap_or(CFG_PRIO_IDLE)
+}
+
+/// Make a woken task ready
+///
+/// Ahead of its peers if it still has round-robin slice left, see
+/// [`ReadyQueues::insert_woken`].
+pub(crate) unsafe fn os_rdy_list_insert(tcb: NonNull<OsTcb>) {
+    unsafe { os_rdy_list_insert_woken(tcb, false) };
+}
+
+/// Make a woken task ready, ahead of its peers with `front`
+pub(crate) unsafe fn os_rdy_list_insert_woken(tcb: NonNull<OsTcb>, front: bool) {
+    unsafe { kernel::rdy_queues().insert_woken(tcb, front, kernel::tcb_cur_ptr()) };
 }
 
 /// Remove a task from ready list
 pub(crate) unsafe fn os_rdy_list_remove(tcb: NonNull<OsTcb>) {
-    let tcb_ref = unsafe { tcb.as_ref() };
-    let prio = tcb_ref.prio;
-    
-    unsafe {
-        let rdy_list = kernel::rdy_list(prio);
-        rdy_list.remove(tcb);
-        
-        if rdy_list.is_empty() {
-            kernel::prio_table().remove(prio);
-        }
-    }
+    unsafe { kernel::rdy_queues().remove(tcb) };
 }
 
 /// Move task to different priority
@@ -130,26 +268,96 @@ pub(crate) unsafe fn os_rdy_list_change_prio(
     tcb: NonNull<OsTcb>,
     new_prio: OsPrio,
 ) {
-    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
-    let old_prio = tcb_ref.prio;
-    
-    if old_prio == new_prio {
+    if unsafe { tcb.as_ref() }.prio == new_prio {
         return;
     }
 
-    unsafe {
-        let old_rdy_list = kernel::rdy_list(old_prio);
-        old_rdy_list.remove(tcb);
-        if old_rdy_list.is_empty() {
-            kernel::prio_table().remove(old_prio);
+    unsafe { kernel::rdy_queues().change_prio(tcb, new_prio) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Only the level with round-robin enabled rotates on quanta expiry
+    #[cfg(not(feature = "rr-precise"))]
+    #[test]
+    fn test_rr_per_prio() {
+        let mut tcbs = [5, 5, 20, 20].map(|prio| {
+            let mut t = OsTcb::new();
+            t.prio = prio;
+            t.time_quanta = 2;
+            t.time_quanta_ctr = 2;
+            t
+        });
+        let [ctl_a, ctl_b, work_a, work_b] = tcbs.each_mut().map(NonNull::from);
+        let mut sched = SchedState::new();
+        for t in [ctl_a, ctl_b, work_a, work_b] {
+            sched.rdy.insert(t);
         }
+        sched.rr_prio.insert(20);
+
+        // The control level keeps its head however many ticks pass
+        for _ in 0..10 {
+            assert_eq!(unsafe { round_robin_charge(&mut sched, ctl_a) }, None);
+            assert_eq!(sched.rdy.list(5).head(), Some(ctl_a));
+        }
+
+        // The worker level hands over every two ticks
+        assert_eq!(unsafe { round_robin_charge(&mut sched, work_a) }, None);
+        assert_eq!(unsafe { round_robin_charge(&mut sched, work_a) }, Some(work_b));
+        assert_eq!(unsafe { round_robin_charge(&mut sched, work_b) }, None);
+        assert_eq!(unsafe { round_robin_charge(&mut sched, work_b) }, Some(work_a));
+
+        // Disabling the level stops the rotation
+        sched.rr_prio.remove(20);
+        for _ in 0..10 {
+            assert_eq!(unsafe { round_robin_charge(&mut sched, work_a) }, None);
+        }
+        assert_eq!(sched.rdy.list(20).head(), Some(work_a));
     }
 
-    tcb_ref.prio = new_prio;
-    
-    unsafe {
-        let new_rdy_list = kernel::rdy_list(new_prio);
-        new_rdy_list.insert_tail(tcb);
-        kernel::prio_table().insert(new_prio);
+    /// A task that always blocks just before the tick still uses up its
+    /// slice; the one that sees the tick pays only for what it ran
+    #[cfg(feature = "rr-precise")]
+    #[test]
+    fn test_rr_precise_charges_run_time() {
+        use slice::{slice_expired, slice_switch_at};
+
+        const TICK: u32 = 1000;
+        let [mut a, mut b] = [(); 2].map(|()| {
+            let mut t = OsTcb::new();
+            t.prio = 20;
+            t.time_quanta = 2;
+            t.time_slice_ts = 2 * TICK;
+            t
+        });
+        let pa: *mut OsTcb = &mut a;
+        let pb: *mut OsTcb = &mut b;
+
+        // A runs 0.9 ticks and blocks, B runs the rest and sees the tick
+        let mut now = 0;
+        unsafe { slice_switch_at(core::ptr::null_mut(), now) };
+        for _ in 0..2 {
+            now += 900;
+            unsafe { slice_switch_at(pa, now) };
+            now += 100;
+            assert!(!slice_expired(unsafe { &mut *pb }, now));
+            unsafe { slice_switch_at(pb, now) };
+        }
+        assert_eq!(unsafe { ((*pa).slice_used, (*pb).slice_used) }, (1800, 200));
+        assert!(unsafe { &*pa }.slice_started());
+
+        // A's third run ends its slice while blocked: woken, it queues
+        // at the tail
+        now += 900;
+        unsafe { slice_switch_at(pa, now) };
+        assert_eq!(unsafe { (*pa).slice_used }, 0);
+        assert!(!unsafe { &*pa }.slice_started());
+
+        // B rotates once it has really run two ticks
+        assert!(!slice_expired(unsafe { &mut *pb }, now + 1799));
+        assert!(slice_expired(unsafe { &mut *pb }, now + 1800));
+        assert_eq!(unsafe { (*pb).slice_used }, 0);
     }
 }
@@ -1,7 +1,8 @@
 //! Ready list - doubly linked list of TCBs at a given priority
 //!
 //! Each priority level has its own ready list. Tasks are added to the
-//! tail (FIFO for round-robin) and scheduled from the head.
+//! tail (FIFO for round-robin), or ahead of their peers when woken with
+//! slice left, and scheduled from the head.
 
 use core::ptr::NonNull;
 
@@ -12,11 +13,18 @@ use crate::task::OsTcb;
 /// Doubly-linked list of tasks ready to run at this priority.
 /// Tasks are inserted at the tail for FIFO ordering and
 /// scheduled from the head.
+///
+/// Neither `Send` nor `Sync`, nor `Clone`: the kernel's lists live in
+/// `kernel::SCHED` behind a `CsCell`, and a copy would alias the TCB links.
+///
+/// ```compile_fail
+/// fn sync<T: Sync>() {}
+/// sync::<ucosiii::sched::ReadyList>();
+/// ```
 #[derive(Debug)]
 pub struct ReadyList {
     head: Option<NonNull<OsTcb>>,
     tail: Option<NonNull<OsTcb>>,
-    #[cfg(feature = "defmt")]
     count: usize,
 }
 
@@ -26,7 +34,6 @@ impl ReadyList {
         ReadyList {
             head: None,
             tail: None,
-            #[cfg(feature = "defmt")]
             count: 0,
         }
     }
@@ -35,10 +42,7 @@ impl ReadyList {
     pub fn init(&mut self) {
         self.head = None;
         self.tail = None;
-        #[cfg(feature = "defmt")]
-        {
-            self.count = 0;
-        }
+        self.count = 0;
     }
 
     /// Get head of list (first to be scheduled)
@@ -59,8 +63,7 @@ impl ReadyList {
         self.head.is_none()
     }
 
-    /// Get number of entries (only in debug mode)
-    #[cfg(feature = "defmt")]
+    /// Get number of entries
     pub fn count(&self) -> usize {
         self.count
     }
@@ -70,6 +73,8 @@ impl ReadyList {
     /// # Safety
     /// Caller must ensure tcb is valid and not already in any list.
     pub fn insert_tail(&mut self, tcb: NonNull<OsTcb>) {
+        os_list_chk!(link, tcb, Ready, self, "ReadyList::insert_tail");
+
         // SAFETY: We have exclusive access via critical section
         let tcb_ref = unsafe { &mut *tcb.as_ptr() };
 
@@ -89,10 +94,9 @@ impl ReadyList {
 
         self.tail = Some(tcb);
 
-        #[cfg(feature = "defmt")]
-        {
-            self.count += 1;
-        }
+        self.count += 1;
+
+        os_list_chk!(walk, self.head, Some(self.tail), self.chk_count(), Ready, self, "ReadyList::insert_tail");
     }
 
     /// Insert TCB at the head of the list (LIFO order)
@@ -102,6 +106,8 @@ impl ReadyList {
     /// # Safety
     /// Caller must ensure tcb is valid and not already in any list.
     pub fn insert_head(&mut self, tcb: NonNull<OsTcb>) {
+        os_list_chk!(link, tcb, Ready, self, "ReadyList::insert_head");
+
         let tcb_ref = unsafe { &mut *tcb.as_ptr() };
 
         tcb_ref.prev_ptr = None;
@@ -120,10 +126,34 @@ impl ReadyList {
 
         self.head = Some(tcb);
 
-        #[cfg(feature = "defmt")]
-        {
-            self.count += 1;
+        self.count += 1;
+
+        os_list_chk!(walk, self.head, Some(self.tail), self.chk_count(), Ready, self, "ReadyList::insert_head");
+    }
+
+    /// Insert TCB right after `anchor`
+    ///
+    /// # Safety
+    /// Caller must ensure `anchor` is in this list and tcb is valid and not
+    /// already in any list.
+    pub fn insert_after(&mut self, anchor: NonNull<OsTcb>, tcb: NonNull<OsTcb>) {
+        os_list_chk!(link, tcb, Ready, self, "ReadyList::insert_after");
+
+        let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+        let next = unsafe { anchor.as_ref() }.next_ptr;
+
+        tcb_ref.prev_ptr = Some(anchor);
+        tcb_ref.next_ptr = next;
+        unsafe { (*anchor.as_ptr()).next_ptr = Some(tcb) };
+
+        match next {
+            Some(next) => unsafe { (*next.as_ptr()).prev_ptr = Some(tcb) },
+            None => self.tail = Some(tcb),
         }
+
+        self.count += 1;
+
+        os_list_chk!(walk, self.head, Some(self.tail), self.chk_count(), Ready, self, "ReadyList::insert_after");
     }
 
     /// Remove a TCB from the list
@@ -131,6 +161,8 @@ impl ReadyList {
     /// # Safety
     /// Caller must ensure tcb is valid and is in this list.
     pub fn remove(&mut self, tcb: NonNull<OsTcb>) {
+        os_list_chk!(unlink, tcb, Ready, self, "ReadyList::remove");
+
         let tcb_ref = unsafe { &mut *tcb.as_ptr() };
 
         // Update previous node's next pointer
@@ -159,10 +191,15 @@ impl ReadyList {
         tcb_ref.prev_ptr = None;
         tcb_ref.next_ptr = None;
 
-        #[cfg(feature = "defmt")]
-        {
-            self.count = self.count.saturating_sub(1);
-        }
+        self.count = self.count.saturating_sub(1);
+
+        os_list_chk!(walk, self.head, Some(self.tail), self.chk_count(), Ready, self, "ReadyList::remove");
+    }
+
+    /// Entry count for list checks
+    #[cfg(feature = "paranoid")]
+    fn chk_count(&self) -> Option<usize> {
+        Some(self.count)
     }
 }
 
@@ -171,15 +208,3 @@ impl Default for ReadyList {
         Self::new()
     }
 }
-
-// SAFETY: ReadyList is only modified within critical sections
-unsafe impl Send for ReadyList {}
-unsafe impl Sync for ReadyList {}
-
-impl Copy for ReadyList {}
-
-impl Clone for ReadyList {
-    fn clone(&self) -> Self {
-        *self
-    }
-}
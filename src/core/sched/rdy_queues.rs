@@ -0,0 +1,134 @@
+//! Ready queues - per-priority ready lists plus the priority bitmap
+//!
+//! Owns both structures so the bitmap bit for a priority is set exactly
+//! while its ready list is non-empty. Callers never touch the bitmap.
+
+use core::ptr::NonNull;
+
+use crate::config::CFG_PRIO_MAX;
+use crate::prio::PrioTable;
+use crate::task::OsTcb;
+use crate::types::OsPrio;
+
+use super::ReadyList;
+
+/// All ready lists and the bitmap indexing them
+pub struct ReadyQueues {
+    prio_tbl: PrioTable,
+    lists: [ReadyList; CFG_PRIO_MAX],
+}
+
+impl ReadyQueues {
+    /// Create empty ready queues
+    pub const fn new() -> Self {
+        const EMPTY: ReadyList = ReadyList::new();
+        ReadyQueues {
+            prio_tbl: PrioTable::new(),
+            lists: [EMPTY; CFG_PRIO_MAX],
+        }
+    }
+
+    /// Empty all queues
+    pub fn init(&mut self) {
+        self.prio_tbl.init();
+        for list in self.lists.iter_mut() {
+            list.init();
+        }
+    }
+
+    /// Bitmap of the non-empty priorities
+    #[inline(always)]
+    pub fn prio_tbl(&self) -> &PrioTable {
+        &self.prio_tbl
+    }
+
+    /// Ready list of a priority
+    #[inline(always)]
+    pub fn list(&self, prio: OsPrio) -> &ReadyList {
+        &self.lists[prio as usize]
+    }
+
+    /// Highest priority with a ready task
+    #[inline(always)]
+    pub fn highest(&self) -> Option<OsPrio> {
+        self.prio_tbl.get_highest_checked()
+    }
+
+    /// Highest priority with a ready task and the task to run
+    #[inline]
+    pub fn highest_tcb(&self) -> Option<(OsPrio, NonNull<OsTcb>)> {
+        let prio = self.highest()?;
+        self.lists[prio as usize].head().map(|tcb| (prio, tcb))
+    }
+
+    /// Insert a task at the tail of the list for its current priority
+    ///
+    /// The task must not be in any ready list.
+    pub fn insert(&mut self, tcb: NonNull<OsTcb>) {
+        let prio = unsafe { tcb.as_ref() }.prio();
+        self.lists[prio as usize].insert_tail(tcb);
+        self.prio_tbl.insert(prio);
+    }
+
+    /// Insert a task at the head of the list for its current priority
+    ///
+    /// The task must not be in any ready list.
+    pub fn insert_head(&mut self, tcb: NonNull<OsTcb>) {
+        let prio = unsafe { tcb.as_ref() }.prio();
+        self.lists[prio as usize].insert_head(tcb);
+        self.prio_tbl.insert(prio);
+    }
+
+    /// Insert a task ahead of the waiting tasks of its priority
+    ///
+    /// That is the head of the list, or right behind `running` when it
+    /// heads the same list, so a peer is never preempted.
+    pub fn insert_front(&mut self, tcb: NonNull<OsTcb>, running: Option<NonNull<OsTcb>>) {
+        let prio = unsafe { tcb.as_ref() }.prio();
+        let list = &mut self.lists[prio as usize];
+        match running.filter(|&r| list.head() == Some(r)) {
+            Some(running) => list.insert_after(running, tcb),
+            None => list.insert_head(tcb),
+        }
+        self.prio_tbl.insert(prio);
+    }
+
+    /// Make a woken task ready
+    ///
+    /// A task that left Ready part-way through its round-robin slice, or
+    /// any task with `front`, goes ahead of its waiting peers to finish the
+    /// slice (see [`insert_front`](Self::insert_front)). Others queue at
+    /// the tail.
+    pub fn insert_woken(&mut self, tcb: NonNull<OsTcb>, front: bool, running: Option<NonNull<OsTcb>>) {
+        if front || unsafe { tcb.as_ref() }.slice_started() {
+            self.insert_front(tcb, running);
+        } else {
+            self.insert(tcb);
+        }
+    }
+
+    /// Remove a task from the list for its current priority
+    ///
+    /// The task must be in that list.
+    pub fn remove(&mut self, tcb: NonNull<OsTcb>) {
+        let prio = unsafe { tcb.as_ref() }.prio();
+        let list = &mut self.lists[prio as usize];
+        list.remove(tcb);
+        if list.is_empty() {
+            self.prio_tbl.remove(prio);
+        }
+    }
+
+    /// Move a ready task to another priority, at the tail of the new list
+    pub fn change_prio(&mut self, tcb: NonNull<OsTcb>, new_prio: OsPrio) {
+        self.remove(tcb);
+        unsafe { (*tcb.as_ptr()).prio = new_prio };
+        self.insert(tcb);
+    }
+}
+
+impl Default for ReadyQueues {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,66 @@
+//! Measured round-robin slices (`rr-precise`)
+//!
+//! The per-tick mode charges a whole tick to whichever task runs when the
+//! tick fires: a task that always blocks just before the tick is never
+//! charged, one switched in just before it pays a full tick for a sliver.
+//! Here the port stamps every context switch with the timestamp counter
+//! and a task is charged what it actually ran. The tick still decides when
+//! the running task is rotated, but only once its measured run time
+//! reaches the slice.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::kernel;
+use crate::task::OsTcb;
+use crate::time::{os_ts_diff, os_ts_freq_hz, os_ts_get};
+
+/// Timestamp the running task was switched in at
+static SLICE_START: AtomicU32 = AtomicU32::new(0);
+
+/// Charge the outgoing task the time it ran and stamp the switch
+///
+/// # Safety
+/// Called by the port's context switch with interrupts masked; `old` is
+/// the outgoing task or null.
+pub(crate) unsafe fn slice_switch(old: *mut OsTcb) {
+    unsafe { slice_switch_at(old, os_ts_get()) }
+}
+
+pub(super) unsafe fn slice_switch_at(old: *mut OsTcb, now: u32) {
+    if let Some(tcb) = unsafe { old.as_mut() } {
+        tcb.slice_used = tcb.slice_used.saturating_add(os_ts_diff(SLICE_START.load(Ordering::Relaxed), now));
+        // Used up while it blocked: a fresh slice, so it queues at the
+        // tail when woken
+        if tcb.slice_used >= slice_len(tcb) {
+            tcb.slice_used = 0;
+        }
+    }
+    SLICE_START.store(now, Ordering::Relaxed);
+}
+
+/// Whether the running task has used up its slice by `now`
+///
+/// Starts its next slice if so.
+pub(super) fn slice_expired(tcb: &mut OsTcb, now: u32) -> bool {
+    let start = SLICE_START.load(Ordering::Relaxed);
+    if tcb.slice_used.saturating_add(os_ts_diff(start, now)) < slice_len(tcb) {
+        return false;
+    }
+    tcb.slice_used = 0;
+    SLICE_START.store(now, Ordering::Relaxed);
+    true
+}
+
+/// Slice length in timestamp counts
+///
+/// `time_slice_ts` if set, otherwise `time_quanta` ticks; 0 (a slice per
+/// tick) while the counter frequency is unknown.
+fn slice_len(tcb: &OsTcb) -> u32 {
+    match tcb.time_slice_ts {
+        0 => {
+            let ts = tcb.time_quanta as u64 * os_ts_freq_hz() as u64 / kernel::cfg().tick_rate_hz as u64;
+            ts.min(u32::MAX as u64) as u32
+        }
+        ts => ts,
+    }
+}
@@ -0,0 +1,278 @@
+//! Kernel self-check
+//!
+//! The `paranoid` feature checks every list operation as it happens, which
+//! production builds cannot afford. This module checks the same state
+//! after the fact: a kernel-owned task, created by `os_init` when the
+//! `self-check` feature is enabled, runs [`os_self_check`] every
+//! `CFG_SELF_CHECK_PERIOD_TICKS` at a low priority. It verifies that
+//!
+//! - every priority bit agrees with its ready list being non-empty;
+//! - the ready, pend and tick queue lists are well formed: each back link
+//!   points at the task before it, the walk ends within a bound and meets
+//!   the recorded tail and count;
+//! - every ready task is in the ready list of its priority, and those lists
+//!   only hold ready tasks of that priority;
+//! - every delayed or timed pending task is on the tick queue, which holds
+//!   nothing else, and every pending task is in its object's wait list;
+//! - `tcb_cur` is a created task.
+//!
+//! A failed check calls the `self_check` application hook with an
+//! [`OsSelfCheckReport`] naming the invariant, then panics.
+//!
+//! The whole check runs in one critical section, like `os_dump`, so its
+//! length grows with the number of tasks and tick queue slots.
+
+use core::ptr::NonNull;
+
+use crate::config::{CFG_PRIO_MAX, CFG_SELF_CHECK_PERIOD_TICKS, CFG_SELF_CHECK_TASK_STK_SIZE};
+use crate::core::cs_cell::SyncUnsafeCell;
+use crate::critical::critical_section;
+use crate::error::OsResult;
+use crate::kernel::{hooks, CPU_STATE, KERNEL, SCHED};
+use crate::task::{OsTcb, Stack};
+use crate::types::{OsObjType, OsPrio, OsTaskState, OsTick};
+
+/// Upper bound on a list walk, catches cycles
+const WALK_LIMIT: usize = 1024;
+
+/// Kernel invariant a self-check found broken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invariant {
+    /// A priority bit disagrees with its ready list being empty
+    PrioBitmap,
+    /// A ready list's links disagree with each other, its tail or its count
+    ReadyList,
+    /// A wait list's links disagree with each other, its tail or its count
+    PendList,
+    /// A tick queue list's links disagree or do not end
+    TickList,
+    /// The task list does not end
+    TaskList,
+    /// A ready task is missing from its ready list, or a ready list holds
+    /// a task that is not ready at that priority
+    ReadyMember,
+    /// A pending task is missing from its object's wait list
+    PendMember,
+    /// A delayed or timed pending task is missing from the tick queue, or
+    /// the tick queue holds a task that is neither
+    TickMember,
+    /// `tcb_cur` is not a created task
+    TcbCur,
+}
+
+/// Failed self-check
+#[derive(Debug, Clone, Copy)]
+pub struct OsSelfCheckReport {
+    /// What is broken
+    pub invariant: Invariant,
+    /// Task where it showed, if one could be blamed
+    pub tcb: Option<NonNull<OsTcb>>,
+    /// Priority level, for the bitmap and ready list checks
+    pub prio: Option<OsPrio>,
+    /// Kernel tick of the check
+    pub tick: OsTick,
+}
+
+/// Self-check task TCB
+static CHECK_TCB: SyncUnsafeCell<OsTcb> = SyncUnsafeCell::new(OsTcb::new());
+
+/// Self-check task stack
+static CHECK_STK: SyncUnsafeCell<Stack<CFG_SELF_CHECK_TASK_STK_SIZE>> = SyncUnsafeCell::new(Stack::new());
+
+type Link = Option<NonNull<OsTcb>>;
+
+/// Next and previous link of one of a TCB's link sets
+type Links = fn(&OsTcb) -> (Link, Link);
+
+fn ready_links(tcb: &OsTcb) -> (Link, Link) {
+    (tcb.next_ptr, tcb.prev_ptr)
+}
+
+fn tick_links(tcb: &OsTcb) -> (Link, Link) {
+    (tcb.tick_next_ptr, tcb.tick_prev_ptr)
+}
+
+#[cfg(any(feature = "sem", feature = "mutex", feature = "rwlock", feature = "barrier"))]
+fn pend_links(tcb: &OsTcb) -> (Link, Link) {
+    (tcb.pend_next_ptr, tcb.pend_prev_ptr)
+}
+
+/// Walk a list, checking every back link against the task before it
+///
+/// `tail` and `count` are `None` for lists that do not track them. Returns
+/// the tasks in the list, or the task to blame.
+fn walk(head: Link, tail: Option<Link>, count: Option<usize>, links: Links) -> Result<usize, Link> {
+    let mut prev = None;
+    let mut cur = head;
+    let mut n = 0;
+
+    while let Some(tcb) = cur {
+        let (next, back) = links(unsafe { tcb.as_ref() });
+        n += 1;
+        if back != prev || n > WALK_LIMIT {
+            return Err(Some(tcb));
+        }
+        prev = cur;
+        cur = next;
+    }
+
+    if tail.is_some_and(|t| t != prev) || count.is_some_and(|c| c != n) {
+        return Err(prev.or(head));
+    }
+    Ok(n)
+}
+
+/// Whether `tcb` is in the list starting at `head`
+fn contains(head: Link, tcb: NonNull<OsTcb>, links: Links) -> bool {
+    let mut cur = head;
+    for _ in 0..WALK_LIMIT {
+        match cur {
+            Some(t) if t == tcb => return true,
+            Some(t) => cur = links(unsafe { t.as_ref() }).0,
+            None => return false,
+        }
+    }
+    false
+}
+
+/// Whether a task in `state` must be on the tick queue
+fn timed(state: OsTaskState) -> bool {
+    matches!(
+        state,
+        OsTaskState::Delayed | OsTaskState::PendTimeout | OsTaskState::DelayedSuspended | OsTaskState::PendTimeoutSuspended
+    )
+}
+
+/// Check the kernel invariants once
+///
+/// # Returns
+/// * `Err(report)` - The first invariant found broken
+pub fn os_self_check() -> Result<(), OsSelfCheckReport> {
+    critical_section(|cs| {
+        let sched = SCHED.get(cs);
+        let tick = KERNEL.tick_get();
+        let fail = |invariant, tcb, prio| {
+            Err(OsSelfCheckReport {
+                invariant,
+                tcb,
+                prio,
+                tick,
+            })
+        };
+
+        for prio in 0..CFG_PRIO_MAX {
+            let prio = prio as OsPrio;
+            let list = sched.rdy.list(prio);
+            if sched.rdy.prio_tbl().is_set(prio) == list.is_empty() {
+                return fail(Invariant::PrioBitmap, list.head(), Some(prio));
+            }
+            if let Err(tcb) = walk(list.head(), Some(list.tail()), Some(list.count()), ready_links) {
+                return fail(Invariant::ReadyList, tcb, Some(prio));
+            }
+
+            let mut node = list.head();
+            while let Some(tcb) = node {
+                let t = unsafe { tcb.as_ref() };
+                if !t.is_ready() || t.prio() != prio {
+                    return fail(Invariant::ReadyMember, Some(tcb), Some(prio));
+                }
+                node = t.next_ptr;
+            }
+        }
+
+        for &head in sched.tick_q.lists() {
+            if let Err(tcb) = walk(head, None, None, tick_links) {
+                return fail(Invariant::TickList, tcb, None);
+            }
+
+            let mut node = head;
+            while let Some(tcb) = node {
+                let t = unsafe { tcb.as_ref() };
+                if !timed(t.state()) {
+                    return fail(Invariant::TickMember, Some(tcb), None);
+                }
+                node = t.tick_next_ptr;
+            }
+        }
+
+        let mut node = sched.task_list;
+        let mut tasks = 0;
+        while let Some(tcb) = node {
+            tasks += 1;
+            if tasks > WALK_LIMIT {
+                return fail(Invariant::TaskList, Some(tcb), None);
+            }
+
+            let t = unsafe { tcb.as_ref() };
+            if t.is_ready() && !contains(sched.rdy.list(t.prio()).head(), tcb, ready_links) {
+                return fail(Invariant::ReadyMember, Some(tcb), Some(t.prio()));
+            }
+            if timed(t.state()) && !sched.tick_q.lists().iter().any(|&head| contains(head, tcb, tick_links)) {
+                return fail(Invariant::TickMember, Some(tcb), None);
+            }
+
+            #[cfg(any(feature = "sem", feature = "mutex", feature = "rwlock", feature = "barrier"))]
+            if t.is_pending() {
+                if let Some(list) = unsafe { crate::sync::pend_list_of(tcb) } {
+                    if let Err(blame) = walk(list.head(), Some(list.tail()), Some(list.len()), pend_links) {
+                        return fail(Invariant::PendList, blame, None);
+                    }
+                    if !contains(list.head(), tcb, pend_links) {
+                        return fail(Invariant::PendMember, Some(tcb), None);
+                    }
+                }
+            }
+
+            node = t.dbg_next_ptr;
+        }
+
+        if KERNEL.is_running() {
+            let cur = NonNull::new(CPU_STATE.get(cs).tcb_cur);
+            let created = cur.is_some_and(|cur| {
+                let mut node = sched.task_list;
+                while let Some(tcb) = node {
+                    if tcb == cur {
+                        return unsafe { cur.as_ref() }.obj_type == OsObjType::Task;
+                    }
+                    node = unsafe { tcb.as_ref() }.dbg_next_ptr;
+                }
+                false
+            });
+            if !created {
+                return fail(Invariant::TcbCur, cur, None);
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Self-check task
+fn os_self_check_task(_: *mut ()) -> ! {
+    loop {
+        let _ = crate::time::os_time_dly(CFG_SELF_CHECK_PERIOD_TICKS);
+        if let Err(report) = os_self_check() {
+            hooks::self_check(&report);
+            panic!("kernel self-check: {:?}", report.invariant);
+        }
+    }
+}
+
+/// Create the self-check task
+///
+/// Called by `os_init`.
+pub(crate) unsafe fn os_self_check_init() -> OsResult<()> {
+    unsafe {
+        crate::task::os_task_create_internal(
+            CHECK_TCB.get(),
+            "SelfCheck",
+            os_self_check_task,
+            core::ptr::null_mut(),
+            crate::kernel::cfg().self_check_task_prio,
+            CHECK_STK.get().cast(),
+            CFG_SELF_CHECK_TASK_STK_SIZE,
+            0,
+            0,
+        )
+    }
+}
@@ -0,0 +1,478 @@
+//! Debug shell
+//!
+//! [`start`] creates a low-priority task that reads command lines through
+//! an application-supplied byte reader, runs them and writes the replies
+//! through an application-supplied writer, so the same shell works over
+//! RTT, a UART ring or a semihosting console.
+//!
+//! Built-in commands:
+//!
+//! * `help` - list commands
+//! * `ps` - priority, state and name of every task
+//! * `stacks` - stack words used out of the stack size
+//! * `sem` - semaphores with their count, maximum and waiters (and counters under
+//!   `obj-stats`)
+//! * `uptime` - ticks and seconds since `os_start`
+//! * `kill <task>` - delete a task by name
+//!
+//! Applications add commands with [`register`]. Output is formatted with
+//! the [`ShellOut`] helpers, which never allocate.
+
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::{
+    CFG_SHELL_CMD_TBL_SIZE, CFG_SHELL_LINE_LEN, CFG_SHELL_POLL_TICKS, CFG_SHELL_TASK_PRIO,
+    CFG_SHELL_TASK_STK_SIZE,
+};
+use crate::core::cs_cell::{CsCell, SyncUnsafeCell};
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel::{self, os_obj_registry_iter};
+use crate::task::{os_task_del, os_task_ext_get, os_task_ext_set, os_task_info, OsTcb, Stack};
+use crate::types::{OsObjType, OsTaskState};
+
+/// Byte reader, `None` when no input is waiting
+pub type ShellReadFn = fn() -> Option<u8>;
+
+/// Output writer
+pub type ShellWriteFn = fn(&[u8]);
+
+/// Command handler, called with the text after the command name
+pub type ShellCmdFn = fn(args: &str, out: &mut ShellOut);
+
+#[derive(Clone, Copy)]
+struct ShellCmd {
+    name: &'static str,
+    handler: ShellCmdFn,
+}
+
+const BUILTINS: [ShellCmd; 6] = [
+    ShellCmd { name: "help", handler: cmd_help },
+    ShellCmd { name: "ps", handler: cmd_ps },
+    ShellCmd { name: "stacks", handler: cmd_stacks },
+    ShellCmd { name: "sem", handler: cmd_sem },
+    ShellCmd { name: "uptime", handler: cmd_uptime },
+    ShellCmd { name: "kill", handler: cmd_kill },
+];
+
+static COMMANDS: CsCell<[Option<ShellCmd>; CFG_SHELL_CMD_TBL_SIZE]> =
+    CsCell::new([None; CFG_SHELL_CMD_TBL_SIZE]);
+
+/// Reader and writer, handed to the shell task as its extension
+struct ShellIo(CsCell<Option<(ShellReadFn, ShellWriteFn)>>);
+
+crate::task_ext!(ShellIo);
+
+static IO: ShellIo = ShellIo(CsCell::new(None));
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Shell task TCB
+static SHELL_TCB: SyncUnsafeCell<OsTcb> = SyncUnsafeCell::new(OsTcb::new());
+
+/// Shell task stack
+static SHELL_STK: SyncUnsafeCell<Stack<CFG_SHELL_TASK_STK_SIZE>> = SyncUnsafeCell::new(Stack::new());
+
+/// Allocation-free output for command handlers
+pub struct ShellOut {
+    write: ShellWriteFn,
+}
+
+impl ShellOut {
+    pub const fn new(write: ShellWriteFn) -> Self {
+        Self { write }
+    }
+
+    /// Write a string
+    #[inline]
+    pub fn str(&mut self, s: &str) {
+        (self.write)(s.as_bytes());
+    }
+
+    /// Write a string padded with spaces to `width`
+    pub fn str_pad(&mut self, s: &str, width: usize) {
+        self.str(s);
+        self.spaces(width.saturating_sub(s.len()));
+    }
+
+    /// Write a number in decimal
+    pub fn u32(&mut self, v: u32) {
+        let mut buf = [0; 10];
+        self.str(fmt_u32(v, &mut buf));
+    }
+
+    /// Write a number right-aligned to `width`
+    pub fn u32_pad(&mut self, v: u32, width: usize) {
+        let mut buf = [0; 10];
+        let s = fmt_u32(v, &mut buf);
+        self.spaces(width.saturating_sub(s.len()));
+        self.str(s);
+    }
+
+    /// End the line
+    #[inline]
+    pub fn nl(&mut self) {
+        self.str("\r\n");
+    }
+
+    fn spaces(&mut self, n: usize) {
+        const SPACES: &str = "                ";
+        let mut n = n;
+        while n > 0 {
+            let k = n.min(SPACES.len());
+            self.str(&SPACES[..k]);
+            n -= k;
+        }
+    }
+}
+
+/// Format `v` in decimal into `buf`
+pub fn fmt_u32(mut v: u32, buf: &mut [u8; 10]) -> &str {
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (v % 10) as u8;
+        v /= 10;
+        if v == 0 {
+            break;
+        }
+    }
+    // Only ASCII digits were written
+    unsafe { core::str::from_utf8_unchecked(&buf[i..]) }
+}
+
+/// Add an application command
+///
+/// Registering an existing name replaces its handler. Application
+/// commands take precedence over built-ins of the same name.
+///
+/// # Returns
+/// * `Err(OsError::ShellFull)` - No free slot in the command table
+pub fn register(name: &'static str, handler: ShellCmdFn) -> OsResult<()> {
+    critical_section(|cs| {
+        let table = COMMANDS.get(cs);
+        let slot = match table.iter().position(|c| c.is_some_and(|c| c.name == name)) {
+            Some(i) => i,
+            None => table.iter().position(|c| c.is_none()).ok_or(OsError::ShellFull)?,
+        };
+        table[slot] = Some(ShellCmd { name, handler });
+        Ok(())
+    })
+}
+
+/// Run one command line, writing the reply through `write`
+///
+/// The shell task calls this for every line; it is public so applications
+/// can drive the commands from their own task or tests.
+pub fn exec(line: &str, write: ShellWriteFn) {
+    let mut out = ShellOut::new(write);
+    let line = line.trim();
+    if line.is_empty() {
+        return;
+    }
+
+    let (name, args) = match line.split_once(' ') {
+        Some((name, args)) => (name, args.trim()),
+        None => (line, ""),
+    };
+
+    let user = critical_section(|cs| COMMANDS.get(cs).iter().flatten().find(|c| c.name == name).copied());
+    match user.or_else(|| BUILTINS.iter().find(|c| c.name == name).copied()) {
+        Some(cmd) => (cmd.handler)(args, &mut out),
+        None => {
+            out.str("unknown command: ");
+            out.str(name);
+            out.nl();
+        }
+    }
+}
+
+/// Create the shell task
+///
+/// # Returns
+/// * `Err(OsError::ObjCreated)` - The shell is already running
+pub fn start(read_byte: ShellReadFn, write: ShellWriteFn) -> OsResult<()> {
+    if STARTED.swap(true, Ordering::AcqRel) {
+        return Err(OsError::ObjCreated);
+    }
+
+    // Attach the I/O before the new task can be switched to
+    let result = critical_section(|cs| {
+        *IO.0.get(cs) = Some((read_byte, write));
+        crate::task::os_task_create_stk(
+            unsafe { &mut *SHELL_TCB.get() },
+            unsafe { &mut *SHELL_STK.get() },
+            "Shell",
+            os_shell_task,
+            CFG_SHELL_TASK_PRIO,
+        )?;
+        os_task_ext_set(NonNull::new(SHELL_TCB.get()), &IO)
+    });
+    if result.is_err() {
+        STARTED.store(false, Ordering::Release);
+    }
+    result
+}
+
+/// Line being typed
+struct LineBuf {
+    buf: [u8; CFG_SHELL_LINE_LEN],
+    len: usize,
+    /// Input exceeded the buffer; drop it up to the end of the line
+    overflow: bool,
+    /// Last byte was a CR, so a following LF is part of the same line end
+    after_cr: bool,
+}
+
+impl LineBuf {
+    const fn new() -> Self {
+        Self { buf: [0; CFG_SHELL_LINE_LEN], len: 0, overflow: false, after_cr: false }
+    }
+
+    /// Take one input byte, echoing it; `true` when a line is complete
+    fn push(&mut self, byte: u8, out: &mut ShellOut) -> bool {
+        if core::mem::replace(&mut self.after_cr, byte == b'\r') && byte == b'\n' {
+            return false;
+        }
+
+        match byte {
+            b'\r' | b'\n' => {
+                out.nl();
+                if core::mem::take(&mut self.overflow) {
+                    out.str("line too long");
+                    out.nl();
+                    self.len = 0;
+                    return false;
+                }
+                true
+            }
+            0x08 | 0x7F => {
+                if self.len > 0 {
+                    self.len -= 1;
+                    out.str("\x08 \x08");
+                }
+                false
+            }
+            _ if self.len == self.buf.len() => {
+                self.overflow = true;
+                false
+            }
+            _ => {
+                self.buf[self.len] = byte;
+                self.len += 1;
+                (out.write)(core::slice::from_ref(&byte));
+                false
+            }
+        }
+    }
+
+    /// Take the completed line
+    fn take(&mut self) -> &str {
+        let len = core::mem::take(&mut self.len);
+        core::str::from_utf8(&self.buf[..len]).unwrap_or("")
+    }
+}
+
+/// Shell task
+fn os_shell_task(_: *mut ()) -> ! {
+    let io = os_task_ext_get::<ShellIo>(None).expect("shell I/O not set");
+    let (read_byte, write) = critical_section(|cs| *io.0.get(cs)).expect("shell I/O not set");
+    let mut out = ShellOut::new(write);
+    let mut line = LineBuf::new();
+
+    out.str("> ");
+    loop {
+        let Some(byte) = read_byte() else {
+            let _ = crate::time::os_time_dly(CFG_SHELL_POLL_TICKS);
+            continue;
+        };
+
+        if line.push(byte, &mut out) {
+            exec(line.take(), write);
+            out.str("> ");
+        }
+    }
+}
+
+// ============ Built-in commands ============
+
+fn state_name(state: OsTaskState) -> &'static str {
+    match state {
+        OsTaskState::Ready => "Ready",
+        OsTaskState::Delayed => "Delayed",
+        OsTaskState::Pend => "Pend",
+        OsTaskState::PendTimeout => "PendTmo",
+        OsTaskState::Suspended => "Susp",
+        OsTaskState::DelayedSuspended => "DlySusp",
+        OsTaskState::PendSuspended => "PendSusp",
+        OsTaskState::PendTimeoutSuspended => "PTmoSusp",
+        OsTaskState::Faulted => "Faulted",
+    }
+}
+
+/// Registered tasks
+fn tasks() -> impl Iterator<Item = (&'static str, NonNull<OsTcb>)> {
+    os_obj_registry_iter()
+        .filter(|e| e.obj_type == OsObjType::Task)
+        .filter_map(|e| Some((e.name(), NonNull::new(e.addr as *mut OsTcb)?)))
+}
+
+fn cmd_help(_: &str, out: &mut ShellOut) {
+    for cmd in BUILTINS.iter() {
+        out.str(cmd.name);
+        out.nl();
+    }
+    for cmd in critical_section(|cs| *COMMANDS.get(cs)).iter().flatten() {
+        out.str(cmd.name);
+        out.nl();
+    }
+}
+
+fn cmd_ps(_: &str, out: &mut ShellOut) {
+    out.str("   ID PRIO STATE    NAME");
+    out.nl();
+    for (name, tcb) in tasks() {
+        let Ok(info) = os_task_info(Some(tcb)) else { continue };
+        out.u32_pad(info.id as u32, 5);
+        out.u32_pad(info.prio as u32, 5);
+        out.str(" ");
+        out.str_pad(state_name(info.state), 9);
+        out.str(name);
+        out.nl();
+    }
+}
+
+fn cmd_stacks(_: &str, out: &mut ShellOut) {
+    out.str(" USED  SIZE NAME");
+    out.nl();
+    for (name, tcb) in tasks() {
+        let Ok(info) = os_task_info(Some(tcb)) else { continue };
+        match info.stk_used {
+            Some(used) => out.u32_pad(used as u32, 5),
+            None => out.str("    -"),
+        }
+        out.u32_pad(info.stk_size as u32, 6);
+        out.str(" ");
+        out.str(name);
+        out.nl();
+    }
+}
+
+fn cmd_sem(_: &str, out: &mut ShellOut) {
+    out.str("COUNT        MAX WAIT NAME");
+    out.nl();
+    #[cfg(feature = "sem")]
+    for e in os_obj_registry_iter().filter(|e| e.obj_type == OsObjType::Sem) {
+        let sem = unsafe { &*(e.addr as *const crate::sem::OsSem) };
+        out.u32_pad(sem.count(), 5);
+        out.u32_pad(sem.max(), 11);
+        out.u32_pad(sem.waiters() as u32, 5);
+        out.str(" ");
+        out.str(sem.name());
+        #[cfg(feature = "obj-stats")]
+        {
+            let st = sem.stats();
+            out.str(" pends ");
+            out.u32(st.pends);
+            out.str(" posts ");
+            out.u32(st.posts);
+            out.str(" timeouts ");
+            out.u32(st.timeouts);
+            out.str(" max ");
+            out.u32(st.waiters_max as u32);
+        }
+        out.nl();
+    }
+}
+
+fn cmd_uptime(_: &str, out: &mut ShellOut) {
+    let ticks = crate::time::os_time_get();
+    let rate = kernel::cfg().tick_rate_hz.max(1);
+    out.u32(ticks);
+    out.str(" ticks, ");
+    out.u32(ticks / rate);
+    out.str(".");
+    let ms = ((ticks % rate) as u64 * 1000 / rate as u64) as u32;
+    out.u32(ms / 100);
+    out.u32((ms / 10) % 10);
+    out.u32(ms % 10);
+    out.str(" s");
+    out.nl();
+}
+
+fn cmd_kill(args: &str, out: &mut ShellOut) {
+    if args.is_empty() {
+        out.str("usage: kill <task>");
+        out.nl();
+        return;
+    }
+
+    let Some((_, tcb)) = tasks().find(|&(name, _)| name == args) else {
+        out.str("no task ");
+        out.str(args);
+        out.nl();
+        return;
+    };
+
+    match os_task_del(Some(tcb)) {
+        Ok(()) => out.str("killed "),
+        Err(e) => {
+            out.str("error ");
+            out.u32(e as u32);
+            out.str(" killing ");
+        }
+    }
+    out.str(args);
+    out.nl();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ECHO: CsCell<([u8; 64], usize)> = CsCell::new(([0; 64], 0));
+
+    fn capture(bytes: &[u8]) {
+        critical_section(|cs| {
+            let (buf, len) = ECHO.get(cs);
+            buf[*len..*len + bytes.len()].copy_from_slice(bytes);
+            *len += bytes.len();
+        });
+    }
+
+    fn echoed() -> ([u8; 64], usize) {
+        critical_section(|cs| *ECHO.get(cs))
+    }
+
+    #[test]
+    fn test_fmt_u32() {
+        let mut buf = [0; 10];
+        assert_eq!(fmt_u32(0, &mut buf), "0");
+        assert_eq!(fmt_u32(907, &mut buf), "907");
+        assert_eq!(fmt_u32(u32::MAX, &mut buf), "4294967295");
+    }
+
+    #[test]
+    fn test_line_editing() {
+        let mut out = ShellOut::new(capture);
+        let mut line = LineBuf::new();
+
+        for &b in b"pz\x7fs" {
+            assert!(!line.push(b, &mut out));
+        }
+        assert!(line.push(b'\r', &mut out));
+        assert_eq!(line.take(), "ps");
+        assert!(!line.push(b'\n', &mut out));
+        let (buf, len) = echoed();
+        assert_eq!(&buf[..len], b"pz\x08 \x08s\r\n");
+
+        // An over-long line is dropped whole
+        for _ in 0..CFG_SHELL_LINE_LEN + 1 {
+            line.push(b'x', &mut ShellOut::new(|_| {}));
+        }
+        assert!(!line.push(b'\n', &mut ShellOut::new(|_| {})));
+        assert!(line.push(b'\n', &mut ShellOut::new(|_| {})));
+        assert_eq!(line.take(), "");
+    }
+}
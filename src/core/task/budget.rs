@@ -0,0 +1,202 @@
+//! Run budgets and period deadlines (`budget`)
+//!
+//! Soft real-time monitoring that needs nothing from the task: with
+//! [`os_task_set_budget`] a task may be given the longest it should run
+//! without being switched out and the longest it should go without
+//! running. A task breaking either limit is reported to
+//! `OsAppHooks::budget_overrun` once per run or gap; the kernel does not
+//! act on it.
+//!
+//! Every context switch is stamped with the timestamp counter. The run
+//! budget is checked when the task is switched out and, for a task that
+//! never is, on every tick. The period is checked on every tick for the
+//! tasks that have one, which are kept in their own list so the tick does
+//! not walk the others.
+//!
+//! ```ignore
+//! // Runs for at most 2 ms at a time and at least once every 50 ms
+//! os_task_set_budget(Some(ctrl), 2, 50)?;
+//! ```
+
+use core::ptr::NonNull;
+
+use crate::core::cs_cell::CsCell;
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::task::{OsTcb, TaskHandle};
+use crate::time::{os_ts_diff, os_ts_freq_hz, os_ts_get};
+use crate::types::{OsObjType, OsTick};
+
+/// Limit a task broke
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OsBudgetLimit {
+    /// Ran longer than its run budget without being switched out
+    Run = 1,
+    /// Went longer than its period without running
+    Period = 2,
+}
+
+/// Tasks with a period deadline, linked through `budget_next_ptr`
+static DEADLINES: CsCell<Option<NonNull<OsTcb>>> = CsCell::new(None);
+
+/// Set a task's run budget and period deadline
+///
+/// Either limit starts afresh from the call. Values of 0 disable each
+/// check.
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+/// * `max_run_ticks` - Longest the task may run without being switched out
+/// * `max_period_ticks` - Longest the task may go without running
+///
+/// # Returns
+/// * `Err(OsError::TcbInvalid)` - `tcb` is not a created task, or `None`
+///   outside a task
+pub fn os_task_set_budget(tcb: Option<NonNull<OsTcb>>, max_run_ticks: OsTick, max_period_ticks: OsTick) -> OsResult<()> {
+    critical_section(|cs| {
+        let tcb_ptr = match tcb {
+            Some(ptr) => ptr,
+            None => unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?,
+        };
+        let tcb_ref = unsafe { &mut *tcb_ptr.as_ptr() };
+        if tcb_ref.obj_type != OsObjType::Task {
+            return Err(OsError::TcbInvalid);
+        }
+
+        let head = DEADLINES.get(cs);
+        let listed = tcb_ref.budget_period != 0;
+        if listed && max_period_ticks == 0 {
+            unsafe { unlink(head, tcb_ptr) };
+        } else if !listed && max_period_ticks != 0 {
+            unsafe { link(head, tcb_ptr) };
+        }
+
+        tcb_ref.budget_run = max_run_ticks;
+        tcb_ref.budget_period = max_period_ticks;
+        tcb_ref.budget_run_ts = os_ts_get();
+        tcb_ref.budget_last_run = kernel::KERNEL.tick_get();
+        tcb_ref.budget_tripped = 0;
+        Ok(())
+    })
+}
+
+unsafe fn link(head: &mut Option<NonNull<OsTcb>>, tcb: NonNull<OsTcb>) {
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+    tcb_ref.budget_prev_ptr = None;
+    tcb_ref.budget_next_ptr = *head;
+    if let Some(mut next) = *head {
+        unsafe { next.as_mut().budget_prev_ptr = Some(tcb) };
+    }
+    *head = Some(tcb);
+}
+
+unsafe fn unlink(head: &mut Option<NonNull<OsTcb>>, tcb: NonNull<OsTcb>) {
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+    match tcb_ref.budget_prev_ptr {
+        Some(mut prev) => unsafe { prev.as_mut().budget_next_ptr = tcb_ref.budget_next_ptr },
+        None => *head = tcb_ref.budget_next_ptr,
+    }
+    if let Some(mut next) = tcb_ref.budget_next_ptr {
+        unsafe { next.as_mut().budget_prev_ptr = tcb_ref.budget_prev_ptr };
+    }
+    tcb_ref.budget_next_ptr = None;
+    tcb_ref.budget_prev_ptr = None;
+}
+
+/// Drop a deleted task from the deadline list
+///
+/// # Safety
+/// Called inside a critical section.
+pub(crate) unsafe fn budget_del(tcb: NonNull<OsTcb>) {
+    if unsafe { tcb.as_ref() }.budget_period != 0 {
+        unsafe { unlink(DEADLINES.get_unchecked(), tcb) };
+    }
+}
+
+/// Check the outgoing task's run and stamp the switch
+///
+/// # Safety
+/// Called by the port's context switch, or `os_start` for the first task,
+/// with interrupts masked; `old` and `new` are the outgoing and incoming
+/// tasks or null.
+pub(crate) unsafe fn budget_switch(old: *mut OsTcb, new: *mut OsTcb) {
+    unsafe { budget_switch_at(old, new, os_ts_get(), kernel::KERNEL.tick_get()) }
+}
+
+pub(crate) unsafe fn budget_switch_at(old: *mut OsTcb, new: *mut OsTcb, now: u32, tick: OsTick) {
+    if let Some(tcb) = unsafe { old.as_mut() } {
+        run_check(tcb, now);
+        tcb.budget_last_run = tick;
+    }
+    if let Some(tcb) = unsafe { new.as_mut() } {
+        tcb.budget_run_ts = now;
+        tcb.budget_last_run = tick;
+        tcb.budget_tripped = 0;
+    }
+}
+
+/// Check the running task's run and every period deadline
+///
+/// Visits only the tasks with a period.
+///
+/// # Safety
+/// Called once per tick, inside a critical section.
+pub(crate) unsafe fn budget_tick(tick: OsTick) {
+    unsafe { budget_tick_at(os_ts_get(), tick) }
+}
+
+pub(crate) unsafe fn budget_tick_at(now: u32, tick: OsTick) {
+    let cur = unsafe { kernel::tcb_cur_ptr() };
+    if let Some(tcb) = cur {
+        let tcb = unsafe { &mut *tcb.as_ptr() };
+        run_check(tcb, now);
+        tcb.budget_last_run = tick;
+    }
+
+    let mut node = unsafe { *DEADLINES.get_unchecked() };
+    while let Some(tcb_ptr) = node {
+        let tcb = unsafe { &mut *tcb_ptr.as_ptr() };
+        node = tcb.budget_next_ptr;
+        if Some(tcb_ptr) != cur && tick.wrapping_sub(tcb.budget_last_run) > tcb.budget_period {
+            trip(tcb, OsBudgetLimit::Period);
+        }
+    }
+}
+
+/// Report a run past the task's budget, once per run
+fn run_check(tcb: &mut OsTcb, now: u32) {
+    if tcb.budget_run != 0 && os_ts_diff(tcb.budget_run_ts, now) > run_ts(tcb.budget_run) {
+        trip(tcb, OsBudgetLimit::Run);
+    }
+}
+
+/// Report `limit` unless it was already for this run or gap
+fn trip(tcb: &mut OsTcb, limit: OsBudgetLimit) {
+    if tcb.budget_tripped & limit as u8 != 0 {
+        return;
+    }
+    tcb.budget_tripped |= limit as u8;
+    crate::__os_log_hot!(
+        warn,
+        "task {} (#{}) overran its {}",
+        tcb.name(),
+        tcb.id,
+        match limit {
+            OsBudgetLimit::Run => "run budget",
+            OsBudgetLimit::Period => "period",
+        }
+    );
+    kernel::hooks::budget_overrun(TaskHandle::new(NonNull::from(tcb)), limit);
+}
+
+/// Run budget in timestamp counts
+fn run_ts(ticks: OsTick) -> u32 {
+    let ts = ticks as u64 * os_ts_freq_hz() as u64 / kernel::cfg().tick_rate_hz as u64;
+    ts.min(u32::MAX as u64) as u32
+}
+
+pub(crate) fn reset() {
+    critical_section(|cs| *DEADLINES.get(cs) = None);
+}
@@ -0,0 +1,105 @@
+//! Cooperative task deletion
+//!
+//! [`os_task_del`](super::os_task_del) removes a task wherever it stands,
+//! which leaves behind any mutex it holds or transfer it started.
+//! [`os_task_del_request`] only flags the task: it checks
+//! [`delete_requested`] at points where it can stop, releases what it owns
+//! and calls [`exit`], which deletes it from its own context.
+//!
+//! A task created with `TASK_DEL_INTERRUPT` does not have to poll: a
+//! request wakes it from a pend or delay, and that call and every blocking
+//! call after it fail with `TaskDelRequested`.
+//!
+//! ```ignore
+//! fn worker(_: *mut ()) -> ! {
+//!     loop {
+//!         match SEM.pend(0, opt::PEND_BLOCKING) {
+//!             Err(OsError::TaskDelRequested) => break,
+//!             _ => work(),
+//!         }
+//!     }
+//!     MUTEX.post(opt::POST_FIFO).ok();
+//!     task::exit()
+//! }
+//! ```
+
+use core::ptr::NonNull;
+
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::sched;
+use crate::task::OsTcb;
+use crate::types::OsObjType;
+
+/// Ask a task to delete itself
+///
+/// Sets the task's request flag, read with [`delete_requested`]. A task
+/// created with `TASK_DEL_INTERRUPT` that waits in a pend or delay is
+/// readied at once and the call returns `Err(OsError::TaskDelRequested)`;
+/// a suspended one stays suspended. Callable from ISRs.
+///
+/// # Returns
+/// * `Err(OsError::OsNotRunning)` - Called before `os_start`
+/// * `Err(OsError::TcbInvalid)` - `tcb` is not a created task
+/// * `Err(OsError::TaskDelIdle)` - `tcb` is the idle task
+pub fn os_task_del_request(tcb: NonNull<OsTcb>) -> OsResult<()> {
+    if !kernel::KERNEL.is_running() {
+        return Err(OsError::OsNotRunning);
+    }
+
+    critical_section(|_cs| {
+        let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+        if tcb_ref.obj_type != OsObjType::Task {
+            return Err(OsError::TcbInvalid);
+        }
+        if tcb == kernel::idle_tcb_ptr() {
+            return Err(OsError::TaskDelIdle);
+        }
+
+        tcb_ref.del_req = true;
+        if !tcb_ref.del_interrupts() {
+            return Ok(());
+        }
+
+        if tcb_ref.is_pending() {
+            unsafe { crate::sync::pend_del_req(tcb) };
+        } else if tcb_ref.is_delayed() {
+            unsafe { kernel::tick_remove(tcb) };
+            let remain = tcb_ref.ticks_left(kernel::KERNEL.tick_get());
+            let ready = tcb_ref.tick_expire();
+            tcb_ref.tick_remain = remain;
+            if ready {
+                unsafe { sched::os_rdy_list_insert(tcb) };
+            }
+        }
+
+        sched::os_sched();
+        Ok(())
+    })
+}
+
+/// Whether deletion of the calling task was requested
+///
+/// `false` outside a task.
+pub fn delete_requested() -> bool {
+    critical_section(|_cs| unsafe { kernel::tcb_cur_ptr() }.is_some_and(|tcb| unsafe { tcb.as_ref() }.del_req))
+}
+
+/// Delete the calling task
+///
+/// The end of a task that honors [`os_task_del_request`], called once it
+/// has released what it holds. Never returns.
+///
+/// # Panics
+/// From an ISR, before `os_start`, from the idle task or with the
+/// scheduler locked, where the task cannot be deleted.
+pub fn exit() -> ! {
+    if let Err(err) = super::os_task_del(None) {
+        panic!("task exit: {:?}", err);
+    }
+    // The switch away is pended and taken before this is reached
+    loop {
+        core::hint::spin_loop();
+    }
+}
@@ -0,0 +1,123 @@
+//! Typed task extensions
+//!
+//! Each TCB holds one extension: a `&'static` reference to context owned by
+//! whoever runs the task, such as a driver's state for the task servicing
+//! it or the arena a worker allocates from. The reference is stored with
+//! the tag of its type, so [`os_task_ext_get`] only hands it back as that
+//! type and returns `None` for anything else.
+//!
+//! Types opt in with [`task_ext!`](crate::task_ext). `os_task_del` clears
+//! the extension; a task created again starts without one.
+//!
+//! ```ignore
+//! struct UartCtx { /* ... */ }
+//! ucosiii::task_ext!(UartCtx);
+//!
+//! static UART: UartCtx = UartCtx { /* ... */ };
+//!
+//! os_task_ext_set(Some(uart_tcb), &UART)?;
+//! // In the task
+//! let ctx = os_task_ext_get::<UartCtx>(None).unwrap();
+//! ```
+
+use core::ptr::NonNull;
+
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::task::OsTcb;
+use crate::types::OsObjType;
+
+/// Identity of an extension type
+///
+/// Only its address matters; it is not zero-sized so no two tags share one.
+pub struct ExtTag(#[allow(dead_code)] u8);
+
+impl ExtTag {
+    #[doc(hidden)]
+    pub const fn new() -> Self {
+        Self(0)
+    }
+}
+
+/// Type usable as a task extension
+///
+/// # Safety
+///
+/// `tag` must return a tag no other type returns. Implement it with
+/// [`task_ext!`](crate::task_ext), which gives each type its own static.
+pub unsafe trait TaskExt: Sync + 'static {
+    fn tag() -> &'static ExtTag;
+}
+
+/// Implement [`TaskExt`] for a type
+///
+/// ```ignore
+/// ucosiii::task_ext!(UartCtx);
+/// ```
+#[macro_export]
+macro_rules! task_ext {
+    ($ty:ty) => {
+        unsafe impl $crate::task::TaskExt for $ty {
+            fn tag() -> &'static $crate::task::ExtTag {
+                static TAG: $crate::task::ExtTag = $crate::task::ExtTag::new();
+                &TAG
+            }
+        }
+    };
+}
+
+fn resolve(tcb: Option<NonNull<OsTcb>>) -> OsResult<NonNull<OsTcb>> {
+    let ptr = match tcb {
+        Some(ptr) => ptr,
+        None => unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?,
+    };
+    if unsafe { ptr.as_ref() }.obj_type != OsObjType::Task {
+        return Err(OsError::TcbInvalid);
+    }
+    Ok(ptr)
+}
+
+/// Attach an extension to a task, replacing any previous one
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+/// * `ext` - Context to attach
+///
+/// # Returns
+/// * `Err(OsError::TcbInvalid)` - `tcb` is not a live task
+pub fn os_task_ext_set<T: TaskExt>(tcb: Option<NonNull<OsTcb>>, ext: &'static T) -> OsResult<()> {
+    critical_section(|_cs| {
+        let tcb_ref = unsafe { &mut *resolve(tcb)?.as_ptr() };
+        tcb_ref.ext_ptr = ext as *const T as *mut ();
+        tcb_ref.ext_tag = T::tag() as *const ExtTag as *const ();
+        Ok(())
+    })
+}
+
+/// A task's extension, if it is a `T`
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+pub fn os_task_ext_get<T: TaskExt>(tcb: Option<NonNull<OsTcb>>) -> Option<&'static T> {
+    critical_section(|_cs| {
+        let tcb_ref = unsafe { resolve(tcb).ok()?.as_ref() };
+        if tcb_ref.ext_tag != T::tag() as *const ExtTag as *const () {
+            return None;
+        }
+        // The tag matched, so the pointer came from a `&'static T`
+        Some(unsafe { &*(tcb_ref.ext_ptr as *const T) })
+    })
+}
+
+/// Detach a task's extension
+///
+/// # Returns
+/// * `Err(OsError::TcbInvalid)` - `tcb` is not a live task
+pub fn os_task_ext_clear(tcb: Option<NonNull<OsTcb>>) -> OsResult<()> {
+    critical_section(|_cs| {
+        let tcb_ref = unsafe { &mut *resolve(tcb)?.as_ptr() };
+        tcb_ref.ext_clear();
+        Ok(())
+    })
+}
@@ -0,0 +1,124 @@
+//! Task fault recovery (`fault-recovery` feature)
+//!
+//! When a task faults in thread mode, the port's fault handler calls
+//! [`fault_take`] with the fault registers. If the task may be recovered,
+//! the report is stored in its TCB and the handler rewrites the stacked
+//! frame so the exception returns into [`os_task_fault_exit`]. That routine
+//! runs on the faulted task's own stack, calls the `task_fault` hook and
+//! removes the task like `os_task_del`, leaving it `Faulted`.
+//!
+//! Faults in handler mode, before `os_start`, or in tasks marked critical
+//! (kernel tasks, or `os_task_critical_set`) take the fatal path. Mutexes
+//! held by the faulted task are not released.
+//!
+//! Cortex-M only. MemManage, BusFault and UsageFault are left disabled, so
+//! they escalate to HardFault, which handles all of them.
+
+use core::ptr::NonNull;
+
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::task::OsTcb;
+use crate::types::{opt, OsTaskState};
+
+/// Fault state captured for a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OsFaultReport {
+    /// Stacked program counter
+    pub pc: u32,
+    /// Stacked link register
+    pub lr: u32,
+    /// Stacked xPSR
+    pub xpsr: u32,
+    /// Configurable Fault Status Register
+    pub cfsr: u32,
+    /// HardFault Status Register
+    pub hfsr: u32,
+    /// MemManage fault address (valid if `cfsr` MMARVALID)
+    pub mmfar: u32,
+    /// BusFault address (valid if `cfsr` BFARVALID)
+    pub bfar: u32,
+}
+
+/// Mark a task as critical: a fault in it is fatal
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+/// * `critical` - Whether faults in the task are fatal
+pub fn os_task_critical_set(tcb: Option<NonNull<OsTcb>>, critical: bool) -> OsResult<()> {
+    critical_section(|_cs| {
+        let tcb_ptr = match tcb {
+            Some(ptr) => ptr,
+            None => unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?,
+        };
+        let tcb_ref = unsafe { &mut *tcb_ptr.as_ptr() };
+
+        if critical {
+            tcb_ref.opt |= opt::TASK_CRITICAL;
+        } else {
+            tcb_ref.opt &= !opt::TASK_CRITICAL;
+        }
+        Ok(())
+    })
+}
+
+/// Claim a thread-mode fault of the running task for recovery
+///
+/// Called from the fault handler. Returns `false` if the fault must take
+/// the fatal path; otherwise the report is stored in the TCB.
+#[cfg_attr(not(target_arch = "arm"), allow(dead_code))]
+pub(crate) fn fault_take(report: OsFaultReport) -> bool {
+    if !kernel::KERNEL.is_running() {
+        return false;
+    }
+
+    let Some(tcb) = (unsafe { kernel::tcb_cur_ptr() }) else {
+        return false;
+    };
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+
+    if tcb_ref.opt & opt::TASK_CRITICAL != 0 || tcb_ref.fault.is_some() {
+        return false;
+    }
+
+    tcb_ref.fault = Some(report);
+    true
+}
+
+/// Where a recovered task resumes after the fault handler returns
+///
+/// Runs in thread mode on the faulted task's stack and never returns.
+#[cfg_attr(not(target_arch = "arm"), allow(dead_code))]
+pub(crate) extern "C" fn os_task_fault_exit() -> ! {
+    critical_section(|cs| unsafe {
+        if let Some(tcb) = kernel::tcb_cur_ptr() {
+            if let Some(report) = tcb.as_ref().fault {
+                crate::__os_log_defer!(
+                    error,
+                    "task '{}' (#{}) faulted at pc {=u32:#x}",
+                    tcb.as_ref().name(),
+                    tcb.as_ref().id,
+                    report.pc
+                );
+                kernel::hooks::task_fault(tcb, &report);
+            }
+            super::os_task_del_cs(cs, tcb, OsTaskState::Faulted);
+        }
+    });
+
+    // The switch is pended and happens once interrupts are enabled
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_take_refused_before_start() {
+        assert!(!fault_take(OsFaultReport::default()));
+    }
+}
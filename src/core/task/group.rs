@@ -0,0 +1,328 @@
+//! Task groups
+//!
+//! A group links its member tasks through their TCBs, so switching an
+//! application mode is a `suspend_all` on one group and a `resume_all` on
+//! another. Each bulk operation runs with the scheduler locked: no member
+//! runs part-way through the transition, and the tasks it readies compete
+//! for the CPU only once every member has changed state. A calling task
+//! that is itself a member is handled last, and leaves the CPU when the
+//! scheduler is unlocked.
+//!
+//! A task belongs to at most one group and leaves it when deleted. Kernel
+//! tasks cannot join a group.
+
+use core::cell::UnsafeCell;
+use core::ptr::NonNull;
+
+use crate::critical::{critical_section, is_isr_context};
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::task::{os_task_del, os_task_resume, os_task_suspend, OsTcb};
+use crate::types::{obj_name, obj_name_str, opt, ObjName, OsObjType};
+
+/// Set of tasks suspended, resumed or deleted together
+pub struct OsTaskGroup {
+    /// Object type marker
+    obj_type: OsObjType,
+    /// First member, most recently attached
+    head: Option<NonNull<OsTcb>>,
+    /// Number of members
+    len: usize,
+    /// Name for debugging
+    name: ObjName,
+}
+
+impl OsTaskGroup {
+    /// Create an empty group
+    pub const fn new() -> Self {
+        OsTaskGroup {
+            obj_type: OsObjType::TaskGroup,
+            head: None,
+            len: 0,
+            name: obj_name(""),
+        }
+    }
+
+    /// Initialize/create the group
+    pub fn create(&mut self, name: &'static str) -> OsResult<()> {
+        if is_isr_context() {
+            return Err(OsError::CreateIsr);
+        }
+
+        if kernel::KERNEL.is_safety_critical() {
+            return Err(OsError::IllegalCreateRunTime);
+        }
+
+        critical_section(|cs| {
+            kernel::registry::register_cs(cs, OsObjType::TaskGroup, name, self as *const _ as *const ());
+            self.obj_type = OsObjType::TaskGroup;
+            self.head = None;
+            self.len = 0;
+            self.name = obj_name(name);
+            Ok(())
+        })
+    }
+
+    /// Add a task, moving it out of any other group
+    ///
+    /// # Returns
+    /// * `Err(OsError::TaskGroupKernel)` - `tcb` is a kernel task
+    pub fn attach(&mut self, tcb: NonNull<OsTcb>) -> OsResult<()> {
+        if self.obj_type != OsObjType::TaskGroup {
+            return Err(OsError::ObjType);
+        }
+
+        critical_section(|_cs| {
+            let this = NonNull::from(&mut *self);
+            let (task_opt, group) = unsafe { (tcb.as_ref().opt, tcb.as_ref().group_ptr) };
+            if task_opt & opt::TASK_KERNEL != 0 {
+                return Err(OsError::TaskGroupKernel);
+            }
+            if group == Some(this) {
+                return Ok(());
+            }
+            unsafe { leave(tcb) };
+
+            let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+            tcb_ref.group_ptr = Some(this);
+            tcb_ref.group_prev_ptr = None;
+            tcb_ref.group_next_ptr = self.head;
+            if let Some(mut old_head) = self.head {
+                unsafe { old_head.as_mut().group_prev_ptr = Some(tcb) };
+            }
+            self.head = Some(tcb);
+            self.len += 1;
+            Ok(())
+        })
+    }
+
+    /// Remove a task from the group
+    ///
+    /// # Returns
+    /// * `Err(OsError::TaskNotInGroup)` - `tcb` is not a member
+    pub fn detach(&mut self, tcb: NonNull<OsTcb>) -> OsResult<()> {
+        critical_section(|_cs| {
+            if unsafe { tcb.as_ref() }.group_ptr != Some(NonNull::from(&mut *self)) {
+                return Err(OsError::TaskNotInGroup);
+            }
+            unsafe { leave(tcb) };
+            Ok(())
+        })
+    }
+
+    /// Suspend every member
+    ///
+    /// Returns the number of tasks suspended.
+    pub fn suspend_all(&mut self) -> OsResult<usize> {
+        self.bulk(|tcb| os_task_suspend(Some(tcb)).map(|()| true))
+    }
+
+    /// Resume every suspended member
+    ///
+    /// Each member's suspend nesting drops by one. Returns the number of
+    /// tasks resumed.
+    pub fn resume_all(&mut self) -> OsResult<usize> {
+        self.bulk(|tcb| {
+            if unsafe { tcb.as_ref() }.suspend_ctr == 0 {
+                return Ok(false);
+            }
+            os_task_resume(tcb).map(|()| true)
+        })
+    }
+
+    /// Delete every member, leaving the group empty
+    ///
+    /// Returns the number of tasks deleted.
+    pub fn delete_all(&mut self) -> OsResult<usize> {
+        self.bulk(|tcb| os_task_del(Some(tcb)).map(|()| true))
+    }
+
+    /// Apply `op` to each member with the scheduler locked, the caller last
+    ///
+    /// `op` returns whether it acted on the task. Membership only changes
+    /// from task code, so the list is stable while the scheduler is locked.
+    fn bulk(&mut self, mut op: impl FnMut(NonNull<OsTcb>) -> OsResult<bool>) -> OsResult<usize> {
+        if is_isr_context() {
+            return Err(OsError::SchedLockIsr);
+        }
+
+        if self.obj_type != OsObjType::TaskGroup {
+            return Err(OsError::ObjType);
+        }
+
+        kernel::os_sched_lock()?;
+        let cur = critical_section(|_cs| unsafe { kernel::tcb_cur_ptr() });
+
+        let mut count = 0;
+        let mut run = |tcb| op(tcb).map(|acted| count += acted as usize);
+        let mut res = Ok(());
+        let mut caller = false;
+        let mut next = self.head;
+        while let Some(tcb) = next {
+            next = unsafe { tcb.as_ref() }.group_next_ptr;
+            if Some(tcb) == cur {
+                caller = true;
+                continue;
+            }
+            res = run(tcb);
+            if res.is_err() {
+                break;
+            }
+        }
+        if let (Ok(()), true, Some(tcb)) = (res, caller, cur) {
+            res = run(tcb);
+        }
+
+        kernel::os_sched_unlock()?;
+        res.map(|()| count)
+    }
+
+    /// Whether `tcb` is a member
+    pub fn contains(&self, tcb: NonNull<OsTcb>) -> bool {
+        unsafe { tcb.as_ref() }.group_ptr.is_some_and(|g| core::ptr::eq(g.as_ptr(), self))
+    }
+
+    /// Number of members
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Name given at creation
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        obj_name_str(self.name)
+    }
+}
+
+impl Default for OsTaskGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Unlink `tcb` from its group, if any
+///
+/// # Safety
+/// Called with the critical section held.
+pub(crate) unsafe fn leave(tcb: NonNull<OsTcb>) {
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+    let Some(group) = tcb_ref.group_ptr.take() else {
+        return;
+    };
+    let group = unsafe { &mut *group.as_ptr() };
+
+    match tcb_ref.group_prev_ptr {
+        Some(mut prev) => unsafe { prev.as_mut().group_next_ptr = tcb_ref.group_next_ptr },
+        None => group.head = tcb_ref.group_next_ptr,
+    }
+    if let Some(mut next) = tcb_ref.group_next_ptr {
+        unsafe { next.as_mut().group_prev_ptr = tcb_ref.group_prev_ptr };
+    }
+
+    tcb_ref.group_next_ptr = None;
+    tcb_ref.group_prev_ptr = None;
+    group.len -= 1;
+}
+
+// ============ Safe Wrapper ============
+
+pub struct Group {
+    inner: UnsafeCell<OsTaskGroup>,
+}
+
+// SAFETY: the group is only mutated inside a critical section on a single
+// core, so any task may share it.
+unsafe impl Sync for Group {}
+unsafe impl Send for Group {}
+
+impl Group {
+    pub const fn new() -> Self {
+        Group {
+            inner: UnsafeCell::new(OsTaskGroup::new()),
+        }
+    }
+
+    pub fn create(&self, name: &'static str) -> OsResult<()> {
+        unsafe { (*self.inner.get()).create(name) }
+    }
+
+    /// Add a task, moving it out of any other group
+    pub fn attach(&self, tcb: NonNull<OsTcb>) -> OsResult<()> {
+        unsafe { (*self.inner.get()).attach(tcb) }
+    }
+
+    pub fn detach(&self, tcb: NonNull<OsTcb>) -> OsResult<()> {
+        unsafe { (*self.inner.get()).detach(tcb) }
+    }
+
+    /// Suspend every member, the calling task last
+    pub fn suspend_all(&self) -> OsResult<usize> {
+        unsafe { (*self.inner.get()).suspend_all() }
+    }
+
+    /// Resume every suspended member
+    pub fn resume_all(&self) -> OsResult<usize> {
+        unsafe { (*self.inner.get()).resume_all() }
+    }
+
+    /// Delete every member, the calling task last
+    pub fn delete_all(&self) -> OsResult<usize> {
+        unsafe { (*self.inner.get()).delete_all() }
+    }
+
+    pub fn contains(&self, tcb: NonNull<OsTcb>) -> bool {
+        unsafe { (*self.inner.get()).contains(tcb) }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        unsafe { (*self.inner.get()).len() }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        unsafe { (*self.inner.get()).is_empty() }
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leave_relinks_members() {
+        let mut group = OsTaskGroup::new();
+        let mut tcbs = [OsTcb::new(), OsTcb::new(), OsTcb::new()];
+        let [a, b, c] = tcbs.each_mut().map(NonNull::from);
+        for t in [a, b, c] {
+            group.attach(t).unwrap();
+        }
+        assert_eq!(group.head, Some(c));
+
+        // Middle, then head
+        unsafe { leave(b) };
+        assert_eq!(unsafe { c.as_ref() }.group_next_ptr, Some(a));
+        assert_eq!(unsafe { a.as_ref() }.group_prev_ptr, Some(c));
+        unsafe { leave(c) };
+        assert_eq!(group.head, Some(a));
+        assert_eq!(unsafe { a.as_ref() }.group_prev_ptr, None);
+        assert_eq!(group.len(), 1);
+
+        // Leaving twice is a no-op
+        unsafe { leave(c) };
+        assert_eq!(group.len(), 1);
+        assert!(!group.contains(c) && group.contains(a));
+    }
+}
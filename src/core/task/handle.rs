@@ -0,0 +1,66 @@
+//! Task handles
+//!
+//! A [`TaskHandle`] names a created task without exposing its TCB. It
+//! converts to the `NonNull<OsTcb>` the task services take.
+
+use core::ptr::NonNull;
+
+use crate::kernel;
+use crate::task::OsTcb;
+use crate::types::{OsPrio, OsTaskId};
+
+/// Copyable reference to a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskHandle(NonNull<OsTcb>);
+
+// SAFETY: TCBs are 'static and the getters read single fields, so a
+// handle may be passed to and read from any task or ISR
+unsafe impl Send for TaskHandle {}
+unsafe impl Sync for TaskHandle {}
+
+impl TaskHandle {
+    /// Handle of a created task
+    #[cfg(feature = "budget")]
+    #[inline]
+    pub(crate) const fn new(tcb: NonNull<OsTcb>) -> Self {
+        Self(tcb)
+    }
+
+    /// TCB of the task, for the `os_task_*` services
+    #[inline]
+    pub fn as_ptr(self) -> NonNull<OsTcb> {
+        self.0
+    }
+
+    /// Task name
+    #[inline]
+    pub fn name(self) -> &'static str {
+        unsafe { self.0.as_ref() }.name()
+    }
+
+    /// Task ID, see [`OsTcb::id`]
+    #[inline]
+    pub fn id(self) -> OsTaskId {
+        unsafe { self.0.as_ref() }.id
+    }
+
+    /// Current (possibly inherited) priority
+    #[inline]
+    pub fn prio(self) -> OsPrio {
+        unsafe { self.0.as_ref() }.prio
+    }
+}
+
+impl From<TaskHandle> for NonNull<OsTcb> {
+    fn from(handle: TaskHandle) -> Self {
+        handle.0
+    }
+}
+
+/// Handle of the running task
+///
+/// From an ISR, the task it interrupted; `None` before `os_start`.
+#[inline]
+pub fn current() -> Option<TaskHandle> {
+    kernel::tcb_cur_snapshot().map(TaskHandle)
+}
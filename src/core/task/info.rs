@@ -0,0 +1,110 @@
+//! Task information snapshot
+//!
+//! [`os_task_info`] copies the monitoring-relevant TCB fields out under a
+//! critical section, so shells and monitors never read a half-updated TCB.
+
+use core::ptr::NonNull;
+
+use crate::config::CFG_STK_CHK_EN;
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::task::OsTcb;
+use crate::types::{OsNestingCtr, OsPendOn, OsPrio, OsStkElement, OsTaskId, OsTaskState, OsTick};
+
+/// Snapshot of a task
+#[derive(Debug, Clone, Copy)]
+pub struct OsTaskInfo {
+    /// Task name
+    pub name: &'static str,
+    /// Task ID
+    pub id: OsTaskId,
+    /// Effective priority, including any inherited boost
+    pub prio: OsPrio,
+    /// Base priority
+    pub base_prio: OsPrio,
+    /// Mutex or write lock `prio` is inherited from, `None` when not boosted
+    pub boosted_by: Option<&'static str>,
+    /// Preemption threshold
+    pub preempt_threshold: OsPrio,
+    /// Task state
+    pub state: OsTaskState,
+    /// Suspend nesting counter
+    pub suspend_ctr: OsNestingCtr,
+    /// Remaining delay or timeout ticks
+    pub tick_remain: OsTick,
+    /// Round-robin time quanta, 0 if exempt
+    pub time_quanta: OsTick,
+    /// Remaining time quanta
+    pub time_quanta_ctr: OsTick,
+    /// Object type the task is pending on
+    pub pend_on: OsPendOn,
+    /// Name of the task's group
+    #[cfg(feature = "task-group")]
+    pub group: Option<&'static str>,
+    /// Base (lowest address) of the stack
+    pub stk_base: *const OsStkElement,
+    /// Stack size in words
+    pub stk_size: usize,
+    /// Stack words ever used, `None` if `CFG_STK_CHK_EN` is off
+    pub stk_used: Option<usize>,
+    /// Stack words never touched, `None` if `CFG_STK_CHK_EN` is off
+    pub stk_free: Option<usize>,
+}
+
+/// Get a snapshot of a task
+///
+/// The TCB fields are copied under a critical section. The stack is scanned
+/// afterwards with interrupts enabled, so `stk_used` may lag the task by a
+/// few words.
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+pub fn os_task_info(tcb: Option<NonNull<OsTcb>>) -> OsResult<OsTaskInfo> {
+    let mut info = critical_section(|_cs| {
+        let tcb_ptr = match tcb {
+            Some(ptr) => ptr,
+            None => unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?,
+        };
+        let tcb_ref = unsafe { tcb_ptr.as_ref() };
+
+        if tcb_ref.stk_base.is_null() {
+            return Err(OsError::TcbInvalid);
+        }
+
+        Ok(OsTaskInfo {
+            name: tcb_ref.name(),
+            id: tcb_ref.id,
+            prio: tcb_ref.prio,
+            base_prio: tcb_ref.base_prio,
+            boosted_by: tcb_ref.boosted_by(),
+            preempt_threshold: tcb_ref.preempt_threshold,
+            state: tcb_ref.task_state,
+            suspend_ctr: tcb_ref.suspend_ctr,
+            tick_remain: tcb_ref.ticks_left(kernel::KERNEL.tick_get()),
+            time_quanta: tcb_ref.time_quanta,
+            time_quanta_ctr: tcb_ref.time_quanta_ctr,
+            pend_on: tcb_ref.pend_on,
+            #[cfg(feature = "task-group")]
+            group: tcb_ref.group_name(),
+            stk_base: tcb_ref.stk_base,
+            stk_size: tcb_ref.stk_size,
+            stk_used: None,
+            stk_free: None,
+        })
+    })?;
+
+    if CFG_STK_CHK_EN {
+        let free = unsafe { super::stk::stk_free(info.stk_base, info.stk_size) };
+        info.stk_free = Some(free);
+        info.stk_used = Some(info.stk_size - free);
+    }
+
+    Ok(info)
+}
+
+/// Get a snapshot of the calling task
+#[inline]
+pub fn os_task_info_self() -> OsResult<OsTaskInfo> {
+    os_task_info(None)
+}
@@ -2,21 +2,108 @@
 //!
 //! Provides task creation, deletion, and control functions.
 
+#[cfg(feature = "budget")]
+pub(crate) mod budget;
+#[cfg(feature = "fault-recovery")]
+pub(crate) mod fault;
+#[cfg(feature = "task-group")]
+mod group;
+mod del_req;
+mod ext;
+mod handle;
+mod info;
+mod reg;
+mod sem;
+mod stk;
+#[cfg(feature = "task-table")]
+mod table;
 mod tcb;
 
+#[cfg(feature = "budget")]
+pub use budget::{os_task_set_budget, OsBudgetLimit};
+#[cfg(feature = "fault-recovery")]
+pub use fault::{os_task_critical_set, OsFaultReport};
+#[cfg(feature = "task-group")]
+pub use group::{Group, OsTaskGroup};
+pub use del_req::{delete_requested, exit, os_task_del_request};
+pub use ext::{os_task_ext_clear, os_task_ext_get, os_task_ext_set, ExtTag, TaskExt};
+pub use handle::{current, TaskHandle};
+pub use info::{os_task_info, os_task_info_self, OsTaskInfo};
+pub use reg::{
+    os_task_reg_get, os_task_reg_get_id, os_task_reg_get_ids, os_task_reg_set,
+    OsReg, OsRegId, TaskLocal,
+};
+pub use sem::{os_task_sem_pend, os_task_sem_post};
+pub use stk::{os_task_stk_chk, Stack, OS_STK_CANARY};
+#[cfg(any(test, target_arch = "arm", target_arch = "riscv32"))]
+pub(crate) use stk::stk_canary_ok;
+pub(crate) use stk::stk_top_aligned;
+#[cfg(feature = "task-table")]
+pub use table::{task_table_prios_unique, task_table_stk_words, StkArena, TaskSpec, TaskTable, TaskTableError};
 pub use tcb::OsTcb;
 
 use core::ptr::NonNull;
 
-use crate::config::{CFG_PRIO_MAX, CFG_STK_SIZE_MIN, CFG_TIME_QUANTA_DEFAULT};
-use crate::critical::{critical_section, is_isr_context};
+use crate::config::{CFG_PRIO_MAX, CFG_PRIO_RESERVED, CFG_STK_SIZE_MIN, CFG_TIME_QUANTA_MAX};
+use crate::critical::{critical_section, is_isr_context, CriticalSection};
 use crate::error::{OsError, OsResult};
 use crate::kernel;
-use crate::types::{OsOpt, OsPrio, OsStkElement, OsTaskState, OsTick};
+use crate::types::{obj_name, OsObjType, OsOpt, OsPrio, OsStkElement, OsTaskId, OsTaskState, OsTick};
 
 /// Task entry point function type
 pub type OsTaskFn = fn(*mut ()) -> !;
 
+/// Task ID of no task: a TCB not created, or the running task before
+/// `os_start`
+pub const TASK_ID_NONE: OsTaskId = 0;
+
+/// Task ID of the idle task
+pub const TASK_ID_IDLE: OsTaskId = 1;
+
+/// Add a TCB to the head of the task registry and give it its ID
+unsafe fn os_task_dbg_list_add(tcb: NonNull<OsTcb>) {
+    let id = if tcb == kernel::idle_tcb_ptr() { TASK_ID_IDLE } else { unsafe { kernel::task_id_alloc() } };
+    let head = unsafe { kernel::task_list() };
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+    tcb_ref.id = id;
+
+    tcb_ref.dbg_prev_ptr = None;
+    tcb_ref.dbg_next_ptr = *head;
+
+    if let Some(mut old_head) = *head {
+        unsafe { old_head.as_mut().dbg_prev_ptr = Some(tcb) };
+    }
+
+    *head = Some(tcb);
+}
+
+/// Remove a TCB from the task registry
+unsafe fn os_task_dbg_list_remove(tcb: NonNull<OsTcb>) {
+    let head = unsafe { kernel::task_list() };
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+
+    match tcb_ref.dbg_prev_ptr {
+        Some(mut prev) => unsafe { prev.as_mut().dbg_next_ptr = tcb_ref.dbg_next_ptr },
+        None => *head = tcb_ref.dbg_next_ptr,
+    }
+
+    if let Some(mut next) = tcb_ref.dbg_next_ptr {
+        unsafe { next.as_mut().dbg_prev_ptr = tcb_ref.dbg_prev_ptr };
+    }
+
+    tcb_ref.dbg_next_ptr = None;
+    tcb_ref.dbg_prev_ptr = None;
+}
+
+/// Whether the stack `stk_size` words from `stk_base` overlaps `tcb`
+fn stk_overlaps_tcb(tcb: *const OsTcb, stk_base: *const OsStkElement, stk_size: usize) -> bool {
+    let tcb_start = tcb as usize;
+    let tcb_end = tcb_start + core::mem::size_of::<OsTcb>();
+    let stk_start = stk_base as usize;
+    let stk_end = stk_start + stk_size * core::mem::size_of::<OsStkElement>();
+    tcb_start < stk_end && stk_start < tcb_end
+}
+
 /// Create a new task
 ///
 /// # Arguments
@@ -28,7 +115,15 @@ pub type OsTaskFn = fn(*mut ()) -> !;
 /// * `stk_base` - Pointer to base of stack array
 /// * `stk_size` - Stack size in words
 /// * `opt` - Task options
-unsafe fn os_task_create_raw(
+///
+/// # Returns
+/// * `Err(OsError::PrioInvalid)` - `prio` out of range or in `CFG_PRIO_RESERVED`
+/// * `Err(OsError::StkInvalid)` - Null stack, or the stack overlaps `tcb`
+/// * `Err(OsError::ObjCreated)` - `tcb` is a live task
+/// * `Err(OsError::OptInvalid)` - `opt` has a bit outside `opt::TASK_MASK`
+/// * `Err(OsError::OsNotInit)` - Called before `os_init`, which would
+///   drop the task from the ready list
+pub(crate) unsafe fn os_task_create_raw(
     tcb: *mut OsTcb,
     name: &'static str,
     task_fn: OsTaskFn,
@@ -50,8 +145,14 @@ unsafe fn os_task_create_raw(
     if stk_size < CFG_STK_SIZE_MIN {
         return Err(OsError::StkSizeInvalid);
     }
+
+    if stk_overlaps_tcb(tcb, stk_base, stk_size) {
+        return Err(OsError::StkInvalid);
+    }
+
+    crate::types::opt::check(opt, crate::types::opt::TASK_MASK)?;
     
-    if prio as usize >= CFG_PRIO_MAX {
+    if prio as usize >= CFG_PRIO_MAX || CFG_PRIO_RESERVED.contains(&prio) {
         return Err(OsError::PrioInvalid);
     }
     
@@ -59,20 +160,36 @@ unsafe fn os_task_create_raw(
         return Err(OsError::TaskCreateIsr);
     }
 
-    critical_section(|_cs| {
+    if kernel::KERNEL.is_safety_critical() {
+        return Err(OsError::IllegalCreateRunTime);
+    }
+
+    critical_section(|cs| {
+        // A live task may still be linked into the kernel lists
+        if unsafe { (*tcb).obj_type } == OsObjType::Task {
+            return Err(OsError::ObjCreated);
+        }
+
+        if !kernel::KERNEL.is_initialized() {
+            return Err(OsError::OsNotInit);
+        }
+
         // Initialize TCB
         let tcb_ref = unsafe { &mut *tcb };
         tcb_ref.init();
+        tcb_ref.obj_type = OsObjType::Task;
         
-        tcb_ref.name = name;
+        tcb_ref.name = obj_name(name);
         tcb_ref.prio = prio;
         tcb_ref.base_prio = prio;
+        tcb_ref.preempt_threshold = prio;
         tcb_ref.time_quanta = time_quanta;
         tcb_ref.time_quanta_ctr = time_quanta;
         tcb_ref.opt = opt;
         tcb_ref.task_state = OsTaskState::Ready;
         
         // Initialize stack
+        unsafe { stk::stk_prepare(stk_base, stk_size) };
         let stk_ptr = unsafe {
             crate::port::os_task_stk_init(task_fn, arg, stk_base, stk_size, opt)
         };
@@ -88,12 +205,12 @@ unsafe fn os_task_create_raw(
         // Add to ready list
         let tcb_nonnull = unsafe { NonNull::new_unchecked(tcb) };
         unsafe {
-            let prio_tbl = kernel::prio_table();
-            let rdy_list = kernel::rdy_list(prio);
-            
-            rdy_list.insert_tail(tcb_nonnull);
-            prio_tbl.insert(prio);
+            kernel::rdy_queues().insert(tcb_nonnull);
+            os_task_dbg_list_add(tcb_nonnull);
         }
+        kernel::registry::register_cs(cs, OsObjType::Task, name, tcb as *const ());
+
+        kernel::hooks::task_create(tcb_nonnull);
         
         if kernel::KERNEL.is_running() {
             crate::sched::os_sched();
@@ -114,6 +231,11 @@ unsafe fn os_task_create_raw(
 /// * `task_fn` - Task entry point function
 /// * `prio` - Task priority (0 = highest)
 ///
+/// # Returns
+/// * `Err(OsError::PrioInvalid)` - `prio` out of range or reserved for the idle task
+/// * `Err(OsError::ObjCreated)` - `tcb` is a live task; delete it first
+/// * `Err(OsError::OsNotInit)` - Called before `os_init`
+///
 /// # Example
 /// ```ignore
 /// static mut TASK_TCB: OsTcb = OsTcb::new();
@@ -148,12 +270,70 @@ pub fn os_task_create(
             prio,
             stack.as_mut_ptr(),
             stack.len(),
-            CFG_TIME_QUANTA_DEFAULT,
+            kernel::cfg().time_quanta_default,
             0,
         )
     }
 }
 
+/// Create a task on a [`Stack`]
+///
+/// Same as [`os_task_create`], with the stack's alignment and minimum size
+/// guaranteed by its type.
+///
+/// # Example
+/// ```ignore
+/// static mut TASK_TCB: OsTcb = OsTcb::new();
+/// static mut TASK_STK: Stack<256> = Stack::new();
+///
+/// os_task_create_stk(
+///     unsafe { &mut TASK_TCB },
+///     unsafe { &mut TASK_STK },
+///     "MyTask",
+///     my_task,
+///     5,
+/// ).expect("Task creation failed");
+/// ```
+pub fn os_task_create_stk<const N: usize>(
+    tcb: &'static mut OsTcb,
+    stack: &'static mut Stack<N>,
+    name: &'static str,
+    task_fn: OsTaskFn,
+    prio: OsPrio,
+) -> OsResult<()> {
+    os_task_create(tcb, stack, name, task_fn, prio)
+}
+
+/// Create a task with options
+///
+/// Same as [`os_task_create`], with `opt` a combination of the `TASK_*`
+/// options in `opt::TASK_MASK`.
+///
+/// # Returns
+/// * `Err(OsError::OptInvalid)` - `opt` has a bit outside `opt::TASK_MASK`
+pub fn os_task_create_opt(
+    tcb: &'static mut OsTcb,
+    stack: &'static mut [OsStkElement],
+    name: &'static str,
+    task_fn: OsTaskFn,
+    prio: OsPrio,
+    opt: OsOpt,
+) -> OsResult<()> {
+    unsafe {
+        os_task_create_raw(
+            tcb as *mut OsTcb,
+            name,
+            task_fn,
+            core::ptr::null_mut(),
+            prio,
+            stack.as_mut_ptr(),
+            stack.len(),
+            kernel::cfg().time_quanta_default,
+            opt,
+        )
+    }
+}
+
 /// Internal task creation for kernel use
 #[doc(hidden)]
 pub unsafe fn os_task_create_internal(
@@ -174,16 +354,20 @@ pub unsafe fn os_task_create_internal(
     // Initialize TCB
     let tcb_ref = unsafe { &mut *tcb };
     tcb_ref.init();
+    tcb_ref.obj_type = OsObjType::Task;
     
-    tcb_ref.name = name;
+    tcb_ref.name = obj_name(name);
     tcb_ref.prio = prio;
     tcb_ref.base_prio = prio;
+    tcb_ref.preempt_threshold = prio;
     tcb_ref.time_quanta = time_quanta;
     tcb_ref.time_quanta_ctr = time_quanta;
-    tcb_ref.opt = opt;
+    // Kernel tasks never go through fault recovery and join no group
+    tcb_ref.opt = opt | crate::types::opt::TASK_CRITICAL | crate::types::opt::TASK_KERNEL;
     tcb_ref.task_state = OsTaskState::Ready;
     
     // Initialize stack
+    unsafe { stk::stk_prepare(stk_base, stk_size) };
     let stk_ptr = unsafe {
         crate::port::os_task_stk_init(task_fn, arg, stk_base, stk_size, opt)
     };
@@ -198,17 +382,26 @@ pub unsafe fn os_task_create_internal(
     // Add to ready list
     let tcb_nonnull = unsafe { NonNull::new_unchecked(tcb) };
     unsafe {
-        let prio_tbl = kernel::prio_table();
-        let rdy_list = kernel::rdy_list(prio);
-        
-        rdy_list.insert_tail(tcb_nonnull);
-        prio_tbl.insert(prio);
+        kernel::rdy_queues().insert(tcb_nonnull);
+        os_task_dbg_list_add(tcb_nonnull);
+        kernel::registry::register_unchecked(OsObjType::Task, name, tcb as *const ());
     }
     
     Ok(())
 }
 
 /// Delete a task
+///
+/// Takes the task down wherever it stands; see [`os_task_del_request`] to
+/// let it release what it holds first.
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+///
+/// # Returns
+/// * `Err(OsError::TaskDelIdle)` - `tcb` is the idle task
+/// * `Err(OsError::SchedLocked)` - Deleting the calling task with the
+///   scheduler locked
 pub fn os_task_del(tcb: Option<NonNull<OsTcb>>) -> OsResult<()> {
     if !kernel::KERNEL.is_running() {
         return Err(OsError::OsNotRunning);
@@ -218,7 +411,7 @@ pub fn os_task_del(tcb: Option<NonNull<OsTcb>>) -> OsResult<()> {
         return Err(OsError::TaskDelIsr);
     }
 
-    critical_section(|_cs| {
+    critical_section(|cs| {
         let tcb_ptr = match tcb {
             Some(ptr) => ptr,
             None => {
@@ -234,30 +427,161 @@ pub fn os_task_del(tcb: Option<NonNull<OsTcb>>) -> OsResult<()> {
             return Err(OsError::TaskDelIdle);
         }
 
-        // Remove from ready list
-        unsafe {
-            let rdy_list = kernel::rdy_list(prio);
-            rdy_list.remove(tcb_ptr);
-            
-            if rdy_list.is_empty() {
-                kernel::prio_table().remove(prio);
-            }
+        if unsafe { kernel::tcb_cur_ptr() } == Some(tcb_ptr) {
+            kernel::sched_lock_chk()?;
         }
 
-        let tcb_mut = unsafe { &mut *tcb_ptr.as_ptr() };
-        tcb_mut.task_state = OsTaskState::Suspended;
+        os_task_del_cs(cs, tcb_ptr, OsTaskState::Suspended);
+        Ok(())
+    })
+}
 
-        // If deleting current task, trigger reschedule
-        let is_current = unsafe { kernel::tcb_cur_ptr() } == Some(tcb_ptr);
-        if is_current {
+/// Unlink a task from the kernel and leave it in `state`
+fn os_task_del_cs(cs: &CriticalSection, tcb_ptr: NonNull<OsTcb>, state: OsTaskState) {
+    // Remove from ready list
+    unsafe { kernel::rdy_queues().remove(tcb_ptr) };
+    // A waiter leaves its object, giving back any priority it lent
+    unsafe { crate::sync::pend_del(tcb_ptr) };
+
+    let tcb_mut = unsafe { &mut *tcb_ptr.as_ptr() };
+    tcb_mut.task_state = state;
+    tcb_mut.obj_type = OsObjType::None;
+    tcb_mut.id = TASK_ID_NONE;
+    tcb_mut.ext_clear();
+
+    unsafe { os_task_dbg_list_remove(tcb_ptr) };
+    kernel::registry::unregister_cs(cs, tcb_ptr.as_ptr() as *const ());
+
+    #[cfg(feature = "task-group")]
+    unsafe { group::leave(tcb_ptr) };
+
+    #[cfg(feature = "wdog")]
+    crate::wdog::deregister_cs(cs, tcb_ptr);
+
+    #[cfg(feature = "budget")]
+    unsafe { budget::budget_del(tcb_ptr) };
+
+    kernel::hooks::task_del(tcb_ptr);
+
+    // If deleting current task, trigger reschedule
+    let is_current = unsafe { kernel::tcb_cur_ptr() } == Some(tcb_ptr);
+    if is_current {
+        crate::sched::os_sched();
+    }
+}
+
+/// Change a task's round-robin time quanta
+///
+/// Safe to call while the task runs: a shorter quanta cuts the current
+/// slice down to it, a longer one applies from the next slice. A quanta of
+/// 0 exempts the task from round-robin.
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+/// * `quanta` - Ticks per slice, at most `CFG_TIME_QUANTA_MAX`
+///
+/// # Returns
+/// * `Err(OsError::SchedInvalidTimeSlice)` - `quanta` above `CFG_TIME_QUANTA_MAX`
+pub fn os_task_time_quanta_set(tcb: Option<NonNull<OsTcb>>, quanta: OsTick) -> OsResult<()> {
+    if quanta > CFG_TIME_QUANTA_MAX {
+        return Err(OsError::SchedInvalidTimeSlice);
+    }
+
+    critical_section(|_cs| {
+        let tcb_ptr = match tcb {
+            Some(ptr) => ptr,
+            None => unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?,
+        };
+        let tcb_ref = unsafe { &mut *tcb_ptr.as_ptr() };
+
+        // A task leaving the exemption starts a full slice
+        tcb_ref.time_quanta_ctr = match tcb_ref.time_quanta_ctr {
+            0 => quanta,
+            ctr => ctr.min(quanta),
+        };
+        tcb_ref.time_quanta = quanta;
+
+        Ok(())
+    })
+}
+
+/// Set a task's round-robin slice in microseconds (`rr-precise`)
+///
+/// Overrides the slice [`os_task_time_quanta_set`] gives in ticks; the
+/// tick still decides when the task is rotated. 0 goes back to the quanta.
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+/// * `us` - Slice length in microseconds
+///
+/// # Returns
+/// * `Err(OsError::SchedInvalidTimeSlice)` - `us` is shorter than one
+///   timestamp count or does not fit the counter
+#[cfg(feature = "rr-precise")]
+pub fn os_task_time_slice_us_set(tcb: Option<NonNull<OsTcb>>, us: u32) -> OsResult<()> {
+    let ts = us as u64 * crate::time::os_ts_freq_hz() as u64 / 1_000_000;
+    if (us > 0 && ts == 0) || ts > u32::MAX as u64 {
+        return Err(OsError::SchedInvalidTimeSlice);
+    }
+
+    critical_section(|_cs| {
+        let tcb_ptr = match tcb {
+            Some(ptr) => ptr,
+            None => unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?,
+        };
+        unsafe { (*tcb_ptr.as_ptr()).time_slice_ts = ts as u32 };
+
+        Ok(())
+    })
+}
+
+/// Set a task's preemption threshold
+///
+/// While the task runs, only ready tasks of priority numerically below
+/// `threshold` preempt it; it still gives up the CPU when it blocks or
+/// yields. A threshold equal to the task's priority is plain preemption.
+/// Wake-up and pend list order keep using the priority. A task with a
+/// threshold below its priority is not time-sliced.
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+/// * `threshold` - At most the task's base priority
+///
+/// # Returns
+/// * `Ok(old)` - The previous threshold
+/// * `Err(OsError::PreemptThresholdInvalid)` - `threshold` above the base priority
+pub fn os_task_set_preempt_threshold(tcb: Option<NonNull<OsTcb>>, threshold: OsPrio) -> OsResult<OsPrio> {
+    critical_section(|_cs| {
+        let tcb_ptr = match tcb {
+            Some(ptr) => ptr,
+            None => unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?,
+        };
+        let tcb_ref = unsafe { &mut *tcb_ptr.as_ptr() };
+
+        if threshold > tcb_ref.base_prio {
+            return Err(OsError::PreemptThresholdInvalid);
+        }
+
+        let old = core::mem::replace(&mut tcb_ref.preempt_threshold, threshold);
+
+        // A raised threshold may let a waiting task in now
+        if threshold > old && unsafe { kernel::tcb_cur_ptr() } == Some(tcb_ptr) {
             crate::sched::os_sched();
         }
 
-        Ok(())
+        Ok(old)
     })
 }
 
 /// Suspend a task
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+///
+/// # Returns
+/// * `Err(OsError::TaskSuspendIdle)` - `tcb` is the idle task
+/// * `Err(OsError::SchedLocked)` - Suspending the calling task with the
+///   scheduler locked
 pub fn os_task_suspend(tcb: Option<NonNull<OsTcb>>) -> OsResult<()> {
     if !kernel::KERNEL.is_running() {
         return Err(OsError::OsNotRunning);
@@ -279,18 +603,16 @@ pub fn os_task_suspend(tcb: Option<NonNull<OsTcb>>) -> OsResult<()> {
             return Err(OsError::TaskSuspendIdle);
         }
 
+        if unsafe { kernel::tcb_cur_ptr() } == Some(tcb_ptr) {
+            kernel::sched_lock_chk()?;
+        }
+
         tcb_ref.suspend_ctr = tcb_ref.suspend_ctr.saturating_add(1);
 
         match tcb_ref.task_state {
             OsTaskState::Ready => {
                 tcb_ref.task_state = OsTaskState::Suspended;
-                unsafe {
-                    let rdy_list = kernel::rdy_list(tcb_ref.prio);
-                    rdy_list.remove(tcb_ptr);
-                    if rdy_list.is_empty() {
-                        kernel::prio_table().remove(tcb_ref.prio);
-                    }
-                }
+                unsafe { kernel::rdy_queues().remove(tcb_ptr) };
             }
             OsTaskState::Delayed => {
                 tcb_ref.task_state = OsTaskState::DelayedSuspended;
@@ -338,11 +660,7 @@ pub fn os_task_resume(tcb: NonNull<OsTcb>) -> OsResult<()> {
             match tcb_ref.task_state {
                 OsTaskState::Suspended => {
                     tcb_ref.task_state = OsTaskState::Ready;
-                    unsafe {
-                        let rdy_list = kernel::rdy_list(tcb_ref.prio);
-                        rdy_list.insert_tail(tcb);
-                        kernel::prio_table().insert(tcb_ref.prio);
-                    }
+                    unsafe { kernel::rdy_queues().insert(tcb) };
                 }
                 OsTaskState::DelayedSuspended => {
                     tcb_ref.task_state = OsTaskState::Delayed;
@@ -362,3 +680,108 @@ pub fn os_task_resume(tcb: NonNull<OsTcb>) -> OsResult<()> {
         Ok(())
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spin(_: *mut ()) -> ! {
+        loop {}
+    }
+
+    #[repr(C)]
+    struct Block {
+        tcb: OsTcb,
+        stk: [OsStkElement; CFG_STK_SIZE_MIN],
+    }
+
+    #[test]
+    fn test_stk_overlaps_tcb() {
+        let mut block = Block { tcb: OsTcb::new(), stk: [0; CFG_STK_SIZE_MIN] };
+        let tcb: *mut OsTcb = &mut block.tcb;
+        let stk = block.stk.as_mut_ptr();
+        assert!(!stk_overlaps_tcb(tcb, stk, CFG_STK_SIZE_MIN));
+
+        // A stack starting inside the TCB, or covering it
+        let inside = unsafe { (tcb as *mut OsStkElement).add(2) };
+        assert!(stk_overlaps_tcb(tcb, inside, CFG_STK_SIZE_MIN));
+        assert!(stk_overlaps_tcb(tcb, tcb as *mut OsStkElement, 1));
+        let create = unsafe { os_task_create_raw(tcb, "t", spin, core::ptr::null_mut(), 3, inside, CFG_STK_SIZE_MIN, 0, 0) };
+        assert_eq!(create, Err(OsError::StkInvalid));
+    }
+
+    #[test]
+    fn test_live_tcb_rejected() {
+        let mut tcb = OsTcb::new();
+        tcb.obj_type = OsObjType::Task;
+        tcb.prio = 7;
+        let mut stk = [0; CFG_STK_SIZE_MIN];
+        let create = unsafe {
+            os_task_create_raw(&mut tcb, "t", spin, core::ptr::null_mut(), 3, stk.as_mut_ptr(), stk.len(), 0, 0)
+        };
+        assert_eq!(create, Err(OsError::ObjCreated));
+
+        // Left untouched
+        assert_eq!(tcb.prio, 7);
+    }
+
+    #[test]
+    fn test_unknown_opt_rejected() {
+        use crate::types::opt;
+
+        let mut tcb = OsTcb::new();
+        let mut stk = [0; CFG_STK_SIZE_MIN];
+        for bad in [opt::TASK_KERNEL, opt::FLAG_CONSUME] {
+            let create = unsafe {
+                os_task_create_raw(&mut tcb, "t", spin, core::ptr::null_mut(), 3, stk.as_mut_ptr(), stk.len(), 0, bad)
+            };
+            assert_eq!(create, Err(OsError::OptInvalid));
+        }
+        assert_eq!(tcb.obj_type, OsObjType::None);
+    }
+
+    #[test]
+    fn test_stk_top_aligned() {
+        use crate::port::OS_STK_ALIGN;
+
+        const N: usize = CFG_STK_SIZE_MIN;
+        let word = core::mem::size_of::<OsStkElement>();
+        let mut stk = Stack::<N>::new();
+        let base = stk.as_mut_ptr();
+        assert_eq!(base as usize % OS_STK_ALIGN, 0);
+        assert_eq!(stk[0], OS_STK_CANARY);
+
+        // Typed stack: the rounding is a no-op
+        assert_eq!(stk_top_aligned(base, N, OS_STK_ALIGN), base.wrapping_add(N));
+
+        // Plain arrays lose the words above the last aligned address
+        let lost = |base: *mut OsStkElement, size: usize| {
+            (base.wrapping_add(size) as usize - stk_top_aligned(base, size, OS_STK_ALIGN) as usize) / word
+        };
+        assert_eq!(lost(base, N - 1), OS_STK_ALIGN / word - 1);
+        assert_eq!(lost(base.wrapping_add(1), N - 1), 0);
+        assert_eq!(lost(base.wrapping_add(1), N - 2), OS_STK_ALIGN / word - 1);
+    }
+
+    #[test]
+    fn test_stk_canary() {
+        const N: usize = CFG_STK_SIZE_MIN;
+        let mut stk = [0xFFFF_FFFF; N];
+        let mut tcb = OsTcb::new();
+        tcb.stk_base = stk.as_mut_ptr();
+        tcb.stk_size = N;
+        let tcb_ptr = NonNull::from(&mut tcb);
+
+        unsafe { stk::stk_prepare(stk.as_mut_ptr(), N) };
+        assert_eq!(stk[0], OS_STK_CANARY);
+        assert!(stk_canary_ok(&tcb));
+        assert_eq!(os_task_stk_chk(Some(tcb_ptr)), Ok((N - 1, 1)));
+
+        stk[N - 4..].fill(1);
+        assert_eq!(os_task_stk_chk(Some(tcb_ptr)), Ok((N - 5, 5)));
+
+        unsafe { tcb.stk_base.write(0) };
+        assert!(!stk_canary_ok(&tcb));
+        assert_eq!(os_task_stk_chk(Some(tcb_ptr)), Err(OsError::StkOvf));
+    }
+}
@@ -0,0 +1,197 @@
+//! Task registers
+//!
+//! Each TCB carries `CFG_TASK_REG_TBL_SIZE` 32-bit slots for per-task data,
+//! equivalent to μC/OS-III `OSTaskRegGet`/`OSTaskRegSet`. Slot ids are
+//! handed out by [`os_task_reg_get_id`] so independent modules never clash.
+//!
+//! [`TaskLocal`] (declared with [`task_local!`](crate::task_local)) builds a
+//! typed per-task variable on top of the slots, always addressing the
+//! calling task.
+
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::config::CFG_TASK_REG_TBL_SIZE;
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::task::OsTcb;
+
+/// Task register value
+pub type OsReg = u32;
+
+/// Task register id
+pub type OsRegId = u8;
+
+const _: () = assert!(CFG_TASK_REG_TBL_SIZE <= 32, "reg_valid mask is 32 bits");
+
+/// Next free register id
+static REG_ID_NEXT: AtomicU8 = AtomicU8::new(0);
+
+/// Allocate `count` consecutive register ids
+///
+/// Ids are global to all tasks and are never released; `os_init` does not
+/// reset them so cached ids stay valid.
+///
+/// # Returns
+/// * `Ok(id)` - First allocated id
+/// * `Err(OsError::NoMoreIdAvail)` - Not enough slots left
+pub fn os_task_reg_get_ids(count: usize) -> OsResult<OsRegId> {
+    critical_section(|_cs| {
+        let next = REG_ID_NEXT.load(Ordering::Relaxed) as usize;
+        if count == 0 || next + count > CFG_TASK_REG_TBL_SIZE {
+            return Err(OsError::NoMoreIdAvail);
+        }
+        REG_ID_NEXT.store((next + count) as u8, Ordering::Relaxed);
+        Ok(next as OsRegId)
+    })
+}
+
+/// Allocate one register id
+#[inline]
+pub fn os_task_reg_get_id() -> OsResult<OsRegId> {
+    os_task_reg_get_ids(1)
+}
+
+fn resolve(tcb: Option<NonNull<OsTcb>>) -> OsResult<NonNull<OsTcb>> {
+    match tcb {
+        Some(ptr) => Ok(ptr),
+        None => unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid),
+    }
+}
+
+/// Set a task register
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+/// * `id` - Register id from [`os_task_reg_get_id`]
+/// * `value` - Value to store
+pub fn os_task_reg_set(tcb: Option<NonNull<OsTcb>>, id: OsRegId, value: OsReg) -> OsResult<()> {
+    if id as usize >= CFG_TASK_REG_TBL_SIZE {
+        return Err(OsError::RegIdInvalid);
+    }
+
+    critical_section(|_cs| {
+        let tcb_ref = unsafe { &mut *resolve(tcb)?.as_ptr() };
+        tcb_ref.regs[id as usize] = value;
+        tcb_ref.reg_valid |= 1 << id;
+        Ok(())
+    })
+}
+
+/// Get a task register
+///
+/// Registers that were never set read as 0.
+pub fn os_task_reg_get(tcb: Option<NonNull<OsTcb>>, id: OsRegId) -> OsResult<OsReg> {
+    if id as usize >= CFG_TASK_REG_TBL_SIZE {
+        return Err(OsError::RegIdInvalid);
+    }
+
+    critical_section(|_cs| {
+        let tcb_ref = unsafe { resolve(tcb)?.as_ref() };
+        Ok(tcb_ref.regs[id as usize])
+    })
+}
+
+// ============ Typed task-local storage ============
+
+/// Marker for an id that has not been allocated yet
+const ID_NONE: u8 = u8::MAX;
+
+/// Typed per-task variable stored in task registers
+///
+/// Occupies `size_of::<T>()` rounded up to whole registers. Slots are
+/// allocated on first use. A task that never set the variable reads the
+/// default value.
+pub struct TaskLocal<T: Copy> {
+    id: AtomicU8,
+    default: T,
+}
+
+impl<T: Copy> TaskLocal<T> {
+    const SLOTS: usize = core::mem::size_of::<T>().div_ceil(core::mem::size_of::<OsReg>());
+
+    /// Create a task-local variable with the value read by tasks that never
+    /// set it
+    pub const fn new(default: T) -> Self {
+        Self {
+            id: AtomicU8::new(ID_NONE),
+            default,
+        }
+    }
+
+    fn id(&self) -> OsResult<usize> {
+        critical_section(|_cs| {
+            let id = self.id.load(Ordering::Relaxed);
+            if id != ID_NONE {
+                return Ok(id as usize);
+            }
+            let id = os_task_reg_get_ids(Self::SLOTS)?;
+            self.id.store(id, Ordering::Relaxed);
+            Ok(id as usize)
+        })
+    }
+
+    /// Get the calling task's value
+    ///
+    /// Returns the default if the variable was never set by this task, or
+    /// if no task is running.
+    pub fn get(&self) -> T {
+        let Ok(id) = self.id() else {
+            return self.default;
+        };
+        let mask = Self::mask(id);
+
+        critical_section(|_cs| {
+            let Some(cur) = (unsafe { kernel::tcb_cur_ptr() }) else {
+                return self.default;
+            };
+            let tcb_ref = unsafe { cur.as_ref() };
+            if tcb_ref.reg_valid & mask != mask {
+                return self.default;
+            }
+            unsafe { (tcb_ref.regs.as_ptr().add(id) as *const T).read_unaligned() }
+        })
+    }
+
+    /// Set the calling task's value
+    ///
+    /// # Returns
+    /// * `Err(OsError::NoMoreIdAvail)` - Not enough free task registers
+    /// * `Err(OsError::TcbInvalid)` - No task is running
+    pub fn set(&self, value: T) -> OsResult<()> {
+        let id = self.id()?;
+        let mask = Self::mask(id);
+
+        critical_section(|_cs| {
+            let cur = unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?;
+            let tcb_ref = unsafe { &mut *cur.as_ptr() };
+            unsafe { (tcb_ref.regs.as_mut_ptr().add(id) as *mut T).write_unaligned(value) };
+            tcb_ref.reg_valid |= mask;
+            Ok(())
+        })
+    }
+
+    #[inline]
+    fn mask(id: usize) -> u32 {
+        (((1u64 << Self::SLOTS) - 1) << id) as u32
+    }
+}
+
+/// Declare a typed per-task variable
+///
+/// ```ignore
+/// task_local! {
+///     static ERRNO: i32 = 0;
+/// }
+///
+/// ERRNO.set(5)?;
+/// assert_eq!(ERRNO.get(), 5);
+/// ```
+#[macro_export]
+macro_rules! task_local {
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr;) => {
+        $(#[$attr])*
+        $vis static $name: $crate::task::TaskLocal<$ty> = $crate::task::TaskLocal::new($init);
+    };
+}
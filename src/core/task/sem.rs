@@ -0,0 +1,121 @@
+//! Task semaphores
+//!
+//! Every TCB embeds a counting semaphore (`OSTaskSemPend`/`OSTaskSemPost`
+//! in μC/OS-III). Only the owning task pends on it, so there is no pend
+//! list: a post either readies the task if it is waiting or bumps the
+//! counter. Posting is allowed from ISRs, which makes it the cheapest way
+//! for an interrupt to wake one specific task.
+
+use core::ptr::NonNull;
+
+use crate::critical::{critical_section, is_isr_context};
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::sched;
+use crate::task::OsTcb;
+use crate::types::{opt, OsOpt, OsPendOn, OsPendStatus, OsSemCtr, OsTick};
+
+/// Wait on the calling task's semaphore
+///
+/// # Arguments
+/// * `timeout` - Maximum ticks to wait (0 = forever)
+/// * `pend_opt` - Pend options
+///
+/// # Returns
+/// * `Ok(count)` - Signal received, returns the remaining count
+/// * `Err(OsError::PendWouldBlock)` - Non-blocking and no signal pending
+/// * `Err(OsError::SchedLocked)` - Would block with the scheduler locked
+/// * `Err(OsError::Timeout)` - Timeout expired
+/// * `Err(OsError::TaskDelRequested)` - Deletion of the calling
+///   `TASK_DEL_INTERRUPT` task requested before or while waiting
+/// * `Err(OsError::OsNotRunning)` - Called before `os_start`
+/// * `Err(OsError::OptInvalid)` - `pend_opt` has a non-`PEND_*` bit
+pub fn os_task_sem_pend(timeout: OsTick, pend_opt: OsOpt) -> OsResult<OsSemCtr> {
+    if is_isr_context() {
+        return Err(OsError::PendIsr);
+    }
+
+    if !kernel::KERNEL.is_running() {
+        return Err(OsError::OsNotRunning);
+    }
+
+    opt::check(pend_opt, opt::PEND_MASK)?;
+
+    let taken = critical_section(|_cs| unsafe {
+        let cur = kernel::tcb_cur_ptr().ok_or(OsError::TcbInvalid)?;
+        let tcb = &mut *cur.as_ptr();
+
+        if tcb.sem_ctr > 0 {
+            tcb.sem_ctr -= 1;
+            tcb.tick_remain = timeout;
+            return Ok(Some(tcb.sem_ctr));
+        }
+
+        if pend_opt & opt::PEND_NON_BLOCKING != 0 {
+            return Err(OsError::PendWouldBlock);
+        }
+
+        if tcb.del_interrupts() {
+            return Err(OsError::TaskDelRequested);
+        }
+
+        kernel::sched_lock_chk()?;
+
+        sched::os_rdy_list_remove(cur);
+        tcb.set_pending(core::ptr::null(), OsPendOn::TaskSem, timeout);
+
+        sched::os_sched();
+
+        Ok(None)
+    })?;
+    if let Some(count) = taken {
+        return Ok(count);
+    }
+
+    // The switch away is taken once the critical section ends; the result
+    // is read when the task runs again
+    critical_section(|_cs| unsafe {
+        let cur = kernel::tcb_cur_ptr().ok_or(OsError::TcbInvalid)?;
+        cur.as_ref().pend_result().map(|()| cur.as_ref().sem_ctr)
+    })
+}
+
+/// Signal a task's semaphore
+///
+/// Callable from ISRs; the switch to the woken task is pended and happens
+/// when the handler returns (see `os_int_exit`). Before `os_start` posts
+/// only count up, for the task to take once it runs.
+///
+/// # Arguments
+/// * `tcb` - Task to signal
+/// * `post_opt` - Post options (`POST_FRONT`, `POST_NO_SCHED`)
+///
+/// # Returns
+/// * `Ok(count)` - Count after the post
+/// * `Err(OsError::SemOvf)` - Counter overflow
+/// * `Err(OsError::OptInvalid)` - `post_opt` has a bit other than
+///   `POST_FRONT` or `POST_NO_SCHED`
+pub fn os_task_sem_post(tcb: NonNull<OsTcb>, post_opt: OsOpt) -> OsResult<OsSemCtr> {
+    opt::check(post_opt, opt::POST_FRONT | opt::POST_NO_SCHED)?;
+
+    critical_section(|_cs| {
+        let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+
+        if tcb_ref.pend_on() == OsPendOn::TaskSem {
+            tcb_ref.clear_pending(OsPendStatus::Ok);
+            unsafe { sched::os_rdy_list_insert_woken(tcb, post_opt & opt::POST_FRONT != 0) };
+
+            if post_opt & opt::POST_NO_SCHED == 0 {
+                sched::os_sched();
+            }
+
+            return Ok(tcb_ref.sem_ctr);
+        }
+
+        if tcb_ref.sem_ctr == OsSemCtr::MAX {
+            return Err(OsError::SemOvf);
+        }
+        tcb_ref.sem_ctr += 1;
+        Ok(tcb_ref.sem_ctr)
+    })
+}
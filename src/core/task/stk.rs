@@ -0,0 +1,166 @@
+//! Task stacks
+//!
+//! Every task stack gets [`OS_STK_CANARY`] in its lowest word at creation,
+//! after the `CFG_STK_CHK_EN` clear. A task that overwrote it has run off
+//! the end of its stack: [`os_task_stk_chk`] reports it, and with
+//! `CFG_TASK_STK_CHK_EN` every context switch checks the outgoing task.
+//!
+//! [`Stack`] carries the port's alignment and the `CFG_STK_SIZE_MIN` check
+//! in its type, so the first frame starts right at the stack top. A plain
+//! array whose top is not aligned loses the words below it to rounding.
+
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+
+use crate::config::{CFG_STK_CHK_EN, CFG_STK_SIZE_MIN};
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::port::OS_STK_ALIGN;
+use crate::task::OsTcb;
+use crate::types::OsStkElement;
+
+/// Lowest word of every task stack while it has not overflowed
+pub const OS_STK_CANARY: OsStkElement = 0xC0DE_5AFE;
+
+/// Task stack of `WORDS` words, aligned for the port's first frame
+///
+/// Create it with [`Stack::new`] and pass it to [`os_task_create_stk`], or
+/// to any API taking `&mut [OsStkElement]`.
+///
+/// ```
+/// use ucosiii::task::{OsTcb, Stack};
+///
+/// static mut TASK_STK: Stack<256> = Stack::new();
+/// static mut TASK_TCB: OsTcb = OsTcb::new();
+/// ```
+///
+/// A stack below `CFG_STK_SIZE_MIN` does not compile:
+///
+/// ```compile_fail
+/// use ucosiii::task::Stack;
+///
+/// static mut TASK_STK: Stack<16> = Stack::new();
+/// ```
+///
+/// [`os_task_create_stk`]: crate::task::os_task_create_stk
+#[cfg_attr(target_arch = "riscv32", repr(C, align(16)))]
+#[cfg_attr(not(target_arch = "riscv32"), repr(C, align(8)))]
+pub struct Stack<const WORDS: usize>([OsStkElement; WORDS]);
+
+const _: () = assert!(core::mem::align_of::<Stack<0>>() >= OS_STK_ALIGN);
+
+impl<const WORDS: usize> Stack<WORDS> {
+    /// Smallest accepted `WORDS`, checked when `new` is instantiated
+    ///
+    /// `WORDS` must also fill whole alignment units, so the top of the
+    /// stack is as aligned as its base.
+    pub const WORDS_MIN: usize = {
+        assert!(WORDS >= CFG_STK_SIZE_MIN, "Stack is smaller than CFG_STK_SIZE_MIN");
+        assert!(
+            (WORDS * core::mem::size_of::<OsStkElement>()).is_multiple_of(OS_STK_ALIGN),
+            "Stack size is not a multiple of the port's stack alignment"
+        );
+        CFG_STK_SIZE_MIN
+    };
+
+    /// Create a cleared stack with its canary in place
+    pub const fn new() -> Self {
+        let _ = Self::WORDS_MIN;
+        let mut stk = [0; WORDS];
+        stk[0] = OS_STK_CANARY;
+        Stack(stk)
+    }
+}
+
+impl<const WORDS: usize> Default for Stack<WORDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WORDS: usize> Deref for Stack<WORDS> {
+    type Target = [OsStkElement];
+
+    fn deref(&self) -> &[OsStkElement] {
+        &self.0
+    }
+}
+
+impl<const WORDS: usize> DerefMut for Stack<WORDS> {
+    fn deref_mut(&mut self) -> &mut [OsStkElement] {
+        &mut self.0
+    }
+}
+
+/// Clear a new task's stack (with `CFG_STK_CHK_EN`) and plant the canary
+///
+/// # Safety
+/// `stk_base` must point to `stk_size` writable words, `stk_size > 0`.
+pub(crate) unsafe fn stk_prepare(stk_base: *mut OsStkElement, stk_size: usize) {
+    if CFG_STK_CHK_EN {
+        unsafe { core::ptr::write_bytes(stk_base, 0, stk_size) };
+    }
+    unsafe { stk_base.write_volatile(OS_STK_CANARY) };
+}
+
+/// Whether the task's stack canary is intact; `true` without a stack
+#[inline]
+pub(crate) fn stk_canary_ok(tcb: &OsTcb) -> bool {
+    tcb.stk_base.is_null() || unsafe { tcb.stk_base.read_volatile() } == OS_STK_CANARY
+}
+
+/// Stack top rounded down to `align` bytes, where ports build the first frame
+///
+/// A no-op for a [`Stack`]; a plain array with an unaligned top loses the
+/// words in between.
+#[inline(always)]
+pub(crate) fn stk_top_aligned(stk_base: *mut OsStkElement, stk_size: usize, align: usize) -> *mut OsStkElement {
+    let stk_top = stk_base.wrapping_add(stk_size);
+    stk_top.wrapping_byte_sub(stk_top as usize % align)
+}
+
+/// Count untouched (still zero) words above the canary
+///
+/// # Safety
+/// `stk_base` must point to `stk_size` readable words.
+pub(crate) unsafe fn stk_free(stk_base: *const OsStkElement, stk_size: usize) -> usize {
+    let stk = unsafe { core::slice::from_raw_parts(stk_base, stk_size) };
+    stk.iter().skip(1).take_while(|&&w| w == 0).count()
+}
+
+/// Check a task's stack
+///
+/// Returns the words never used and the words used, like `OSTaskStkChk`.
+/// The canary word counts as used.
+///
+/// # Arguments
+/// * `tcb` - Task, `None` for the calling task
+///
+/// # Returns
+/// * `Err(OsError::StkOvf)` - The canary was overwritten
+/// * `Err(OsError::StkInvalid)` - `CFG_STK_CHK_EN` is off, so usage is unknown
+/// * `Err(OsError::TcbInvalid)` - No such task
+pub fn os_task_stk_chk(tcb: Option<NonNull<OsTcb>>) -> OsResult<(usize, usize)> {
+    let (stk_base, stk_size) = critical_section(|_cs| {
+        let tcb_ptr = match tcb {
+            Some(ptr) => ptr,
+            None => unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?,
+        };
+        let tcb_ref = unsafe { tcb_ptr.as_ref() };
+        if tcb_ref.stk_base.is_null() {
+            return Err(OsError::TcbInvalid);
+        }
+        if !stk_canary_ok(tcb_ref) {
+            return Err(OsError::StkOvf);
+        }
+        Ok((tcb_ref.stk_base as *const OsStkElement, tcb_ref.stk_size))
+    })?;
+
+    if !CFG_STK_CHK_EN {
+        return Err(OsError::StkInvalid);
+    }
+
+    let free = unsafe { stk_free(stk_base, stk_size) };
+    Ok((free, stk_size - free))
+}
@@ -0,0 +1,279 @@
+//! Compile-time task table
+//!
+//! [`os_task_table!`](crate::os_task_table) declares every application
+//! task in one place. The macro sums the stack sizes at compile time into a
+//! single arena, so the whole stack budget is `TaskTable::stk_words` and
+//! nothing is allocated per task. Passing the table in
+//! [`OsConfig::task_table`](crate::kernel::OsConfig::task_table) makes
+//! `os_init_with` create the tasks in table order, after the kernel tasks.
+//!
+//! ```ignore
+//! const SPECS: &[TaskSpec] = &[
+//!     TaskSpec::new("Blink", blink, 10, 256),
+//!     TaskSpec::new("Comms", comms, 5, 512).opt(opt::TASK_STK_CHK),
+//! ];
+//! // Optional build-time policy
+//! const _: () = assert!(task_table_prios_unique(SPECS));
+//!
+//! ucosiii::os_task_table!(static TASKS = SPECS;);
+//!
+//! os_init_with(&OsConfig { task_table: Some(&TASKS), ..OsConfig::default() })?;
+//! ```
+
+use core::ptr::NonNull;
+
+use crate::core::cs_cell::{CsCell, SyncUnsafeCell};
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::task::{OsTaskFn, OsTcb};
+use crate::types::{OsOpt, OsPrio, OsStkElement};
+
+/// One statically configured task
+#[derive(Debug, Clone, Copy)]
+pub struct TaskSpec {
+    /// Task name
+    pub name: &'static str,
+    /// Entry point, called with a null argument
+    pub entry: OsTaskFn,
+    /// Task priority
+    pub prio: OsPrio,
+    /// Stack size in words
+    pub stk_size: usize,
+    /// Task options
+    pub opt: OsOpt,
+}
+
+impl TaskSpec {
+    pub const fn new(name: &'static str, entry: OsTaskFn, prio: OsPrio, stk_size: usize) -> Self {
+        TaskSpec { name, entry, prio, stk_size, opt: 0 }
+    }
+
+    /// Set the task options
+    pub const fn opt(mut self, opt: OsOpt) -> Self {
+        self.opt = opt;
+        self
+    }
+
+    /// Words the task takes in the arena, rounded up for 8-byte alignment
+    const fn arena_words(&self) -> usize {
+        (self.stk_size + 1) & !1
+    }
+}
+
+/// Arena words needed by `specs`
+pub const fn task_table_stk_words(specs: &[TaskSpec]) -> usize {
+    let mut words = 0;
+    let mut i = 0;
+    while i < specs.len() {
+        words += specs[i].arena_words();
+        i += 1;
+    }
+    words
+}
+
+/// Whether no two entries of `specs` share a priority
+pub const fn task_table_prios_unique(specs: &[TaskSpec]) -> bool {
+    let mut i = 0;
+    while i < specs.len() {
+        let mut j = i + 1;
+        while j < specs.len() {
+            if specs[i].prio == specs[j].prio {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Stack arena of `W` words, 8-byte aligned
+#[doc(hidden)]
+#[repr(C, align(8))]
+pub struct StkArena<const W: usize>(SyncUnsafeCell<[OsStkElement; W]>);
+
+impl<const W: usize> StkArena<W> {
+    pub const fn new() -> Self {
+        StkArena(SyncUnsafeCell::new([0; W]))
+    }
+
+    pub const fn as_ptr(&self) -> *mut OsStkElement {
+        self.0.get().cast()
+    }
+}
+
+impl<const W: usize> Default for StkArena<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Task table entry that could not be created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskTableError {
+    /// Position in the table
+    pub index: usize,
+    /// Name of the entry
+    pub name: &'static str,
+    /// Error from task creation
+    pub err: OsError,
+}
+
+/// Task table with its TCBs and stack arena, built by `os_task_table!`
+pub struct TaskTable {
+    specs: &'static [TaskSpec],
+    tcbs: &'static [SyncUnsafeCell<OsTcb>],
+    arena: *mut OsStkElement,
+    arena_words: usize,
+    /// First entry that failed in the last `os_init`
+    error: CsCell<Option<TaskTableError>>,
+}
+
+// SAFETY: the specs are immutable, the TCBs and stack arena are only
+// written by `os_init` before any task runs, and `error` is a `CsCell`.
+unsafe impl Sync for TaskTable {}
+
+impl TaskTable {
+    #[doc(hidden)]
+    pub const fn new(
+        specs: &'static [TaskSpec],
+        tcbs: &'static [SyncUnsafeCell<OsTcb>],
+        arena: *mut OsStkElement,
+        arena_words: usize,
+    ) -> Self {
+        assert!(specs.len() == tcbs.len());
+        assert!(task_table_stk_words(specs) == arena_words);
+        TaskTable { specs, tcbs, arena, arena_words, error: CsCell::new(None) }
+    }
+
+    /// The declared tasks
+    #[inline]
+    pub const fn specs(&self) -> &'static [TaskSpec] {
+        self.specs
+    }
+
+    /// Total stack words of the table
+    #[inline]
+    pub const fn stk_words(&self) -> usize {
+        self.arena_words
+    }
+
+    /// TCB of entry `index`
+    pub fn tcb(&self, index: usize) -> Option<NonNull<OsTcb>> {
+        self.tcbs.get(index).and_then(|t| NonNull::new(t.get()))
+    }
+
+    /// TCB of the entry named `name`
+    pub fn tcb_by_name(&self, name: &str) -> Option<NonNull<OsTcb>> {
+        self.specs.iter().position(|s| s.name == name).and_then(|i| self.tcb(i))
+    }
+
+    /// The entry that made the last `os_init` fail, if any
+    pub fn error(&self) -> Option<TaskTableError> {
+        critical_section(|cs| *self.error.get(cs))
+    }
+
+    /// Create every task in table order
+    ///
+    /// Stops at the first failure, which is recorded for [`error`](Self::error).
+    pub(crate) fn create_all(&self) -> OsResult<()> {
+        critical_section(|cs| *self.error.get(cs) = None);
+
+        let mut offset = 0;
+        for (index, (spec, tcb)) in self.specs.iter().zip(self.tcbs).enumerate() {
+            let res = unsafe {
+                super::os_task_create_raw(
+                    tcb.get(),
+                    spec.name,
+                    spec.entry,
+                    core::ptr::null_mut(),
+                    spec.prio,
+                    self.arena.add(offset),
+                    spec.stk_size,
+                    kernel::cfg().time_quanta_default,
+                    spec.opt,
+                )
+            };
+            if let Err(err) = res {
+                crate::warn!("task table entry {} ({}) failed: {}", index, spec.name, err as u16);
+                critical_section(|cs| *self.error.get(cs) = Some(TaskTableError { index, name: spec.name, err }));
+                return Err(err);
+            }
+            offset += spec.arena_words();
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for TaskTable {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TaskTable")
+            .field("specs", &self.specs)
+            .field("stk_words", &self.arena_words)
+            .finish()
+    }
+}
+
+impl PartialEq for TaskTable {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self, other)
+    }
+}
+
+impl Eq for TaskTable {}
+
+/// Declare a [`TaskTable`](crate::task::TaskTable) with its TCBs and stack arena
+///
+/// Takes the entries inline, or a `&[TaskSpec]` constant that const
+/// checks can also see.
+///
+/// ```ignore
+/// ucosiii::os_task_table! {
+///     pub static TASKS = [
+///         TaskSpec::new("Blink", blink, 10, 256),
+///     ];
+/// }
+/// ```
+#[macro_export]
+macro_rules! os_task_table {
+    ($vis:vis static $name:ident = [$($spec:expr),* $(,)?];) => {
+        $crate::os_task_table!($vis static $name = &[$($spec),*];);
+    };
+    ($vis:vis static $name:ident = $specs:expr;) => {
+        $vis static $name: $crate::task::TaskTable = {
+            use $crate::core::cs_cell::SyncUnsafeCell;
+            use $crate::task::{OsTcb, StkArena, TaskSpec};
+
+            const __SPECS: &[TaskSpec] = $specs;
+            const __WORDS: usize = $crate::task::task_table_stk_words(__SPECS);
+            static __TCBS: [SyncUnsafeCell<OsTcb>; __SPECS.len()] =
+                [const { SyncUnsafeCell::new(OsTcb::new()) }; __SPECS.len()];
+            static __ARENA: StkArena<__WORDS> = StkArena::new();
+
+            $crate::task::TaskTable::new(__SPECS, &__TCBS, __ARENA.as_ptr(), __WORDS)
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(_: *mut ()) -> ! {
+        loop {}
+    }
+
+    #[test]
+    fn test_arena_rounds_to_even_words() {
+        let specs = [
+            TaskSpec::new("A", entry, 3, 128),
+            TaskSpec::new("B", entry, 4, 65),
+            TaskSpec::new("C", entry, 4, 1),
+        ];
+        assert_eq!(task_table_stk_words(&specs), 128 + 66 + 2);
+        assert_eq!(task_table_stk_words(&[]), 0);
+        assert!(!task_table_prios_unique(&specs));
+        assert!(task_table_prios_unique(&specs[..2]));
+    }
+}
@@ -4,12 +4,21 @@
 
 use core::ptr::NonNull;
 
+use crate::config::CFG_TASK_REG_TBL_SIZE;
+use super::reg::OsReg;
+
+use crate::error::{OsError, OsResult};
 use crate::types::{
-    OsFlags, OsMsgSize, OsNestingCtr, OsOpt, OsPendOn, OsPendStatus,
-    OsPrio, OsSemCtr, OsStkElement, OsTaskState, OsTick,
+    obj_name, obj_name_str, ObjName, OsFlags, OsMsgSize, OsNestingCtr, OsObjType, OsOpt, OsPendOn, OsPendStatus,
+    OsPrio, OsSemCtr, OsStkElement, OsTaskId, OsTaskState, OsTick,
 };
 
 /// Task Control Block
+///
+/// The list links, state and priority fields are hidden and will become
+/// crate-private; read them through [`prio`](Self::prio),
+/// [`state`](Self::state) and friends. Writing them from application code
+/// corrupts scheduler invariants.
 #[repr(C)]
 pub struct OsTcb {
     // ============ Stack pointer ============
@@ -26,47 +35,76 @@ pub struct OsTcb {
 
     // ============ Task identification ============
     /// Task name
-    pub name: &'static str,
+    pub name: ObjName,
+    /// `OsObjType::Task` from creation until deletion
+    #[doc(hidden)]
+    pub obj_type: OsObjType,
+    /// Task ID, `TASK_ID_NONE` until created and after deletion
+    #[doc(hidden)]
+    pub id: OsTaskId,
 
     // ============ Ready list links ============
     /// Next TCB in ready list
+    #[doc(hidden)]
     pub next_ptr: Option<NonNull<OsTcb>>,
     /// Previous TCB in ready list
+    #[doc(hidden)]
     pub prev_ptr: Option<NonNull<OsTcb>>,
 
     // ============ Pend list links ============
     /// Next TCB in pend list
+    #[doc(hidden)]
     pub pend_next_ptr: Option<NonNull<OsTcb>>,
     /// Previous TCB in pend list
+    #[doc(hidden)]
     pub pend_prev_ptr: Option<NonNull<OsTcb>>,
     /// Object this task is pending on
+    #[doc(hidden)]
     pub pend_obj_ptr: *const (),
     /// What type of object the task is pending on
+    #[doc(hidden)]
     pub pend_on: OsPendOn,
     /// Result of pend operation
+    #[doc(hidden)]
     pub pend_status: OsPendStatus,
 
     // ============ Tick list links ============
     /// Next TCB in tick list
+    #[doc(hidden)]
     pub tick_next_ptr: Option<NonNull<OsTcb>>,
     /// Previous TCB in tick list
+    #[doc(hidden)]
     pub tick_prev_ptr: Option<NonNull<OsTcb>>,
-    /// Remaining ticks for delay/timeout
-    pub tick_remain: OsTick,
+    /// Absolute tick at which the delay/timeout expires
+    #[doc(hidden)]
+    pub tick_expiry: OsTick,
     /// Which tick wheel slot this task is in
+    #[doc(hidden)]
     pub tick_wheel_slot: u8,
+    /// Ticks left of the last delay or pend timeout when the task woke
+    #[doc(hidden)]
+    pub tick_remain: OsTick,
 
     // ============ Priority ============
     /// Current priority
+    #[doc(hidden)]
     pub prio: OsPrio,
     /// Base priority
+    #[doc(hidden)]
     pub base_prio: OsPrio,
+    /// Only tasks of numerically lower priority preempt this one while it runs
+    #[doc(hidden)]
+    pub preempt_threshold: OsPrio,
 
     // ============ State ============
     /// Current task state
+    #[doc(hidden)]
     pub task_state: OsTaskState,
     /// Task options
     pub opt: OsOpt,
+    /// Deletion requested through `os_task_del_request`
+    #[doc(hidden)]
+    pub del_req: bool,
 
     // ============ Suspend ============
     /// Suspend nesting counter
@@ -77,6 +115,13 @@ pub struct OsTcb {
     pub time_quanta: OsTick,
     /// Remaining time quanta
     pub time_quanta_ctr: OsTick,
+    /// Timestamp counts run in the current slice (`rr-precise`)
+    #[cfg(feature = "rr-precise")]
+    pub slice_used: u32,
+    /// Slice length in timestamp counts, 0 for `time_quanta` ticks
+    /// (`rr-precise`)
+    #[cfg(feature = "rr-precise")]
+    pub time_slice_ts: u32,
 
     // ============ Task semaphore ============
     /// Task-specific semaphore counter
@@ -99,6 +144,12 @@ pub struct OsTcb {
     // ============ Mutex priority inheritance ============
     /// Head of list of mutexes owned by this task
     pub mutex_grp_head: *const (),
+    /// Lock whose waiter set the inherited priority, null when not boosted
+    #[doc(hidden)]
+    pub boost_obj: *const (),
+    /// Name of `boost_obj`
+    #[doc(hidden)]
+    pub boost_name: ObjName,
 
     // ============ Task entry point ============
     /// Task function address
@@ -106,9 +157,78 @@ pub struct OsTcb {
     /// Task argument
     pub task_entry_arg: *mut (),
 
-    // ============ Extension pointer ============
-    /// User-defined extension data
+    // ============ Extension ============
+    /// Extension data, see `os_task_ext_set`
     pub ext_ptr: *mut (),
+    /// Tag of the extension's type, null without one
+    pub ext_tag: *const (),
+
+    // ============ Task registers ============
+    /// Per-task storage slots
+    pub regs: [OsReg; CFG_TASK_REG_TBL_SIZE],
+    /// Bitmask of slots written since creation
+    pub reg_valid: u32,
+
+    // ============ Fault recovery ============
+    /// Fault that removed the task
+    #[cfg(feature = "fault-recovery")]
+    pub fault: Option<crate::task::OsFaultReport>,
+
+    // ============ Task group ============
+    /// Group the task belongs to
+    #[cfg(feature = "task-group")]
+    #[doc(hidden)]
+    pub group_ptr: Option<NonNull<crate::task::OsTaskGroup>>,
+    /// Next member of the group
+    #[cfg(feature = "task-group")]
+    #[doc(hidden)]
+    pub group_next_ptr: Option<NonNull<OsTcb>>,
+    /// Previous member of the group
+    #[cfg(feature = "task-group")]
+    #[doc(hidden)]
+    pub group_prev_ptr: Option<NonNull<OsTcb>>,
+
+    // ============ Budget ============
+    /// Longest continuous run in ticks, 0 for none
+    #[cfg(feature = "budget")]
+    #[doc(hidden)]
+    pub budget_run: OsTick,
+    /// Longest gap between runs in ticks, 0 for none
+    #[cfg(feature = "budget")]
+    #[doc(hidden)]
+    pub budget_period: OsTick,
+    /// Timestamp the task was last switched in at
+    #[cfg(feature = "budget")]
+    #[doc(hidden)]
+    pub budget_run_ts: u32,
+    /// Last tick the task ran at
+    #[cfg(feature = "budget")]
+    #[doc(hidden)]
+    pub budget_last_run: OsTick,
+    /// Limits reported for the current run and gap, `OsBudgetLimit` bits
+    #[cfg(feature = "budget")]
+    #[doc(hidden)]
+    pub budget_tripped: u8,
+    /// Next task with a period deadline
+    #[cfg(feature = "budget")]
+    #[doc(hidden)]
+    pub budget_next_ptr: Option<NonNull<OsTcb>>,
+    /// Previous task with a period deadline
+    #[cfg(feature = "budget")]
+    #[doc(hidden)]
+    pub budget_prev_ptr: Option<NonNull<OsTcb>>,
+
+    // ============ Task registry links ============
+    /// Next TCB in the list of all tasks
+    pub dbg_next_ptr: Option<NonNull<OsTcb>>,
+    /// Previous TCB in the list of all tasks
+    pub dbg_prev_ptr: Option<NonNull<OsTcb>>,
+
+    // ============ List checks ============
+    /// Lists this TCB is linked into
+    #[cfg(feature = "paranoid")]
+    #[doc(hidden)]
+    pub which_list: crate::core::paranoid::ListTag,
 }
 
 impl OsTcb {
@@ -120,7 +240,9 @@ impl OsTcb {
             stk_limit: core::ptr::null_mut(),
             stk_size: 0,
             
-            name: "",
+            name: obj_name(""),
+            obj_type: OsObjType::None,
+            id: super::TASK_ID_NONE,
             
             next_ptr: None,
             prev_ptr: None,
@@ -133,19 +255,27 @@ impl OsTcb {
             
             tick_next_ptr: None,
             tick_prev_ptr: None,
-            tick_remain: 0,
+            tick_expiry: 0,
             tick_wheel_slot: 0,
+            tick_remain: 0,
             
             prio: 0,
             base_prio: 0,
+            // No threshold: `prio` alone decides
+            preempt_threshold: OsPrio::MAX,
             
             task_state: OsTaskState::Ready,
             opt: 0,
+            del_req: false,
             
             suspend_ctr: 0,
             
             time_quanta: 0,
             time_quanta_ctr: 0,
+            #[cfg(feature = "rr-precise")]
+            slice_used: 0,
+            #[cfg(feature = "rr-precise")]
+            time_slice_ts: 0,
             
             sem_ctr: 0,
             
@@ -157,11 +287,48 @@ impl OsTcb {
             msg_size: 0,
             
             mutex_grp_head: core::ptr::null(),
+            boost_obj: core::ptr::null(),
+            boost_name: obj_name(""),
             
             task_entry_addr: 0,
             task_entry_arg: core::ptr::null_mut(),
             
             ext_ptr: core::ptr::null_mut(),
+            ext_tag: core::ptr::null(),
+
+            regs: [0; CFG_TASK_REG_TBL_SIZE],
+            reg_valid: 0,
+
+            #[cfg(feature = "fault-recovery")]
+            fault: None,
+
+            #[cfg(feature = "task-group")]
+            group_ptr: None,
+            #[cfg(feature = "task-group")]
+            group_next_ptr: None,
+            #[cfg(feature = "task-group")]
+            group_prev_ptr: None,
+
+            #[cfg(feature = "budget")]
+            budget_run: 0,
+            #[cfg(feature = "budget")]
+            budget_period: 0,
+            #[cfg(feature = "budget")]
+            budget_run_ts: 0,
+            #[cfg(feature = "budget")]
+            budget_last_run: 0,
+            #[cfg(feature = "budget")]
+            budget_tripped: 0,
+            #[cfg(feature = "budget")]
+            budget_next_ptr: None,
+            #[cfg(feature = "budget")]
+            budget_prev_ptr: None,
+
+            dbg_next_ptr: None,
+            dbg_prev_ptr: None,
+
+            #[cfg(feature = "paranoid")]
+            which_list: crate::core::paranoid::ListTag::UNLINKED,
         }
     }
 
@@ -170,6 +337,188 @@ impl OsTcb {
         *self = Self::new();
     }
 
+    /// Drop the extension
+    #[inline]
+    pub(crate) fn ext_clear(&mut self) {
+        self.ext_ptr = core::ptr::null_mut();
+        self.ext_tag = core::ptr::null();
+    }
+
+    /// Task name
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        obj_name_str(self.name)
+    }
+
+    /// Task ID, given at creation
+    #[inline]
+    pub fn id(&self) -> OsTaskId {
+        self.id
+    }
+
+    /// Current priority, including any inherited boost
+    #[inline]
+    pub fn prio(&self) -> OsPrio {
+        self.prio
+    }
+
+    /// Base priority
+    #[inline]
+    pub fn base_prio(&self) -> OsPrio {
+        self.base_prio
+    }
+
+    /// Name of the mutex or write lock the current priority is inherited
+    /// from, `None` while running at the base priority
+    #[inline]
+    pub fn boosted_by(&self) -> Option<&'static str> {
+        (!self.boost_obj.is_null()).then(|| obj_name_str(self.boost_name))
+    }
+
+    /// Preemption threshold
+    #[inline]
+    pub fn preempt_threshold(&self) -> OsPrio {
+        self.preempt_threshold
+    }
+
+    /// Whether a ready task of priority `prio` preempts this one
+    ///
+    /// Compares against the threshold, or against `prio` while a boost
+    /// raises it above the threshold.
+    #[inline]
+    pub fn preempted_by(&self, prio: OsPrio) -> bool {
+        prio < self.preempt_threshold.min(self.prio)
+    }
+
+    /// Name of the task's group
+    #[cfg(feature = "task-group")]
+    #[inline]
+    pub fn group_name(&self) -> Option<&'static str> {
+        self.group_ptr.map(|g| unsafe { g.as_ref() }.name())
+    }
+
+    /// Current task state
+    #[inline]
+    pub fn state(&self) -> OsTaskState {
+        self.task_state
+    }
+
+    /// Object type the task is pending on
+    #[inline]
+    pub fn pend_on(&self) -> OsPendOn {
+        self.pend_on
+    }
+
+    /// Block the task on a kernel object
+    ///
+    /// The caller removes the task from the ready list and inserts it into
+    /// the object's pend list.
+    pub(crate) fn set_pending(&mut self, obj: *const (), on: OsPendOn, timeout: OsTick) {
+        self.pend_on = on;
+        self.pend_status = OsPendStatus::Ok;
+        self.pend_obj_ptr = obj;
+        self.task_state = if timeout > 0 {
+            self.tick_expiry = crate::kernel::KERNEL.tick_get().wrapping_add(timeout);
+            OsTaskState::PendTimeout
+        } else {
+            OsTaskState::Pend
+        };
+    }
+
+    /// Make a pending task ready with the given pend result
+    ///
+    /// The caller removes the task from the pend list and inserts it into
+    /// the ready list.
+    pub(crate) fn clear_pending(&mut self, status: OsPendStatus) {
+        self.tick_remain = self.ticks_left(crate::kernel::KERNEL.tick_get());
+        self.pend_on = OsPendOn::Nothing;
+        self.pend_status = status;
+        self.pend_obj_ptr = core::ptr::null();
+        self.task_state = OsTaskState::Ready;
+    }
+
+    /// Result of the last pend
+    pub(crate) fn pend_result(&self) -> OsResult<()> {
+        match self.pend_status {
+            OsPendStatus::Ok => Ok(()),
+            OsPendStatus::Timeout => Err(OsError::Timeout),
+            OsPendStatus::Abort => Err(OsError::PendAbort),
+            OsPendStatus::Del => Err(OsError::ObjDel),
+            OsPendStatus::DelReq => Err(OsError::TaskDelRequested),
+        }
+    }
+
+    /// Whether blocking calls fail for a requested deletion: the task was
+    /// created with `TASK_DEL_INTERRUPT` and its deletion requested
+    #[inline]
+    pub(crate) fn del_interrupts(&self) -> bool {
+        self.del_req && self.opt & crate::types::opt::TASK_DEL_INTERRUPT != 0
+    }
+
+    /// Put the running task to sleep
+    ///
+    /// The caller inserts the task into the tick queue.
+    pub(crate) fn set_delayed(&mut self) {
+        self.task_state = OsTaskState::Delayed;
+    }
+
+    /// Ticks left until the delay or timeout expires, 0 if none is running
+    pub(crate) fn ticks_left(&self, now: OsTick) -> OsTick {
+        match self.task_state {
+            OsTaskState::Delayed
+            | OsTaskState::DelayedSuspended
+            | OsTaskState::PendTimeout
+            | OsTaskState::PendTimeoutSuspended => {
+                crate::time::tick::tick_until(now, self.tick_expiry)
+            }
+            _ => 0,
+        }
+    }
+
+    /// End a delay or pend timeout
+    ///
+    /// Returns `true` if the task became ready and must be inserted into the
+    /// ready list.
+    pub(crate) fn tick_expire(&mut self) -> bool {
+        self.tick_remain = 0;
+        match self.task_state {
+            OsTaskState::Delayed => {
+                self.task_state = OsTaskState::Ready;
+                true
+            }
+            OsTaskState::DelayedSuspended => {
+                self.task_state = OsTaskState::Suspended;
+                false
+            }
+            OsTaskState::PendTimeout => {
+                self.task_state = OsTaskState::Ready;
+                self.pend_status = OsPendStatus::Timeout;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether part of the round-robin slice has been used
+    ///
+    /// The counter is refilled as soon as a slice runs out, so a task that
+    /// blocked with its slice exhausted reads as not started.
+    #[cfg(not(feature = "rr-precise"))]
+    #[inline]
+    pub fn slice_started(&self) -> bool {
+        self.time_quanta_ctr > 0 && self.time_quanta_ctr < self.time_quanta
+    }
+
+    /// Whether part of the round-robin slice has been used
+    ///
+    /// The measured time is cleared as soon as a slice runs out, so a task
+    /// that blocked with its slice exhausted reads as not started.
+    #[cfg(feature = "rr-precise")]
+    #[inline]
+    pub fn slice_started(&self) -> bool {
+        self.slice_used > 0
+    }
+
     /// Check if task is ready to run
     #[inline]
     pub fn is_ready(&self) -> bool {
@@ -212,5 +561,33 @@ impl Default for OsTcb {
     }
 }
 
+// SAFETY: a TCB is shared by design between its task, the kernel and ISRs.
+// Its links and state are only written inside a critical section on a
+// single core, and the public getters read single fields.
 unsafe impl Send for OsTcb {}
 unsafe impl Sync for OsTcb {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick_remain_on_wake() {
+        let mut tcb = OsTcb::new();
+
+        // Posted before the timeout: the whole budget is left
+        tcb.set_pending(core::ptr::null(), OsPendOn::Semaphore, 10);
+        tcb.clear_pending(OsPendStatus::Ok);
+        assert_eq!(tcb.tick_remain, 10);
+
+        // Timed out: nothing left
+        tcb.set_pending(core::ptr::null(), OsPendOn::Semaphore, 10);
+        assert!(tcb.tick_expire());
+        assert_eq!((tcb.tick_remain, tcb.pend_result()), (0, Err(OsError::Timeout)));
+
+        // Forever pends report 0
+        tcb.set_pending(core::ptr::null(), OsPendOn::Semaphore, 0);
+        tcb.clear_pending(OsPendStatus::Ok);
+        assert_eq!(tcb.tick_remain, 0);
+    }
+}
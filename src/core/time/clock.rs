@@ -0,0 +1,179 @@
+//! Calendar time (`clock` feature)
+//!
+//! Keeps seconds since the Unix epoch plus the fraction of the current
+//! second, advanced by the tick interrupt by however many ticks it
+//! accounts for, so the mapping from ticks to wall-clock time holds across
+//! `os_tick_rate_set`. The fraction is counted in millionths of a tick:
+//! each tick adds `1_000_000 + ppm`, which lets [`adjust_ppm`] slew the
+//! clock without stepping it.
+//!
+//! The time is published through a seqlock with two copies (a latch): the
+//! writer updates one copy while readers use the other, so [`now`] never
+//! waits on a writer it preempted and can be called from any context,
+//! including interrupts the kernel never masks.
+//!
+//! An [`Rtc`] registered with [`rtc_set`] seeds the clock at `os_init`,
+//! receives every [`set`] and is written back every
+//! `CFG_CLOCK_RTC_SYNC_SECS`. Conversion to a broken-down date is left to
+//! the application.
+
+use core::ptr::{addr_of, addr_of_mut};
+use core::sync::atomic::{fence, AtomicI32, AtomicU32, Ordering};
+
+use crate::config::{CFG_CLOCK_ADJ_MAX_PPM, CFG_CLOCK_RTC_SYNC_SECS};
+use crate::core::cs_cell::{CsCell, SyncUnsafeCell};
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::types::OsTick;
+
+/// Fraction units per tick at the nominal rate
+const UNITS_PER_TICK: u64 = 1_000_000;
+
+/// Battery-backed real-time clock
+///
+/// Both methods are called with interrupts disabled, `write` from the tick
+/// interrupt, so they must be short and must not block. An RTC behind a
+/// slow bus should cache the value and write it from a task.
+pub trait Rtc: Sync {
+    /// Seconds since the Unix epoch, or `None` if the RTC lost its time
+    fn read(&self) -> Option<u64>;
+    /// Store seconds since the Unix epoch
+    fn write(&self, unix_secs: u64);
+}
+
+/// Wall-clock time
+#[derive(Clone, Copy)]
+struct Wall {
+    /// Seconds since the Unix epoch
+    secs: u64,
+    /// Fraction of the second, `UNITS_PER_TICK` per nominal tick
+    frac: u64,
+}
+
+/// Update count; its low bit selects the copy readers use
+static SEQ: AtomicU32 = AtomicU32::new(0);
+
+/// The two copies of the time
+static COPIES: SyncUnsafeCell<[Wall; 2]> = SyncUnsafeCell::new([Wall { secs: 0, frac: 0 }; 2]);
+
+/// Rate adjustment in parts per million
+static ADJ_PPM: AtomicI32 = AtomicI32::new(0);
+
+/// Registered RTC, kept across `os_init`
+static RTC: CsCell<Option<&'static dyn Rtc>> = CsCell::new(None);
+
+/// Fraction units in one second
+fn units_per_sec() -> u64 {
+    kernel::cfg().tick_rate_hz as u64 * UNITS_PER_TICK
+}
+
+fn read() -> Wall {
+    loop {
+        let seq = SEQ.load(Ordering::Acquire);
+        let copy = unsafe { addr_of!((*COPIES.get())[(seq & 1) as usize]) };
+        let wall = unsafe { copy.read_volatile() };
+        fence(Ordering::Acquire);
+        if SEQ.load(Ordering::Relaxed) == seq {
+            return wall;
+        }
+    }
+}
+
+/// Publish `wall`; callers are serialized by a critical section
+fn write(wall: Wall) {
+    let seq = SEQ.load(Ordering::Relaxed);
+    let copies = COPIES.get();
+
+    // Readers move to copy 1 while copy 0 changes, then back
+    SEQ.store(seq.wrapping_add(1), Ordering::Relaxed);
+    fence(Ordering::Release);
+    unsafe { addr_of_mut!((*copies)[0]).write_volatile(wall) };
+    fence(Ordering::Release);
+    SEQ.store(seq.wrapping_add(2), Ordering::Relaxed);
+    fence(Ordering::Release);
+    unsafe { addr_of_mut!((*copies)[1]).write_volatile(wall) };
+}
+
+fn rtc() -> Option<&'static dyn Rtc> {
+    critical_section(|cs| *RTC.get(cs))
+}
+
+/// Current time as seconds since the Unix epoch and milliseconds
+///
+/// Counts from 0 after `os_init` until [`set`] or an [`Rtc`] provides the
+/// time. Safe from any context.
+pub fn now() -> (u64, u16) {
+    let wall = read();
+    (wall.secs, (wall.frac * 1000 / units_per_sec()) as u16)
+}
+
+/// Set the time to `unix_secs`, at the start of that second
+///
+/// Steps the clock; use [`adjust_ppm`] for small corrections. Also writes
+/// the registered [`Rtc`].
+pub fn set(unix_secs: u64) {
+    critical_section(|_cs| {
+        write(Wall { secs: unix_secs, frac: 0 });
+        if let Some(rtc) = rtc() {
+            rtc.write(unix_secs);
+        }
+    });
+}
+
+/// Run the clock `ppm` parts per million fast (or slow, if negative)
+///
+/// Takes effect from the next tick and stays until changed or `os_init`,
+/// so an offset found by comparing with a reference is corrected
+/// gradually instead of stepping the time.
+///
+/// # Returns
+/// * `Err(OsError::ClockAdjInvalid)` - `ppm` beyond `CFG_CLOCK_ADJ_MAX_PPM`
+pub fn adjust_ppm(ppm: i32) -> OsResult<()> {
+    if ppm.unsigned_abs() > CFG_CLOCK_ADJ_MAX_PPM {
+        return Err(OsError::ClockAdjInvalid);
+    }
+    ADJ_PPM.store(ppm, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Register the RTC, or remove it with `None`
+///
+/// Kept across `os_init`, which seeds the clock from it, so register it
+/// before.
+pub fn rtc_set(rtc: Option<&'static dyn Rtc>) {
+    critical_section(|cs| {
+        *RTC.get(cs) = rtc;
+    });
+}
+
+/// Advance the clock by `ticks`
+///
+/// Called from the tick interrupt.
+pub(crate) fn advance(ticks: OsTick) {
+    let per_sec = units_per_sec();
+    let step = (UNITS_PER_TICK as i64 + ADJ_PPM.load(Ordering::Relaxed) as i64) as u64;
+
+    let synced = critical_section(|_cs| {
+        let mut wall = read();
+        let before = wall.secs;
+        wall.frac += ticks as u64 * step;
+        wall.secs += wall.frac / per_sec;
+        wall.frac %= per_sec;
+        write(wall);
+
+        let sync = CFG_CLOCK_RTC_SYNC_SECS as u64;
+        (sync != 0 && before / sync != wall.secs / sync).then_some(wall.secs)
+    });
+
+    if let (Some(secs), Some(rtc)) = (synced, rtc()) {
+        rtc.write(secs);
+    }
+}
+
+/// Reset the rate and seed the time from the RTC, or 0
+pub(crate) fn reset() {
+    ADJ_PPM.store(0, Ordering::Relaxed);
+    let secs = rtc().and_then(|rtc| rtc.read()).unwrap_or(0);
+    critical_section(|_cs| write(Wall { secs, frac: 0 }));
+}
@@ -0,0 +1,99 @@
+//! `embedded-hal` delay provider
+//!
+//! [`OsDelay`] lets driver crates that take `embedded_hal::delay::DelayNs`
+//! block through the scheduler instead of burning CPU. Whole ticks are slept
+//! with `os_time_dly`; the sub-tick remainder is spun out on the timestamp
+//! counter (`CFG_DELAY_SPIN_REMAINDER`) or rounded up to one more tick.
+//!
+//! Before `os_start`, in an ISR or with the scheduler locked the task cannot
+//! block, so the whole delay is a busy-wait on the timestamp counter, at
+//! [`os_ts_freq_hz`](super::os_ts_freq_hz).
+
+use embedded_hal::delay::DelayNs;
+
+use crate::config::CFG_DELAY_SPIN_REMAINDER;
+use crate::critical::is_isr_context;
+use crate::kernel;
+use crate::types::OsTick;
+
+/// Scheduler-backed delay, usable wherever `DelayNs` is expected
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsDelay;
+
+impl OsDelay {
+    pub const fn new() -> Self {
+        OsDelay
+    }
+}
+
+/// Timestamp cycles for `ns` nanoseconds, `None` if it would not fit
+fn ns_to_cycles(ns: u64) -> Option<u32> {
+    let cycles = (ns * super::os_ts_freq_hz() as u64).div_ceil(1_000_000_000);
+    u32::try_from(cycles).ok().filter(|&c| c < u32::MAX / 2)
+}
+
+/// Spin until `cycles` have passed since `start`
+fn spin_until(start: u32, cycles: u32) {
+    while crate::port::os_cpu_ts_get().wrapping_sub(start) < cycles {
+        core::hint::spin_loop();
+    }
+}
+
+/// Busy-wait `ns` nanoseconds
+fn busy_wait_ns(ns: u64) {
+    // Before `os_start` the counter may not be running yet
+    crate::port::os_cpu_ts_init();
+
+    let mut left = ns;
+    while left > 0 {
+        // Chunk long waits so the 32-bit counter cannot wrap
+        let chunk = left.min(100_000_000);
+        let start = crate::port::os_cpu_ts_get();
+        spin_until(start, ns_to_cycles(chunk).unwrap_or(u32::MAX / 2));
+        left -= chunk;
+    }
+}
+
+fn can_block() -> bool {
+    kernel::KERNEL.is_running() && !is_isr_context() && kernel::KERNEL.sched_lock_nesting() == 0
+}
+
+/// Delay at least `ns` nanoseconds
+fn delay(ns: u64) {
+    if !can_block() {
+        busy_wait_ns(ns);
+        return;
+    }
+
+    let tick_ns = 1_000_000_000 / kernel::cfg().tick_rate_hz as u64;
+    let whole = (ns / tick_ns) as OsTick;
+    let start = crate::port::os_cpu_ts_get();
+
+    match ns_to_cycles(ns).filter(|_| CFG_DELAY_SPIN_REMAINDER) {
+        Some(cycles) => {
+            // `os_time_dly(n)` returns after more than n - 1 ticks; spin the rest
+            if whole > 0 {
+                let _ = super::os_time_dly(whole);
+            }
+            spin_until(start, cycles);
+        }
+        None => {
+            // The first tick may be partial, so add one to guarantee `ns`
+            let _ = super::os_time_dly(ns.div_ceil(tick_ns) as OsTick + 1);
+        }
+    }
+}
+
+impl DelayNs for OsDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        delay(ns as u64);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        delay(us as u64 * 1_000);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        delay(ms as u64 * 1_000_000);
+    }
+}
@@ -0,0 +1,175 @@
+//! Duration-based time API
+//!
+//! [`TickDuration`] keeps delays and timeouts in real time units and turns
+//! them into ticks only when used, rounding up so a non-zero duration never
+//! becomes 0 ticks. Application code therefore keeps its meaning when the
+//! tick rate changes. With the `fugit` feature any `fugit::Duration<u32, _, _>`
+//! converts into a [`TickDuration`] or a [`Timeout`].
+//!
+//! [`Instant`] wraps the tick counter for building deadlines by hand.
+
+use core::cmp::Ordering;
+use core::ops::Add;
+
+use crate::error::OsResult;
+use crate::kernel;
+use super::tick::tick_diff;
+use crate::types::{OsTick, Timeout};
+
+/// Convert microseconds to ticks at `rate_hz`, rounding up
+pub const fn micros_to_ticks(us: u64, rate_hz: u32) -> OsTick {
+    let ticks = us.saturating_mul(rate_hz as u64).div_ceil(1_000_000);
+    if ticks > OsTick::MAX as u64 {
+        OsTick::MAX
+    } else {
+        ticks as OsTick
+    }
+}
+
+/// Convert milliseconds to ticks at `rate_hz`, rounding up
+pub const fn millis_to_ticks(ms: u32, rate_hz: u32) -> OsTick {
+    micros_to_ticks(ms as u64 * 1_000, rate_hz)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Repr {
+    Ticks(OsTick),
+    Micros(u64),
+}
+
+/// A delay or timeout, in ticks or in real time
+#[derive(Debug, Clone, Copy)]
+pub struct TickDuration(Repr);
+
+impl TickDuration {
+    /// Exact number of ticks, independent of the tick rate
+    pub const fn from_ticks(ticks: OsTick) -> Self {
+        Self(Repr::Ticks(ticks))
+    }
+
+    /// Duration in microseconds
+    pub const fn from_micros(us: u64) -> Self {
+        Self(Repr::Micros(us))
+    }
+
+    /// Duration in milliseconds
+    pub const fn from_millis(ms: u32) -> Self {
+        Self(Repr::Micros(ms as u64 * 1_000))
+    }
+
+    /// Duration in seconds
+    pub const fn from_secs(s: u32) -> Self {
+        Self(Repr::Micros(s as u64 * 1_000_000))
+    }
+
+    /// Convert a `fugit` duration
+    #[cfg(feature = "fugit")]
+    pub const fn from_fugit<const NOM: u32, const DENOM: u32>(d: fugit::Duration<u32, NOM, DENOM>) -> Self {
+        let num = d.ticks() as u64 * NOM as u64 * 1_000_000;
+        Self(Repr::Micros(num.div_ceil(DENOM as u64)))
+    }
+
+    /// Ticks at `rate_hz`, rounded up
+    pub const fn ticks_at(self, rate_hz: u32) -> OsTick {
+        match self.0 {
+            Repr::Ticks(ticks) => ticks,
+            Repr::Micros(us) => micros_to_ticks(us, rate_hz),
+        }
+    }
+
+    /// Ticks at the configured tick rate, rounded up
+    pub fn ticks(self) -> OsTick {
+        self.ticks_at(kernel::cfg().tick_rate_hz)
+    }
+}
+
+#[cfg(feature = "fugit")]
+impl<const NOM: u32, const DENOM: u32> From<fugit::Duration<u32, NOM, DENOM>> for TickDuration {
+    fn from(d: fugit::Duration<u32, NOM, DENOM>) -> Self {
+        Self::from_fugit(d)
+    }
+}
+
+impl From<TickDuration> for Timeout {
+    /// A duration of 0 ticks does not block
+    fn from(d: TickDuration) -> Self {
+        Timeout::Ticks(d.ticks())
+    }
+}
+
+#[cfg(feature = "fugit")]
+impl<const NOM: u32, const DENOM: u32> From<fugit::Duration<u32, NOM, DENOM>> for Timeout {
+    fn from(d: fugit::Duration<u32, NOM, DENOM>) -> Self {
+        TickDuration::from_fugit(d).into()
+    }
+}
+
+/// Delay the calling task for a duration
+///
+/// See [`os_time_dly`](super::os_time_dly).
+pub fn dly(duration: impl Into<TickDuration>) -> OsResult<()> {
+    super::os_time_dly(duration.into().ticks())
+}
+
+/// A point on the tick counter
+///
+/// Comparisons are wrap-safe for instants less than 2^31 ticks apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instant(OsTick);
+
+impl Instant {
+    /// Current tick
+    pub fn now() -> Self {
+        Self(kernel::KERNEL.tick_get())
+    }
+
+    /// Instant at a raw tick count
+    pub const fn from_ticks(ticks: OsTick) -> Self {
+        Self(ticks)
+    }
+
+    /// Raw tick count
+    pub const fn ticks(self) -> OsTick {
+        self.0
+    }
+
+    /// Ticks from `earlier` to `self`
+    pub const fn ticks_since(self, earlier: Instant) -> OsTick {
+        self.0.wrapping_sub(earlier.0)
+    }
+
+    /// Time since this instant
+    pub fn elapsed(self) -> TickDuration {
+        TickDuration::from_ticks(Self::now().ticks_since(self))
+    }
+
+    /// Timeout that expires at this instant, `NoWait` if already reached
+    pub fn until(self) -> Timeout {
+        let now = Self::now();
+        if now >= self {
+            Timeout::NoWait
+        } else {
+            Timeout::Ticks(self.ticks_since(now))
+        }
+    }
+}
+
+impl Ord for Instant {
+    fn cmp(&self, other: &Self) -> Ordering {
+        tick_diff(self.0, other.0).cmp(&0)
+    }
+}
+
+impl PartialOrd for Instant {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add<TickDuration> for Instant {
+    type Output = Instant;
+
+    fn add(self, d: TickDuration) -> Instant {
+        Self(self.0.wrapping_add(d.ticks()))
+    }
+}
@@ -1,16 +1,38 @@
 //! Time management module
 //!
 //! Provides tick handling, time delays, and timeout management.
+//!
+//! By default the tick queue and round-robin are processed inside the tick
+//! interrupt. With the `tick-task` feature the interrupt only signals a
+//! kernel task that does the work, bounding interrupt latency.
+//!
+//! The `clock` feature adds calendar time on top of the tick ([`clock`]).
+
+#[cfg(feature = "clock")]
+pub mod clock;
+#[cfg(feature = "embedded-hal")]
+mod delay;
+mod duration;
+pub mod tick;
+#[cfg(feature = "tick-task")]
+pub(crate) mod tick_task;
+pub mod ts;
+
+#[cfg(feature = "embedded-hal")]
+pub use delay::OsDelay;
+pub use duration::{dly, micros_to_ticks, millis_to_ticks, Instant, TickDuration};
+pub use ts::{os_ts_diff, os_ts_freq_hz, os_ts_get, os_ts_init, os_ts_to_ns};
+#[cfg(armv6m)]
+pub use ts::os_ts_source_set;
 
 use core::ptr::NonNull;
 
-use crate::config::{CFG_TICK_RATE_HZ, CFG_TICK_WHEEL_SIZE};
 use crate::critical::{critical_section, is_isr_context};
 use crate::error::{OsError, OsResult};
 use crate::kernel;
 use crate::sched;
 use crate::task::OsTcb;
-use crate::types::{OsTaskState, OsTick};
+use crate::types::{opt, OsOpt, OsTick};
 
 /// Time delay in ticks
 ///
@@ -22,9 +44,11 @@ use crate::types::{OsTaskState, OsTick};
 /// * `ticks` - Number of ticks to delay (0 = no delay)
 ///
 /// # Returns
-/// * `Ok(())` - Delay completed
+/// * `Ok(())` - Delay completed or resumed early (see `os_time_dly_remaining`)
 /// * `Err(OsError::TimeDlyIsr)` - Cannot delay from ISR
 /// * `Err(OsError::SchedLocked)` - Scheduler is locked
+/// * `Err(OsError::TaskDelRequested)` - Deletion of the calling
+///   `TASK_DEL_INTERRUPT` task requested before or during the delay
 pub fn os_time_dly(ticks: OsTick) -> OsResult<()> {
     if !kernel::KERNEL.is_running() {
         return Err(OsError::OsNotRunning);
@@ -34,71 +58,139 @@ pub fn os_time_dly(ticks: OsTick) -> OsResult<()> {
         return Err(OsError::TimeDlyIsr);
     }
 
-    if kernel::KERNEL.sched_lock_nesting() > 0 {
-        return Err(OsError::SchedLocked);
-    }
+    kernel::sched_lock_chk()?;
 
     if ticks == 0 {
+        critical_section(|_cs| unsafe { kernel::tick_remain_set(0) });
         return Ok(());
     }
 
+    crate::core::os_trace!(Delay { ticks });
+
     critical_section(|_cs| {
         unsafe {
             if let Some(cur_tcb) = kernel::tcb_cur_ptr() {
                 let tcb = &mut *cur_tcb.as_ptr();
+                if tcb.del_interrupts() {
+                    return Err(OsError::TaskDelRequested);
+                }
                 
-                // Set delay tick count
-                tcb.tick_remain = ticks;
-                tcb.task_state = OsTaskState::Delayed;
+                tcb.set_delayed();
                 
                 let current_tick = kernel::KERNEL.tick_get();
                 let expiry_tick = current_tick.wrapping_add(ticks);
-                kernel::tick_wheel_insert(cur_tcb, expiry_tick);
+                kernel::tick_insert(cur_tcb, expiry_tick);
                 
                 sched::os_rdy_list_remove(cur_tcb);
             }
         }
-    });
+        Ok(())
+    })?;
     
     sched::os_sched();
 
-    Ok(())
+    // A deletion request cuts the delay short
+    critical_section(|_cs| match unsafe { kernel::tcb_cur_ptr() } {
+        Some(cur) if unsafe { cur.as_ref() }.del_interrupts() => Err(OsError::TaskDelRequested),
+        _ => Ok(()),
+    })
+}
+
+/// Ticks that were left of the calling task's last delay or timed pend
+///
+/// Read it right after `os_time_dly` or a pend with a timeout returns:
+/// 0 if the delay ran out or the pend timed out, otherwise what was left
+/// when `os_time_dly_resume` or a post woke the task. A pend that got
+/// through without blocking reports its full timeout. Returns 0
+/// outside a task.
+///
+/// 0 means the budget is spent: do not pass it on as a timeout, where it
+/// means wait forever.
+///
+/// ```ignore
+/// sem.pend(budget, opt::NONE)?;
+/// let budget = os_time_dly_remaining();
+/// mutex.pend(budget, opt::NONE)?;
+/// ```
+pub fn os_time_dly_remaining() -> OsTick {
+    critical_section(|_cs| unsafe {
+        kernel::tcb_cur_ptr().map_or(0, |tcb| tcb.as_ref().tick_remain)
+    })
 }
 
 /// Time delay in hours, minutes, seconds, milliseconds
 ///
 /// # Arguments
 /// * `hours` - Hours (0-999)
-/// * `minutes` - Minutes (0-59)
-/// * `seconds` - Seconds (0-59)
-/// * `milliseconds` - Milliseconds (0-999)
+/// * `minutes` - Minutes (0-59, or 0-9999 with `TIME_HMSM_NON_STRICT`)
+/// * `seconds` - Seconds (0-59, any with `TIME_HMSM_NON_STRICT`)
+/// * `milliseconds` - Milliseconds (0-999, any with `TIME_HMSM_NON_STRICT`)
+/// * `dly_opt` - `TIME_HMSM_STRICT` or `TIME_HMSM_NON_STRICT`
+///
+/// # Returns
+/// * `Ok(())` - Delay completed or resumed early
+/// * `Err(OsError::TimeInvalidHours)` / `Minutes` / `Seconds` /
+///   `Milliseconds` - Field out of range
+/// * `Err(OsError::TimeZeroDly)` - All fields are 0
+/// * `Err(OsError::TimeDlyTooLong)` - More than 2^31 - 1 ticks
 pub fn os_time_dly_hmsm(
     hours: u16,
-    minutes: u8,
-    seconds: u8,
-    milliseconds: u16,
+    minutes: u16,
+    seconds: u16,
+    milliseconds: u32,
+    dly_opt: OsOpt,
 ) -> OsResult<()> {
-    if minutes > 59 {
-        return Err(OsError::StateInvalid);
-    }
-    if seconds > 59 {
-        return Err(OsError::StateInvalid);
+    let ticks = hmsm_to_ticks(hours, minutes, seconds, milliseconds, dly_opt, kernel::cfg().tick_rate_hz)?;
+    os_time_dly(ticks)
+}
+
+/// Validate an hours/minutes/seconds/milliseconds delay and convert it to
+/// ticks at `rate_hz`, rounding up
+fn hmsm_to_ticks(
+    hours: u16,
+    minutes: u16,
+    seconds: u16,
+    milliseconds: u32,
+    dly_opt: OsOpt,
+    rate_hz: u32,
+) -> OsResult<OsTick> {
+    if hours > 999 {
+        return Err(OsError::TimeInvalidHours);
     }
-    if milliseconds > 999 {
-        return Err(OsError::StateInvalid);
+    if dly_opt & opt::TIME_HMSM_NON_STRICT == 0 {
+        if minutes > 59 {
+            return Err(OsError::TimeInvalidMinutes);
+        }
+        if seconds > 59 {
+            return Err(OsError::TimeInvalidSeconds);
+        }
+        if milliseconds > 999 {
+            return Err(OsError::TimeInvalidMilliseconds);
+        }
+    } else if minutes > 9999 {
+        return Err(OsError::TimeInvalidMinutes);
     }
 
-    let total_ms = (hours as u32) * 3600_000
-        + (minutes as u32) * 60_000
-        + (seconds as u32) * 1000
-        + (milliseconds as u32);
-
-    let ticks = (total_ms * CFG_TICK_RATE_HZ) / 1000;
+    let total_ms = hours as u64 * 3_600_000
+        + minutes as u64 * 60_000
+        + seconds as u64 * 1_000
+        + milliseconds as u64;
+    if total_ms == 0 {
+        return Err(OsError::TimeZeroDly);
+    }
 
-    os_time_dly(ticks)
+    // Deadlines compare by signed difference, so stay below 2^31 ticks
+    let ticks = (total_ms * rate_hz as u64).div_ceil(1_000);
+    if ticks > i32::MAX as u64 {
+        return Err(OsError::TimeDlyTooLong);
+    }
+    Ok(ticks as OsTick)
 }
 
 /// Resume a delayed task before its delay expires
+///
+/// Allowed with the scheduler locked; the resumed task runs once it is
+/// unlocked.
 pub fn os_time_dly_resume(tcb: NonNull<OsTcb>) -> OsResult<()> {
     if !kernel::KERNEL.is_running() {
         return Err(OsError::OsNotRunning);
@@ -115,17 +207,14 @@ pub fn os_time_dly_resume(tcb: NonNull<OsTcb>) -> OsResult<()> {
             return Err(OsError::TaskNotDly);
         }
 
-        tcb_ref.tick_remain = 0;
+        unsafe { kernel::tick_remove(tcb) };
 
-        match tcb_ref.task_state {
-            OsTaskState::Delayed => {
-                tcb_ref.task_state = OsTaskState::Ready;
-                unsafe { sched::os_rdy_list_insert(tcb) };
-            }
-            OsTaskState::DelayedSuspended => {
-                tcb_ref.task_state = OsTaskState::Suspended;
-            }
-            _ => {}
+        // Taken from the absolute expiry before the delay ends
+        let remain = tcb_ref.ticks_left(kernel::KERNEL.tick_get());
+        let ready = tcb_ref.tick_expire();
+        tcb_ref.tick_remain = remain;
+        if ready {
+            unsafe { sched::os_rdy_list_insert(tcb) };
         }
 
         sched::os_sched();
@@ -140,6 +229,53 @@ pub fn os_time_get() -> OsTick {
     kernel::KERNEL.tick_get()
 }
 
+/// Run the tick interrupt once every `divider` ticks
+///
+/// Stretches the tick timer period to `divider` ticks at
+/// `OsConfig::tick_rate_hz` for low-power phases, without going tickless.
+/// Each interrupt then advances `os_time_get` by `divider`, so delays and
+/// timeouts keep their nominal length but fire at the first interrupt at
+/// or after their deadline, up to `divider - 1` ticks late. Round-robin
+/// quanta are charged once per interrupt, stretching slices by `divider`.
+///
+/// The period in progress keeps its length and is counted at the old
+/// divider; the new one applies from the next interrupt on. Setting the
+/// divider back to 1 therefore loses no ticks. With `no-systick` only the
+/// accounting changes and the application reprograms its timer.
+///
+/// # Returns
+/// * `Err(OsError::OsNotRunning)` - Called before `os_start`
+/// * `Err(OsError::TickReloadInvalid)` - `divider` is 0 or the period does
+///   not fit the tick timer
+pub fn os_tick_rate_set(divider: u32) -> OsResult<()> {
+    if !kernel::KERNEL.is_running() {
+        return Err(OsError::OsNotRunning);
+    }
+    if divider == 0 {
+        return Err(OsError::TickReloadInvalid);
+    }
+
+    critical_section(|_cs| {
+        #[cfg(not(feature = "no-systick"))]
+        let late = {
+            let cnts = ts::core_clk() / kernel::cfg().tick_rate_hz;
+            crate::port::os_cpu_systick_reload_set(cnts.checked_mul(divider).ok_or(OsError::TickReloadInvalid)?)?
+        };
+        #[cfg(feature = "no-systick")]
+        let late = false;
+        kernel::KERNEL.tick_step_set(divider, late);
+        Ok(())
+    })
+}
+
+/// Current tick divider, see [`os_tick_rate_set`]
+///
+/// The divider of the tick period in progress.
+#[inline]
+pub fn os_tick_rate_get() -> u32 {
+    kernel::KERNEL.tick_step()
+}
+
 /// Tick handler
 pub fn os_tick_handler() {
     if !kernel::KERNEL.is_running() {
@@ -147,63 +283,76 @@ pub fn os_tick_handler() {
     }
 
     kernel::KERNEL.int_enter();
+    crate::core::os_trace!(TickEnter);
 
-    let _tick = kernel::KERNEL.tick_increment();
+    let (tick, step) = kernel::KERNEL.tick_increment();
+    #[cfg(feature = "clock")]
+    clock::advance(step);
 
-    critical_section(|_cs| {
-        // Process delayed tasks
-        process_delayed_tasks();
-        // Round-robin time slicing
-        sched::os_sched_round_robin();
-    });
+    kernel::hooks::tick();
+    kernel::hooks::tick_hook();
+
+    #[cfg(feature = "budget")]
+    critical_section(|_cs| unsafe { crate::task::budget::budget_tick(tick) });
+
+    if cfg!(feature = "tick-task") && kernel::cfg().tick_task_en {
+        #[cfg(feature = "tick-task")]
+        tick_task::signal(step);
+    } else {
+        critical_section(|_cs| {
+            // Process delayed tasks
+            process_delayed_tasks(tick, step);
+            // Round-robin time slicing
+            sched::os_sched_round_robin();
+        });
+
+        #[cfg(feature = "embassy-time-driver")]
+        crate::core::embassy::on_tick();
+    }
 
+    crate::core::os_trace!(TickExit);
     kernel::os_int_exit();
 }
 
-/// Process delayed tasks in the current tick wheel slot
-fn process_delayed_tasks() {
-    let current_tick = kernel::KERNEL.tick_get();
-    let slot = (current_tick as usize) % CFG_TICK_WHEEL_SIZE;
-    
+/// Expire the tasks due by `tick`, `span` ticks after the last call
+///
+/// The tick queue is sorted by expiry, so only expired entries and the
+/// first pending one are visited.
+fn process_delayed_tasks(tick: OsTick, span: OsTick) {
     unsafe {
-        let mut current = kernel::tick_wheel_head(slot);
-        
-        while let Some(tcb_ptr) = current {
-            let tcb = &mut *tcb_ptr.as_ptr();
-            
-            let next = tcb.tick_next_ptr;
-            
-            // Check if task is due this rotation
-            if tcb.tick_remain <= CFG_TICK_WHEEL_SIZE as u32 {
-                kernel::tick_wheel_remove(tcb_ptr);
-                tcb.tick_remain = 0;
-                
-                match tcb.task_state {
-                    OsTaskState::Delayed => {
-                        tcb.task_state = OsTaskState::Ready;
-                        sched::os_rdy_list_insert(tcb_ptr);
-                    }
-                    OsTaskState::DelayedSuspended => {
-                        tcb.task_state = OsTaskState::Suspended;
-                    }
-                    OsTaskState::PendTimeout => {
-                        tcb.task_state = OsTaskState::Ready;
-                        tcb.pend_status = crate::types::OsPendStatus::Timeout;
-                        sched::os_rdy_list_insert(tcb_ptr);
-                    }
-                    _ => {}
-                }
-            } else {
-                tcb.tick_remain -= CFG_TICK_WHEEL_SIZE as u32;
+        kernel::tick_expire(tick, span, |tcb_ptr| {
+            if (*tcb_ptr.as_ptr()).is_pending() {
+                crate::sync::pend_expire(tcb_ptr);
+            } else if (*tcb_ptr.as_ptr()).tick_expire() {
+                sched::os_rdy_list_insert(tcb_ptr);
             }
-            
-            current = next;
-        }
+        });
     }
 }
 
 /// SysTick interrupt handler
+///
+/// Disabled by the `no-systick` feature so the application can drive
+/// `os_tick_handler` from another timer.
+#[cfg(not(feature = "no-systick"))]
 #[no_mangle]
 pub extern "C" fn SysTick() {
     os_tick_handler();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmsm_math_in_u64() {
+        // 999 h at 1 kHz no longer overflows, but exceeds 2^31 ticks
+        assert_eq!(hmsm_to_ticks(596, 0, 0, 0, opt::TIME_HMSM_STRICT, 1_000), Ok(2_145_600_000));
+        assert_eq!(hmsm_to_ticks(999, 0, 0, 0, opt::TIME_HMSM_STRICT, 1_000), Err(OsError::TimeDlyTooLong));
+        assert_eq!(hmsm_to_ticks(999, 0, 0, 0, opt::TIME_HMSM_STRICT, 100), Ok(359_640_000));
+
+        // Rounded up to whole ticks
+        assert_eq!(hmsm_to_ticks(0, 0, 0, 1, opt::TIME_HMSM_STRICT, 100), Ok(1));
+        assert_eq!(hmsm_to_ticks(0, 0, 1, 15, opt::TIME_HMSM_STRICT, 100), Ok(102));
+    }
+}
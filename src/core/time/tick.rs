@@ -0,0 +1,57 @@
+//! Wrap-safe tick arithmetic
+//!
+//! The tick counter wraps every 2^32 ticks (about 49.7 days at 1 kHz).
+//! Ticks are compared through their signed wrapping difference, which is
+//! correct as long as the two are less than 2^31 ticks apart. Use these
+//! helpers instead of `<`/`>` on raw `OsTick` values.
+
+use crate::types::OsTick;
+
+/// Signed distance from `b` to `a`
+#[inline(always)]
+pub const fn tick_diff(a: OsTick, b: OsTick) -> i32 {
+    a.wrapping_sub(b) as i32
+}
+
+/// Whether `a` is strictly later than `b`
+#[inline(always)]
+pub const fn tick_after(a: OsTick, b: OsTick) -> bool {
+    tick_diff(a, b) > 0
+}
+
+/// Whether `now` has reached `deadline`
+#[inline(always)]
+pub const fn tick_reached(now: OsTick, deadline: OsTick) -> bool {
+    tick_diff(now, deadline) >= 0
+}
+
+/// Ticks from `now` to `deadline`, 0 once it is reached
+#[inline(always)]
+pub const fn tick_until(now: OsTick, deadline: OsTick) -> OsTick {
+    if tick_reached(now, deadline) {
+        0
+    } else {
+        deadline.wrapping_sub(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_across_wrap() {
+        assert!(tick_reached(5, 5));
+        assert!(!tick_reached(4, 5));
+        assert!(tick_reached(2, u32::MAX - 1));
+        assert!(!tick_reached(u32::MAX - 1, 2));
+
+        assert!(tick_after(1, u32::MAX));
+        assert!(!tick_after(u32::MAX, u32::MAX));
+        assert_eq!(tick_diff(1, u32::MAX), 2);
+        assert_eq!(tick_diff(u32::MAX, 1), -2);
+
+        assert_eq!(tick_until(u32::MAX - 1, 3), 5);
+        assert_eq!(tick_until(3, u32::MAX - 1), 0);
+    }
+}
@@ -0,0 +1,131 @@
+//! Tick task
+//!
+//! With the `tick-task` feature the tick interrupt only counts the tick and
+//! signals this task through its task semaphore. The task, created by
+//! `os_init` at `OsConfig::tick_task_prio`, then walks the tick queue and
+//! charges round-robin quanta in task context, one critical section per
+//! step.
+//! Clearing `OsConfig::tick_task_en` skips the task and keeps the work in
+//! the interrupt.
+//!
+//! Ticks that arrive while the task is still busy are counted in the task
+//! semaphore and processed in order, so no expiry is lost.
+
+use core::ptr::NonNull;
+
+use crate::config::CFG_TICK_TASK_STK_SIZE;
+use crate::core::cs_cell::SyncUnsafeCell;
+use crate::critical::critical_section;
+use crate::error::OsResult;
+use crate::kernel;
+use crate::sched;
+use crate::task::{OsTcb, Stack};
+use crate::types::{OsPendOn, OsPendStatus, OsTick};
+
+/// Tick task TCB
+static TICK_TCB: SyncUnsafeCell<OsTcb> = SyncUnsafeCell::new(OsTcb::new());
+
+/// Tick task stack
+static TICK_STK: SyncUnsafeCell<Stack<CFG_TICK_TASK_STK_SIZE>> = SyncUnsafeCell::new(Stack::new());
+
+/// Last tick processed by the task
+static mut TICK_PROCESSED: OsTick = 0;
+
+/// Task running when the last tick arrived, charged for round-robin
+static mut TICK_PREEMPTED: Option<NonNull<OsTcb>> = None;
+
+#[inline(always)]
+fn tick_tcb() -> NonNull<OsTcb> {
+    unsafe { NonNull::new_unchecked(TICK_TCB.get()) }
+}
+
+/// Signal `step` ticks from the tick interrupt
+pub(crate) fn signal(step: OsTick) {
+    critical_section(|_cs| unsafe {
+        let tcb = tick_tcb();
+        let tcb_ref = &mut *tcb.as_ptr();
+
+        TICK_PREEMPTED = kernel::tcb_cur_ptr();
+        tcb_ref.sem_ctr = tcb_ref.sem_ctr.saturating_add(step);
+
+        if tcb_ref.pend_on() == OsPendOn::TaskSem {
+            tcb_ref.clear_pending(OsPendStatus::Ok);
+            sched::os_rdy_list_insert(tcb);
+        }
+    });
+}
+
+/// Take one pending tick, returning the tick to process
+fn take() -> Option<(OsTick, Option<NonNull<OsTcb>>)> {
+    critical_section(|_cs| unsafe {
+        let tcb_ref = &mut *tick_tcb().as_ptr();
+        if tcb_ref.sem_ctr == 0 {
+            return None;
+        }
+
+        tcb_ref.sem_ctr -= 1;
+        TICK_PROCESSED = TICK_PROCESSED.wrapping_add(1);
+        Some((TICK_PROCESSED, TICK_PREEMPTED))
+    })
+}
+
+/// Block until the next tick signal
+fn wait() {
+    critical_section(|_cs| unsafe {
+        let tcb = tick_tcb();
+        let tcb_ref = &mut *tcb.as_ptr();
+        if tcb_ref.sem_ctr == 0 {
+            sched::os_rdy_list_remove(tcb);
+            tcb_ref.set_pending(core::ptr::null(), OsPendOn::TaskSem, 0);
+        }
+    });
+
+    sched::os_sched();
+}
+
+/// Tick task
+fn os_tick_task(_: *mut ()) -> ! {
+    loop {
+        wait();
+
+        while let Some((tick, preempted)) = take() {
+            critical_section(|_cs| super::process_delayed_tasks(tick, 1));
+
+            #[cfg(feature = "embassy-time-driver")]
+            crate::core::embassy::on_tick();
+
+            if kernel::KERNEL.sched_lock_nesting() > 0 {
+                continue;
+            }
+
+            critical_section(|_cs| unsafe {
+                // Charge the interrupted task if it is still ready
+                if let Some(tcb) = preempted.filter(|&t| t != tick_tcb() && t.as_ref().is_ready()) {
+                    let _ = sched::os_sched_round_robin_charge(tcb);
+                }
+            });
+        }
+    }
+}
+
+/// Create the tick task
+///
+/// Called by `os_init`.
+pub(crate) unsafe fn os_tick_task_init() -> OsResult<()> {
+    unsafe {
+        TICK_PROCESSED = 0;
+        TICK_PREEMPTED = None;
+
+        crate::task::os_task_create_internal(
+            TICK_TCB.get(),
+            "Tick",
+            os_tick_task,
+            core::ptr::null_mut(),
+            crate::kernel::cfg().tick_task_prio,
+            TICK_STK.get().cast(),
+            CFG_TICK_TASK_STK_SIZE,
+            0,
+            0,
+        )
+    }
+}
@@ -0,0 +1,88 @@
+//! High-resolution timestamps
+//!
+//! A free-running 32-bit counter for measuring short intervals, far below
+//! the tick. The source depends on the port:
+//!
+//! - Cortex-M3/M4/M7/M33: the DWT cycle counter, at the core clock
+//! - Cortex-M0/M0+: a timer the application registers with
+//!   `os_ts_source_set`, at the frequency given there
+//! - RISC-V: `mcycle`, at the core clock
+//! - Host stub: nanoseconds since first use with the `std` feature,
+//!   otherwise a counter that advances by one per read
+//!
+//! `os_start` enables the counter; call [`os_ts_init`] to use it earlier.
+//! The core clock is the one given to `os_start_with_clock`, or
+//! `OsConfig::cpu_clk_hz` before the kernel starts.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::kernel;
+
+/// Core clock passed to `os_start_with_clock`, 0 before it runs
+static CORE_CLK_HZ: AtomicU32 = AtomicU32::new(0);
+
+/// Record the core clock the kernel was started with
+pub(crate) fn core_clk_set(hz: u32) {
+    CORE_CLK_HZ.store(hz, Ordering::Relaxed);
+}
+
+/// Core clock passed to `os_start_with_clock`, 0 before it runs
+pub(crate) fn core_clk() -> u32 {
+    CORE_CLK_HZ.load(Ordering::Relaxed)
+}
+
+/// Start the timestamp counter
+pub fn os_ts_init() {
+    crate::port::os_cpu_ts_init();
+}
+
+/// Read the timestamp counter
+#[inline(always)]
+pub fn os_ts_get() -> u32 {
+    crate::port::os_cpu_ts_get()
+}
+
+/// Counts from `start` to `end`, correct across one counter wrap
+#[inline(always)]
+pub const fn os_ts_diff(start: u32, end: u32) -> u32 {
+    end.wrapping_sub(start)
+}
+
+/// Counter frequency in Hz
+pub fn os_ts_freq_hz() -> u32 {
+    crate::port::os_cpu_ts_freq_hz().unwrap_or_else(|| match CORE_CLK_HZ.load(Ordering::Relaxed) {
+        0 => kernel::cfg().cpu_clk_hz,
+        hz => hz,
+    })
+}
+
+/// Nanoseconds for `delta` counts, 0 if the frequency is unknown
+pub fn os_ts_to_ns(delta: u32) -> u64 {
+    ts_to_ns(delta, os_ts_freq_hz())
+}
+
+fn ts_to_ns(delta: u32, freq_hz: u32) -> u64 {
+    if freq_hz == 0 {
+        return 0;
+    }
+    delta as u64 * 1_000_000_000 / freq_hz as u64
+}
+
+#[cfg(armv6m)]
+pub use crate::port::os_cpu_ts_source_set as os_ts_source_set;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_and_ns() {
+        assert_eq!(os_ts_diff(10, 25), 15);
+        assert_eq!(os_ts_diff(u32::MAX - 4, 5), 10);
+
+        assert_eq!(ts_to_ns(16, 16_000_000), 1_000);
+        assert_eq!(ts_to_ns(u32::MAX, 1_000_000_000), u32::MAX as u64);
+        assert_eq!(ts_to_ns(480, 480_000_000), 1_000);
+        assert_eq!(ts_to_ns(5, 0), 0);
+    }
+}
@@ -29,6 +29,44 @@ pub type OsStkElement = u32;
 /// Event flags type
 pub type OsFlags = u32;
 
+/// Task ID type
+pub type OsTaskId = u16;
+
+/// Name stored in a TCB, kernel object or registry entry
+///
+/// Zero-sized with the `no-names` feature; create calls still take the
+/// string and drop it. Build one with [`obj_name`], read it with
+/// [`obj_name_str`].
+#[cfg(not(feature = "no-names"))]
+pub type ObjName = &'static str;
+
+/// Name stored in a TCB, kernel object or registry entry
+#[cfg(feature = "no-names")]
+pub type ObjName = ();
+
+/// Name to store for `name`
+#[inline(always)]
+pub const fn obj_name(name: &'static str) -> ObjName {
+    #[cfg(not(feature = "no-names"))]
+    return name;
+    #[cfg(feature = "no-names")]
+    {
+        let _ = name;
+    }
+}
+
+/// Stored name as a string, `""` with `no-names`
+#[inline(always)]
+pub const fn obj_name_str(name: ObjName) -> &'static str {
+    #[cfg(not(feature = "no-names"))]
+    return name;
+    #[cfg(feature = "no-names")]
+    {
+        let () = name;
+        ""
+    }
+}
+
 /// Task state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -49,6 +87,8 @@ pub enum OsTaskState {
     PendSuspended = 6,
     /// Task is pending with timeout and suspended
     PendTimeoutSuspended = 7,
+    /// Task was removed after a fault (`fault-recovery` feature)
+    Faulted = 8,
 }
 
 /// What the task is pending on
@@ -63,6 +103,9 @@ pub enum OsPendOn {
     TaskSem = 5,
     TaskQueue = 6,
     Cond = 7,
+    RwLockRead = 8,
+    RwLockWrite = 9,
+    Barrier = 10,
 }
 
 /// Pend status
@@ -77,6 +120,8 @@ pub enum OsPendStatus {
     Del = 2,
     /// Timeout occurred
     Timeout = 3,
+    /// Task deletion was requested while pending
+    DelReq = 4,
 }
 
 /// Kernel object type marker
@@ -84,15 +129,40 @@ pub enum OsPendStatus {
 #[repr(u32)]
 pub enum OsObjType {
     None = 0,
+    Barrier = 0x42415252, // 'BARR'
     Flag = 0x464C4147,    // 'FLAG'
     Mem = 0x4D454D20,     // 'MEM '
     Mutex = 0x4D555458,   // 'MUTX'
     Queue = 0x51554555,   // 'QUEU'
+    RwLock = 0x52574C4B,  // 'RWLK'
     Sem = 0x53454D41,     // 'SEMA'
     Task = 0x5441534B,    // 'TASK'
+    TaskGroup = 0x54475250, // 'TGRP'
     Timer = 0x544D5220,   // 'TMR '
 }
 
+/// Pend timeout for the safe wrappers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    /// Block until the object is available
+    Forever,
+    /// Block for at most this many ticks; `Ticks(0)` does not block
+    Ticks(OsTick),
+    /// Fail with `OsError::PendWouldBlock` instead of blocking
+    NoWait,
+}
+
+impl Timeout {
+    /// Raw `(timeout, opt)` pair for the `pend` APIs
+    pub const fn to_raw(self) -> (OsTick, OsOpt) {
+        match self {
+            Timeout::Forever => (0, opt::PEND_BLOCKING),
+            Timeout::Ticks(0) | Timeout::NoWait => (0, opt::PEND_NON_BLOCKING),
+            Timeout::Ticks(ticks) => (ticks, opt::PEND_BLOCKING),
+        }
+    }
+}
+
 // ============ Option flags ============
 
 /// Delete options
@@ -108,18 +178,33 @@ pub mod opt {
     // Pend options
     pub const PEND_BLOCKING: OsOpt = 0x0000;
     pub const PEND_NON_BLOCKING: OsOpt = 0x8000;
+    /// Take a semaphore's whole count instead of one
+    pub const PEND_CONSUME_ALL: OsOpt = 0x0100;
     
     // Post options
     pub const POST_FIFO: OsOpt = 0x0000;
     pub const POST_LIFO: OsOpt = 0x0010;
     pub const POST_ALL: OsOpt = 0x0200;
+    /// Ready the woken task ahead of the others waiting at its priority
+    pub const POST_FRONT: OsOpt = 0x0400;
     pub const POST_NO_SCHED: OsOpt = 0x8000;
+
+    // Time options
+    pub const TIME_HMSM_STRICT: OsOpt = 0x0000;
+    pub const TIME_HMSM_NON_STRICT: OsOpt = 0x0010;
     
     // Task options
     pub const TASK_NONE: OsOpt = 0x0000;
     pub const TASK_STK_CHK: OsOpt = 0x0001;
     pub const TASK_STK_CLR: OsOpt = 0x0002;
     pub const TASK_SAVE_FP: OsOpt = 0x0004;
+    /// A fault in this task is fatal even with `fault-recovery`
+    pub const TASK_CRITICAL: OsOpt = 0x0008;
+    /// Blocking calls fail with `TaskDelRequested` once
+    /// `os_task_del_request` was called for the task
+    pub const TASK_DEL_INTERRUPT: OsOpt = 0x0010;
+    /// Set by the kernel on its own tasks
+    pub const TASK_KERNEL: OsOpt = 0x0080;
     
     // Flag options
     pub const FLAG_CLR_ALL: OsOpt = 0x0001;
@@ -127,4 +212,51 @@ pub mod opt {
     pub const FLAG_SET_ALL: OsOpt = 0x0004;
     pub const FLAG_SET_ANY: OsOpt = 0x0008;
     pub const FLAG_CONSUME: OsOpt = 0x0100;
+
+    // Legal bits per call
+    pub const PEND_MASK: OsOpt = PEND_BLOCKING | PEND_NON_BLOCKING;
+    pub const SEM_PEND_MASK: OsOpt = PEND_MASK | PEND_CONSUME_ALL;
+    pub const POST_MASK: OsOpt = POST_FIFO | POST_LIFO | POST_ALL | POST_FRONT | POST_NO_SCHED;
+    /// `TASK_KERNEL` is kernel-only
+    pub const TASK_MASK: OsOpt = TASK_STK_CHK | TASK_STK_CLR | TASK_SAVE_FP | TASK_CRITICAL | TASK_DEL_INTERRUPT;
+
+    /// `Err(OsError::OptInvalid)` if `opt` has a bit outside `legal`
+    #[inline(always)]
+    pub const fn check(opt: OsOpt, legal: OsOpt) -> crate::error::OsResult<()> {
+        if opt & !legal != 0 {
+            return Err(crate::error::OsError::OptInvalid);
+        }
+        Ok(())
+    }
+}
+
+/// Post options for the safe wrappers
+///
+/// Combine with `|`, e.g. `PostOpt::LIFO | PostOpt::NO_SCHED`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PostOpt(OsOpt);
+
+impl PostOpt {
+    /// Wake the oldest waiter of the highest priority
+    pub const FIFO: PostOpt = PostOpt(opt::POST_FIFO);
+    /// Wake the newest waiter of the highest priority
+    pub const LIFO: PostOpt = PostOpt(opt::POST_LIFO);
+    /// Ready the woken task ahead of its waiting peers
+    pub const FRONT: PostOpt = PostOpt(opt::POST_FRONT);
+    /// Do not reschedule after the post
+    pub const NO_SCHED: PostOpt = PostOpt(opt::POST_NO_SCHED);
+
+    /// Raw `POST_*` bits
+    #[inline]
+    pub const fn bits(self) -> OsOpt {
+        self.0
+    }
+}
+
+impl core::ops::BitOr for PostOpt {
+    type Output = PostOpt;
+
+    fn bitor(self, rhs: PostOpt) -> PostOpt {
+        PostOpt(self.0 | rhs.0)
+    }
 }
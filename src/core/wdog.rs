@@ -0,0 +1,239 @@
+//! Software watchdog supervision
+//!
+//! Tasks register with [`register`] and must call [`checkin`] at least once
+//! per registered period. A kernel-owned supervisor task, created by
+//! `os_init` when the `wdog` feature is enabled, scans the registrations
+//! every `CFG_WDOG_SCAN_TICKS` and reports every task that missed its
+//! deadline to the miss callback.
+//!
+//! The supervisor also drives the hardware watchdog through the feed
+//! callback: it is called after each scan unless a task is overdue and the
+//! miss callback asked to stop feeding, which lets the hardware watchdog
+//! reset the MCU.
+
+use core::ptr::NonNull;
+
+use crate::config::{CFG_WDOG_IGNORE_SUSPENDED, CFG_WDOG_SCAN_TICKS, CFG_WDOG_TASK_STK_SIZE, CFG_WDOG_TBL_SIZE};
+use crate::core::cs_cell::{CsCell, SyncUnsafeCell};
+use crate::critical::{critical_section, CriticalSection};
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::task::{os_task_ext_get, os_task_ext_set, OsTcb, Stack};
+use crate::types::OsTick;
+
+/// Miss callback
+///
+/// Receives the overdue task. Return `true` to keep feeding the hardware
+/// watchdog anyway, `false` to let it expire.
+pub type WdogMissFn = fn(NonNull<OsTcb>) -> bool;
+
+/// Hardware watchdog feed callback
+pub type WdogFeedFn = fn();
+
+#[derive(Clone, Copy)]
+struct WdogEntry {
+    tcb: Option<NonNull<OsTcb>>,
+    period: OsTick,
+    last_checkin: OsTick,
+    /// Overdue and already reported
+    missed: bool,
+    /// Last verdict of the miss callback while overdue
+    feed: bool,
+}
+
+impl WdogEntry {
+    const EMPTY: Self = Self {
+        tcb: None,
+        period: 0,
+        last_checkin: 0,
+        missed: false,
+        feed: true,
+    };
+}
+
+struct WdogState {
+    entries: [WdogEntry; CFG_WDOG_TBL_SIZE],
+    on_miss: Option<WdogMissFn>,
+    feed: Option<WdogFeedFn>,
+}
+
+// The supervisor reaches the table through its task extension
+crate::task_ext!(CsCell<WdogState>);
+
+static WDOG: CsCell<WdogState> = CsCell::new(WdogState {
+    entries: [WdogEntry::EMPTY; CFG_WDOG_TBL_SIZE],
+    on_miss: None,
+    feed: None,
+});
+
+/// Supervisor task TCB
+static WDOG_TCB: SyncUnsafeCell<OsTcb> = SyncUnsafeCell::new(OsTcb::new());
+
+/// Supervisor task stack
+static WDOG_STK: SyncUnsafeCell<Stack<CFG_WDOG_TASK_STK_SIZE>> = SyncUnsafeCell::new(Stack::new());
+
+/// Set the miss and hardware feed callbacks
+pub fn set_callbacks(on_miss: Option<WdogMissFn>, feed: Option<WdogFeedFn>) {
+    critical_section(|cs| {
+        let wdog = WDOG.get(cs);
+        wdog.on_miss = on_miss;
+        wdog.feed = feed;
+    });
+}
+
+/// Register a task for supervision
+///
+/// Registering an already supervised task updates its period.
+///
+/// # Returns
+/// * `Err(OsError::TmrInvalidPeriod)` - `period_ticks` is zero
+/// * `Err(OsError::WdogFull)` - No free slot in the table
+pub fn register(tcb: NonNull<OsTcb>, period_ticks: OsTick) -> OsResult<()> {
+    if period_ticks == 0 {
+        return Err(OsError::TmrInvalidPeriod);
+    }
+
+    critical_section(|cs| {
+        let wdog = WDOG.get(cs);
+        let now = kernel::KERNEL.tick_get();
+
+        let slot = match wdog.entries.iter().position(|e| e.tcb == Some(tcb)) {
+            Some(i) => i,
+            None => wdog.entries.iter().position(|e| e.tcb.is_none()).ok_or(OsError::WdogFull)?,
+        };
+
+        wdog.entries[slot] = WdogEntry {
+            tcb: Some(tcb),
+            period: period_ticks,
+            last_checkin: now,
+            missed: false,
+            feed: true,
+        };
+        Ok(())
+    })
+}
+
+/// Stop supervising a task
+///
+/// Called automatically by `os_task_del`.
+pub fn deregister(tcb: NonNull<OsTcb>) {
+    critical_section(|cs| deregister_cs(cs, tcb));
+}
+
+/// Stop supervising a task, inside an existing critical section
+pub(crate) fn deregister_cs(cs: &CriticalSection, tcb: NonNull<OsTcb>) {
+    for e in WDOG.get(cs).entries.iter_mut().filter(|e| e.tcb == Some(tcb)) {
+        *e = WdogEntry::EMPTY;
+    }
+}
+
+/// Check in for the current task
+///
+/// # Returns
+/// * `Err(OsError::WdogNotRegistered)` - The current task is not supervised
+pub fn checkin() -> OsResult<()> {
+    let cur = unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?;
+    checkin_task(cur)
+}
+
+/// Check in on behalf of a task
+///
+/// May be called from ISR context, e.g. when a task's liveness is proven by
+/// a completed DMA transfer.
+pub fn checkin_task(tcb: NonNull<OsTcb>) -> OsResult<()> {
+    critical_section(|cs| {
+        let now = kernel::KERNEL.tick_get();
+        let entry = WDOG
+            .get(cs)
+            .entries
+            .iter_mut()
+            .find(|e| e.tcb == Some(tcb))
+            .ok_or(OsError::WdogNotRegistered)?;
+
+        entry.last_checkin = now;
+        entry.missed = false;
+        entry.feed = true;
+        Ok(())
+    })
+}
+
+/// Scan the table once, report overdue tasks and feed the hardware watchdog
+fn scan(state: &CsCell<WdogState>) {
+    let now = kernel::KERNEL.tick_get();
+
+    for i in 0..CFG_WDOG_TBL_SIZE {
+        // Find a newly overdue task under the critical section, report it
+        // outside so the callback may log or take its time.
+        let (overdue, on_miss) = critical_section(|cs| {
+            let wdog = state.get(cs);
+            let entry = &mut wdog.entries[i];
+            let Some(tcb) = entry.tcb else {
+                return (None, None);
+            };
+
+            if CFG_WDOG_IGNORE_SUSPENDED && unsafe { tcb.as_ref() }.is_suspended() {
+                entry.last_checkin = now;
+                return (None, None);
+            }
+
+            if entry.missed || now.wrapping_sub(entry.last_checkin) <= entry.period {
+                return (None, None);
+            }
+
+            entry.missed = true;
+            (Some(tcb), wdog.on_miss)
+        });
+
+        if let Some(tcb) = overdue {
+            let feed = on_miss.is_some_and(|f| f(tcb));
+            critical_section(|cs| state.get(cs).entries[i].feed = feed);
+        }
+    }
+
+    let feed = critical_section(|cs| {
+        let wdog = state.get(cs);
+        if wdog.entries.iter().all(|e| !e.missed || e.feed) {
+            wdog.feed
+        } else {
+            None
+        }
+    });
+
+    if let Some(feed) = feed {
+        feed();
+    }
+}
+
+/// Supervisor task
+fn os_wdog_task(_: *mut ()) -> ! {
+    let state = os_task_ext_get::<CsCell<WdogState>>(None).expect("wdog state not set");
+    loop {
+        let _ = crate::time::os_time_dly(CFG_WDOG_SCAN_TICKS);
+        scan(state);
+    }
+}
+
+/// Reset the table and create the supervisor task
+///
+/// Called by `os_init`.
+pub(crate) unsafe fn os_wdog_init() -> OsResult<()> {
+    unsafe {
+        let wdog = WDOG.get_unchecked();
+        wdog.entries = [WdogEntry::EMPTY; CFG_WDOG_TBL_SIZE];
+        wdog.on_miss = None;
+        wdog.feed = None;
+
+        crate::task::os_task_create_internal(
+            WDOG_TCB.get(),
+            "Wdog",
+            os_wdog_task,
+            core::ptr::null_mut(),
+            crate::kernel::cfg().wdog_task_prio,
+            WDOG_STK.get().cast(),
+            CFG_WDOG_TASK_STK_SIZE,
+            0,
+            0,
+        )?;
+        os_task_ext_set(NonNull::new(WDOG_TCB.get()), &WDOG)
+    }
+}
@@ -1,33 +1,79 @@
 //! Language items and default exception handlers
+//!
+//! The panic and HardFault handlers fill the kernel crash record
+//! (`kernel::os_last_crash`) and log it over defmt before trapping.
 
-// When defmt feature is enabled on ARM targets, use defmt_rtt and panic_probe
+#[cfg(any(target_arch = "arm", target_arch = "riscv32"))]
+use crate::core::kernel::crash::{self, CrashKind};
+
+// When defmt feature is enabled on ARM targets, log over RTT
 #[cfg(all(feature = "defmt", target_arch = "arm"))]
 use defmt_rtt as _;
 
-#[cfg(all(feature = "defmt", target_arch = "arm"))]
-use panic_probe as _;
+/// Current stack pointer
+#[cfg(any(target_arch = "arm", target_arch = "riscv32"))]
+#[inline(always)]
+fn sp() -> u32 {
+    let sp: u32;
+    #[cfg(target_arch = "arm")]
+    unsafe { core::arch::asm!("mov {}, sp", out(reg) sp, options(nomem, nostack, preserves_flags)) };
+    #[cfg(target_arch = "riscv32")]
+    unsafe { core::arch::asm!("mv {}, sp", out(reg) sp, options(nomem, nostack, preserves_flags)) };
+    sp
+}
 
-// Defmt panic handler
+// Defmt panic handler (`defmt::panic!` and friends)
 #[cfg(all(feature = "defmt", target_arch = "arm"))]
 #[defmt::panic_handler]
 fn defmt_panic() -> ! {
+    cortex_m::interrupt::disable();
+    crash::report(crash::record(CrashKind::Panic, sp(), None, None));
     cortex_m::asm::udf()
 }
 
-// Panic handler when defmt is disabled
-#[cfg(all(not(feature = "defmt"), target_arch = "arm"))]
+// Panic handler on ARM
+#[cfg(target_arch = "arm")]
 #[panic_handler]
-fn panic(_: &core::panic::PanicInfo) -> ! {
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    let rec = crash::record(CrashKind::Panic, sp(), info.location(), None);
+
+    #[cfg(feature = "defmt")]
+    defmt::error!("{}", defmt::Display2Format(info));
+    crash::report(rec);
+
     loop { cortex_m::asm::udf(); }
 }
 
+// Panic handler on RISC-V
+#[cfg(target_arch = "riscv32")]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::arch::asm!("csrci mstatus, 8") };
+    crash::record(CrashKind::Panic, sp(), info.location(), None);
+    loop { unsafe { core::arch::asm!("ebreak") }; }
+}
+
 // Default HardFault handler
 #[cfg(target_arch = "arm")]
 #[cortex_m_rt::exception]
-unsafe fn HardFault(_ef: &cortex_m_rt::ExceptionFrame) -> ! {
+unsafe fn HardFault(ef: &cortex_m_rt::ExceptionFrame) -> ! {
+    // Returns only if the faulting task cannot be removed on its own
+    #[cfg(feature = "fault-recovery")]
+    unsafe { crate::port::os_cpu_fault_recover(ef as *const _ as *mut _) };
+
+    let frame = [ef.r0(), ef.r1(), ef.r2(), ef.r3(), ef.r12(), ef.lr(), ef.pc(), ef.xpsr()];
+    // The stacked frame sits at the faulting stack pointer
+    let rec = crash::record(CrashKind::HardFault, ef as *const _ as u32, None, Some(&frame));
+    crash::report(rec);
+
     loop { cortex_m::asm::udf(); }
 }
 
-// Defmt timestamp
+// Defmt timestamp, attributed to the running task
 #[cfg(all(feature = "defmt", target_arch = "arm"))]
-defmt::timestamp!("{=u32}", crate::core::kernel::KERNEL.tick_get());
+defmt::timestamp!(
+    "{=u32} {=str}",
+    crate::core::kernel::KERNEL.tick_get(),
+    crate::core::kernel::current_task_name()
+);
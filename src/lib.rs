@@ -4,11 +4,14 @@
 //! - Priority-based preemptive scheduling
 //! - Synchronization primitives (semaphores, mutexes)
 //! - Time management with tick-based delays
-//! - Context switching for ARM Cortex-M
+//! - Context switching for ARM Cortex-M and RISC-V (RV32)
 
 #![no_std]
 #![deny(unsafe_op_in_unsafe_fn)]
 
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+
 // ============ Critical Section ============
 
 #[cfg(target_arch = "arm")]
@@ -35,6 +38,57 @@ mod cs_impl {
     }
 }
 
+#[cfg(target_arch = "riscv32")]
+mod cs_impl {
+    use critical_section::{set_impl, Impl, RawRestoreState};
+
+    struct SingleHartCriticalSection;
+    set_impl!(SingleHartCriticalSection);
+
+    unsafe impl Impl for SingleHartCriticalSection {
+        unsafe fn acquire() -> RawRestoreState {
+            let mstatus: usize;
+            unsafe { core::arch::asm!("csrrci {0}, mstatus, 8", out(reg) mstatus) };
+            mstatus & 8 != 0
+        }
+
+        unsafe fn release(was_active: RawRestoreState) {
+            if was_active {
+                unsafe { core::arch::asm!("csrsi mstatus, 8") };
+            }
+        }
+    }
+}
+
+#[cfg(all(not(any(target_arch = "arm", target_arch = "riscv32")), not(feature = "std")))]
+mod cs_impl {
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use critical_section::{set_impl, Impl, RawRestoreState};
+
+    /// Host stand-in for the interrupt mask
+    static MASKED: AtomicBool = AtomicBool::new(false);
+
+    /// Host provider modelling a single core
+    ///
+    /// Nests like PRIMASK but does not exclude other host threads; enable
+    /// the `std` feature for the `critical-section` crate's mutex-based
+    /// provider when tests share kernel state across threads.
+    struct HostCriticalSection;
+    set_impl!(HostCriticalSection);
+
+    unsafe impl Impl for HostCriticalSection {
+        unsafe fn acquire() -> RawRestoreState {
+            MASKED.swap(true, Ordering::Acquire)
+        }
+
+        unsafe fn release(was_masked: RawRestoreState) {
+            if !was_masked {
+                MASKED.store(false, Ordering::Release);
+            }
+        }
+    }
+}
+
 // ============ Modules ============
 
 pub mod log;
@@ -52,19 +106,40 @@ pub use core::critical;
 pub use core::error;
 pub use core::error::OsError;
 pub use core::kernel;
-pub use core::kernel::{os_init, os_start};
+pub use core::kernel::{
+    os_init, os_init_with, os_int_enter, os_int_exit, os_run, os_start, os_start_with, os_start_with_clock, OsConfig,
+};
+pub use core::kernel::hooks::{os_app_hooks_set, OsAppHooks};
 pub use core::prio;
 pub use core::types;
 pub use core::types::*;
 pub use core::task;
-pub use core::task::os_task_create;
+pub use core::task::{os_task_create, os_task_create_stk};
 pub use core::sched;
 pub use core::time;
+#[cfg(feature = "os3-compat")]
+pub use core::compat;
+#[cfg(feature = "embassy-time-driver")]
+pub use core::embassy;
+#[cfg(feature = "self-check")]
+pub use core::selfcheck;
+#[cfg(feature = "shell")]
+pub use core::shell;
+#[cfg(feature = "trace")]
+pub use core::trace;
+#[cfg(feature = "wdog")]
+pub use core::wdog;
 
 #[cfg(feature = "sem")]
 pub use sync::sem;
 #[cfg(feature = "mutex")]
 pub use sync::mutex;
+#[cfg(feature = "rwlock")]
+pub use sync::rwlock;
+#[cfg(feature = "barrier")]
+pub use sync::barrier;
+#[cfg(feature = "ring")]
+pub use sync::ring;
 
 #[cfg(feature = "pac")]
 pub use stm32_metapac as pac;
@@ -1,55 +1,430 @@
 //! Logging macros for uCOS-III
 //!
 //! Provides logging macros that work with or without the debug feature.
+//!
+//! With `defmt`, a message is written to the log as it is made, which
+//! inside a kernel critical section keeps interrupts masked for the whole
+//! RTT copy (or indefinitely in blocking RTT mode with no host attached).
+//! [`set_policy`] chooses what happens to such messages: dropped, emitted
+//! in place, or (the default) buffered in a small ring and written once
+//! the idle task or a logger task calls [`flush`], outside the mask.
+//!
+//! A buffered message keeps its arguments until then, which only the
+//! kernel's own messages can promise: their arguments are owned values
+//! and `'static` names. The logging macros take any arguments, so inside
+//! a critical section their messages are written in place under
+//! `Buffer` and discarded under `Drop`; outside one they are always
+//! written at once. The kernel's PendSV and tick paths always apply the
+//! policy, even where the mask is the exception's rather than a
+//! `CriticalSection`; crash reports are always emitted in place.
+
+use core::mem::{align_of, size_of, MaybeUninit};
+
+use crate::config::{CFG_LOG_DEFER_ARG_BYTES, CFG_LOG_DEFER_SLOTS};
+use crate::core::cs_cell::CsCell;
+use crate::critical::{critical_section, CriticalSection};
+
+/// What a message made inside a critical section does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InCritical {
+    /// Discarded and counted in [`dropped`]
+    Drop,
+    /// Kept in the ring until [`flush`]; messages from the logging macros,
+    /// whose arguments may borrow, are written at once
+    Buffer,
+    /// Written at once, interrupts still masked
+    Emit,
+}
+
+/// Logging policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogPolicy {
+    /// Messages made inside a critical section
+    pub in_critical: InCritical,
+    /// The idle task flushes the ring on every pass; turn off when a
+    /// logger task calls [`flush`] instead
+    pub flush_in_idle: bool,
+}
+
+impl LogPolicy {
+    /// Buffer in critical sections, flush from idle
+    pub const fn new() -> Self {
+        Self { in_critical: InCritical::Buffer, flush_in_idle: true }
+    }
+}
+
+impl Default for LogPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Arguments and entry function of a deferred message
+#[derive(Clone, Copy)]
+struct Slot {
+    /// Reads the payload back and writes the message
+    replay: unsafe fn(*const u64),
+    payload: MaybeUninit<[u64; CFG_LOG_DEFER_ARG_BYTES / 8]>,
+}
+
+impl Slot {
+    const EMPTY: Self = Self { replay: replay_none, payload: MaybeUninit::uninit() };
+}
+
+struct LogState {
+    policy: LogPolicy,
+    slots: [Slot; CFG_LOG_DEFER_SLOTS],
+    head: usize,
+    len: usize,
+    dropped: u32,
+}
+
+static LOG: CsCell<LogState> = CsCell::new(LogState {
+    policy: LogPolicy::new(),
+    slots: [Slot::EMPTY; CFG_LOG_DEFER_SLOTS],
+    head: 0,
+    len: 0,
+    dropped: 0,
+});
+
+const _: () = assert!(CFG_LOG_DEFER_ARG_BYTES.is_multiple_of(8), "CFG_LOG_DEFER_ARG_BYTES must be a multiple of 8");
+
+/// Set the logging policy
+///
+/// Kept across `os_init`, so it may be set before the kernel exists.
+pub fn set_policy(policy: LogPolicy) {
+    critical_section(|cs| LOG.get(cs).policy = policy);
+}
+
+/// Current logging policy
+pub fn policy() -> LogPolicy {
+    critical_section(|cs| LOG.get(cs).policy)
+}
+
+/// Write out the buffered messages, oldest first
+///
+/// Each message is written outside the critical section it is taken out
+/// under. Returns how many were written.
+pub fn flush() -> usize {
+    let mut n = 0;
+    loop {
+        let slot = critical_section(|cs| {
+            let log = LOG.get(cs);
+            if log.len == 0 {
+                return None;
+            }
+            let slot = log.slots[log.head];
+            log.head = (log.head + 1) % CFG_LOG_DEFER_SLOTS;
+            log.len -= 1;
+            Some(slot)
+        });
+        let Some(slot) = slot else { return n };
+        unsafe { (slot.replay)(slot.payload.as_ptr().cast()) };
+        n += 1;
+    }
+}
+
+/// Messages waiting for [`flush`]
+pub fn pending() -> usize {
+    critical_section(|cs| LOG.get(cs).len)
+}
+
+/// Messages dropped by the policy, for a full ring or for arguments above
+/// `CFG_LOG_DEFER_ARG_BYTES`
+pub fn dropped() -> u32 {
+    critical_section(|cs| LOG.get(cs).dropped)
+}
+
+/// Flush from the idle task if the policy says so
+#[cfg(feature = "defmt")]
+pub(crate) fn idle_flush() {
+    if policy().flush_in_idle {
+        flush();
+    }
+}
+
+/// Whether a message from the logging macros is written now
+///
+/// Its arguments may borrow, so it is never buffered: inside a critical
+/// section it is discarded under `Drop` and written otherwise.
+#[doc(hidden)]
+#[inline]
+pub fn emit_now() -> bool {
+    if !CriticalSection::is_active() {
+        return true;
+    }
+    match policy().in_critical {
+        InCritical::Drop => {
+            critical_section(|cs| LOG.get(cs).dropped += 1);
+            false
+        }
+        InCritical::Emit | InCritical::Buffer => true,
+    }
+}
+
+/// Write `args` with `emit`, now or later as the policy says
+#[doc(hidden)]
+#[inline]
+pub fn dispatch<T: 'static>(args: T, emit: fn(T)) {
+    if CriticalSection::is_active() {
+        dispatch_critical(args, emit);
+    } else {
+        emit(args);
+    }
+}
+
+/// [`dispatch`] for a caller known to run with interrupts masked
+#[doc(hidden)]
+pub fn dispatch_critical<T: 'static>(args: T, emit: fn(T)) {
+    match policy().in_critical {
+        InCritical::Emit => emit(args),
+        InCritical::Drop => critical_section(|cs| LOG.get(cs).dropped += 1),
+        InCritical::Buffer => defer(args, emit),
+    }
+}
+
+fn defer<T: 'static>(args: T, emit: fn(T)) {
+    let fits = size_of::<(T, fn(T))>() <= CFG_LOG_DEFER_ARG_BYTES && align_of::<(T, fn(T))>() <= align_of::<u64>();
+    critical_section(|cs| {
+        let log = LOG.get(cs);
+        if !fits || log.len == CFG_LOG_DEFER_SLOTS {
+            log.dropped += 1;
+            return;
+        }
+        let slot = &mut log.slots[(log.head + log.len) % CFG_LOG_DEFER_SLOTS];
+        unsafe { slot.payload.as_mut_ptr().cast::<(T, fn(T))>().write((args, emit)) };
+        slot.replay = replay::<T>;
+        log.len += 1;
+    });
+}
+
+/// # Safety
+/// `payload` holds a `(T, fn(T))` written by [`defer`], read once.
+unsafe fn replay<T>(payload: *const u64) {
+    let (args, emit) = unsafe { payload.cast::<(T, fn(T))>().read() };
+    emit(args);
+}
+
+unsafe fn replay_none(_: *const u64) {}
+
+/// Bind each argument, then pass them as a tuple to `$via` together with a
+/// non-capturing function that logs them
+#[cfg(feature = "defmt")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __os_log {
+    ($via:ident, $level:ident, $fmt:tt $(, $arg:expr)* $(,)?) => {
+        $crate::__os_log!(@bind $via $level $fmt [] $($arg,)*)
+    };
+    (@bind $via:ident $level:ident $fmt:tt [$($bound:ident)*]) => {
+        $crate::log::$via(($($bound,)*), |($($bound,)*)| defmt::$level!($fmt $(, $bound)*))
+    };
+    (@bind $via:ident $level:ident $fmt:tt [$($bound:ident)*] $arg:expr, $($rest:expr,)*) => {
+        match $arg {
+            arg => $crate::__os_log!(@bind $via $level $fmt [$($bound)* arg] $($rest,)*),
+        }
+    };
+}
+
+/// Kernel messages with owned or `'static` arguments, buffered inside a
+/// critical section as the policy says
+#[cfg(feature = "defmt")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __os_log_defer {
+    ($level:ident, $($arg:tt)*) => { $crate::__os_log!(dispatch, $level, $($arg)*) };
+}
+
+/// Kernel PendSV and tick paths: the in-critical policy applies whatever
+/// masks interrupts
+#[cfg(feature = "defmt")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __os_log_hot {
+    ($level:ident, $($arg:tt)*) => { $crate::__os_log!(dispatch_critical, $level, $($arg)*) };
+}
+
+/// Written in place whatever the policy (crash reports, `os_dump_defmt`)
+#[cfg(feature = "defmt")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __os_log_now {
+    ($level:ident, $($arg:tt)*) => { defmt::$level!($($arg)*) };
+}
 
 /// Debug message
 #[cfg(feature = "defmt")]
 #[macro_export]
 macro_rules! debug {
-    ($($arg:tt)*) => { defmt::debug!($($arg)*) };
+    ($($arg:tt)*) => { if $crate::log::emit_now() { defmt::debug!($($arg)*) } };
 }
 
 /// Info message
 #[cfg(feature = "defmt")]
 #[macro_export]
 macro_rules! info {
-    ($($arg:tt)*) => { defmt::info!($($arg)*) };
+    ($($arg:tt)*) => { if $crate::log::emit_now() { defmt::info!($($arg)*) } };
 }
 
 /// Error message
 #[cfg(feature = "defmt")]
 #[macro_export]
 macro_rules! error {
-    ($($arg:tt)*) => { defmt::error!($($arg)*) };
+    ($($arg:tt)*) => { if $crate::log::emit_now() { defmt::error!($($arg)*) } };
 }
 
 /// Trace message
 #[cfg(feature = "defmt")]
 #[macro_export]
 macro_rules! trace {
-    ($($arg:tt)*) => { defmt::trace!($($arg)*) };
+    ($($arg:tt)*) => { if $crate::log::emit_now() { defmt::trace!($($arg)*) } };
 }
 
 /// Warning message
 #[cfg(feature = "defmt")]
 #[macro_export]
 macro_rules! warn {
-    ($($arg:tt)*) => { defmt::warn!($($arg)*) };
+    ($($arg:tt)*) => { if $crate::log::emit_now() { defmt::warn!($($arg)*) } };
 }
 
 // No-op versions when debug is disabled
 #[cfg(not(feature = "defmt"))]
 #[macro_export]
-macro_rules! debug { ($($arg:tt)*) => {}; }
+macro_rules! debug { ($($arg:tt)*) => { () }; }
 #[cfg(not(feature = "defmt"))]
 #[macro_export]
-macro_rules! info { ($($arg:tt)*) => {}; }
+macro_rules! info { ($($arg:tt)*) => { () }; }
 #[cfg(not(feature = "defmt"))]
 #[macro_export]
-macro_rules! error { ($($arg:tt)*) => {}; }
+macro_rules! error { ($($arg:tt)*) => { () }; }
 #[cfg(not(feature = "defmt"))]
 #[macro_export]
-macro_rules! trace { ($($arg:tt)*) => {}; }
+macro_rules! trace { ($($arg:tt)*) => { () }; }
 #[cfg(not(feature = "defmt"))]
 #[macro_export]
-macro_rules! warn { ($($arg:tt)*) => {}; }
+macro_rules! warn { ($($arg:tt)*) => { () }; }
+#[cfg(not(feature = "defmt"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __os_log_defer { ($($arg:tt)*) => { () }; }
+#[cfg(not(feature = "defmt"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __os_log_hot { ($($arg:tt)*) => { () }; }
+#[cfg(not(feature = "defmt"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __os_log_now { ($($arg:tt)*) => { () }; }
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    use super::*;
+
+    /// Set while `masked` holds its critical section
+    static MASKED: AtomicBool = AtomicBool::new(false);
+    /// Sum of the messages written, and how many were written masked
+    static WRITTEN: AtomicU32 = AtomicU32::new(0);
+    static WRITTEN_MASKED: AtomicU32 = AtomicU32::new(0);
+
+    fn emit((n,): (u32,)) {
+        WRITTEN.fetch_add(n, Ordering::Relaxed);
+        if MASKED.load(Ordering::Relaxed) {
+            WRITTEN_MASKED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn emit_big(_: ([u64; 8],)) {
+        emit((1,));
+    }
+
+    fn masked(f: impl FnOnce()) {
+        critical_section(|_cs| {
+            MASKED.store(true, Ordering::Relaxed);
+            f();
+            MASKED.store(false, Ordering::Relaxed);
+        });
+    }
+
+    /// Run `f` under `in_critical` from an empty ring; returns the sum
+    /// written, the messages written masked, pending and dropped
+    fn case(in_critical: InCritical, f: impl FnOnce()) -> (u32, u32, usize, u32) {
+        set_policy(LogPolicy { in_critical, ..LogPolicy::new() });
+        flush();
+        WRITTEN.store(0, Ordering::Relaxed);
+        WRITTEN_MASKED.store(0, Ordering::Relaxed);
+        let lost = dropped();
+        f();
+        let written = (WRITTEN.load(Ordering::Relaxed), WRITTEN_MASKED.load(Ordering::Relaxed));
+        (written.0, written.1, pending(), dropped() - lost)
+    }
+
+    /// A message made inside a critical section is dropped, written in
+    /// place or kept until `flush` writes it outside the mask
+    #[test]
+    fn test_policy() {
+        // Kernel tests log through the same ring
+        let _lock = crate::core::kernel::test_lock();
+        assert_eq!(LogPolicy::default(), LogPolicy { in_critical: InCritical::Buffer, flush_in_idle: true });
+
+        assert_eq!(case(InCritical::Emit, || masked(|| dispatch((1,), emit))), (1, 1, 0, 0));
+        assert_eq!(case(InCritical::Drop, || masked(|| dispatch((1,), emit))), (0, 0, 0, 1));
+
+        // Buffered messages are never written with the mask held
+        let buffered = case(InCritical::Buffer, || {
+            masked(|| {
+                dispatch((2,), emit);
+                dispatch((3,), emit);
+                assert_eq!(WRITTEN.load(Ordering::Relaxed), 0);
+            });
+            assert_eq!(pending(), 2);
+            assert_eq!(flush(), 2);
+        });
+        assert_eq!(buffered, (5, 0, 0, 0));
+
+        // Outside a critical section only the hot path variant defers
+        assert_eq!(case(InCritical::Buffer, || dispatch((4,), emit)), (4, 0, 0, 0));
+        assert_eq!(case(InCritical::Buffer, || dispatch_critical((4,), emit)), (0, 0, 1, 0));
+
+        // A full ring and arguments too large for a slot are dropped
+        let full = case(InCritical::Buffer, || {
+            masked(|| {
+                for _ in 0..=CFG_LOG_DEFER_SLOTS {
+                    dispatch((1,), emit);
+                }
+                dispatch(([0; 8],), emit_big);
+            })
+        });
+        assert_eq!(full, (0, 0, CFG_LOG_DEFER_SLOTS, 2));
+        assert_eq!(flush(), CFG_LOG_DEFER_SLOTS);
+
+        // Messages from the logging macros are written or dropped, never kept
+        assert_eq!(case(InCritical::Buffer, || masked(|| assert!(emit_now()))), (0, 0, 0, 0));
+        assert_eq!(case(InCritical::Drop, || masked(|| assert!(!emit_now()))), (0, 0, 0, 1));
+
+        set_policy(LogPolicy::new());
+    }
+
+    /// The logging macros take borrowed arguments and work as expressions;
+    /// under `Drop` a message made inside a critical section is counted,
+    /// and none is ever kept in the ring
+    #[test]
+    fn test_macros_take_borrowed_args() {
+        let _lock = crate::core::kernel::test_lock();
+        let bytes = [b'x'; 4];
+        let text = core::str::from_utf8(&bytes).unwrap();
+        let n = bytes.len();
+        crate::info!("{=str} {}", text, &n);
+
+        set_policy(LogPolicy { in_critical: InCritical::Drop, ..LogPolicy::new() });
+        flush();
+        let lost = dropped();
+        let () = critical_section(|_cs| crate::warn!("{=str} {}", text, n));
+        assert_eq!(pending(), 0);
+        // Without `defmt` there is no message to drop
+        assert_eq!(dropped() - lost, cfg!(feature = "defmt") as u32);
+        assert_eq!((text, n), ("xxxx", 4));
+        set_policy(LogPolicy::new());
+    }
+}
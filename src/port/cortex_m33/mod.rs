@@ -0,0 +1,199 @@
+//! Cortex-M33 (ARMv8-M Mainline) port implementation
+//!
+//! Provides context switching via PendSV exception handler. Each task's
+//! stack base is loaded into PSPLIM on every switch, so a task overflowing
+//! its stack raises a UsageFault (STKOF) instead of silently corrupting
+//! memory. The MSP is limited the same way with MSPLIM.
+//!
+//! Only Non-secure operation is supported: tasks return to Thread mode on
+//! the Non-secure process stack.
+
+#![allow(named_asm_labels)]
+
+use core::arch::{asm, naked_asm};
+
+use cortex_m::peripheral::scb::SystemHandler;
+use crate::config::CFG_ISR_STK_SIZE;
+use crate::task::OsTaskFn;
+use crate::types::{OsOpt, OsStkElement};
+
+use super::cortex_m_common::{
+    os_cpu_kernel_prio, os_task_return, pendsv_stamp_enter, pendsv_stamp_exit, INTERRUPT_STACK,
+};
+#[cfg(debug_assertions)]
+use super::cortex_m_common::os_cpu_prio_check;
+
+/// EXC_RETURN: Thread mode, PSP, Non-secure, standard frame, default stacking
+const EXC_RETURN_THREAD_PSP_NS: u32 = 0xFFFF_FFBC;
+
+/// EXC_RETURN used to resume a task from a fault handler
+pub(crate) const OS_EXC_RETURN_TASK: u32 = EXC_RETURN_THREAD_PSP_NS;
+
+/// Word of the saved context holding the task's EXC_RETURN (after PSPLIM, R4-R11)
+#[cfg(all(has_fpu, feature = "fpu-check"))]
+pub(crate) const CTX_EXC_RETURN_IDX: usize = 9;
+
+/// Start the highest priority ready task
+#[no_mangle]
+pub unsafe extern "C" fn os_start_high_rdy() {
+    unsafe {
+        let mut scb = cortex_m::Peripherals::steal().SCB;
+
+        // Set PendSV and SysTick priority to lowest
+        let kernel_prio = os_cpu_kernel_prio();
+        scb.set_priority(SystemHandler::PendSV, kernel_prio);
+        scb.set_priority(SystemHandler::SysTick, kernel_prio);
+
+        #[cfg(debug_assertions)]
+        os_cpu_prio_check(kernel_prio);
+
+        // Switch MSP to dedicated interrupt stack and limit it
+        let msp_base = INTERRUPT_STACK.get() as u32;
+        let msp_top = msp_base + CFG_ISR_STK_SIZE as u32;
+
+        asm!("msr msplim, {0}", in(reg) 0);
+        asm!("msr msp, {0}", in(reg) msp_top,);
+        asm!("msr msplim, {0}", in(reg) msp_base);
+        asm!("msr psplim, {0}", in(reg) 0);
+        asm!("msr psp, {0}", in(reg) 0);
+
+        let cpu = crate::kernel::CPU_STATE.get_unchecked();
+        cpu.tcb_cur = core::ptr::null_mut();
+        cpu.sw_pending = true;
+
+        cortex_m::interrupt::enable();
+        cortex_m::peripheral::SCB::set_pendsv();
+    }
+}
+
+/// Context structure stored on stack
+///
+/// `stk_ptr` of a switched-out task points at `psplim`. The words from
+/// `r0` on are the frame the hardware pops on exception return.
+#[repr(C, align(4))]
+struct UcStk {
+    psplim: u32,      // Stack limit restored into PSPLIM
+    r4: u32,
+    r5: u32,
+    r6: u32,
+    r7: u32,
+    r8: u32,
+    r9: u32,
+    r10: u32,
+    r11: u32,
+    exc_return: u32,  // LR value for exception return
+    r0: u32,
+    r1: u32,
+    r2: u32,
+    r3: u32,
+    r12: u32,
+    lr: u32,
+    pc: u32,
+    xpsr: u32,
+}
+const CONTEXT_STACK_SIZE: usize = 18;
+
+const _: () = assert!(core::mem::size_of::<UcStk>() == CONTEXT_STACK_SIZE * 4);
+
+/// Words of a switched-out task's frame, FP extension included
+pub const OS_CTX_FRAME_WORDS: usize = CONTEXT_STACK_SIZE + super::cortex_m_common::FP_FRAME_WORDS;
+
+/// Words lost to aligning the stack top and the PSPLIM base, and the
+/// exception realign pad
+pub const OS_STK_ISR_HEADROOM_WORDS: usize = 3;
+
+/// Alignment of the first frame in bytes (AAPCS)
+pub const OS_STK_ALIGN: usize = 8;
+
+/// Initialize task stack
+///
+/// The stack base, rounded up to 8 bytes, becomes the task's PSPLIM. The
+/// frame ends at the stack top rounded down to 8 bytes, so the hardware
+/// frame is 8-byte aligned as AAPCS requires.
+pub unsafe fn os_task_stk_init(
+    task_fn: OsTaskFn,
+    arg: *mut (),
+    stk_base: *mut OsStkElement,
+    stk_size: usize,
+    _opt: OsOpt,
+) -> *mut OsStkElement {
+    unsafe {
+        let stk_aligned = crate::task::stk_top_aligned(stk_base, stk_size, OS_STK_ALIGN);
+        let stk_limit = ((stk_base as usize + 7) & !7) as u32;
+
+        let frame_ptr = stk_aligned.sub(CONTEXT_STACK_SIZE) as *mut UcStk;
+
+        (*frame_ptr) = UcStk {
+            psplim: stk_limit,
+            r4: 0x04040404,
+            r5: 0x05050505,
+            r6: 0x06060606,
+            r7: 0x07070707,
+            r8: 0x08080808,
+            r9: 0x09090909,
+            r10: 0x10101010,
+            r11: 0x11111111,
+            exc_return: EXC_RETURN_THREAD_PSP_NS,
+            r0: arg as u32,
+            r1: 0,
+            r2: 0,
+            r3: 0,
+            r12: 0,
+            lr: os_task_return as *const () as u32,
+            pc: (task_fn as usize as u32) | 1,
+            xpsr: 0x0100_0000,
+        };
+
+        frame_ptr as *mut OsStkElement
+    }
+}
+
+/// PendSV exception handler - performs full context switch
+///
+/// 1. Save PSPLIM, R4-R11, LR to current task's PSP (skip if first task)
+/// 2. Call switch_context to swap TCB pointers
+/// 3. Restore PSPLIM, R4-R11, LR from new task's stack
+/// 4. Exception return
+#[no_mangle]
+#[unsafe(naked)]
+pub unsafe extern "C" fn PendSV() {
+    use crate::kernel::CPU_STATE;
+
+    naked_asm!(
+        "cpsid i",
+        "dsb",
+        "isb",
+        pendsv_stamp_enter!(),
+
+        "mrs r0, psp",
+
+        "ldr r1, ={cpu_state}",
+        "ldr r1, [r1]",
+        "cbz r1, 1f",
+
+        "mrs r2, psplim",
+        "stmdb r0!, {{r2, r4-r11, lr}}",
+
+        "1:",
+        "bl pendsv_switch_context",
+
+        "cbz r0, 2f",
+        "ldmia r0!, {{r2, r4-r11, lr}}",
+
+        // Lower the limit first so the new PSP is never below it
+        "movs r1, #0",
+        "msr psplim, r1",
+        "msr psp, r0",
+        "msr psplim, r2",
+
+        "2:",
+        pendsv_stamp_exit!(),
+        "cpsie i",
+        "dsb",
+        "isb",
+
+        "bx lr",
+
+        cpu_state = sym CPU_STATE,
+    );
+}
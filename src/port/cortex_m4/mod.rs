@@ -7,69 +7,56 @@
 use core::arch::{asm, naked_asm};
 
 use cortex_m::peripheral::scb::SystemHandler;
-use cortex_m::peripheral::syst::SystClkSource;
+use crate::config::CFG_ISR_STK_SIZE;
 use crate::task::OsTaskFn;
 use crate::types::{OsOpt, OsStkElement};
 
-/// Interrupt stack for MSP
-#[no_mangle]
-static mut INTERRUPT_STACK: [u64; 256] = [0xDEADBEEF_DEADBEEF; 256];
+use super::cortex_m_common::{
+    os_cpu_kernel_prio, os_task_return, pendsv_stamp_enter, pendsv_stamp_exit, INTERRUPT_STACK,
+};
+#[cfg(debug_assertions)]
+use super::cortex_m_common::os_cpu_prio_check;
 
-/// Initialize SysTick timer for system tick generation
-///
-/// # Arguments
-/// * `cnts` - Reload value
-///
-/// # Example
-/// For 16MHz clock with 1000Hz tick rate: cnts = 16_000_000 / 1000 = 16_000
-pub fn os_cpu_systick_init(cnts: u32) {
-    let mut p = unsafe { cortex_m::Peripherals::steal() };
-    
-    // Configure SysTick timer
-    p.SYST.set_reload(cnts - 1);
-    p.SYST.clear_current();
-    p.SYST.set_clock_source(SystClkSource::Core);
-    p.SYST.enable_interrupt();
-    p.SYST.enable_counter();
-}
+/// EXC_RETURN: Thread mode, PSP, standard frame
+pub(crate) const OS_EXC_RETURN_TASK: u32 = 0xFFFF_FFFD;
+
+/// Word of the saved context holding the task's EXC_RETURN (after R4-R11)
+#[cfg(all(has_fpu, feature = "fpu-check"))]
+pub(crate) const CTX_EXC_RETURN_IDX: usize = 8;
 
 /// Start the highest priority ready task
 #[no_mangle]
-#[allow(static_mut_refs)]
 pub unsafe extern "C" fn os_start_high_rdy() {
     unsafe {
         let mut scb = cortex_m::Peripherals::steal().SCB;
         
         // Set PendSV and SysTick priority to lowest
-        scb.set_priority(SystemHandler::PendSV, 0xF0);
-        scb.set_priority(SystemHandler::SysTick, 0xF0);
+        let kernel_prio = os_cpu_kernel_prio();
+        scb.set_priority(SystemHandler::PendSV, kernel_prio);
+        scb.set_priority(SystemHandler::SysTick, kernel_prio);
+
+        #[cfg(debug_assertions)]
+        os_cpu_prio_check(kernel_prio);
 
         // Switch MSP to dedicated interrupt stack
-        let msp_top = &INTERRUPT_STACK as *const _ as u32 + core::mem::size_of_val(&INTERRUPT_STACK) as u32;
+        let msp_top = INTERRUPT_STACK.get() as u32 + CFG_ISR_STK_SIZE as u32;
         
         asm!("msr msp, {0}", in(reg) msp_top,);
         asm!("msr psp, {0}", in(reg) 0);
 
-        crate::kernel::CPU_STATE.tcb_cur = core::ptr::null_mut();
+        let cpu = crate::kernel::CPU_STATE.get_unchecked();
+        cpu.tcb_cur = core::ptr::null_mut();
+        cpu.sw_pending = true;
 
         cortex_m::interrupt::enable();
         cortex_m::peripheral::SCB::set_pendsv();
     }
 }
 
-/// Trigger context switch from task level
-#[inline(always)]
-pub fn os_ctx_sw() {
-    cortex_m::peripheral::SCB::set_pendsv();
-}
-
-/// Trigger context switch from interrupt level
-#[inline(always)]
-pub fn os_int_ctx_sw() {
-    cortex_m::peripheral::SCB::set_pendsv();
-}
-
 /// Context structure stored on stack
+///
+/// `stk_ptr` of a switched-out task points at `r4`. The words from `r0` on
+/// are the frame the hardware pops on exception return.
 #[repr(C, align(4))]
 struct UcStk {
     r4: u32,
@@ -92,7 +79,22 @@ struct UcStk {
 }
 const CONTEXT_STACK_SIZE: usize = 17;
 
+const _: () = assert!(core::mem::size_of::<UcStk>() == CONTEXT_STACK_SIZE * 4);
+
+/// Words of a switched-out task's frame, FP extension included
+pub const OS_CTX_FRAME_WORDS: usize = CONTEXT_STACK_SIZE + super::cortex_m_common::FP_FRAME_WORDS;
+
+/// Words lost to aligning the stack top and the exception realign pad
+pub const OS_STK_ISR_HEADROOM_WORDS: usize = 2;
+
+/// Alignment of the first frame in bytes (AAPCS)
+pub const OS_STK_ALIGN: usize = 8;
+
 /// Initialize task stack
+///
+/// The frame ends at the stack top rounded down to 8 bytes, so the
+/// hardware frame is 8-byte aligned as AAPCS requires and the task starts
+/// with an aligned SP. xPSR bit 9 stays clear: no padding word to pop.
 pub unsafe fn os_task_stk_init(
     task_fn: OsTaskFn,
     arg: *mut (),
@@ -101,8 +103,7 @@ pub unsafe fn os_task_stk_init(
     _opt: OsOpt,
 ) -> *mut OsStkElement {
     unsafe {
-        let stk_top = stk_base.add(stk_size);
-        let stk_aligned = ((stk_top as usize) & !7) as *mut u32;
+        let stk_aligned = crate::task::stk_top_aligned(stk_base, stk_size, OS_STK_ALIGN);
         
         let frame_ptr = stk_aligned.sub(CONTEXT_STACK_SIZE) as *mut UcStk;
         
@@ -115,7 +116,7 @@ pub unsafe fn os_task_stk_init(
             r9: 0x09090909,
             r10: 0x10101010,
             r11: 0x11111111,
-            exc_return: 0xFFFF_FFFD,
+            exc_return: OS_EXC_RETURN_TASK,
             r0: arg as u32,
             r1: 0,
             r2: 0,
@@ -125,34 +126,8 @@ pub unsafe fn os_task_stk_init(
             pc: (task_fn as usize as u32) | 1,
             xpsr: 0x0100_0000,
         };
-        
-        // Return pointer 4 bytes before frame to match PendSV's "add r0, r0, #4"
-        (frame_ptr as *mut u32).sub(1) as *mut OsStkElement
-    }
-}
 
-/// Helper function called from PendSV to perform TCB switching
-/// Returns new task's stack pointer
-#[inline(never)]
-#[no_mangle]
-unsafe extern "C" fn pendsv_switch_context(cur_sp: *mut u32) -> *mut u32 {
-    unsafe {
-        let cur_tcb_ptr = crate::kernel::CPU_STATE.tcb_cur;
-        
-        if !cur_tcb_ptr.is_null() {
-            (*cur_tcb_ptr).stk_ptr = cur_sp;
-        }
-        
-        crate::kernel::CPU_STATE.tcb_cur = crate::kernel::CPU_STATE.tcb_high_rdy;
-        crate::kernel::CPU_STATE.prio_cur = crate::kernel::CPU_STATE.prio_high_rdy;
-        
-        let new_tcb_ptr = crate::kernel::CPU_STATE.tcb_cur;
-        
-        if new_tcb_ptr.is_null() {
-            core::ptr::null_mut()
-        } else {
-            (*new_tcb_ptr).stk_ptr
-        }
+        frame_ptr as *mut OsStkElement
     }
 }
 
@@ -171,6 +146,7 @@ pub unsafe extern "C" fn PendSV() {
         "cpsid i",
         "dsb",
         "isb",
+        pendsv_stamp_enter!(),
         
         "mrs r0, psp",
         
@@ -180,18 +156,16 @@ pub unsafe extern "C" fn PendSV() {
         
         "stmdb r0!, {{r4-r11, lr}}",
         
-        "sub r0, r0, #4",
-        
         "1:",
         "bl pendsv_switch_context",
         
         "cbz r0, 2f",
-        "add r0, r0, #4",
         "ldmia r0!, {{r4-r11, lr}}",
         
         "msr psp, r0",
         
         "2:",
+        pendsv_stamp_exit!(),
         "cpsie i",
         "dsb",
         "isb",
@@ -201,15 +175,3 @@ pub unsafe extern "C" fn PendSV() {
         cpu_state = sym CPU_STATE,
     );
 }
-
-/// Task switch hook
-#[no_mangle]
-fn os_task_sw_hook() {}
-
-/// Task return handler
-#[no_mangle]
-fn os_task_return() -> ! {
-    loop {
-        cortex_m::asm::wfi();
-    }
-}
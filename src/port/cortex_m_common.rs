@@ -0,0 +1,468 @@
+//! Shared Cortex-M port support
+//!
+//! SysTick setup, exception priority handling, the interrupt (MSP) stack and
+//! the TCB switch called from PendSV. Used by every Cortex-M port; only the
+//! stack frame layout and the PendSV handler itself are core specific.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use cortex_m::peripheral::scb::SystemHandler;
+use cortex_m::peripheral::syst::SystClkSource;
+use crate::config::CFG_ISR_STK_SIZE;
+use crate::core::cs_cell::SyncUnsafeCell;
+use crate::error::{OsError, OsResult};
+
+/// Fill pattern of the interrupt stack
+const INTERRUPT_STACK_FILL: u64 = 0xDEADBEEF_DEADBEEF;
+
+/// Interrupt stack for MSP
+#[no_mangle]
+pub(crate) static INTERRUPT_STACK: SyncUnsafeCell<[u64; CFG_ISR_STK_SIZE / 8]> =
+    SyncUnsafeCell::new([INTERRUPT_STACK_FILL; CFG_ISR_STK_SIZE / 8]);
+
+/// Get interrupt stack usage in bytes as `(used, total)`
+///
+/// Scans upward from the low end for the first word that no longer holds
+/// the fill pattern, so `used` is a high watermark.
+pub fn os_cpu_int_stk_usage() -> (usize, usize) {
+    let base = INTERRUPT_STACK.get().cast::<u64>();
+    let free = (0..CFG_ISR_STK_SIZE / 8)
+        .take_while(|&i| unsafe { base.add(i).read_volatile() } == INTERRUPT_STACK_FILL)
+        .count();
+    (CFG_ISR_STK_SIZE - free * 8, CFG_ISR_STK_SIZE)
+}
+
+/// Check that the lowest word of the interrupt stack is untouched
+#[inline]
+pub fn os_cpu_int_stk_canary_ok() -> bool {
+    unsafe { INTERRUPT_STACK.get().cast::<u64>().read_volatile() == INTERRUPT_STACK_FILL }
+}
+
+/// Priority for PendSV and SysTick (0 = probe the lowest implemented level)
+static KERNEL_EXC_PRIO: AtomicU8 = AtomicU8::new(0);
+
+/// Override the priority used for PendSV and SysTick
+///
+/// By default the kernel probes the lowest implemented priority level,
+/// which is correct for any number of `NVIC_PRIO_BITS`. Only override this
+/// when the application needs a specific value; it must stay numerically
+/// greater than or equal to every interrupt that calls kernel services and
+/// greater than `OS_KA_BASEPRI_Boundary`. Must be called before `os_start`.
+pub fn os_cpu_kernel_prio_set(prio: u8) {
+    KERNEL_EXC_PRIO.store(prio, Ordering::Relaxed);
+}
+
+/// Lowest implemented exception priority, found by probing
+///
+/// Writing 0xFF to a priority register and reading it back leaves only the
+/// implemented bits set, e.g. 0xF0 with 4 priority bits or 0xC0 with 2.
+pub fn os_cpu_lowest_prio() -> u8 {
+    unsafe {
+        let mut scb = cortex_m::Peripherals::steal().SCB;
+        let saved = cortex_m::peripheral::SCB::get_priority(SystemHandler::PendSV);
+        scb.set_priority(SystemHandler::PendSV, 0xFF);
+        let lowest = cortex_m::peripheral::SCB::get_priority(SystemHandler::PendSV);
+        scb.set_priority(SystemHandler::PendSV, saved);
+        lowest
+    }
+}
+
+/// Number of implemented NVIC priority bits
+#[inline]
+pub fn os_cpu_nvic_prio_bits() -> u8 {
+    os_cpu_lowest_prio().count_ones() as u8
+}
+
+/// Priority applied to PendSV and SysTick at `os_start`
+pub(crate) fn os_cpu_kernel_prio() -> u8 {
+    match KERNEL_EXC_PRIO.load(Ordering::Relaxed) {
+        0 => os_cpu_lowest_prio(),
+        prio => prio,
+    }
+}
+
+/// Check that no enabled interrupt is numerically lower priority than PendSV
+///
+/// Such an interrupt could be preempted by a context switch.
+#[cfg(debug_assertions)]
+pub(crate) fn os_cpu_prio_check(kernel_prio: u8) {
+    let boundary = crate::kernel::OS_KA_BASEPRI_Boundary;
+    debug_assert!(
+        boundary == 0 || boundary < kernel_prio as u32,
+        "BASEPRI boundary must be above the PendSV priority"
+    );
+
+    let nvic = unsafe { &*cortex_m::peripheral::NVIC::PTR };
+    for irq in 0..240usize {
+        if nvic.iser[irq / 32].read() & (1 << (irq % 32)) == 0 {
+            continue;
+        }
+        debug_assert!(
+            nvic.ipr[irq].read() <= kernel_prio,
+            "PendSV is not the lowest configured priority"
+        );
+    }
+}
+
+/// Resume a task that faulted in thread mode (`fault-recovery` feature)
+///
+/// `frame` is the stacked exception frame, on the PSP or the MSP.
+///
+/// Returns if the fault must take the fatal path: it came from handler
+/// mode or MSP, or the task is not recoverable. Otherwise the stacked frame
+/// is redirected to `os_task_fault_exit`, the interrupt stack is reset (no
+/// handler was active under thread mode) and the exception returns.
+#[cfg(feature = "fault-recovery")]
+pub(crate) unsafe fn os_cpu_fault_recover(frame: *mut cortex_m_rt::ExceptionFrame) {
+    const SCB_CFSR: *mut u32 = 0xE000_ED28 as *mut u32;
+    const SCB_HFSR: *mut u32 = 0xE000_ED2C as *mut u32;
+    const SCB_MMFAR: *const u32 = 0xE000_ED34 as *const u32;
+    const SCB_BFAR: *const u32 = 0xE000_ED38 as *const u32;
+
+    // Thread mode stacks IPSR = 0, and the frame must be on the PSP
+    let ef = unsafe { &*frame };
+    if ef.xpsr() & 0x1FF != 0 || frame as u32 != cortex_m::register::psp::read() {
+        return;
+    }
+
+    let report = unsafe {
+        crate::task::OsFaultReport {
+            pc: ef.pc(),
+            lr: ef.lr(),
+            xpsr: ef.xpsr(),
+            cfsr: SCB_CFSR.read_volatile(),
+            hfsr: SCB_HFSR.read_volatile(),
+            mmfar: SCB_MMFAR.read_volatile(),
+            bfar: SCB_BFAR.read_volatile(),
+        }
+    };
+    if !crate::task::fault::fault_take(report) {
+        return;
+    }
+
+    unsafe {
+        // Status bits are write-one-to-clear
+        SCB_CFSR.write_volatile(report.cfsr);
+        SCB_HFSR.write_volatile(report.hfsr);
+
+        let exit = crate::task::fault::os_task_fault_exit as extern "C" fn() -> !;
+        (*frame).set_pc(exit as usize as u32 & !1);
+        (*frame).set_lr(0xFFFF_FFFF);
+        (*frame).set_xpsr(1 << 24); // Thumb bit only
+
+        let msp_top = INTERRUPT_STACK.get() as u32 + CFG_ISR_STK_SIZE as u32;
+        core::arch::asm!(
+            "msr msp, {top}",
+            "bx {ret}",
+            top = in(reg) msp_top,
+            ret = in(reg) crate::port::OS_EXC_RETURN_TASK,
+            options(noreturn),
+        );
+    }
+}
+
+/// Enable the DWT cycle counter used for timestamps
+#[cfg(not(armv6m))]
+pub fn os_cpu_ts_init() {
+    let mut p = unsafe { cortex_m::Peripherals::steal() };
+    p.DCB.enable_trace();
+    p.DWT.enable_cycle_counter();
+}
+
+/// Read the DWT cycle counter
+#[cfg(not(armv6m))]
+#[inline(always)]
+pub fn os_cpu_ts_get() -> u32 {
+    cortex_m::peripheral::DWT::cycle_count()
+}
+
+/// The cycle counter runs at the core clock
+#[cfg(not(armv6m))]
+pub fn os_cpu_ts_freq_hz() -> Option<u32> {
+    None
+}
+
+// ARMv6-M has no cycle counter: timestamps come from an application timer
+
+/// Reader registered with `os_cpu_ts_source_set`, null if none
+#[cfg(armv6m)]
+static TS_READ: core::sync::atomic::AtomicPtr<()> = core::sync::atomic::AtomicPtr::new(core::ptr::null_mut());
+
+/// Frequency of the registered timer
+#[cfg(armv6m)]
+static TS_FREQ_HZ: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(0);
+
+/// Register the free-running up-counter used for timestamps
+///
+/// `read` must wrap through the full 32 bits; narrower timers need
+/// extending in software.
+#[cfg(armv6m)]
+pub fn os_cpu_ts_source_set(read: fn() -> u32, freq_hz: u32) {
+    TS_FREQ_HZ.store(freq_hz, Ordering::Relaxed);
+    TS_READ.store(read as *mut (), Ordering::Release);
+}
+
+/// Nothing to enable: the registered timer is already running
+#[cfg(armv6m)]
+pub fn os_cpu_ts_init() {}
+
+/// Read the registered timer, 0 if none
+#[cfg(armv6m)]
+#[inline(always)]
+pub fn os_cpu_ts_get() -> u32 {
+    let read = TS_READ.load(Ordering::Acquire);
+    if read.is_null() {
+        return 0;
+    }
+    let read: fn() -> u32 = unsafe { core::mem::transmute(read) };
+    read()
+}
+
+/// Frequency of the registered timer, 0 if none
+#[cfg(armv6m)]
+pub fn os_cpu_ts_freq_hz() -> Option<u32> {
+    Some(TS_FREQ_HZ.load(Ordering::Relaxed))
+}
+
+/// Words the FP extension adds to an exception frame (S0-S15, FPSCR, pad)
+#[cfg(has_fpu)]
+pub(crate) const FP_FRAME_WORDS: usize = 18;
+#[cfg(not(has_fpu))]
+pub(crate) const FP_FRAME_WORDS: usize = 0;
+
+/// Coprocessor Access Control Register
+#[cfg(has_fpu)]
+const CPACR: *mut u32 = 0xE000_ED88 as *mut u32;
+/// FP Context Control Register
+#[cfg(has_fpu)]
+const FPCCR: *mut u32 = 0xE000_EF34 as *mut u32;
+/// CPACR: full access to CP10 and CP11
+#[cfg(has_fpu)]
+const CPACR_CP10_CP11_FULL: u32 = 0xF << 20;
+/// FPCCR: automatic FP state preservation (ASPEN) and lazy stacking (LSPEN)
+#[cfg(has_fpu)]
+const FPCCR_ASPEN_LSPEN: u32 = 0b11 << 30;
+
+/// Configure the FPU for tasks
+///
+/// Grants thread and handler mode full access to the FPU and enables
+/// automatic, lazy FP stacking, so a task's first float instruction sets
+/// CONTROL.FPCA and its exception frames carry the FP registers. Settings
+/// left by the startup code are overridden. Nothing to do without an FPU.
+pub fn os_cpu_fpu_init() {
+    #[cfg(has_fpu)]
+    unsafe {
+        core::ptr::write_volatile(CPACR, core::ptr::read_volatile(CPACR) | CPACR_CP10_CP11_FULL);
+        core::ptr::write_volatile(FPCCR, core::ptr::read_volatile(FPCCR) | FPCCR_ASPEN_LSPEN);
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+}
+
+/// EXC_RETURN bit clear when the exception frame holds FP state
+#[cfg(all(has_fpu, feature = "fpu-check"))]
+const EXC_RETURN_STD_FRAME: u32 = 1 << 4;
+
+/// Report a task switched out with FP state active but no `TASK_SAVE_FP`
+///
+/// The task's EXC_RETURN mirrors CONTROL.FPCA at PendSV entry: an FP frame
+/// means the task ran a float instruction since its last switch in.
+#[cfg(all(has_fpu, feature = "fpu-check"))]
+unsafe fn fpu_usage_chk(tcb: &crate::task::OsTcb, cur_sp: *const u32) {
+    let exc_return = unsafe { *cur_sp.add(crate::port::CTX_EXC_RETURN_IDX) };
+    if exc_return & EXC_RETURN_STD_FRAME == 0 && tcb.opt & crate::types::opt::TASK_SAVE_FP == 0 {
+        crate::__os_log_hot!(warn, "task {} (#{}) used the FPU without TASK_SAVE_FP", tcb.name(), tcb.id);
+        crate::kernel::hooks::fault(OsError::TaskFpUsageUnsaved);
+    }
+}
+
+/// Largest value accepted by the 24-bit SYST_RVR register
+const SYST_RELOAD_MAX: u32 = 0x00FF_FFFF;
+
+/// Initialize SysTick timer for system tick generation
+///
+/// If `cnts` does not fit the 24-bit reload register, the external
+/// reference clock (core clock / 8) is used instead.
+///
+/// # Arguments
+/// * `cnts` - Core clock cycles per tick
+///
+/// # Returns
+/// * `Err(OsError::TickReloadInvalid)` - `cnts` is zero or too large even
+///   for the divided clock
+///
+/// # Example
+/// For 16MHz clock with 1000Hz tick rate: cnts = 16_000_000 / 1000 = 16_000
+pub fn os_cpu_systick_init(cnts: u32) -> OsResult<()> {
+    let (reload, source) = if cnts == 0 {
+        return Err(OsError::TickReloadInvalid);
+    } else if cnts - 1 <= SYST_RELOAD_MAX {
+        (cnts - 1, SystClkSource::Core)
+    } else if cnts / 8 > 0 && cnts / 8 - 1 <= SYST_RELOAD_MAX {
+        (cnts / 8 - 1, SystClkSource::External)
+    } else {
+        return Err(OsError::TickReloadInvalid);
+    };
+
+    let mut p = unsafe { cortex_m::Peripherals::steal() };
+    
+    // Configure SysTick timer
+    p.SYST.set_reload(reload);
+    p.SYST.clear_current();
+    p.SYST.set_clock_source(source);
+    p.SYST.enable_interrupt();
+    p.SYST.enable_counter();
+
+    Ok(())
+}
+
+/// Change the SysTick period to `cnts` core clock cycles
+///
+/// Keeps the clock source chosen by `os_cpu_systick_init`. The counter is
+/// not cleared: the period in progress completes at the old reload and the
+/// next one uses the new value. Call with interrupts masked.
+///
+/// # Returns
+/// * `Ok(true)` - SysTick is already pending, so the period in progress
+///   started at the old reload after all and the new one starts a period later
+/// * `Err(OsError::TickReloadInvalid)` - `cnts` does not fit the reload
+///   register for the clock source in use
+pub fn os_cpu_systick_reload_set(cnts: u32) -> OsResult<bool> {
+    let mut p = unsafe { cortex_m::Peripherals::steal() };
+    let cnts = match p.SYST.get_clock_source() {
+        SystClkSource::Core => cnts,
+        SystClkSource::External => cnts / 8,
+    };
+    if cnts == 0 || cnts - 1 > SYST_RELOAD_MAX {
+        return Err(OsError::TickReloadInvalid);
+    }
+
+    p.SYST.set_reload(cnts - 1);
+    Ok(cortex_m::peripheral::SCB::is_pendst_pending())
+}
+
+/// Stop the SysTick timer and its interrupt
+pub fn os_cpu_systick_stop() {
+    let mut p = unsafe { cortex_m::Peripherals::steal() };
+    p.SYST.disable_interrupt();
+    p.SYST.disable_counter();
+}
+
+/// PendSV entry: stamp `CpuState::sw_start` (`latency-stats` feature)
+///
+/// Uses r2/r3 only; the hardware frame restores them on return.
+#[cfg(feature = "latency-stats")]
+macro_rules! pendsv_stamp_enter {
+    () => {
+        "ldr r2, =0xE0001004
+         ldr r2, [r2]
+         ldr r3, ={cpu_state}
+         str r2, [r3, #16]"
+    };
+}
+#[cfg(not(feature = "latency-stats"))]
+macro_rules! pendsv_stamp_enter {
+    () => { "" };
+}
+
+/// PendSV exit: store the elapsed cycles in `CpuState::sw_cycles`
+#[cfg(feature = "latency-stats")]
+macro_rules! pendsv_stamp_exit {
+    () => {
+        "ldr r2, =0xE0001004
+         ldr r2, [r2]
+         ldr r3, ={cpu_state}
+         ldr r1, [r3, #16]
+         subs r2, r2, r1
+         str r2, [r3, #20]"
+    };
+}
+#[cfg(not(feature = "latency-stats"))]
+macro_rules! pendsv_stamp_exit {
+    () => { "" };
+}
+
+pub(crate) use {pendsv_stamp_enter, pendsv_stamp_exit};
+
+/// Trigger context switch from task level
+///
+/// Only called through `kernel::ctx_sw_request`; the switch picks its task.
+#[inline(always)]
+pub fn os_ctx_sw() {
+    cortex_m::peripheral::SCB::set_pendsv();
+}
+
+/// Trigger context switch from interrupt level
+///
+/// Only called through `kernel::ctx_sw_request`; the switch picks its task.
+#[inline(always)]
+pub fn os_int_ctx_sw() {
+    cortex_m::peripheral::SCB::set_pendsv();
+}
+
+/// Helper function called from PendSV to perform TCB switching
+/// Returns new task's stack pointer
+#[inline(never)]
+#[no_mangle]
+unsafe extern "C" fn pendsv_switch_context(cur_sp: *mut u32) -> *mut u32 {
+    unsafe {
+        #[cfg(feature = "latency-stats")]
+        crate::core::kernel::stats::ctx_sw_fold();
+
+        let cpu = crate::kernel::CPU_STATE.get_unchecked();
+        // Pick the task now: interrupts since the request may have changed
+        // it. A later request pends anew
+        crate::kernel::high_rdy_resolve(cpu);
+        let cur_tcb_ptr = cpu.tcb_cur;
+        
+        if !cur_tcb_ptr.is_null() {
+            (*cur_tcb_ptr).stk_ptr = cur_sp;
+            if crate::config::CFG_TASK_STK_CHK_EN && !crate::task::stk_canary_ok(&*cur_tcb_ptr) {
+                crate::kernel::hooks::fault(OsError::StkOvf);
+            }
+            #[cfg(all(has_fpu, feature = "fpu-check"))]
+            fpu_usage_chk(&*cur_tcb_ptr, cur_sp);
+        }
+        
+        crate::kernel::hooks::task_switch(
+            core::ptr::NonNull::new(cur_tcb_ptr),
+            core::ptr::NonNull::new(cpu.tcb_high_rdy),
+        );
+
+        #[cfg(feature = "trace")]
+        if !cur_tcb_ptr.is_null() {
+            crate::core::os_trace!(TaskSwitchOut {
+                id: crate::core::trace::task_id(cur_tcb_ptr),
+                prio: cpu.prio_cur,
+            });
+        }
+        crate::core::os_trace!(TaskSwitchIn {
+            id: crate::core::trace::task_id(cpu.tcb_high_rdy),
+            prio: cpu.prio_high_rdy,
+        });
+
+        #[cfg(feature = "rr-precise")]
+        crate::sched::slice_switch(cur_tcb_ptr);
+        #[cfg(feature = "budget")]
+        crate::task::budget::budget_switch(cur_tcb_ptr, cpu.tcb_high_rdy);
+
+        cpu.tcb_cur = cpu.tcb_high_rdy;
+        cpu.prio_cur = cpu.prio_high_rdy;
+        
+        let new_tcb_ptr = cpu.tcb_cur;
+        
+        if new_tcb_ptr.is_null() {
+            core::ptr::null_mut()
+        } else {
+            (*new_tcb_ptr).stk_ptr
+        }
+    }
+}
+
+/// Task return handler
+#[no_mangle]
+pub(crate) fn os_task_return() -> ! {
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
@@ -4,44 +4,257 @@
 //! and other CPU-specific operations.
 
 #[cfg(target_arch = "arm")]
-pub mod cortex_m4;
+mod cortex_m_common;
 
 #[cfg(target_arch = "arm")]
+pub use cortex_m_common::*;
+
+#[cfg(all(target_arch = "arm", not(armv8m)))]
+pub mod cortex_m4;
+
+#[cfg(all(target_arch = "arm", not(armv8m)))]
 pub use cortex_m4::*;
 
-// Stub implementations for non-ARM targets (for testing)
-#[cfg(not(target_arch = "arm"))]
+#[cfg(all(target_arch = "arm", armv8m))]
+pub mod cortex_m33;
+
+#[cfg(all(target_arch = "arm", armv8m))]
+pub use cortex_m33::*;
+
+#[cfg(target_arch = "riscv32")]
+pub mod riscv32;
+
+#[cfg(target_arch = "riscv32")]
+pub use riscv32::*;
+
+// Stub implementations for host targets (for testing)
+//
+// Context switch requests are recorded instead of performed, and tasks get
+// a fake Cortex-M4 frame, so host tests can drive the kernel through the
+// public API and check what it asked the port to do.
+#[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
 pub mod stub {
-    use crate::task::OsTaskFn;
+    use core::ops::Deref;
+    #[cfg(not(feature = "std"))]
+    use core::sync::atomic::AtomicU32;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::core::cs_cell::CsCell;
+    use crate::critical::critical_section;
+    use crate::error::OsResult;
+    use crate::task::{OsTaskFn, OsTcb};
     use crate::types::{OsOpt, OsStkElement};
 
+    /// Cortex-M4 numbers, so host builds check the same stack minimum
+    pub const OS_CTX_FRAME_WORDS: usize = 17;
+    pub const OS_STK_ISR_HEADROOM_WORDS: usize = 2;
+    pub const OS_STK_ALIGN: usize = 8;
+
     pub unsafe fn os_start_high_rdy() {
         panic!("os_start_high_rdy not available on this platform");
     }
 
+    /// Port request recorded by the stub, with the task a switch taken at
+    /// the time would run
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PortEvent {
+        /// `os_ctx_sw`
+        CtxSw { to: *const OsTcb },
+        /// `os_int_ctx_sw`
+        IntCtxSw { to: *const OsTcb },
+    }
+
+    /// Events kept between two `take_events` calls; later ones are dropped
+    pub const PORT_EVENTS_MAX: usize = 32;
+
+    /// Recorded events, oldest first
+    pub struct PortEvents {
+        events: [PortEvent; PORT_EVENTS_MAX],
+        len: usize,
+        /// Events dropped because the log was full
+        pub dropped: usize,
+    }
+
+    impl PortEvents {
+        const fn new() -> Self {
+            Self { events: [PortEvent::CtxSw { to: core::ptr::null() }; PORT_EVENTS_MAX], len: 0, dropped: 0 }
+        }
+    }
+
+    impl Deref for PortEvents {
+        type Target = [PortEvent];
+
+        fn deref(&self) -> &[PortEvent] {
+            &self.events[..self.len]
+        }
+    }
+
+    static EVENTS: CsCell<PortEvents> = CsCell::new(PortEvents::new());
+
+    fn record(event: fn(*const OsTcb) -> PortEvent) {
+        critical_section(|cs| {
+            let to = crate::kernel::high_rdy_next(cs) as *const OsTcb;
+            let log = EVENTS.get(cs);
+            if log.len == PORT_EVENTS_MAX {
+                log.dropped += 1;
+            } else {
+                log.events[log.len] = event(to);
+                log.len += 1;
+            }
+        });
+    }
+
+    /// Take the events recorded since the last call
+    pub fn take_events() -> PortEvents {
+        critical_section(|cs| core::mem::replace(EVENTS.get(cs), PortEvents::new()))
+    }
+
+    /// Record a context switch request; the switch itself does not happen
+    /// unless a test set a switch handler
     pub fn os_ctx_sw() {
-        // No-op for testing
+        record(|to| PortEvent::CtxSw { to });
+        #[cfg(test)]
+        switch_pend();
     }
 
+    /// Record a context switch request from interrupt level
     pub fn os_int_ctx_sw() {
-        // No-op for testing
+        record(|to| PortEvent::IntCtxSw { to });
+        #[cfg(test)]
+        switch_pend();
+    }
+
+    /// Switch handler of the calling test thread, standing in for PendSV
+    #[cfg(test)]
+    #[derive(Clone, Copy)]
+    struct PendSv {
+        handler: Option<fn()>,
+        pending: bool,
+        running: bool,
+    }
+
+    #[cfg(test)]
+    std::thread_local! {
+        static PEND_SV: core::cell::Cell<PendSv> =
+            const { core::cell::Cell::new(PendSv { handler: None, pending: false, running: false }) };
     }
 
+    /// Take switch requests through `handler` once the outermost critical
+    /// section ends, as PendSV is taken once interrupts are unmasked;
+    /// `None` only records them
+    #[cfg(test)]
+    pub fn switch_handler_set(handler: Option<fn()>) {
+        PEND_SV.with(|pend_sv| pend_sv.set(PendSv { handler, pending: false, running: false }));
+    }
+
+    #[cfg(test)]
+    fn switch_pend() {
+        PEND_SV.with(|pend_sv| pend_sv.set(PendSv { pending: pend_sv.get().handler.is_some(), ..pend_sv.get() }));
+    }
+
+    /// Run the switch handler for a request made while masked; requests
+    /// made by the handler itself are taken when it returns
+    #[cfg(test)]
+    pub(crate) fn cs_exit() {
+        PEND_SV.with(|pend_sv| {
+            if pend_sv.get().running {
+                return;
+            }
+            while let PendSv { handler: Some(handler), pending: true, .. } = pend_sv.get() {
+                pend_sv.set(PendSv { pending: false, running: true, ..pend_sv.get() });
+                handler();
+            }
+            pend_sv.set(PendSv { running: false, ..pend_sv.get() });
+        });
+    }
+
+    /// Build a Cortex-M4 style frame: R4-R11, EXC_RETURN, then R0-R3, R12,
+    /// LR, PC, xPSR, ending at the stack top rounded down to 8 bytes
     pub unsafe fn os_task_stk_init(
-        _task_fn: OsTaskFn,
-        _arg: *mut (),
+        task_fn: OsTaskFn,
+        arg: *mut (),
         stk_base: *mut OsStkElement,
         stk_size: usize,
         _opt: OsOpt,
     ) -> *mut OsStkElement {
-        // Return top of stack for testing
-        unsafe { stk_base.add(stk_size - 1) }
+        let top = crate::task::stk_top_aligned(stk_base, stk_size, OS_STK_ALIGN);
+        let frame: [OsStkElement; OS_CTX_FRAME_WORDS] = [
+            0x04040404, 0x05050505, 0x06060606, 0x07070707,
+            0x08080808, 0x09090909, 0x10101010, 0x11111111,
+            0xFFFF_FFFD,
+            arg as usize as u32, 0, 0, 0, 0,
+            0,
+            task_fn as usize as u32 | 1,
+            0x0100_0000,
+        ];
+        unsafe {
+            let stk_ptr = top.sub(OS_CTX_FRAME_WORDS);
+            stk_ptr.copy_from_nonoverlapping(frame.as_ptr(), OS_CTX_FRAME_WORDS);
+            stk_ptr
+        }
+    }
+
+    pub fn os_cpu_int_stk_usage() -> (usize, usize) {
+        (0, 0)
+    }
+
+    /// Cleared by `int_stk_canary_set` to fake an interrupt stack overflow
+    static INT_STK_CANARY_OK: AtomicBool = AtomicBool::new(true);
+
+    pub fn os_cpu_int_stk_canary_ok() -> bool {
+        INT_STK_CANARY_OK.load(Ordering::Relaxed)
+    }
+
+    /// Mark the interrupt stack canary intact or overwritten
+    pub fn int_stk_canary_set(ok: bool) {
+        INT_STK_CANARY_OK.store(ok, Ordering::Relaxed);
+    }
+
+    pub fn os_cpu_ts_init() {}
+
+    pub fn os_cpu_fpu_init() {}
+
+    /// Fake timestamp counter, advances by one per read
+    #[cfg(not(feature = "std"))]
+    static TS: AtomicU32 = AtomicU32::new(0);
+
+    #[cfg(not(feature = "std"))]
+    pub fn os_cpu_ts_get() -> u32 {
+        TS.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// The fake counter has no rate; conversions use the core clock
+    #[cfg(not(feature = "std"))]
+    pub fn os_cpu_ts_freq_hz() -> Option<u32> {
+        None
+    }
+
+    /// Nanoseconds since the first read
+    #[cfg(feature = "std")]
+    pub fn os_cpu_ts_get() -> u32 {
+        static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        START.get_or_init(std::time::Instant::now).elapsed().as_nanos() as u32
+    }
+
+    #[cfg(feature = "std")]
+    pub fn os_cpu_ts_freq_hz() -> Option<u32> {
+        Some(1_000_000_000)
+    }
+
+    pub fn os_cpu_systick_init(_cnts: u32) -> OsResult<()> {
+        // No-op for testing
+        Ok(())
+    }
+
+    pub fn os_cpu_systick_reload_set(_cnts: u32) -> OsResult<bool> {
+        // No-op for testing
+        Ok(false)
     }
 
-    pub fn os_cpu_systick_init(_freq: u32) {
+    pub fn os_cpu_systick_stop() {
         // No-op for testing
     }
 }
 
-#[cfg(not(target_arch = "arm"))]
+#[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
 pub use stub::*;
@@ -0,0 +1,488 @@
+//! RISC-V (RV32IMAC, machine mode) port implementation
+//!
+//! Every trap saves the full integer context on the interrupted task's stack,
+//! records the stack pointer in the current TCB and runs the Rust dispatcher
+//! on a dedicated interrupt stack. On exit the context of whatever task is
+//! then current is restored, so a context switch is just a TCB swap done by
+//! the machine software interrupt (the PendSV equivalent).
+//!
+//! The tick comes from the CLINT `mtime`/`mtimecmp` pair. Parts without a
+//! CLINT (e.g. ESP32-C3) must build with `no-systick` and call
+//! `os_tick_handler` from their own timer, registered with
+//! [`os_cpu_ext_irq_handler_set`].
+//!
+//! Interrupts do not nest: `mstatus.MIE` stays clear for the whole trap.
+
+use core::arch::{asm, global_asm};
+use core::sync::atomic::{AtomicU8, AtomicU32, Ordering};
+
+use crate::config::CFG_ISR_STK_SIZE;
+use crate::core::cs_cell::{CsCell, SyncUnsafeCell};
+use crate::error::{OsError, OsResult};
+use crate::task::OsTaskFn;
+use crate::types::{OsOpt, OsStkElement};
+
+/// CLINT base address (SiFive FE310 layout)
+pub const CFG_CLINT_BASE: usize = 0x0200_0000;
+
+const CLINT_MSIP: *mut u32 = CFG_CLINT_BASE as *mut u32;
+const CLINT_MTIMECMP: *mut u32 = (CFG_CLINT_BASE + 0x4000) as *mut u32;
+const CLINT_MTIME: *const u32 = (CFG_CLINT_BASE + 0xBFF8) as *const u32;
+
+/// `mie`/`mip` bits
+const MIE_MSIE: usize = 1 << 3;
+const MIE_MTIE: usize = 1 << 7;
+
+/// `mstatus` value for a new task: MPP = Machine, MPIE = 1
+const MSTATUS_TASK_INIT: u32 = 0x0000_1880;
+
+/// `mcause` values handled by the kernel
+const MCAUSE_INTERRUPT: u32 = 1 << 31;
+const MCAUSE_M_SOFT: u32 = MCAUSE_INTERRUPT | 3;
+const MCAUSE_M_TIMER: u32 = MCAUSE_INTERRUPT | 7;
+const MCAUSE_M_EXT: u32 = MCAUSE_INTERRUPT | 11;
+const MCAUSE_ECALL_M: u32 = 11;
+
+/// Size of the saved context in words (16-byte aligned)
+const CONTEXT_STACK_SIZE: usize = 32;
+
+/// Words of a switched-out task's frame
+pub const OS_CTX_FRAME_WORDS: usize = CONTEXT_STACK_SIZE;
+
+/// Words lost to aligning the stack top to 16 bytes
+pub const OS_STK_ISR_HEADROOM_WORDS: usize = 3;
+
+/// Alignment of the first frame in bytes (RISC-V psABI)
+pub const OS_STK_ALIGN: usize = 16;
+
+// ============ Interrupt stack ============
+
+/// Fill pattern of the interrupt stack
+const INTERRUPT_STACK_FILL: u64 = 0xDEADBEEF_DEADBEEF;
+
+/// Interrupt stack used while dispatching traps
+#[no_mangle]
+static INTERRUPT_STACK: SyncUnsafeCell<[u64; CFG_ISR_STK_SIZE / 8]> =
+    SyncUnsafeCell::new([INTERRUPT_STACK_FILL; CFG_ISR_STK_SIZE / 8]);
+
+/// Get interrupt stack usage in bytes as `(used, total)`
+pub fn os_cpu_int_stk_usage() -> (usize, usize) {
+    let base = INTERRUPT_STACK.get().cast::<u64>();
+    let free = (0..CFG_ISR_STK_SIZE / 8)
+        .take_while(|&i| unsafe { base.add(i).read_volatile() } == INTERRUPT_STACK_FILL)
+        .count();
+    (CFG_ISR_STK_SIZE - free * 8, CFG_ISR_STK_SIZE)
+}
+
+/// Check that the lowest word of the interrupt stack is untouched
+#[inline]
+pub fn os_cpu_int_stk_canary_ok() -> bool {
+    unsafe { INTERRUPT_STACK.get().cast::<u64>().read_volatile() == INTERRUPT_STACK_FILL }
+}
+
+// ============ Trap nesting ============
+
+/// Non-zero while a trap is being dispatched
+static TRAP_NESTING: AtomicU8 = AtomicU8::new(0);
+
+/// Check if the CPU is dispatching a trap
+///
+/// RISC-V has no IPSR, so the port tracks this itself.
+#[inline(always)]
+pub fn os_cpu_in_trap() -> bool {
+    TRAP_NESTING.load(Ordering::Relaxed) != 0
+}
+
+// ============ Tick ============
+
+/// `mtime` ticks per kernel tick
+static TICK_RELOAD: AtomicU32 = AtomicU32::new(0);
+
+fn mtime_read() -> u64 {
+    unsafe {
+        loop {
+            let hi = core::ptr::read_volatile(CLINT_MTIME.add(1));
+            let lo = core::ptr::read_volatile(CLINT_MTIME);
+            if hi == core::ptr::read_volatile(CLINT_MTIME.add(1)) {
+                return ((hi as u64) << 32) | lo as u64;
+            }
+        }
+    }
+}
+
+fn mtimecmp_write(value: u64) {
+    unsafe {
+        // Write high word to max first so no spurious match occurs
+        core::ptr::write_volatile(CLINT_MTIMECMP.add(1), u32::MAX);
+        core::ptr::write_volatile(CLINT_MTIMECMP, value as u32);
+        core::ptr::write_volatile(CLINT_MTIMECMP.add(1), (value >> 32) as u32);
+    }
+}
+
+fn mtimecmp_read() -> u64 {
+    unsafe {
+        let lo = core::ptr::read_volatile(CLINT_MTIMECMP);
+        let hi = core::ptr::read_volatile(CLINT_MTIMECMP.add(1));
+        ((hi as u64) << 32) | lo as u64
+    }
+}
+
+/// Initialize the machine timer for system tick generation
+///
+/// # Arguments
+/// * `cnts` - `mtime` increments per tick (the clock passed to
+///   `os_start_with_clock` is the `mtime` frequency, 32768 Hz on FE310)
+pub fn os_cpu_systick_init(cnts: u32) -> OsResult<()> {
+    if cnts == 0 {
+        return Err(OsError::TickReloadInvalid);
+    }
+
+    TICK_RELOAD.store(cnts, Ordering::Relaxed);
+    mtimecmp_write(mtime_read() + cnts as u64);
+
+    unsafe { asm!("csrs mie, {0}", in(reg) MIE_MTIE) };
+
+    Ok(())
+}
+
+/// Change the tick period to `cnts` `mtime` increments
+///
+/// The compare value already programmed ends the period in progress; the
+/// trap handler programs the next period with the new value, even when
+/// the timer interrupt is already pending, so this always returns
+/// `Ok(false)`.
+///
+/// # Returns
+/// * `Err(OsError::TickReloadInvalid)` - `cnts` is zero
+pub fn os_cpu_systick_reload_set(cnts: u32) -> OsResult<bool> {
+    if cnts == 0 {
+        return Err(OsError::TickReloadInvalid);
+    }
+
+    TICK_RELOAD.store(cnts, Ordering::Relaxed);
+    Ok(false)
+}
+
+/// Stop the machine timer interrupt
+pub fn os_cpu_systick_stop() {
+    unsafe { asm!("csrc mie, {0}", in(reg) MIE_MTIE) };
+}
+
+/// Nothing to configure: the port targets RV32IMAC, without an FPU
+pub fn os_cpu_fpu_init() {}
+
+/// Nothing to enable: `mcycle` always counts in machine mode
+pub fn os_cpu_ts_init() {}
+
+/// Read the low word of `mcycle`
+#[inline(always)]
+pub fn os_cpu_ts_get() -> u32 {
+    let cycles: u32;
+    unsafe { asm!("csrr {0}, mcycle", out(reg) cycles) };
+    cycles
+}
+
+/// `mcycle` runs at the core clock
+pub fn os_cpu_ts_freq_hz() -> Option<u32> {
+    None
+}
+
+// ============ External interrupts ============
+
+/// Handler for machine external interrupts (PLIC claim/complete etc.)
+static EXT_IRQ_HANDLER: CsCell<Option<fn()>> = CsCell::new(None);
+
+/// Register the machine external interrupt handler
+///
+/// The handler runs in trap context; it may call `os_tick_handler` or post
+/// to kernel objects.
+pub fn os_cpu_ext_irq_handler_set(handler: fn()) {
+    crate::critical::critical_section(|cs| {
+        *EXT_IRQ_HANDLER.get(cs) = Some(handler);
+    });
+    unsafe { asm!("csrs mie, {0}", in(reg) 1usize << 11) };
+}
+
+// ============ Context switch ============
+
+/// Start the highest priority ready task
+///
+/// `CPU_STATE.tcb_cur` must already point at the first task.
+pub unsafe fn os_start_high_rdy() {
+    unsafe {
+        asm!("csrci mstatus, 8");
+        asm!("csrw mtvec, {0}", in(reg) os_cpu_trap_entry as usize);
+        asm!("csrs mie, {0}", in(reg) MIE_MSIE);
+        os_cpu_trap_restore();
+    }
+}
+
+/// Trigger context switch from task level
+///
+/// Only called through `kernel::ctx_sw_request`; the switch picks its task.
+#[inline(always)]
+pub fn os_ctx_sw() {
+    unsafe { core::ptr::write_volatile(CLINT_MSIP, 1) };
+}
+
+/// Trigger context switch from interrupt level
+///
+/// Only called through `kernel::ctx_sw_request`; the switch picks its task.
+#[inline(always)]
+pub fn os_int_ctx_sw() {
+    unsafe { core::ptr::write_volatile(CLINT_MSIP, 1) };
+}
+
+/// Context structure stored on stack
+#[repr(C, align(16))]
+struct UcStk {
+    mepc: u32,
+    ra: u32,
+    t0: u32,
+    t1: u32,
+    t2: u32,
+    s0: u32,
+    s1: u32,
+    a: [u32; 8],
+    s: [u32; 10],
+    t3: u32,
+    t4: u32,
+    t5: u32,
+    t6: u32,
+    mstatus: u32,
+    _pad: [u32; 2],
+}
+
+/// Initialize task stack
+pub unsafe fn os_task_stk_init(
+    task_fn: OsTaskFn,
+    arg: *mut (),
+    stk_base: *mut OsStkElement,
+    stk_size: usize,
+    _opt: OsOpt,
+) -> *mut OsStkElement {
+    unsafe {
+        let stk_aligned = crate::task::stk_top_aligned(stk_base, stk_size, OS_STK_ALIGN);
+
+        let frame_ptr = stk_aligned.sub(CONTEXT_STACK_SIZE) as *mut UcStk;
+
+        let mut a = [0u32; 8];
+        a[0] = arg as u32;
+
+        (*frame_ptr) = UcStk {
+            mepc: task_fn as usize as u32,
+            ra: os_task_return as *const () as u32,
+            t0: 0x05050505,
+            t1: 0x06060606,
+            t2: 0x07070707,
+            s0: 0x08080808,
+            s1: 0x09090909,
+            a,
+            s: [0x12121212; 10],
+            t3: 0x28282828,
+            t4: 0x29292929,
+            t5: 0x30303030,
+            t6: 0x31313131,
+            mstatus: MSTATUS_TASK_INIT,
+            _pad: [0; 2],
+        };
+
+        frame_ptr as *mut OsStkElement
+    }
+}
+
+/// Switch the current TCB to the highest ready one
+unsafe fn os_cpu_switch_tcb() {
+    unsafe {
+        let cpu = crate::kernel::CPU_STATE.get_unchecked();
+        crate::kernel::high_rdy_resolve(cpu);
+        let cur = cpu.tcb_cur;
+        let high = cpu.tcb_high_rdy;
+
+        if crate::config::CFG_TASK_STK_CHK_EN && !cur.is_null() && !crate::task::stk_canary_ok(&*cur) {
+            crate::kernel::hooks::fault(OsError::StkOvf);
+        }
+
+        crate::kernel::hooks::task_switch(
+            core::ptr::NonNull::new(cur),
+            core::ptr::NonNull::new(high),
+        );
+
+        #[cfg(feature = "trace")]
+        if !cur.is_null() {
+            crate::core::os_trace!(TaskSwitchOut {
+                id: crate::core::trace::task_id(cur),
+                prio: cpu.prio_cur,
+            });
+        }
+        crate::core::os_trace!(TaskSwitchIn {
+            id: crate::core::trace::task_id(high),
+            prio: cpu.prio_high_rdy,
+        });
+
+        #[cfg(feature = "rr-precise")]
+        crate::sched::slice_switch(cur);
+        #[cfg(feature = "budget")]
+        crate::task::budget::budget_switch(cur, high);
+
+        if !high.is_null() {
+            cpu.tcb_cur = high;
+            cpu.prio_cur = cpu.prio_high_rdy;
+        }
+    }
+}
+
+/// Rust side of the trap handler, runs on the interrupt stack
+///
+/// `frame` is the context saved on the interrupted task's stack.
+#[no_mangle]
+unsafe extern "C" fn os_cpu_trap_dispatch(mcause: u32, frame: *mut u32) {
+    TRAP_NESTING.fetch_add(1, Ordering::Relaxed);
+
+    match mcause {
+        MCAUSE_M_SOFT => unsafe {
+            core::ptr::write_volatile(CLINT_MSIP, 0);
+            os_cpu_switch_tcb();
+        },
+        MCAUSE_M_TIMER => {
+            let reload = TICK_RELOAD.load(Ordering::Relaxed) as u64;
+            mtimecmp_write(mtimecmp_read() + reload);
+            #[cfg(not(feature = "no-systick"))]
+            crate::time::os_tick_handler();
+        }
+        MCAUSE_M_EXT => {
+            if let Some(handler) = unsafe { *EXT_IRQ_HANDLER.get_unchecked() } {
+                handler();
+            }
+        }
+        MCAUSE_ECALL_M => unsafe {
+            // Voluntary yield: resume after the ecall instruction
+            *frame = (*frame).wrapping_add(4);
+            os_cpu_switch_tcb();
+        },
+        _ => {
+            crate::kernel::hooks::fault(OsError::FatalReturn);
+            loop {
+                unsafe { asm!("wfi") };
+            }
+        }
+    }
+
+    TRAP_NESTING.fetch_sub(1, Ordering::Relaxed);
+}
+
+extern "C" {
+    fn os_cpu_trap_entry();
+    fn os_cpu_trap_restore() -> !;
+}
+
+// Trap entry and context restore.
+//
+// Frame layout (word offsets): 0 mepc, 1 ra, 2-4 t0-t2, 5-6 s0-s1,
+// 7-14 a0-a7, 15-24 s2-s11, 25-28 t3-t6, 29 mstatus.
+global_asm!(
+    ".section .text.os_cpu_trap_entry",
+    ".global os_cpu_trap_entry",
+    ".align 4",
+    "os_cpu_trap_entry:",
+    "addi sp, sp, -128",
+    "sw ra, 4(sp)",
+    "sw t0, 8(sp)",
+    "sw t1, 12(sp)",
+    "sw t2, 16(sp)",
+    "sw s0, 20(sp)",
+    "sw s1, 24(sp)",
+    "sw a0, 28(sp)",
+    "sw a1, 32(sp)",
+    "sw a2, 36(sp)",
+    "sw a3, 40(sp)",
+    "sw a4, 44(sp)",
+    "sw a5, 48(sp)",
+    "sw a6, 52(sp)",
+    "sw a7, 56(sp)",
+    "sw s2, 60(sp)",
+    "sw s3, 64(sp)",
+    "sw s4, 68(sp)",
+    "sw s5, 72(sp)",
+    "sw s6, 76(sp)",
+    "sw s7, 80(sp)",
+    "sw s8, 84(sp)",
+    "sw s9, 88(sp)",
+    "sw s10, 92(sp)",
+    "sw s11, 96(sp)",
+    "sw t3, 100(sp)",
+    "sw t4, 104(sp)",
+    "sw t5, 108(sp)",
+    "sw t6, 112(sp)",
+    "csrr t0, mepc",
+    "sw t0, 0(sp)",
+    "csrr t0, mstatus",
+    "sw t0, 116(sp)",
+
+    // tcb_cur->stk_ptr = sp
+    "la t0, {cpu_state}",
+    "lw t1, 0(t0)",
+    "sw sp, 0(t1)",
+
+    // Dispatch on the interrupt stack
+    "mv a1, sp",
+    "la sp, {isr_stk}",
+    "li t0, {isr_stk_size}",
+    "add sp, sp, t0",
+    "csrr a0, mcause",
+    "call os_cpu_trap_dispatch",
+
+    ".global os_cpu_trap_restore",
+    "os_cpu_trap_restore:",
+    // sp = tcb_cur->stk_ptr
+    "la t0, {cpu_state}",
+    "lw t1, 0(t0)",
+    "lw sp, 0(t1)",
+
+    "lw t0, 0(sp)",
+    "csrw mepc, t0",
+    "lw t0, 116(sp)",
+    "csrw mstatus, t0",
+    "lw ra, 4(sp)",
+    "lw t0, 8(sp)",
+    "lw t1, 12(sp)",
+    "lw t2, 16(sp)",
+    "lw s0, 20(sp)",
+    "lw s1, 24(sp)",
+    "lw a0, 28(sp)",
+    "lw a1, 32(sp)",
+    "lw a2, 36(sp)",
+    "lw a3, 40(sp)",
+    "lw a4, 44(sp)",
+    "lw a5, 48(sp)",
+    "lw a6, 52(sp)",
+    "lw a7, 56(sp)",
+    "lw s2, 60(sp)",
+    "lw s3, 64(sp)",
+    "lw s4, 68(sp)",
+    "lw s5, 72(sp)",
+    "lw s6, 76(sp)",
+    "lw s7, 80(sp)",
+    "lw s8, 84(sp)",
+    "lw s9, 88(sp)",
+    "lw s10, 92(sp)",
+    "lw s11, 96(sp)",
+    "lw t3, 100(sp)",
+    "lw t4, 104(sp)",
+    "lw t5, 108(sp)",
+    "lw t6, 112(sp)",
+    "addi sp, sp, 128",
+    "mret",
+
+    cpu_state = sym crate::kernel::CPU_STATE,
+    isr_stk = sym INTERRUPT_STACK,
+    isr_stk_size = const CFG_ISR_STK_SIZE,
+);
+
+/// Task return handler
+#[no_mangle]
+fn os_task_return() -> ! {
+    loop {
+        unsafe { asm!("wfi") };
+    }
+}
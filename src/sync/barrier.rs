@@ -0,0 +1,339 @@
+//! Task barrier
+//!
+//! A barrier for `n` tasks blocks each caller of `wait` until the `n`-th
+//! arrives. The last task releases the others in priority order with a
+//! single reschedule, is told it was the leader, and the barrier starts
+//! its next generation with no tasks arrived.
+//!
+//! A waiter whose pend times out is withdrawn: it no longer counts as
+//! arrived, so the remaining waiters keep waiting for `n` arrivals and a
+//! late task can still complete the generation. `reset` releases all
+//! current waiters with `OsError::PendAbort` and starts a new generation.
+
+use core::ptr::NonNull;
+
+use crate::critical::{critical_section, is_isr_context};
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::sched;
+use crate::sem::PendList;
+use crate::sync::{pend_block, pend_wait, pend_wake, PendObject, Pended};
+use crate::task::OsTcb;
+use crate::types::{obj_name, obj_name_str, ObjName, OsObjType, OsOpt, OsPendOn, OsPendStatus, OsTick, Timeout, opt};
+
+/// Barrier for a fixed number of tasks
+#[repr(C)]
+pub struct OsBarrier {
+    /// Object type marker
+    pub(crate) obj_type: OsObjType,
+    /// Tasks waiting for the rest to arrive
+    pend_list: PendList,
+    /// Tasks needed to release the barrier
+    count: usize,
+    /// Tasks arrived in the current generation
+    arrived: usize,
+    /// Completed generations
+    generation: u32,
+    /// Name for debugging
+    name: ObjName,
+}
+
+impl OsBarrier {
+    /// Create a barrier for `count` tasks
+    pub const fn new(count: usize) -> Self {
+        OsBarrier {
+            obj_type: OsObjType::Barrier,
+            pend_list: PendList::new(),
+            count,
+            arrived: 0,
+            generation: 0,
+            name: obj_name(""),
+        }
+    }
+
+    /// Initialize/create the barrier
+    pub fn create(&mut self, count: usize, name: &'static str) -> OsResult<()> {
+        if is_isr_context() {
+            return Err(OsError::CreateIsr);
+        }
+
+        if kernel::KERNEL.is_safety_critical() {
+            return Err(OsError::IllegalCreateRunTime);
+        }
+
+        if count == 0 {
+            return Err(OsError::BarrierCountInvalid);
+        }
+
+        critical_section(|cs| {
+            kernel::registry::register_cs(cs, OsObjType::Barrier, name, self as *const _ as *const ());
+            self.obj_type = OsObjType::Barrier;
+            self.pend_list.init();
+            self.count = count;
+            self.arrived = 0;
+            self.generation = 0;
+            self.name = obj_name(name);
+            Ok(())
+        })
+    }
+
+    /// Wait for all tasks to arrive
+    ///
+    /// # Arguments
+    /// * `timeout` - Maximum ticks to wait (0 = forever)
+    /// * `opt` - Pend options
+    ///
+    /// # Returns
+    /// * `Ok(true)` - This task arrived last and released the others
+    /// * `Ok(false)` - Released by the last task
+    /// * `Err(OsError::Timeout)` - Timeout expired, arrival withdrawn
+    /// * `Err(OsError::PendAbort)` - Released by `reset`
+    /// * `Err(OsError::PendWouldBlock)` - Non-blocking and not the last task
+    /// * `Err(OsError::OsNotRunning)` - Called before `os_start`
+    /// * `Err(OsError::OptInvalid)` - `pend_opt` has a non-`PEND_*` bit
+    pub fn wait(&mut self, timeout: OsTick, pend_opt: OsOpt) -> OsResult<bool> {
+        if is_isr_context() {
+            return Err(OsError::PendIsr);
+        }
+
+        if !kernel::KERNEL.is_running() {
+            return Err(OsError::OsNotRunning);
+        }
+
+        opt::check(pend_opt, opt::PEND_MASK)?;
+
+        if self.obj_type != OsObjType::Barrier {
+            return Err(OsError::ObjType);
+        }
+
+        let pended = critical_section(|_cs| unsafe {
+            pend_block(self, OsPendOn::Barrier, timeout, pend_opt, |barrier, _| {
+                if !barrier.completes() {
+                    return None;
+                }
+                if barrier.release(|tcb_ptr| pend_wake(tcb_ptr, OsPendStatus::Ok)) {
+                    sched::os_sched();
+                }
+                Some(Ok(()))
+            })
+        })?;
+        match pended {
+            Pended::Acquired(()) => Ok(true),
+            Pended::Blocked(cur) => unsafe { pend_wait(cur, |result| result.map(|()| false)) },
+        }
+    }
+
+    /// Release all waiters with `OsError::PendAbort` and start over
+    ///
+    /// Returns the number of tasks released.
+    pub fn reset(&mut self) -> OsResult<usize> {
+        if self.obj_type != OsObjType::Barrier {
+            return Err(OsError::ObjType);
+        }
+
+        critical_section(|_cs| {
+            let waiters = self.pend_list.len();
+            if self.release(|tcb_ptr| unsafe { pend_wake(tcb_ptr, OsPendStatus::Abort) }) {
+                sched::os_sched();
+            }
+            Ok(waiters)
+        })
+    }
+
+    /// Whether the next arrival releases the barrier
+    fn completes(&self) -> bool {
+        self.arrived + 1 >= self.count
+    }
+
+    /// End the current generation
+    ///
+    /// Waiters are passed to `wake` highest priority first. Returns whether
+    /// there were any.
+    fn release(&mut self, mut wake: impl FnMut(NonNull<OsTcb>)) -> bool {
+        let woke = !self.pend_list.is_empty();
+        while let Some(tcb_ptr) = self.pend_list.head() {
+            self.pend_list.remove(tcb_ptr);
+            wake(tcb_ptr);
+        }
+        self.arrived = 0;
+        self.generation = self.generation.wrapping_add(1);
+        woke
+    }
+
+    /// Re-sort a waiter whose priority changed
+    #[cfg(any(feature = "mutex", feature = "rwlock"))]
+    pub(crate) fn pend_prio_change(&mut self, tcb: NonNull<OsTcb>) {
+        self.pend_list.resort(tcb);
+    }
+
+    /// Number of tasks that release the barrier
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Name given at creation
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        obj_name_str(self.name)
+    }
+
+    /// Number of tasks waiting in the current generation
+    #[inline]
+    pub fn waiters(&self) -> usize {
+        self.pend_list.len()
+    }
+
+    /// Number of completed generations, wrapping
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl PendObject for OsBarrier {
+    fn pend_list(&mut self, _on: OsPendOn) -> &mut PendList {
+        &mut self.pend_list
+    }
+
+    /// Count the task as arrived
+    unsafe fn pend_blocking(&mut self, _tcb: NonNull<OsTcb>) -> OsResult<()> {
+        self.arrived += 1;
+        Ok(())
+    }
+
+    /// Withdraw a waiter whose pend timed out or that was deleted
+    unsafe fn pend_unlink(&mut self, tcb: NonNull<OsTcb>) {
+        self.pend_list.remove(tcb);
+        self.arrived -= 1;
+    }
+}
+
+// ============ Safe Wrapper ============
+
+use core::cell::UnsafeCell;
+
+pub struct Barrier {
+    inner: UnsafeCell<OsBarrier>,
+}
+
+// SAFETY: the kernel object is only mutated inside a critical section on a
+// single core, so any task or ISR may share it; a pending task borrows the
+// wrapper, so it cannot move while waited on.
+unsafe impl Sync for Barrier {}
+unsafe impl Send for Barrier {}
+
+impl Barrier {
+    pub const fn new(count: usize) -> Self {
+        Barrier {
+            inner: UnsafeCell::new(OsBarrier::new(count)),
+        }
+    }
+
+    pub fn create(&self, count: usize, name: &'static str) -> OsResult<()> {
+        unsafe { (*self.inner.get()).create(count, name) }
+    }
+
+    /// Wait for all tasks; `Ok(true)` for the task that arrived last
+    pub fn wait(&self, timeout: impl Into<Timeout>) -> OsResult<bool> {
+        let (ticks, opt) = timeout.into().to_raw();
+        unsafe { (*self.inner.get()).wait(ticks, opt) }
+    }
+
+    /// Release all waiters with `OsError::PendAbort`
+    pub fn reset(&self) -> OsResult<usize> {
+        unsafe { (*self.inner.get()).reset() }
+    }
+
+    #[inline]
+    pub fn count(&self) -> usize {
+        unsafe { (*self.inner.get()).count() }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        unsafe { (*self.inner.get()).name() }
+    }
+
+    /// Number of tasks waiting in the current generation
+    #[inline]
+    pub fn waiters(&self) -> usize {
+        unsafe { (*self.inner.get()).waiters() }
+    }
+
+    /// Number of completed generations, wrapping
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        unsafe { (*self.inner.get()).generation() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OsPrio;
+
+    fn tcb(prio: OsPrio) -> OsTcb {
+        let mut tcb = OsTcb::new();
+        tcb.prio = prio;
+        tcb
+    }
+
+    /// Arrive and wait the way `pend_block` does, without the scheduler
+    fn arrive(barrier: &mut OsBarrier, tcb: NonNull<OsTcb>) {
+        unsafe { barrier.pend_blocking(tcb) }.unwrap();
+        barrier.pend_list.insert_by_prio(tcb);
+    }
+
+    fn release(barrier: &mut OsBarrier) -> [Option<NonNull<OsTcb>>; 4] {
+        let mut woken = [None; 4];
+        let mut n = 0;
+        barrier.release(|t| {
+            woken[n] = Some(t);
+            n += 1;
+        });
+        woken
+    }
+
+    #[test]
+    fn test_reuse_across_generations() {
+        let mut barrier = OsBarrier::new(3);
+        let (mut a, mut b) = (tcb(7), tcb(2));
+        let (pa, pb) = (NonNull::from(&mut a), NonNull::from(&mut b));
+
+        for gen in 1..=3 {
+            arrive(&mut barrier, pa);
+            arrive(&mut barrier, pb);
+            assert_eq!(barrier.waiters(), 2);
+
+            // The third arrival releases both, highest priority first
+            assert!(barrier.completes());
+            assert_eq!(release(&mut barrier), [Some(pb), Some(pa), None, None]);
+            assert_eq!(barrier.generation(), gen);
+            assert_eq!(barrier.arrived, 0);
+            assert_eq!(barrier.waiters(), 0);
+        }
+    }
+
+    #[test]
+    fn test_timeout_withdraws_arrival() {
+        let mut barrier = OsBarrier::new(3);
+        let (mut a, mut b, mut c) = (tcb(4), tcb(5), tcb(6));
+        let (pa, pb, pc) = (NonNull::from(&mut a), NonNull::from(&mut b), NonNull::from(&mut c));
+
+        arrive(&mut barrier, pa);
+        arrive(&mut barrier, pb);
+
+        // B gives up while A is still blocked
+        unsafe { barrier.pend_unlink(pb) };
+        assert_eq!(barrier.arrived, 1);
+        assert_eq!(barrier.waiters(), 1);
+
+        // C takes B's place, then a third task completes the generation
+        assert!(!barrier.completes());
+        arrive(&mut barrier, pc);
+        assert!(barrier.completes());
+        assert_eq!(release(&mut barrier), [Some(pa), Some(pc), None, None]);
+        assert_eq!(barrier.generation(), 1);
+    }
+}
@@ -1,9 +1,308 @@
 //! Synchronization primitives
 //!
-//! Contains semaphores and mutexes.
+//! Contains semaphores, mutexes, readers-writer locks, barriers and the
+//! ISR byte ring, and [`os_post`] to post to an object by tag.
+
+use core::ptr::NonNull;
+
+use crate::task::OsTcb;
+#[cfg(any(feature = "sem", feature = "mutex", feature = "rwlock", feature = "barrier"))]
+use crate::types::OsPendOn;
+#[cfg(any(feature = "mutex", feature = "rwlock"))]
+use crate::types::{obj_name, ObjName, OsPrio};
+use crate::types::{OsPendStatus, OsTaskState};
+
+#[cfg(any(feature = "sem", feature = "mutex", feature = "rwlock", feature = "barrier"))]
+mod pend;
+#[cfg(any(feature = "sem", feature = "mutex", feature = "rwlock", feature = "barrier"))]
+pub(crate) use pend::{pend_block, pend_wait, PendObject, Pended};
 
 #[cfg(feature = "sem")]
 pub mod sem;
 
 #[cfg(feature = "mutex")]
 pub mod mutex;
+
+#[cfg(feature = "rwlock")]
+pub mod rwlock;
+
+#[cfg(feature = "barrier")]
+pub mod barrier;
+
+#[cfg(feature = "ring")]
+pub mod ring;
+
+#[cfg(feature = "obj-stats")]
+pub mod stats;
+
+mod post;
+pub use post::{os_post, ObjRef, PostValue};
+
+#[cfg(feature = "obj-pool")]
+mod pool;
+#[cfg(feature = "obj-pool")]
+pub(crate) use pool::{ObjHandle, ObjPool};
+
+/// Take a pending task off its object's wait list
+///
+/// Called with the critical section held. Waiters of the task semaphore
+/// have no list and `false` is returned.
+pub(crate) unsafe fn pend_unlink(tcb: NonNull<OsTcb>) -> bool {
+    let tcb_ref = unsafe { tcb.as_ref() };
+    match tcb_ref.pend_on() {
+        #[cfg(feature = "sem")]
+        OsPendOn::Semaphore => unsafe {
+            (*(tcb_ref.pend_obj_ptr as *mut sem::OsSem)).pend_unlink(tcb);
+            true
+        },
+        #[cfg(feature = "mutex")]
+        OsPendOn::Mutex => unsafe {
+            (*(tcb_ref.pend_obj_ptr as *mut mutex::OsMutex)).pend_unlink(tcb);
+            true
+        },
+        #[cfg(feature = "rwlock")]
+        OsPendOn::RwLockRead | OsPendOn::RwLockWrite => unsafe {
+            (*(tcb_ref.pend_obj_ptr as *mut rwlock::OsRwLock)).pend_unlink(tcb);
+            true
+        },
+        #[cfg(feature = "barrier")]
+        OsPendOn::Barrier => unsafe {
+            (*(tcb_ref.pend_obj_ptr as *mut barrier::OsBarrier)).pend_unlink(tcb);
+            true
+        },
+        _ => false,
+    }
+}
+
+/// Wait list of the object a pending task waits on
+///
+/// `None` for the task semaphore, which has no list.
+///
+/// # Safety
+/// Called with the critical section held, `tcb` pending.
+#[cfg(all(feature = "self-check", any(feature = "sem", feature = "mutex", feature = "rwlock", feature = "barrier")))]
+pub(crate) unsafe fn pend_list_of(tcb: NonNull<OsTcb>) -> Option<&'static mut sem::PendList> {
+    let tcb_ref = unsafe { tcb.as_ref() };
+    let on = tcb_ref.pend_on();
+    match on {
+        #[cfg(feature = "sem")]
+        OsPendOn::Semaphore => Some(unsafe { (*(tcb_ref.pend_obj_ptr as *mut sem::OsSem)).pend_list(on) }),
+        #[cfg(feature = "mutex")]
+        OsPendOn::Mutex => Some(unsafe { (*(tcb_ref.pend_obj_ptr as *mut mutex::OsMutex)).pend_list(on) }),
+        #[cfg(feature = "rwlock")]
+        OsPendOn::RwLockRead | OsPendOn::RwLockWrite => {
+            Some(unsafe { (*(tcb_ref.pend_obj_ptr as *mut rwlock::OsRwLock)).pend_list(on) })
+        }
+        #[cfg(feature = "barrier")]
+        OsPendOn::Barrier => Some(unsafe { (*(tcb_ref.pend_obj_ptr as *mut barrier::OsBarrier)).pend_list(on) }),
+        _ => None,
+    }
+}
+
+/// Take a task being deleted off its object's wait list and the tick queue
+///
+/// Called from task delete with the critical section held.
+pub(crate) unsafe fn pend_del(tcb: NonNull<OsTcb>) {
+    let tcb_ref = unsafe { tcb.as_ref() };
+    let timed = matches!(tcb_ref.state(), OsTaskState::PendTimeout | OsTaskState::PendTimeoutSuspended);
+    if tcb_ref.is_pending() && unsafe { pend_unlink(tcb) } && timed {
+        unsafe { crate::kernel::tick_remove(tcb) };
+    }
+    unsafe { late_leave(tcb) };
+}
+
+/// End a pend for a deletion request: unlink the waiter and ready it with
+/// `DelReq`
+///
+/// Called with the critical section held, `tcb` pending. A suspended
+/// waiter stays suspended.
+pub(crate) unsafe fn pend_del_req(tcb: NonNull<OsTcb>) {
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+    if matches!(tcb_ref.state(), OsTaskState::PendTimeout | OsTaskState::PendTimeoutSuspended) {
+        unsafe { crate::kernel::tick_remove(tcb) };
+    }
+    unsafe { pend_unlink(tcb) };
+    if pend_finish(tcb_ref, OsPendStatus::DelReq) {
+        unsafe { crate::sched::os_rdy_list_insert(tcb) };
+    }
+}
+
+/// End a timed-out pend: unlink the waiter and ready it with `Timeout`
+///
+/// Called from the tick handler with the critical section held, the task
+/// already off the tick queue. A suspended waiter stays suspended. A
+/// semaphore waiter stays within reach of a post until it runs, see
+/// [`pend_resume`].
+pub(crate) unsafe fn pend_expire(tcb: NonNull<OsTcb>) {
+    #[cfg(feature = "sem")]
+    let sem = {
+        let tcb_ref = unsafe { tcb.as_ref() };
+        (tcb_ref.pend_on() == OsPendOn::Semaphore).then_some(tcb_ref.pend_obj_ptr as *mut sem::OsSem)
+    };
+
+    unsafe { pend_unlink(tcb) };
+    if pend_finish(unsafe { &mut *tcb.as_ptr() }, OsPendStatus::Timeout) {
+        unsafe { crate::sched::os_rdy_list_insert(tcb) };
+    }
+
+    #[cfg(feature = "sem")]
+    if let Some(sem) = sem {
+        unsafe { (*sem).late_insert(tcb) };
+    }
+}
+
+/// Result of a waiter's pend, read once it runs again
+///
+/// A post that finds no waiter but a semaphore waiter that timed out and
+/// has not run yet goes to that waiter: it was still blocked as far as it
+/// can tell, so its pend returns `Ok` and the count is not raised. Reading
+/// the result closes that window; after this the timeout stands and posts
+/// count up.
+///
+/// # Safety
+/// Called with the critical section held, by the task `tcb` once it runs
+/// again (see `pend_wait`).
+#[cfg(any(feature = "sem", feature = "mutex", feature = "rwlock", feature = "barrier"))]
+pub(crate) unsafe fn pend_resume(tcb: NonNull<OsTcb>) -> crate::error::OsResult<()> {
+    unsafe { late_leave(tcb) };
+    unsafe { tcb.as_ref() }.pend_result()
+}
+
+/// Take a task off the late list of the semaphore it timed out on, if any
+///
+/// A late waiter is ready with `pend_obj_ptr` still naming the semaphore;
+/// no other object keeps it after the pend ends.
+unsafe fn late_leave(tcb: NonNull<OsTcb>) {
+    #[cfg(feature = "sem")]
+    {
+        let tcb_ref = unsafe { tcb.as_ref() };
+        if tcb_ref.pend_on() == OsPendOn::Nothing && !tcb_ref.pend_obj_ptr.is_null() {
+            unsafe { (*(tcb_ref.pend_obj_ptr as *mut sem::OsSem)).late_remove(tcb) };
+        }
+    }
+    #[cfg(not(feature = "sem"))]
+    let _ = tcb;
+}
+
+/// End a pend with `status`; returns whether the task must be made ready
+///
+/// Takes the task off the tick queue. A suspended waiter becomes plain
+/// suspended and is readied by `os_task_resume`. The caller has already
+/// removed it from the object's pend list.
+#[cfg(any(feature = "sem", feature = "mutex", feature = "rwlock", feature = "barrier"))]
+pub(crate) unsafe fn pend_end(tcb: NonNull<OsTcb>, status: OsPendStatus) -> bool {
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+    if matches!(tcb_ref.task_state, OsTaskState::PendTimeout | OsTaskState::PendTimeoutSuspended) {
+        unsafe { crate::kernel::tick_remove(tcb) };
+    }
+    pend_finish(tcb_ref, status)
+}
+
+fn pend_finish(tcb: &mut OsTcb, status: OsPendStatus) -> bool {
+    let suspended = matches!(tcb.task_state, OsTaskState::PendSuspended | OsTaskState::PendTimeoutSuspended);
+    tcb.clear_pending(status);
+    if suspended {
+        tcb.task_state = OsTaskState::Suspended;
+    }
+    !suspended
+}
+
+/// Change the priority of a task in any state
+///
+/// A ready task moves ready lists. A pending task is re-sorted in its
+/// object's wait list, and the change passes on to the owner of the mutex
+/// (or, for a boost, the write lock) it waits on, and so on down the chain.
+/// A delayed or suspended task only takes the new priority, which it is
+/// readied at.
+///
+/// # Safety
+/// Called with the critical section held.
+#[cfg(any(feature = "mutex", feature = "rwlock"))]
+pub(crate) unsafe fn prio_change(tcb: NonNull<OsTcb>, prio: OsPrio) {
+    let tcb_ref = unsafe { &mut *tcb.as_ptr() };
+    if tcb_ref.is_ready() {
+        unsafe { crate::sched::os_rdy_list_change_prio(tcb, prio) };
+        return;
+    }
+
+    let _old = core::mem::replace(&mut tcb_ref.prio, prio);
+    if !tcb_ref.is_pending() {
+        return;
+    }
+    match tcb_ref.pend_on() {
+        #[cfg(feature = "sem")]
+        OsPendOn::Semaphore => unsafe {
+            (*(tcb_ref.pend_obj_ptr as *mut sem::OsSem)).pend_prio_change(tcb)
+        },
+        #[cfg(feature = "mutex")]
+        OsPendOn::Mutex => unsafe {
+            (*(tcb_ref.pend_obj_ptr as *mut mutex::OsMutex)).pend_prio_change(tcb, _old)
+        },
+        #[cfg(feature = "rwlock")]
+        OsPendOn::RwLockRead | OsPendOn::RwLockWrite => unsafe {
+            (*(tcb_ref.pend_obj_ptr as *mut rwlock::OsRwLock)).pend_prio_change(tcb)
+        },
+        #[cfg(feature = "barrier")]
+        OsPendOn::Barrier => unsafe {
+            (*(tcb_ref.pend_obj_ptr as *mut barrier::OsBarrier)).pend_prio_change(tcb)
+        },
+        _ => {}
+    }
+}
+
+/// Set a lock owner's priority for priority inheritance from `obj`
+///
+/// A `prio` back at the owner's base priority ends the boost. Records
+/// which lock the boost comes from in the TCB and traces the change.
+///
+/// # Safety
+/// Called with the critical section held.
+#[cfg(any(feature = "mutex", feature = "rwlock"))]
+pub(crate) unsafe fn prio_inherit(owner: NonNull<OsTcb>, prio: OsPrio, obj: *const (), name: ObjName) {
+    let from = unsafe { owner.as_ref() }.prio();
+    if prio != from {
+        unsafe { prio_change(owner, prio) };
+    }
+
+    let tcb_ref = unsafe { &mut *owner.as_ptr() };
+    if prio == tcb_ref.base_prio {
+        if !tcb_ref.boost_obj.is_null() {
+            crate::core::os_trace!(PrioDisinherit {
+                id: crate::core::trace::task_id(owner.as_ptr()),
+                from,
+                to: prio,
+                obj: tcb_ref.boost_obj as u32,
+            });
+        }
+        tcb_ref.boost_obj = core::ptr::null();
+        tcb_ref.boost_name = obj_name("");
+    } else if prio != from || tcb_ref.boost_obj != obj {
+        tcb_ref.boost_obj = obj;
+        tcb_ref.boost_name = name;
+        crate::core::os_trace!(PrioInherit {
+            id: crate::core::trace::task_id(owner.as_ptr()),
+            from,
+            to: prio,
+            obj: obj as u32,
+        });
+    }
+}
+
+/// Drop the calling lock owner back to its base priority on release
+///
+/// # Safety
+/// Called with the critical section held, `tcb` the running task.
+#[cfg(any(feature = "mutex", feature = "rwlock"))]
+pub(crate) unsafe fn prio_disinherit(tcb: NonNull<OsTcb>) {
+    let base = unsafe { tcb.as_ref() }.base_prio();
+    let obj = unsafe { tcb.as_ref() }.boost_obj;
+    unsafe { prio_inherit(tcb, base, obj, obj_name("")) };
+}
+
+/// Wake a waiter with `status` through [`pend_end`], readying it unless suspended
+#[cfg(any(feature = "mutex", feature = "rwlock", feature = "barrier"))]
+pub(crate) unsafe fn pend_wake(tcb: NonNull<OsTcb>, status: OsPendStatus) {
+    if unsafe { pend_end(tcb, status) } {
+        unsafe { crate::sched::os_rdy_list_insert(tcb) };
+    }
+}
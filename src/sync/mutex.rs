@@ -5,27 +5,44 @@
 
 use core::ptr::NonNull;
 
+#[cfg(feature = "obj-pool")]
+use crate::config::CFG_MUTEX_POOL_SIZE;
+#[cfg(feature = "obj-pool")]
+use crate::core::cs_cell::CsCell;
 use crate::critical::{critical_section, is_isr_context};
+#[cfg(feature = "obj-pool")]
+use crate::critical::CriticalSection;
 use crate::error::{OsError, OsResult};
 use crate::kernel;
 use crate::sched;
 use crate::sem::PendList;
+use crate::sync::{pend_block, pend_wait, pend_wake, prio_disinherit, prio_inherit, PendObject, Pended};
+#[cfg(feature = "obj-pool")]
+use crate::sync::{ObjHandle, ObjPool};
+#[cfg(feature = "obj-stats")]
+use crate::sync::stats::{MutexStats, OsObjStats};
 use crate::task::OsTcb;
-use crate::types::{OsNestingCtr, OsObjType, OsOpt, OsPendOn, OsPendStatus, OsPrio, OsTaskState, OsTick, opt};
+use crate::types::{obj_name, obj_name_str, ObjName, OsNestingCtr, OsObjType, OsOpt, OsPendOn, OsPendStatus, OsPrio, OsTick, PostOpt, Timeout, opt};
 
 /// Mutex with priority inheritance
+#[repr(C)]
 pub struct OsMutex {
     /// Object type marker
-    obj_type: OsObjType,
+    pub(crate) obj_type: OsObjType,
     /// List of tasks waiting on this mutex
     pend_list: PendList,
     /// Task that owns the mutex
     owner: Option<NonNull<OsTcb>>,
     /// Nesting counter
     nesting_ctr: OsNestingCtr,
+    /// Pend/post counters
+    #[cfg(feature = "obj-stats")]
+    stats: OsObjStats,
+    /// Tick the current owner acquired the mutex
+    #[cfg(feature = "obj-stats")]
+    held_since: OsTick,
     /// Name for debugging
-    #[cfg(feature = "defmt")]
-    name: &'static str,
+    name: ObjName,
 }
 
 impl OsMutex {
@@ -36,26 +53,35 @@ impl OsMutex {
             pend_list: PendList::new(),
             owner: None,
             nesting_ctr: 0,
-            #[cfg(feature = "defmt")]
-            name: "",
+            #[cfg(feature = "obj-stats")]
+            stats: OsObjStats::new(),
+            #[cfg(feature = "obj-stats")]
+            held_since: 0,
+            name: obj_name(""),
         }
     }
 
     /// Initialize the mutex
-    pub fn create(&mut self, _name: &'static str) -> OsResult<()> {
+    pub fn create(&mut self, name: &'static str) -> OsResult<()> {
         if is_isr_context() {
             return Err(OsError::CreateIsr);
         }
 
-        critical_section(|_cs| {
+        if kernel::KERNEL.is_safety_critical() {
+            return Err(OsError::IllegalCreateRunTime);
+        }
+
+        critical_section(|cs| {
+            kernel::registry::register_cs(cs, OsObjType::Mutex, name, self as *const _ as *const ());
             self.obj_type = OsObjType::Mutex;
             self.pend_list.init();
             self.owner = None;
             self.nesting_ctr = 0;
-            #[cfg(feature = "defmt")]
+            #[cfg(feature = "obj-stats")]
             {
-                self.name = _name;
+                self.stats = OsObjStats::new();
             }
+            self.name = obj_name(name);
             Ok(())
         })
     }
@@ -68,6 +94,13 @@ impl OsMutex {
     /// # Arguments
     /// * `timeout` - Maximum ticks to wait
     /// * `opt` - Pend options
+    ///
+    /// # Returns
+    /// * `Err(OsError::SchedLocked)` - Owned by another task and the
+    ///   scheduler is locked
+    /// * `Err(OsError::OsNotRunning)` - Called before `os_start`; only a
+    ///   task can own a mutex
+    /// * `Err(OsError::OptInvalid)` - `pend_opt` has a non-`PEND_*` bit
     pub fn pend(&mut self, timeout: OsTick, pend_opt: OsOpt) -> OsResult<()> {
         if is_isr_context() {
             return Err(OsError::PendIsr);
@@ -77,90 +110,66 @@ impl OsMutex {
             return Err(OsError::OsNotRunning);
         }
 
+        opt::check(pend_opt, opt::PEND_MASK)?;
+
         if self.obj_type != OsObjType::Mutex {
             return Err(OsError::ObjType);
         }
 
-        critical_section(|_cs| {
-            let cur_tcb_ptr = unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?;
-            
-            if self.owner.is_none() {
-                self.owner = Some(cur_tcb_ptr);
-                self.nesting_ctr = 1;
-                return Ok(());
-            }
-
-            // Check if current task already owns it
-            if self.owner == Some(cur_tcb_ptr) {
-                if self.nesting_ctr == OsNestingCtr::MAX {
-                    return Err(OsError::MutexOvf);
-                }
-                self.nesting_ctr += 1;
-                return Ok(());
-            }
+        crate::core::os_trace!(MutexPend { obj: self as *const _ as u32 });
 
-            // Mutex is owned by another task
-            if pend_opt & opt::PEND_NON_BLOCKING != 0 {
-                return Err(OsError::PendWouldBlock);
+        let pended = critical_section(|_cs| {
+            #[cfg(feature = "obj-stats")]
+            {
+                self.stats.pends = self.stats.pends.wrapping_add(1);
             }
 
-            if kernel::KERNEL.sched_lock_nesting() > 0 {
-                return Err(OsError::SchedLocked);
-            }
+            unsafe { pend_block(self, OsPendOn::Mutex, timeout, pend_opt, Self::try_acquire) }
+        })?;
+        match pended {
+            Pended::Acquired(()) => Ok(()),
+            Pended::Blocked(cur) => unsafe {
+                pend_wait(cur, |result| {
+                    #[cfg(feature = "obj-stats")]
+                    self.stats.woke(&result);
+
+                    result
+                })
+            },
+        }
+    }
 
-            // Priority inheritance
-            let cur_tcb = unsafe { cur_tcb_ptr.as_ref() };
-            let cur_prio = cur_tcb.prio;
-
-            if let Some(owner_ptr) = self.owner {
-                let owner = unsafe { &mut *owner_ptr.as_ptr() };
-                if cur_prio < owner.prio {
-                    if owner.task_state == OsTaskState::Ready {
-                        unsafe { sched::os_rdy_list_change_prio(owner_ptr, cur_prio) };
-                    } else {
-                        owner.prio = cur_prio;
-                    }
-                }
+    /// Take the mutex if free or already owned by `cur`; `None` to wait
+    fn try_acquire(&mut self, cur: NonNull<OsTcb>) -> Option<OsResult<()>> {
+        if self.owner.is_none() {
+            self.owner = Some(cur);
+            self.nesting_ctr = 1;
+            #[cfg(feature = "obj-stats")]
+            {
+                self.held_since = kernel::KERNEL.tick_get();
             }
+            return Some(Ok(()));
+        }
 
-            // Block current task
-            unsafe {
-                let cur_tcb = &mut *cur_tcb_ptr.as_ptr();
-
-                sched::os_rdy_list_remove(cur_tcb_ptr);
-
-                cur_tcb.pend_on = OsPendOn::Mutex;
-                cur_tcb.pend_status = OsPendStatus::Ok;
-                cur_tcb.pend_obj_ptr = self as *const _ as *const ();
-                cur_tcb.tick_remain = timeout;
-
-                if timeout > 0 {
-                    cur_tcb.task_state = OsTaskState::PendTimeout;
-                } else {
-                    cur_tcb.task_state = OsTaskState::Pend;
-                }
-
-                self.pend_list.insert_by_prio(cur_tcb_ptr);
+        // Check if current task already owns it
+        if self.owner == Some(cur) {
+            if self.nesting_ctr == OsNestingCtr::MAX {
+                return Some(Err(OsError::MutexOvf));
             }
+            self.nesting_ctr += 1;
+            return Some(Ok(()));
+        }
 
-            sched::os_sched();
-
-            unsafe {
-                let cur_tcb = cur_tcb_ptr.as_ref();
-                match cur_tcb.pend_status {
-                    OsPendStatus::Ok => Ok(()),
-                    OsPendStatus::Timeout => Err(OsError::Timeout),
-                    OsPendStatus::Abort => Err(OsError::PendAbort),
-                    OsPendStatus::Del => Err(OsError::ObjDel),
-                }
-            }
-        })
+        None
     }
 
     /// Release the mutex
     ///
     /// If the current task's priority was boosted due to priority inheritance,
-    /// it is restored to its base priority.
+    /// it is restored to its base priority. With `POST_LIFO` the mutex goes
+    /// to the most recent waiter of the highest waiting priority.
+    ///
+    /// `POST_ALL` is rejected with `OsError::OptInvalid`: a mutex has one owner.
     pub fn post(&mut self, post_opt: OsOpt) -> OsResult<()> {
         if is_isr_context() {
             return Err(OsError::AcceptIsr);
@@ -170,10 +179,14 @@ impl OsMutex {
             return Err(OsError::OsNotRunning);
         }
 
+        opt::check(post_opt, opt::POST_LIFO | opt::POST_NO_SCHED)?;
+
         if self.obj_type != OsObjType::Mutex {
             return Err(OsError::ObjType);
         }
 
+        crate::core::os_trace!(MutexPost { obj: self as *const _ as u32 });
+
         critical_section(|_cs| {
             let cur_tcb_ptr = unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?;
 
@@ -181,6 +194,11 @@ impl OsMutex {
                 return Err(OsError::MutexNotOwner);
             }
 
+            #[cfg(feature = "obj-stats")]
+            {
+                self.stats.posts = self.stats.posts.wrapping_add(1);
+            }
+
             if self.nesting_ctr > 1 {
                 self.nesting_ctr -= 1;
                 return Ok(());
@@ -189,30 +207,25 @@ impl OsMutex {
             // Unlock completely
             self.nesting_ctr = 0;
 
-            // Restore owner's priority if it was boosted
-            let cur_tcb = unsafe { &mut *cur_tcb_ptr.as_ptr() };
-            if cur_tcb.prio != cur_tcb.base_prio {
-                if cur_tcb.task_state == OsTaskState::Ready {
-                    unsafe { sched::os_rdy_list_change_prio(cur_tcb_ptr, cur_tcb.base_prio) };
-                }
-                cur_tcb.prio = cur_tcb.base_prio;
-            }
+            #[cfg(feature = "obj-stats")]
+            let now = kernel::KERNEL.tick_get();
+            #[cfg(feature = "obj-stats")]
+            self.stats.released(self.held_since, now);
 
-            if let Some(waiter_ptr) = self.pend_list.head() {
-                let waiter = unsafe { &mut *waiter_ptr.as_ptr() };
+            // Restore owner's priority if it was boosted
+            unsafe { prio_disinherit(cur_tcb_ptr) };
 
+            if let Some(waiter_ptr) = self.pend_list.next_waiter(post_opt) {
                 self.pend_list.remove(waiter_ptr);
 
-                waiter.pend_on = OsPendOn::Nothing;
-                waiter.pend_status = OsPendStatus::Ok;
-                waiter.pend_obj_ptr = core::ptr::null();
-                waiter.tick_remain = 0;
-                waiter.task_state = OsTaskState::Ready;
-
                 self.owner = Some(waiter_ptr);
                 self.nesting_ctr = 1;
+                #[cfg(feature = "obj-stats")]
+                {
+                    self.held_since = now;
+                }
 
-                unsafe { sched::os_rdy_list_insert(waiter_ptr) };
+                unsafe { pend_wake(waiter_ptr, OsPendStatus::Ok) };
 
                 if post_opt & opt::POST_NO_SCHED == 0 {
                     sched::os_sched();
@@ -225,15 +238,126 @@ impl OsMutex {
         })
     }
 
+    /// Re-sort a waiter whose priority changed from `old`
+    ///
+    /// A raised waiter boosts the owner. A lowered one that the owner's
+    /// priority came from hands the owner the next inherited priority.
+    ///
+    /// # Safety
+    /// Called with the critical section held, `tcb` pending on this mutex.
+    pub(crate) unsafe fn pend_prio_change(&mut self, tcb: NonNull<OsTcb>, old: OsPrio) {
+        self.pend_list.resort(tcb);
+
+        let Some(owner_ptr) = self.owner else { return };
+        let owner = unsafe { owner_ptr.as_ref() };
+        let prio = unsafe { tcb.as_ref() }.prio();
+        let obj = self as *const _ as *const ();
+        if prio < owner.prio() {
+            unsafe { prio_inherit(owner_ptr, prio, obj, self.name) };
+        } else if prio > old && owner.prio() == old {
+            let inherited = self.inherited_prio(owner.base_prio());
+            if inherited != old {
+                unsafe { prio_inherit(owner_ptr, inherited, obj, self.name) };
+            }
+        }
+    }
+
+    /// Priority an owner at `base_prio` inherits from the current waiters
+    fn inherited_prio(&self, base_prio: OsPrio) -> OsPrio {
+        match self.pend_list.head() {
+            Some(head) => base_prio.min(unsafe { head.as_ref() }.prio()),
+            None => base_prio,
+        }
+    }
+
+    /// Whether blocking `cur` on this mutex would close a wait cycle
+    ///
+    /// Follows owner -> mutex the owner pends on -> its owner, for at most
+    /// `max_hops` links. Called with the critical section held.
+    #[cfg(all(feature = "deadlock-detect", debug_assertions))]
+    unsafe fn would_deadlock(&self, cur: NonNull<OsTcb>, max_hops: usize) -> bool {
+        let mut owner = self.owner;
+        for _ in 0..=max_hops {
+            let Some(tcb_ptr) = owner else { return false };
+            if tcb_ptr == cur {
+                #[cfg(feature = "defmt")]
+                unsafe { self.log_cycle(cur) };
+                return true;
+            }
+
+            let tcb = unsafe { tcb_ptr.as_ref() };
+            if tcb.pend_on() != OsPendOn::Mutex {
+                return false;
+            }
+            owner = unsafe { (*(tcb.pend_obj_ptr as *const OsMutex)).owner };
+        }
+        false
+    }
+
+    /// Log the cycle found by `would_deadlock`
+    #[cfg(all(feature = "deadlock-detect", debug_assertions, feature = "defmt"))]
+    unsafe fn log_cycle(&self, cur: NonNull<OsTcb>) {
+        let cur_ref = unsafe { cur.as_ref() };
+        crate::__os_log_defer!(warn, "deadlock: {} (#{}) waits on {}", cur_ref.name(), cur_ref.id, self.name());
+        let mut owner = self.owner;
+        while let Some(tcb_ptr) = owner.filter(|&t| t != cur) {
+            let tcb = unsafe { tcb_ptr.as_ref() };
+            let mutex = unsafe { &*(tcb.pend_obj_ptr as *const OsMutex) };
+            crate::__os_log_defer!(warn, "  {} (#{}) waits on {}", tcb.name(), tcb.id, mutex.name());
+            owner = mutex.owner;
+        }
+    }
+
     /// Check if mutex is owned
     #[inline]
     pub fn is_owned(&self) -> bool {
         self.owner.is_some()
     }
 
+    /// Name given at creation
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        obj_name_str(self.name)
+    }
+
+    /// Number of tasks waiting on the mutex
+    #[inline]
+    pub fn waiters(&self) -> usize {
+        self.pend_list.len()
+    }
+
+    /// Copy of the pend/post counters and longest hold time
+    #[cfg(feature = "obj-stats")]
+    pub fn stats(&self) -> MutexStats {
+        critical_section(|_cs| self.stats.snapshot(self.pend_list.len()))
+    }
+
     /// Get owner's priority
     pub fn owner_prio(&self) -> Option<OsPrio> {
-        self.owner.map(|ptr| unsafe { ptr.as_ref().prio })
+        self.owner.map(|ptr| unsafe { ptr.as_ref().prio() })
+    }
+
+    /// Wake every waiter with `ObjDel`, release the owner and unregister,
+    /// as a delete with `DEL_ALWAYS` would; returns the number of waiters
+    ///
+    /// An owner boosted through this mutex drops back to its base priority.
+    #[cfg(feature = "obj-pool")]
+    fn del_always(&mut self, cs: &CriticalSection) -> usize {
+        let mut woken = 0;
+        while let Some(tcb_ptr) = self.pend_list.head() {
+            self.pend_list.remove(tcb_ptr);
+            unsafe { pend_wake(tcb_ptr, OsPendStatus::Del) };
+            woken += 1;
+        }
+        if let Some(owner_ptr) = self.owner.take() {
+            if unsafe { owner_ptr.as_ref() }.boost_obj == self as *const _ as *const () {
+                unsafe { prio_disinherit(owner_ptr) };
+            }
+        }
+        self.nesting_ctr = 0;
+        kernel::registry::unregister_cs(cs, self as *const _ as *const ());
+        self.obj_type = OsObjType::None;
+        woken
     }
 }
 
@@ -243,6 +367,58 @@ impl Default for OsMutex {
     }
 }
 
+/// Number of created tasks, bounding the deadlock walk
+#[cfg(all(feature = "deadlock-detect", debug_assertions))]
+unsafe fn task_count() -> usize {
+    let mut n = 0;
+    let mut next = unsafe { *kernel::task_list() };
+    while let Some(tcb) = next {
+        n += 1;
+        next = unsafe { tcb.as_ref() }.dbg_next_ptr;
+    }
+    n
+}
+
+impl PendObject for OsMutex {
+    fn pend_list(&mut self, _on: OsPendOn) -> &mut PendList {
+        &mut self.pend_list
+    }
+
+    /// Refuse a wait that closes a lock cycle, then boost the owner
+    unsafe fn pend_blocking(&mut self, tcb: NonNull<OsTcb>) -> OsResult<()> {
+        #[cfg(all(feature = "deadlock-detect", debug_assertions))]
+        if unsafe { self.would_deadlock(tcb, task_count()) } {
+            return Err(OsError::MutexWouldDeadlock);
+        }
+
+        // Priority inheritance
+        let prio = unsafe { tcb.as_ref() }.prio();
+        if let Some(owner_ptr) = self.owner {
+            if prio < unsafe { owner_ptr.as_ref() }.prio() {
+                unsafe { prio_inherit(owner_ptr, prio, self as *const _ as *const (), self.name) };
+            }
+        }
+
+        #[cfg(feature = "obj-stats")]
+        self.stats.blocked(self.pend_list.len() + 1);
+        Ok(())
+    }
+
+    /// Unlink a waiter whose pend timed out or that was deleted
+    ///
+    /// The owner's inherited priority drops to the highest priority still
+    /// waiting, or to its base priority once no task waits.
+    unsafe fn pend_unlink(&mut self, tcb: NonNull<OsTcb>) {
+        self.pend_list.remove(tcb);
+
+        let Some(owner_ptr) = self.owner else { return };
+        let prio = self.inherited_prio(unsafe { owner_ptr.as_ref() }.base_prio());
+        if prio != unsafe { owner_ptr.as_ref() }.prio() {
+            unsafe { prio_inherit(owner_ptr, prio, self as *const _ as *const (), self.name) };
+        }
+    }
+}
+
 // ============ Safe Wrapper ============
 
 use core::cell::UnsafeCell;
@@ -250,6 +426,9 @@ pub struct Mutex {
     inner: UnsafeCell<OsMutex>,
 }
 
+// SAFETY: the kernel object is only mutated inside a critical section on a
+// single core, so any task or ISR may share it; a pending task borrows the
+// wrapper, so it cannot move while waited on.
 unsafe impl Sync for Mutex {}
 unsafe impl Send for Mutex {}
 
@@ -264,18 +443,43 @@ impl Mutex {
         unsafe { (*self.inner.get()).create(name) }
     }
 
-    pub fn lock(&self, timeout: OsTick, opt: OsOpt) -> OsResult<()> {
-        unsafe { (*self.inner.get()).pend(timeout, opt) }
+    /// Lock the mutex
+    pub fn lock(&self, timeout: impl Into<Timeout>) -> OsResult<()> {
+        let (ticks, opt) = timeout.into().to_raw();
+        unsafe { (*self.inner.get()).pend(ticks, opt) }
+    }
+
+    /// Unlock the mutex with the default options
+    pub fn unlock(&self) -> OsResult<()> {
+        self.unlock_opt(PostOpt::FIFO)
     }
 
-    pub fn unlock(&self, opt: OsOpt) -> OsResult<()> {
-        unsafe { (*self.inner.get()).post(opt) }
+    /// Unlock the mutex with post options
+    pub fn unlock_opt(&self, opt: PostOpt) -> OsResult<()> {
+        unsafe { (*self.inner.get()).post(opt.bits()) }
     }
 
     #[inline]
     pub fn is_owned(&self) -> bool {
         unsafe { (*self.inner.get()).is_owned() }
     }
+
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        unsafe { (*self.inner.get()).name() }
+    }
+
+    /// Number of tasks waiting on the mutex
+    #[inline]
+    pub fn waiters(&self) -> usize {
+        unsafe { (*self.inner.get()).waiters() }
+    }
+
+    /// Copy of the pend/post counters and longest hold time
+    #[cfg(feature = "obj-stats")]
+    pub fn stats(&self) -> MutexStats {
+        unsafe { (*self.inner.get()).stats() }
+    }
 }
 
 impl Default for Mutex {
@@ -283,3 +487,298 @@ impl Default for Mutex {
         Self::new()
     }
 }
+
+// ============ Kernel Pool ============
+
+#[cfg(feature = "obj-pool")]
+const FREE_MUTEX: OsMutex = OsMutex::new();
+
+#[cfg(feature = "obj-pool")]
+static MUTEX_POOL: CsCell<ObjPool<OsMutex, CFG_MUTEX_POOL_SIZE>> =
+    CsCell::new(ObjPool::new([FREE_MUTEX; CFG_MUTEX_POOL_SIZE]));
+
+#[cfg(feature = "obj-pool")]
+pub(crate) fn pool_reset() {
+    critical_section(|cs| MUTEX_POOL.get(cs).reset());
+}
+
+/// Mutex allocated from the kernel pool (`obj-pool`)
+///
+/// Copyable; every use checks that the mutex was not freed since.
+#[cfg(feature = "obj-pool")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MutexHandle(ObjHandle);
+
+/// Allocate a mutex from the kernel pool
+///
+/// # Returns
+/// * `Err(OsError::ObjPoolEmpty)` - All `CFG_MUTEX_POOL_SIZE` mutexes are allocated
+/// * `Err(OsError::CreateIsr)` - Called from an ISR
+#[cfg(feature = "obj-pool")]
+pub fn alloc() -> OsResult<MutexHandle> {
+    critical_section(|cs| {
+        let pool = MUTEX_POOL.get(cs);
+        let (handle, mutex) = pool.alloc()?;
+        if let Err(err) = unsafe { (*mutex).create("") } {
+            let _ = pool.release(handle);
+            return Err(err);
+        }
+        Ok(MutexHandle(handle))
+    })
+}
+
+/// Return a mutex to the kernel pool
+///
+/// Tasks still waiting on it are readied with `OsError::ObjDel` and the
+/// owner loses it, as by a delete with `DEL_ALWAYS`; the handle and its
+/// copies go stale.
+///
+/// # Returns
+/// * `Ok(n)` - Number of waiters readied
+/// * `Err(OsError::ObjDel)` - Already freed
+/// * `Err(OsError::DelIsr)` - Called from an ISR
+#[cfg(feature = "obj-pool")]
+pub fn free(handle: MutexHandle) -> OsResult<usize> {
+    if is_isr_context() {
+        return Err(OsError::DelIsr);
+    }
+
+    critical_section(|cs| {
+        let mutex = MUTEX_POOL.get(cs).release(handle.0)?;
+        let woken = unsafe { (*mutex).del_always(cs) };
+        sched::os_sched();
+        Ok(woken)
+    })
+}
+
+#[cfg(feature = "obj-pool")]
+impl MutexHandle {
+    /// Run `f` on the mutex unless it was freed
+    fn with<R>(self, f: impl FnOnce(&mut OsMutex) -> OsResult<R>) -> OsResult<R> {
+        critical_section(|cs| {
+            let mutex = MUTEX_POOL.get(cs).get(self.0)?;
+            f(unsafe { &mut *mutex })
+        })
+    }
+
+    /// Lock the mutex
+    pub fn lock(self, timeout: impl Into<Timeout>) -> OsResult<()> {
+        let (ticks, opt) = timeout.into().to_raw();
+        self.with(|mutex| mutex.pend(ticks, opt))
+    }
+
+    /// Unlock the mutex with the default options
+    pub fn unlock(self) -> OsResult<()> {
+        self.unlock_opt(PostOpt::FIFO)
+    }
+
+    /// Unlock the mutex with post options
+    pub fn unlock_opt(self, opt: PostOpt) -> OsResult<()> {
+        self.with(|mutex| mutex.post(opt.bits()))
+    }
+
+    pub fn is_owned(self) -> OsResult<bool> {
+        self.with(|mutex| Ok(mutex.is_owned()))
+    }
+
+    /// Number of tasks waiting on the mutex
+    pub fn waiters(self) -> OsResult<usize> {
+        self.with(|mutex| Ok(mutex.waiters()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OsTaskState;
+
+    fn pend_on(tcb: &mut OsTcb, mutex: &OsMutex) {
+        tcb.set_pending(mutex as *const _ as *const (), OsPendOn::Mutex, 0);
+    }
+
+    fn tcb(prio: OsPrio) -> OsTcb {
+        let mut tcb = OsTcb::new();
+        tcb.prio = prio;
+        tcb.base_prio = prio;
+        tcb
+    }
+
+    /// Block `tcb` on `mutex` and boost the owner the way `pend()` does
+    fn wait(mutex: &mut OsMutex, tcb: &mut OsTcb) {
+        pend_on(tcb, mutex);
+        mutex.pend_list.insert_by_prio(NonNull::from(&mut *tcb));
+        let owner = mutex.owner.unwrap();
+        if tcb.prio() < unsafe { owner.as_ref() }.prio() {
+            unsafe { prio_inherit(owner, tcb.prio(), mutex as *const _ as *const (), mutex.name) };
+        }
+    }
+
+    #[test]
+    fn test_timeout_drops_inherited_prio() {
+        // Off the ready list, so the kernel's ready queues are not touched
+        let mut owner = tcb(10);
+        owner.task_state = OsTaskState::Suspended;
+        let (mut high, mut mid) = (tcb(3), tcb(6));
+        let mut m = OsMutex::new();
+        m.owner = Some(NonNull::from(&mut owner));
+
+        wait(&mut m, &mut mid);
+        wait(&mut m, &mut high);
+        assert_eq!(m.owner_prio(), Some(3));
+
+        // The booster leaves: back to the next waiter, then to base
+        unsafe { m.pend_unlink(NonNull::from(&mut high)) };
+        assert_eq!(m.owner_prio(), Some(6));
+        assert_eq!(m.waiters(), 1);
+        // Deleting the last waiter goes the same way
+        unsafe { crate::sync::pend_del(NonNull::from(&mut mid)) };
+        assert_eq!(m.owner_prio(), Some(10));
+        assert_eq!(m.waiters(), 0);
+    }
+
+    #[test]
+    fn test_lower_waiter_leaving_keeps_boost() {
+        let mut owner = tcb(10);
+        owner.task_state = OsTaskState::Suspended;
+        let (mut high, mut low) = (tcb(3), tcb(12));
+        let mut m = OsMutex::new();
+        m.owner = Some(NonNull::from(&mut owner));
+
+        wait(&mut m, &mut high);
+        wait(&mut m, &mut low);
+        unsafe { m.pend_unlink(NonNull::from(&mut low)) };
+        assert_eq!(m.owner_prio(), Some(3));
+        unsafe { m.pend_unlink(NonNull::from(&mut high)) };
+        assert_eq!(m.owner_prio(), Some(10));
+    }
+
+    #[test]
+    fn test_boost_follows_pending_owner() {
+        // T2 holds B; T1 holds A and waits on B behind X
+        let mut t2 = tcb(12);
+        t2.task_state = OsTaskState::Suspended;
+        let (mut t1, mut x, mut high) = (tcb(10), tcb(9), tcb(3));
+        let (mut a, mut b) = (OsMutex::new(), OsMutex::new());
+        b.owner = Some(NonNull::from(&mut t2));
+        a.owner = Some(NonNull::from(&mut t1));
+        wait(&mut b, &mut x);
+        wait(&mut b, &mut t1);
+        assert_eq!(b.owner_prio(), Some(9));
+        assert_eq!(b.pend_list.head(), Some(NonNull::from(&mut x)));
+
+        // A high waiter on A lifts T1 ahead of X on B, and T2 with it
+        wait(&mut a, &mut high);
+        assert_eq!(t1.prio(), 3);
+        assert_eq!(t2.boosted_by(), Some(obj_name_str(b.name)));
+        assert_eq!(b.pend_list.head(), Some(NonNull::from(&mut t1)));
+        assert_eq!(b.owner_prio(), Some(3));
+
+        // It times out: T1 drops back behind X and T2 to X's priority
+        unsafe { a.pend_unlink(NonNull::from(&mut high)) };
+        assert_eq!(t1.prio(), 10);
+        assert_eq!(b.pend_list.head(), Some(NonNull::from(&mut x)));
+        assert_eq!(b.owner_prio(), Some(9));
+    }
+
+    #[test]
+    fn test_boost_reported() {
+        // Low owner, high waiter: the classic inversion
+        let mut stk = [0; 8];
+        let mut owner = tcb(10);
+        owner.task_state = OsTaskState::Suspended;
+        owner.stk_base = stk.as_mut_ptr();
+        owner.stk_size = stk.len();
+        let owner_ptr = NonNull::from(&mut owner);
+        let (mut high, mut mid) = (tcb(3), tcb(6));
+        let mut m = OsMutex::new();
+        m.name = obj_name("m");
+        m.owner = Some(owner_ptr);
+        let m_name = obj_name_str(m.name);
+
+        let info = crate::task::os_task_info(Some(owner_ptr)).unwrap();
+        assert_eq!((info.prio, info.base_prio, info.boosted_by), (10, 10, None));
+
+        wait(&mut m, &mut mid);
+        wait(&mut m, &mut high);
+        let info = crate::task::os_task_info(Some(owner_ptr)).unwrap();
+        assert_eq!((info.prio, info.base_prio, info.boosted_by), (3, 10, Some(m_name)));
+
+        // Still boosted, by the same mutex, after the top waiter leaves
+        unsafe { m.pend_unlink(NonNull::from(&mut high)) };
+        assert_eq!((owner.prio(), owner.boosted_by()), (6, Some(m_name)));
+        unsafe { m.pend_unlink(NonNull::from(&mut mid)) };
+        assert_eq!((owner.prio(), owner.boosted_by()), (10, None));
+    }
+
+    #[test]
+    fn test_boost_delayed_owner() {
+        let mut owner = tcb(10);
+        owner.task_state = OsTaskState::Delayed;
+        let mut high = tcb(3);
+        let mut m = OsMutex::new();
+        m.owner = Some(NonNull::from(&mut owner));
+
+        wait(&mut m, &mut high);
+        assert_eq!(owner.prio(), 3);
+
+        // Readied at the boosted priority when the delay ends
+        assert!(owner.tick_expire());
+        let mut rdy = sched::ReadyQueues::new();
+        rdy.insert(NonNull::from(&mut owner));
+        assert_eq!(rdy.highest_tcb(), Some((3, NonNull::from(&mut owner))));
+    }
+
+    #[cfg(all(feature = "deadlock-detect", debug_assertions))]
+    #[test]
+    fn test_abba_detected() {
+        let mut t1 = OsTcb::new();
+        let mut t2 = OsTcb::new();
+        let (p1, p2) = (NonNull::from(&mut t1), NonNull::from(&mut t2));
+        let mut a = OsMutex::new();
+        let mut b = OsMutex::new();
+
+        // T1 holds A, T2 holds B and waits for A
+        a.owner = Some(p1);
+        b.owner = Some(p2);
+        pend_on(unsafe { &mut *p2.as_ptr() }, &a);
+
+        // T1 locking B closes the cycle; T2 locking A again does too
+        assert!(unsafe { b.would_deadlock(p1, 2) });
+        assert!(unsafe { a.would_deadlock(p1, 2) });
+    }
+
+    #[cfg(all(feature = "deadlock-detect", debug_assertions))]
+    #[test]
+    fn test_chain_without_cycle() {
+        let mut t1 = OsTcb::new();
+        let mut t2 = OsTcb::new();
+        let mut t3 = OsTcb::new();
+        let (p1, p2, p3) = (NonNull::from(&mut t1), NonNull::from(&mut t2), NonNull::from(&mut t3));
+        let mut a = OsMutex::new();
+        let mut b = OsMutex::new();
+
+        // T2 holds B and waits for A held by T3, which is not blocked
+        a.owner = Some(p3);
+        b.owner = Some(p2);
+        pend_on(unsafe { &mut *p2.as_ptr() }, &a);
+        assert!(!unsafe { b.would_deadlock(p1, 3) });
+
+        // T3 waiting on something else ends the walk too
+        unsafe { &mut *p3.as_ptr() }.set_pending(core::ptr::null(), OsPendOn::TaskSem, 10);
+        assert!(!unsafe { b.would_deadlock(p1, 3) });
+    }
+
+    #[cfg(all(feature = "deadlock-detect", debug_assertions))]
+    #[test]
+    fn test_walk_bounded() {
+        let mut t1 = OsTcb::new();
+        let mut t2 = OsTcb::new();
+        let (p1, p2) = (NonNull::from(&mut t1), NonNull::from(&mut t2));
+        let mut a = OsMutex::new();
+
+        // Corrupt self-loop between T2 and A must not spin forever
+        a.owner = Some(p2);
+        pend_on(unsafe { &mut *p2.as_ptr() }, &a);
+        assert!(!unsafe { a.would_deadlock(p1, 4) });
+    }
+}
@@ -0,0 +1,131 @@
+//! Blocking on a kernel object
+//!
+//! Every object that tasks wait on blocks the running task through
+//! [`pend_block`]: the object only says how to acquire it and which wait
+//! list to join, and the state transitions, the tick queue and the pend
+//! result are handled here once. Waiters leave through `pend_end` /
+//! `pend_wake` when posted, aborted or deleted, through `pend_expire`
+//! when their timeout runs out and through `pend_del_req` when their
+//! deletion is requested; either way a suspended waiter stays
+//! suspended. The switch away from a blocked waiter is only taken once
+//! the critical section it blocked in ends, so its result is read by
+//! [`pend_wait`] in a fresh one, once the waiter runs again.
+
+use core::ptr::NonNull;
+
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::sched;
+use crate::sem::PendList;
+use crate::task::OsTcb;
+use crate::types::{opt, OsOpt, OsPendOn, OsTick};
+
+/// Kernel object tasks pend on
+pub(crate) trait PendObject {
+    /// Wait list for a pend of kind `on`
+    fn pend_list(&mut self, on: OsPendOn) -> &mut PendList;
+
+    /// Account for the running task `tcb` about to block
+    ///
+    /// Called after the non-blocking and scheduler lock checks; an error
+    /// fails the pend without blocking.
+    ///
+    /// # Safety
+    /// Called with the critical section held.
+    unsafe fn pend_blocking(&mut self, _tcb: NonNull<OsTcb>) -> OsResult<()> {
+        Ok(())
+    }
+
+    /// Unlink a waiter that timed out or is being deleted
+    ///
+    /// # Safety
+    /// Called with the critical section held, `tcb` pending on this object.
+    unsafe fn pend_unlink(&mut self, tcb: NonNull<OsTcb>) {
+        let on = unsafe { tcb.as_ref() }.pend_on();
+        self.pend_list(on).remove(tcb);
+    }
+}
+
+/// How a [`pend_block`] that did not fail ended
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Pended<R> {
+    /// Acquired without blocking
+    Acquired(R),
+    /// Blocked; [`pend_wait`] reads the result once the task runs again
+    Blocked(NonNull<OsTcb>),
+}
+
+/// Pend the running task on `obj`
+///
+/// `try_acquire` runs first and returns `None` when the task has to wait.
+/// Otherwise `PEND_NON_BLOCKING` fails with `PendWouldBlock`, a requested
+/// deletion of a `TASK_DEL_INTERRUPT` task with `TaskDelRequested`, a
+/// locked scheduler with `SchedLocked`, and the task joins `obj`'s wait list for
+/// `on`, and the tick queue when `timeout > 0`, until a post, abort,
+/// delete or the timeout readies it. The switch away from the task is
+/// requested here and taken once interrupts are unmasked; the caller
+/// releases the critical section and calls [`pend_wait`].
+///
+/// # Safety
+/// Called with the critical section held, the kernel running and outside
+/// an ISR; `obj` must stay in place while the task waits.
+pub(crate) unsafe fn pend_block<O: PendObject, R>(
+    obj: &mut O,
+    on: OsPendOn,
+    timeout: OsTick,
+    pend_opt: OsOpt,
+    try_acquire: impl FnOnce(&mut O, NonNull<OsTcb>) -> Option<OsResult<R>>,
+) -> OsResult<Pended<R>> {
+    let cur = unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?;
+    // Running again means the last pend is over, whether or not its result
+    // was read through `pend_resume`
+    unsafe { super::late_leave(cur) };
+
+    if let Some(result) = try_acquire(obj, cur) {
+        let acquired = result?;
+        unsafe { kernel::tick_remain_set(timeout) };
+        return Ok(Pended::Acquired(acquired));
+    }
+
+    if pend_opt & opt::PEND_NON_BLOCKING != 0 {
+        return Err(OsError::PendWouldBlock);
+    }
+
+    if unsafe { cur.as_ref() }.del_interrupts() {
+        return Err(OsError::TaskDelRequested);
+    }
+
+    kernel::sched_lock_chk()?;
+
+    unsafe {
+        obj.pend_blocking(cur)?;
+
+        let tcb = &mut *cur.as_ptr();
+        sched::os_rdy_list_remove(cur);
+        tcb.set_pending(obj as *const O as *const (), on, timeout);
+        if timeout > 0 {
+            kernel::tick_insert(cur, tcb.tick_expiry);
+        }
+        obj.pend_list(on).insert_by_prio(cur);
+    }
+
+    sched::os_sched();
+
+    Ok(Pended::Blocked(cur))
+}
+
+/// Read the result of a pend that blocked in [`pend_block`]
+///
+/// Returns once the task runs again and passes its result through
+/// `woken`, which runs in the same critical section.
+///
+/// # Safety
+/// Called by the task `tcb`, after the critical section `pend_block` ran
+/// in is released.
+pub(crate) unsafe fn pend_wait<T>(
+    tcb: NonNull<OsTcb>,
+    woken: impl FnOnce(OsResult<()>) -> OsResult<T>,
+) -> OsResult<T> {
+    critical_section(|_cs| woken(unsafe { super::pend_resume(tcb) }))
+}
@@ -0,0 +1,111 @@
+//! Kernel object pools (`obj-pool`)
+//!
+//! Fixed pools of semaphores and mutexes for objects whose number is only
+//! known at run time, e.g. one per connected client. `sem::alloc` and
+//! `mutex::alloc` hand out a handle naming a slot: its index and the
+//! generation it was allocated in. Freeing a slot bumps its generation, so
+//! a handle kept past `free` fails with `OsError::ObjDel` instead of
+//! reaching whichever object took the slot next.
+//!
+//! Static objects stay the primary API; the pools only add storage. They
+//! are emptied by `os_init`, which also makes every earlier handle stale.
+
+use crate::error::{OsError, OsResult};
+
+/// Slot index and allocation generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ObjHandle {
+    idx: u16,
+    gen: u16,
+}
+
+/// `N` objects and which of them are allocated
+pub(crate) struct ObjPool<T, const N: usize> {
+    objs: [T; N],
+    gens: [u16; N],
+    used: [bool; N],
+}
+
+impl<T, const N: usize> ObjPool<T, N> {
+    pub(crate) const fn new(objs: [T; N]) -> Self {
+        assert!(N <= u16::MAX as usize, "object pool larger than a handle index");
+        Self {
+            objs,
+            gens: [0; N],
+            used: [false; N],
+        }
+    }
+
+    /// Free every slot
+    ///
+    /// Generations are kept, so handles from before stay stale.
+    pub(crate) fn reset(&mut self) {
+        for (used, gen) in self.used.iter_mut().zip(self.gens.iter_mut()) {
+            if *used {
+                *used = false;
+                *gen = gen.wrapping_add(1);
+            }
+        }
+    }
+
+    /// Take a free slot
+    ///
+    /// # Returns
+    /// * `Err(OsError::ObjPoolEmpty)` - Every slot is allocated
+    pub(crate) fn alloc(&mut self) -> OsResult<(ObjHandle, *mut T)> {
+        let idx = self.used.iter().position(|used| !used).ok_or(OsError::ObjPoolEmpty)?;
+        self.used[idx] = true;
+        let handle = ObjHandle { idx: idx as u16, gen: self.gens[idx] };
+        Ok((handle, &mut self.objs[idx] as *mut T))
+    }
+
+    /// Object `handle` names
+    ///
+    /// # Returns
+    /// * `Err(OsError::ObjDel)` - The slot was freed since `handle` was allocated
+    pub(crate) fn get(&mut self, handle: ObjHandle) -> OsResult<*mut T> {
+        let idx = handle.idx as usize;
+        if idx >= N || !self.used[idx] || self.gens[idx] != handle.gen {
+            return Err(OsError::ObjDel);
+        }
+        Ok(&mut self.objs[idx] as *mut T)
+    }
+
+    /// Free the slot `handle` names, returning its object
+    ///
+    /// # Returns
+    /// * `Err(OsError::ObjDel)` - The slot was freed since `handle` was allocated
+    pub(crate) fn release(&mut self, handle: ObjHandle) -> OsResult<*mut T> {
+        let obj = self.get(handle)?;
+        let idx = handle.idx as usize;
+        self.used[idx] = false;
+        self.gens[idx] = self.gens[idx].wrapping_add(1);
+        Ok(obj)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exhaust_and_stale() {
+        let mut pool = ObjPool::new([0u32; 3]);
+        let handles = [(); 3].map(|_| pool.alloc().unwrap().0);
+        assert_eq!(pool.alloc().err(), Some(OsError::ObjPoolEmpty));
+
+        // The freed slot comes back under a new generation
+        let obj = pool.release(handles[1]).unwrap();
+        let (again, again_obj) = pool.alloc().unwrap();
+        assert_eq!(again_obj, obj);
+        assert_ne!(again, handles[1]);
+        assert_eq!(pool.get(handles[1]), Err(OsError::ObjDel));
+        assert_eq!(pool.release(handles[1]), Err(OsError::ObjDel));
+        assert_eq!(pool.get(again), Ok(obj));
+
+        pool.reset();
+        assert_eq!(pool.get(handles[0]), Err(OsError::ObjDel));
+        assert_eq!(pool.get(again), Err(OsError::ObjDel));
+        assert!(pool.alloc().is_ok());
+    }
+}
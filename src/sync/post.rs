@@ -0,0 +1,171 @@
+//! Posting to any kernel object
+//!
+//! [`os_post`] is a facade over the concrete post routines for callers that
+//! only hold "post to object X with value V": a deferred ISR post queue, a
+//! C binding, a trace decoder. It dispatches on the [`ObjRef`] tag with a
+//! plain `match`, and the direct APIs (`OsSem::post`, `OsMutex::post`, ...)
+//! do not go through it.
+//!
+//! The tag is checked against the object's own `obj_type` field before the
+//! post, so an erased pointer tagged with the wrong type (or a deleted
+//! object) fails with `OsError::ObjType`. The kernel objects are `repr(C)`
+//! with `obj_type` first for this check.
+
+use core::ptr::{addr_of, NonNull};
+
+use crate::critical::critical_section;
+use crate::error::{OsError, OsResult};
+#[cfg(feature = "mutex")]
+use crate::mutex::OsMutex;
+#[cfg(feature = "rwlock")]
+use crate::rwlock::OsRwLock;
+use crate::sched;
+#[cfg(feature = "sem")]
+use crate::sem::OsSem;
+use crate::task::OsTcb;
+use crate::types::{opt, OsObjType, OsOpt, OsSemCtr};
+
+/// Kernel object a post goes to, tagged with its type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjRef {
+    #[cfg(feature = "sem")]
+    Sem(NonNull<OsSem>),
+    #[cfg(feature = "mutex")]
+    Mutex(NonNull<OsMutex>),
+    #[cfg(feature = "rwlock")]
+    RwLock(NonNull<OsRwLock>),
+    /// A task's built-in semaphore
+    Task(NonNull<OsTcb>),
+}
+
+impl ObjRef {
+    /// Tag an erased object pointer with the type the caller says it has
+    ///
+    /// # Returns
+    /// * `Err(OsError::ObjPtrNull)` - `ptr` is null
+    /// * `Err(OsError::ObjType)` - Objects of `obj_type` take no posts
+    ///
+    /// # Safety
+    /// `ptr` must point at a semaphore, mutex, readers-writer lock, barrier
+    /// or TCB, which stays in place until the post returns. Its type may
+    /// differ from `obj_type`; `os_post` rejects it then.
+    pub unsafe fn from_raw(obj_type: OsObjType, ptr: *const ()) -> OsResult<Self> {
+        let ptr = NonNull::new(ptr as *mut ()).ok_or(OsError::ObjPtrNull)?;
+        match obj_type {
+            #[cfg(feature = "sem")]
+            OsObjType::Sem => Ok(ObjRef::Sem(ptr.cast())),
+            #[cfg(feature = "mutex")]
+            OsObjType::Mutex => Ok(ObjRef::Mutex(ptr.cast())),
+            #[cfg(feature = "rwlock")]
+            OsObjType::RwLock => Ok(ObjRef::RwLock(ptr.cast())),
+            OsObjType::Task => Ok(ObjRef::Task(ptr.cast())),
+            _ => Err(OsError::ObjType),
+        }
+    }
+
+    /// Type the tag claims
+    pub fn obj_type(self) -> OsObjType {
+        match self {
+            #[cfg(feature = "sem")]
+            ObjRef::Sem(_) => OsObjType::Sem,
+            #[cfg(feature = "mutex")]
+            ObjRef::Mutex(_) => OsObjType::Mutex,
+            #[cfg(feature = "rwlock")]
+            ObjRef::RwLock(_) => OsObjType::RwLock,
+            ObjRef::Task(_) => OsObjType::Task,
+        }
+    }
+
+    /// `obj_type` field of the object, read as its raw value
+    ///
+    /// An object of another type holds its own marker there; a deleted
+    /// one holds `OsObjType::None`.
+    unsafe fn marker(self) -> u32 {
+        unsafe {
+            match self {
+                #[cfg(feature = "sem")]
+                ObjRef::Sem(p) => (addr_of!((*p.as_ptr()).obj_type) as *const u32).read(),
+                #[cfg(feature = "mutex")]
+                ObjRef::Mutex(p) => (addr_of!((*p.as_ptr()).obj_type) as *const u32).read(),
+                #[cfg(feature = "rwlock")]
+                ObjRef::RwLock(p) => (addr_of!((*p.as_ptr()).obj_type) as *const u32).read(),
+                ObjRef::Task(p) => (addr_of!((*p.as_ptr()).obj_type) as *const u32).read(),
+            }
+        }
+    }
+}
+
+/// What a post carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostValue {
+    /// Count a semaphore once, or release a mutex or readers-writer lock
+    Signal,
+    /// Count a semaphore `n` times, e.g. posts an ISR coalesced
+    Count(OsSemCtr),
+}
+
+/// Post `value` to `obj`
+///
+/// Each object gets its own post routine with `POST_NO_SCHED` added, and
+/// the scheduler runs once at the end unless `post_opt` has
+/// `POST_NO_SCHED`, also after a failed post that woke tasks before it
+/// failed. A readers-writer lock releases the write lock if it is write
+/// locked, otherwise a read lock.
+///
+/// # Returns
+/// * `Err(OsError::ObjType)` - `obj` is not an object of its tag's type,
+///   or `PostValue::Count` went to a mutex or readers-writer lock
+/// * `Err(_)` - The object's own post failed; see `OsSem::post`,
+///   `OsMutex::post`, `OsRwLock::write_unlock`, `os_task_sem_post`
+pub fn os_post(obj: ObjRef, value: PostValue, post_opt: OsOpt) -> OsResult<()> {
+    opt::check(post_opt, opt::POST_MASK)?;
+
+    critical_section(|_cs| {
+        if unsafe { obj.marker() } != obj.obj_type() as u32 {
+            return Err(OsError::ObjType);
+        }
+
+        let result = unsafe { post_to(obj, value, post_opt | opt::POST_NO_SCHED) };
+        if post_opt & opt::POST_NO_SCHED == 0 {
+            sched::os_sched();
+        }
+        result
+    })
+}
+
+/// Run the post routine of `obj`'s type
+unsafe fn post_to(obj: ObjRef, value: PostValue, post_opt: OsOpt) -> OsResult<()> {
+    let times = match value {
+        PostValue::Signal => 1,
+        PostValue::Count(n) => n,
+    };
+    match obj {
+        #[cfg(feature = "sem")]
+        ObjRef::Sem(p) => {
+            for _ in 0..times {
+                unsafe { (*p.as_ptr()).post(post_opt) }?;
+            }
+            Ok(())
+        }
+        #[cfg(feature = "mutex")]
+        ObjRef::Mutex(p) => match value {
+            PostValue::Signal => unsafe { (*p.as_ptr()).post(post_opt) },
+            PostValue::Count(_) => Err(OsError::ObjType),
+        },
+        #[cfg(feature = "rwlock")]
+        ObjRef::RwLock(p) => {
+            let lock = unsafe { &mut *p.as_ptr() };
+            match value {
+                PostValue::Signal if lock.is_write_locked() => lock.write_unlock(post_opt),
+                PostValue::Signal => lock.read_unlock(post_opt),
+                PostValue::Count(_) => Err(OsError::ObjType),
+            }
+        }
+        ObjRef::Task(p) => {
+            for _ in 0..times {
+                crate::task::os_task_sem_post(p, post_opt)?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Lock-free single-producer single-consumer byte ring
+//!
+//! Streams bytes between an interrupt handler and one task without
+//! masking interrupts: UART RX pushes with `push_from_isr` and the task
+//! drains with `read`, UART TX fills with `write` and the handler drains
+//! with `pop_from_isr`. Exactly one context may produce and one may
+//! consume; the ring is not a kernel object and has no pend list.
+//!
+//! # Notification
+//!
+//! With a task set through `set_notify`, the ISR-side calls post that
+//! task's semaphore when they make progress possible for it: `push_from_isr`
+//! when the ring goes from empty to non-empty, `pop_from_isr` when it goes
+//! from full to not full. The task loops until `read` (or `write`) returns
+//! 0 and only then pends on `os_task_sem_pend`, so it may see a spurious
+//! wake-up but never misses one.
+//!
+//! # Memory ordering
+//!
+//! `head` is written only by the producer and `tail` only by the consumer.
+//! Each side stores its index after touching the buffer, and loads the
+//! other side's index before touching it, so a slot is never read before
+//! its byte is written nor overwritten before it is read (release/acquire
+//! would suffice for this).
+//!
+//! The notification needs more: the producer stores `head` then loads
+//! `tail` to see whether the ring was empty, while the consumer stores
+//! `tail` then loads `head` to see whether more data arrived. Both pairs
+//! are `SeqCst`, so at least one side observes the other's store: either
+//! the consumer finds the new byte, or the producer finds the ring drained
+//! and posts. The same argument covers the full case for `write` and
+//! `pop_from_isr`. On single-core Cortex-M this costs one `dmb` per call.
+
+use core::cell::UnsafeCell;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+use crate::task::{os_task_sem_post, OsTcb};
+use crate::types::opt;
+
+/// Byte ring of `N` bytes, `N` a power of two
+pub struct SpscRing<const N: usize> {
+    buf: UnsafeCell<[u8; N]>,
+    /// Next slot to write, free-running
+    head: AtomicUsize,
+    /// Next slot to read, free-running
+    tail: AtomicUsize,
+    /// Task whose semaphore the ISR side posts
+    notify: AtomicPtr<OsTcb>,
+}
+
+// SAFETY: one producer writes only the slots between `tail` and `head`,
+// one consumer reads only the published ones; the indices are atomics.
+unsafe impl<const N: usize> Sync for SpscRing<N> {}
+
+impl<const N: usize> SpscRing<N> {
+    const SIZE_OK: () = assert!(N.is_power_of_two(), "SpscRing size must be a power of two");
+
+    /// Create an empty ring
+    pub const fn new() -> Self {
+        let () = Self::SIZE_OK;
+        SpscRing {
+            buf: UnsafeCell::new([0; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            notify: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Post `tcb`'s task semaphore on ISR-side transitions, or stop posting
+    pub fn set_notify(&self, tcb: Option<NonNull<OsTcb>>) {
+        let ptr = tcb.map_or(core::ptr::null_mut(), |t| t.as_ptr());
+        self.notify.store(ptr, Ordering::Release);
+    }
+
+    /// Push one byte from the producing ISR
+    ///
+    /// Returns `false` and drops the byte if the ring is full.
+    #[inline]
+    pub fn push_from_isr(&self, byte: u8) -> bool {
+        self.produce(&[byte], true) == 1
+    }
+
+    /// Copy as many bytes of `buf` as fit from the producing task
+    ///
+    /// Returns the number of bytes queued.
+    #[inline]
+    pub fn write(&self, buf: &[u8]) -> usize {
+        self.produce(buf, false)
+    }
+
+    /// Pop one byte from the consuming ISR
+    #[inline]
+    pub fn pop_from_isr(&self) -> Option<u8> {
+        let mut byte = 0;
+        (self.consume(core::slice::from_mut(&mut byte), true) == 1).then_some(byte)
+    }
+
+    /// Copy queued bytes into `buf` from the consuming task
+    ///
+    /// Returns the number of bytes read; 0 means the ring was empty.
+    #[inline]
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        self.consume(buf, false)
+    }
+
+    /// Number of queued bytes
+    #[inline]
+    pub fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        self.head.load(Ordering::Acquire).wrapping_sub(tail)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    fn slot(&self, idx: usize) -> *mut u8 {
+        unsafe { (self.buf.get() as *mut u8).add(idx & (N - 1)) }
+    }
+
+    fn produce(&self, src: &[u8], notify: bool) -> usize {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::SeqCst);
+        let n = src.len().min(N - head.wrapping_sub(tail));
+        if n == 0 {
+            return 0;
+        }
+
+        for (i, &byte) in src[..n].iter().enumerate() {
+            unsafe { self.slot(head.wrapping_add(i)).write_volatile(byte) };
+        }
+        self.head.store(head.wrapping_add(n), Ordering::SeqCst);
+
+        // Nothing before these bytes is left: the consumer may be pending
+        if notify && self.tail.load(Ordering::SeqCst) == head {
+            self.post();
+        }
+        n
+    }
+
+    fn consume(&self, dst: &mut [u8], notify: bool) -> usize {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::SeqCst);
+        let n = dst.len().min(head.wrapping_sub(tail));
+        if n == 0 {
+            return 0;
+        }
+
+        for (i, byte) in dst[..n].iter_mut().enumerate() {
+            *byte = unsafe { self.slot(tail.wrapping_add(i)).read_volatile() };
+        }
+        self.tail.store(tail.wrapping_add(n), Ordering::SeqCst);
+
+        // The ring was still full: the producer may be pending
+        if notify && self.head.load(Ordering::SeqCst).wrapping_sub(tail) == N {
+            self.post();
+        }
+        n
+    }
+
+    fn post(&self) {
+        if let Some(tcb) = NonNull::new(self.notify.load(Ordering::Acquire)) {
+            let _ = os_task_sem_post(tcb, opt::NONE);
+        }
+    }
+}
+
+impl<const N: usize> Default for SpscRing<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
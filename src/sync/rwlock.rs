@@ -0,0 +1,628 @@
+//! Readers-writer lock
+//!
+//! Any number of tasks may hold the lock for reading, or one task for
+//! writing. Writers are preferred: once a writer waits, new readers block
+//! behind it, so a stream of readers cannot starve writers. When a writer
+//! releases the lock, the readers that queued up meanwhile are admitted
+//! before the next writer, so writers cannot starve readers either.
+//!
+//! Priority inheritance is applied to the write owner only: a task that
+//! blocks behind a writer boosts it like a mutex owner. Readers are not
+//! tracked individually, so a writer blocked by readers does not boost
+//! them. Keep read sections short, or give readers a priority no lower
+//! than the writers they may delay.
+
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+
+use crate::critical::{critical_section, is_isr_context};
+use crate::error::{OsError, OsResult};
+use crate::kernel;
+use crate::sched;
+use crate::sem::PendList;
+#[cfg(feature = "obj-stats")]
+use crate::sync::stats::OsObjStats;
+use crate::sync::{pend_block, pend_wait, pend_wake, prio_disinherit, prio_inherit, PendObject, Pended};
+use crate::task::OsTcb;
+use crate::types::{obj_name, obj_name_str, ObjName, OsObjType, OsOpt, OsPendOn, OsPendStatus, OsPrio, OsTick, Timeout, opt};
+
+/// Readers-writer lock with writer preference
+#[repr(C)]
+pub struct OsRwLock {
+    /// Object type marker
+    pub(crate) obj_type: OsObjType,
+    /// Tasks waiting to read
+    read_list: PendList,
+    /// Tasks waiting to write
+    write_list: PendList,
+    /// Number of read locks held
+    readers: usize,
+    /// Task holding the write lock
+    writer: Option<NonNull<OsTcb>>,
+    /// Pend/post counters
+    #[cfg(feature = "obj-stats")]
+    stats: OsObjStats,
+    /// Name for debugging
+    name: ObjName,
+}
+
+impl OsRwLock {
+    /// Create a new, unlocked readers-writer lock
+    pub const fn new() -> Self {
+        OsRwLock {
+            obj_type: OsObjType::RwLock,
+            read_list: PendList::new(),
+            write_list: PendList::new(),
+            readers: 0,
+            writer: None,
+            #[cfg(feature = "obj-stats")]
+            stats: OsObjStats::new(),
+            name: obj_name(""),
+        }
+    }
+
+    /// Initialize the lock
+    pub fn create(&mut self, name: &'static str) -> OsResult<()> {
+        if is_isr_context() {
+            return Err(OsError::CreateIsr);
+        }
+
+        if kernel::KERNEL.is_safety_critical() {
+            return Err(OsError::IllegalCreateRunTime);
+        }
+
+        critical_section(|cs| {
+            kernel::registry::register_cs(cs, OsObjType::RwLock, name, self as *const _ as *const ());
+            self.obj_type = OsObjType::RwLock;
+            self.read_list.init();
+            self.write_list.init();
+            self.readers = 0;
+            self.writer = None;
+            #[cfg(feature = "obj-stats")]
+            {
+                self.stats = OsObjStats::new();
+            }
+            self.name = obj_name(name);
+            Ok(())
+        })
+    }
+
+    /// Acquire the lock for reading
+    ///
+    /// Blocks while a task holds or waits for the write lock.
+    ///
+    /// # Arguments
+    /// * `timeout` - Maximum ticks to wait (0 = forever)
+    /// * `opt` - Pend options
+    pub fn read_lock(&mut self, timeout: OsTick, pend_opt: OsOpt) -> OsResult<()> {
+        self.lock(timeout, pend_opt, OsPendOn::RwLockRead)
+    }
+
+    /// Acquire the lock for writing
+    ///
+    /// Blocks while any task holds the lock. The write lock does not nest.
+    ///
+    /// # Arguments
+    /// * `timeout` - Maximum ticks to wait (0 = forever)
+    /// * `opt` - Pend options
+    pub fn write_lock(&mut self, timeout: OsTick, pend_opt: OsOpt) -> OsResult<()> {
+        self.lock(timeout, pend_opt, OsPendOn::RwLockWrite)
+    }
+
+    fn lock(&mut self, timeout: OsTick, pend_opt: OsOpt, on: OsPendOn) -> OsResult<()> {
+        if is_isr_context() {
+            return Err(OsError::PendIsr);
+        }
+
+        if !kernel::KERNEL.is_running() {
+            return Err(OsError::OsNotRunning);
+        }
+
+        opt::check(pend_opt, opt::PEND_MASK)?;
+
+        if self.obj_type != OsObjType::RwLock {
+            return Err(OsError::ObjType);
+        }
+
+        let pended = critical_section(|_cs| {
+            #[cfg(feature = "obj-stats")]
+            {
+                self.stats.pends = self.stats.pends.wrapping_add(1);
+            }
+
+            unsafe {
+                pend_block(self, on, timeout, pend_opt, |lock, cur| {
+                    if lock.writer == Some(cur) {
+                        return Some(Err(OsError::RwLockOwner));
+                    }
+                    let acquired = match on {
+                        OsPendOn::RwLockRead => lock.try_read(),
+                        _ => lock.try_write(cur),
+                    };
+                    acquired.then_some(Ok(()))
+                })
+            }
+        })?;
+        match pended {
+            Pended::Acquired(()) => Ok(()),
+            Pended::Blocked(cur) => unsafe {
+                pend_wait(cur, |result| {
+                    #[cfg(feature = "obj-stats")]
+                    self.stats.woke(&result);
+
+                    result
+                })
+            },
+        }
+    }
+
+    /// Release a read lock
+    ///
+    /// Read locks are counted, not tracked per task, so the caller is not
+    /// checked. The last reader hands the lock to the first waiting writer.
+    pub fn read_unlock(&mut self, post_opt: OsOpt) -> OsResult<()> {
+        opt::check(post_opt, opt::POST_LIFO | opt::POST_NO_SCHED)?;
+
+        if self.obj_type != OsObjType::RwLock {
+            return Err(OsError::ObjType);
+        }
+
+        critical_section(|_cs| {
+            if self.readers == 0 {
+                return Err(OsError::RwLockNotLocked);
+            }
+
+            #[cfg(feature = "obj-stats")]
+            {
+                self.stats.posts = self.stats.posts.wrapping_add(1);
+            }
+
+            self.readers -= 1;
+            self.release(false, post_opt);
+            Ok(())
+        })
+    }
+
+    /// Release the write lock
+    ///
+    /// Restores a priority boosted by waiters. Readers that queued behind
+    /// the writer are admitted first, otherwise the next writer gets the
+    /// lock (`POST_LIFO` picks the most recent writer of the highest
+    /// waiting priority).
+    pub fn write_unlock(&mut self, post_opt: OsOpt) -> OsResult<()> {
+        if is_isr_context() {
+            return Err(OsError::AcceptIsr);
+        }
+
+        if !kernel::KERNEL.is_running() {
+            return Err(OsError::OsNotRunning);
+        }
+
+        opt::check(post_opt, opt::POST_LIFO | opt::POST_NO_SCHED)?;
+
+        if self.obj_type != OsObjType::RwLock {
+            return Err(OsError::ObjType);
+        }
+
+        critical_section(|_cs| {
+            let cur_tcb_ptr = unsafe { kernel::tcb_cur_ptr() }.ok_or(OsError::TcbInvalid)?;
+
+            if self.writer != Some(cur_tcb_ptr) {
+                return Err(OsError::RwLockNotOwner);
+            }
+
+            #[cfg(feature = "obj-stats")]
+            {
+                self.stats.posts = self.stats.posts.wrapping_add(1);
+            }
+
+            // Restore owner's priority if it was boosted
+            unsafe { prio_disinherit(cur_tcb_ptr) };
+
+            self.writer = None;
+            self.release(true, post_opt);
+            Ok(())
+        })
+    }
+
+    /// Take a read lock if no writer holds or waits
+    fn try_read(&mut self) -> bool {
+        if self.writer.is_none() && self.write_list.is_empty() {
+            self.readers += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Take the write lock for `cur` if the lock is free
+    fn try_write(&mut self, cur: NonNull<OsTcb>) -> bool {
+        if self.writer.is_none() && self.readers == 0 {
+            self.writer = Some(cur);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Boost the write owner to `prio` if a more urgent task waits
+    fn boost_writer(&self, prio: OsPrio) {
+        let Some(owner_ptr) = self.writer else { return };
+        if prio < unsafe { owner_ptr.as_ref() }.prio() {
+            unsafe { prio_inherit(owner_ptr, prio, self as *const _ as *const (), self.name) };
+        }
+    }
+
+    /// Re-sort a waiter whose priority changed, passing a boost on to the writer
+    ///
+    /// # Safety
+    /// Called with the critical section held, `tcb` pending on this lock.
+    pub(crate) unsafe fn pend_prio_change(&mut self, tcb: NonNull<OsTcb>) {
+        let tcb_ref = unsafe { tcb.as_ref() };
+        self.waiters_mut(tcb_ref.pend_on()).resort(tcb);
+        self.boost_writer(tcb_ref.prio());
+    }
+
+    fn waiters_mut(&mut self, on: OsPendOn) -> &mut PendList {
+        match on {
+            OsPendOn::RwLockRead => &mut self.read_list,
+            _ => &mut self.write_list,
+        }
+    }
+
+    /// Hand the lock to waiters and reschedule
+    fn release(&mut self, readers_first: bool, post_opt: OsOpt) {
+        let woke = self.grant(readers_first, post_opt, |tcb_ptr| unsafe { pend_wake(tcb_ptr, OsPendStatus::Ok) });
+        if woke && post_opt & opt::POST_NO_SCHED == 0 {
+            sched::os_sched();
+        }
+    }
+
+    /// Move waiters that can now hold the lock out of the pend lists
+    ///
+    /// `readers_first` admits queued readers ahead of the next writer, which
+    /// is what a write unlock does. `wake` is called for every task granted
+    /// the lock. Returns whether any task was granted.
+    fn grant(&mut self, readers_first: bool, post_opt: OsOpt, mut wake: impl FnMut(NonNull<OsTcb>)) -> bool {
+        if self.writer.is_some() {
+            return false;
+        }
+
+        let admit_readers = !self.read_list.is_empty()
+            && (readers_first || self.write_list.is_empty());
+
+        if admit_readers {
+            while let Some(tcb_ptr) = self.read_list.head() {
+                self.read_list.remove(tcb_ptr);
+                self.readers += 1;
+                wake(tcb_ptr);
+            }
+            return true;
+        }
+
+        if self.readers == 0 {
+            if let Some(tcb_ptr) = self.write_list.next_waiter(post_opt) {
+                self.write_list.remove(tcb_ptr);
+                self.writer = Some(tcb_ptr);
+                wake(tcb_ptr);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn unlink(&mut self, tcb: NonNull<OsTcb>, wake: impl FnMut(NonNull<OsTcb>)) {
+        let on = unsafe { tcb.as_ref() }.pend_on();
+        self.waiters_mut(on).remove(tcb);
+        if on == OsPendOn::RwLockWrite {
+            self.grant(false, opt::NONE, wake);
+        }
+    }
+
+    /// Number of read locks held
+    #[inline]
+    pub fn readers(&self) -> usize {
+        self.readers
+    }
+
+    /// Check if the write lock is held
+    #[inline]
+    pub fn is_write_locked(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Name given at creation
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        obj_name_str(self.name)
+    }
+
+    /// Number of tasks waiting to read or write
+    #[inline]
+    pub fn waiters(&self) -> usize {
+        self.read_list.len() + self.write_list.len()
+    }
+
+    /// Copy of the pend/post counters
+    #[cfg(feature = "obj-stats")]
+    pub fn stats(&self) -> OsObjStats {
+        critical_section(|_cs| self.stats.snapshot(self.waiters()))
+    }
+}
+
+impl Default for OsRwLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PendObject for OsRwLock {
+    fn pend_list(&mut self, on: OsPendOn) -> &mut PendList {
+        self.waiters_mut(on)
+    }
+
+    unsafe fn pend_blocking(&mut self, tcb: NonNull<OsTcb>) -> OsResult<()> {
+        self.boost_writer(unsafe { tcb.as_ref() }.prio());
+
+        #[cfg(feature = "obj-stats")]
+        self.stats.blocked(self.read_list.len() + self.write_list.len() + 1);
+        Ok(())
+    }
+
+    /// Unlink a waiter whose pend timed out or that was deleted
+    ///
+    /// A writer leaving may unblock the readers queued behind it.
+    unsafe fn pend_unlink(&mut self, tcb: NonNull<OsTcb>) {
+        self.unlink(tcb, |tcb_ptr| unsafe { pend_wake(tcb_ptr, OsPendStatus::Ok) });
+    }
+}
+
+// ============ Safe Wrapper ============
+
+use core::cell::UnsafeCell;
+
+pub struct RwLock {
+    inner: UnsafeCell<OsRwLock>,
+}
+
+// SAFETY: the kernel object is only mutated inside a critical section on a
+// single core, so any task or ISR may share it; a pending task borrows the
+// wrapper, so it cannot move while waited on.
+unsafe impl Sync for RwLock {}
+unsafe impl Send for RwLock {}
+
+impl RwLock {
+    pub const fn new() -> Self {
+        RwLock {
+            inner: UnsafeCell::new(OsRwLock::new()),
+        }
+    }
+
+    pub fn create(&self, name: &'static str) -> OsResult<()> {
+        unsafe { (*self.inner.get()).create(name) }
+    }
+
+    /// Lock for reading
+    pub fn read_lock(&self, timeout: impl Into<Timeout>) -> OsResult<()> {
+        let (ticks, opt) = timeout.into().to_raw();
+        unsafe { (*self.inner.get()).read_lock(ticks, opt) }
+    }
+
+    /// Release a read lock
+    pub fn read_unlock(&self) -> OsResult<()> {
+        unsafe { (*self.inner.get()).read_unlock(opt::NONE) }
+    }
+
+    /// Lock for writing
+    pub fn write_lock(&self, timeout: impl Into<Timeout>) -> OsResult<()> {
+        let (ticks, opt) = timeout.into().to_raw();
+        unsafe { (*self.inner.get()).write_lock(ticks, opt) }
+    }
+
+    /// Release the write lock
+    pub fn write_unlock(&self) -> OsResult<()> {
+        unsafe { (*self.inner.get()).write_unlock(opt::NONE) }
+    }
+
+    /// Lock for reading, unlocked when the guard is dropped
+    pub fn read(&self, timeout: impl Into<Timeout>) -> OsResult<RwLockReadGuard<'_>> {
+        self.read_lock(timeout)?;
+        Ok(RwLockReadGuard { lock: self, _task: PhantomData })
+    }
+
+    /// Lock for writing, unlocked when the guard is dropped
+    pub fn write(&self, timeout: impl Into<Timeout>) -> OsResult<RwLockWriteGuard<'_>> {
+        self.write_lock(timeout)?;
+        Ok(RwLockWriteGuard { lock: self, _task: PhantomData })
+    }
+
+    /// Number of read locks held
+    #[inline]
+    pub fn readers(&self) -> usize {
+        unsafe { (*self.inner.get()).readers() }
+    }
+
+    /// Check if the write lock is held
+    #[inline]
+    pub fn is_write_locked(&self) -> bool {
+        unsafe { (*self.inner.get()).is_write_locked() }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        unsafe { (*self.inner.get()).name() }
+    }
+
+    /// Number of tasks waiting to read or write
+    #[inline]
+    pub fn waiters(&self) -> usize {
+        unsafe { (*self.inner.get()).waiters() }
+    }
+
+    /// Copy of the pend/post counters
+    #[cfg(feature = "obj-stats")]
+    pub fn stats(&self) -> OsObjStats {
+        unsafe { (*self.inner.get()).stats() }
+    }
+}
+
+impl Default for RwLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read lock held until dropped
+///
+/// Neither `Send` nor `Sync`: the lock belongs to the task that took it.
+///
+/// ```compile_fail
+/// fn send<T: Send>() {}
+/// send::<ucosiii::rwlock::RwLockReadGuard<'static>>();
+/// ```
+pub struct RwLockReadGuard<'a> {
+    lock: &'a RwLock,
+    _task: PhantomData<*mut ()>,
+}
+
+impl Drop for RwLockReadGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.lock.read_unlock();
+    }
+}
+
+/// Write lock held until dropped
+///
+/// Neither `Send` nor `Sync`: the lock belongs to the task that took it.
+///
+/// ```compile_fail
+/// fn send<T: Send>() {}
+/// send::<ucosiii::rwlock::RwLockWriteGuard<'static>>();
+/// ```
+pub struct RwLockWriteGuard<'a> {
+    lock: &'a RwLock,
+    _task: PhantomData<*mut ()>,
+}
+
+impl Drop for RwLockWriteGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.lock.write_unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Block `tcb` on `lock` the way `lock()` does, without the scheduler
+    fn wait(lock: &mut OsRwLock, tcb: &mut OsTcb, on: OsPendOn) {
+        let ptr = NonNull::from(&mut *tcb);
+        tcb.set_pending(lock as *const _ as *const (), on, 0);
+        lock.waiters_mut(on).insert_by_prio(ptr);
+    }
+
+    fn tcb(prio: OsPrio) -> OsTcb {
+        let mut tcb = OsTcb::new();
+        tcb.prio = prio;
+        tcb.base_prio = prio;
+        tcb
+    }
+
+    #[test]
+    fn test_readers_share() {
+        let mut lock = OsRwLock::new();
+        let mut w = tcb(5);
+
+        assert!(lock.try_read());
+        assert!(lock.try_read());
+        assert!(lock.try_read());
+        assert_eq!(lock.readers(), 3);
+
+        // A writer waits for all of them
+        assert!(!lock.try_write(NonNull::from(&mut w)));
+        assert!(!lock.is_write_locked());
+    }
+
+    #[test]
+    fn test_writer_excludes() {
+        let mut lock = OsRwLock::new();
+        let (mut w1, mut w2) = (tcb(5), tcb(6));
+
+        assert!(lock.try_write(NonNull::from(&mut w1)));
+        assert!(!lock.try_read());
+        assert!(!lock.try_write(NonNull::from(&mut w2)));
+
+        // Release: the second writer gets it and the count of readers stays 0
+        wait(&mut lock, &mut w2, OsPendOn::RwLockWrite);
+        lock.writer = None;
+        let mut woken = [None; 2];
+        let mut n = 0;
+        assert!(lock.grant(true, opt::NONE, |t| { woken[n] = Some(t); n += 1; }));
+        assert_eq!(woken, [Some(NonNull::from(&mut w2)), None]);
+        assert_eq!(lock.writer, Some(NonNull::from(&mut w2)));
+        assert_eq!(lock.readers(), 0);
+    }
+
+    #[test]
+    fn test_writer_preference_and_reader_turn() {
+        let mut lock = OsRwLock::new();
+        let (mut w1, mut w2) = (tcb(5), tcb(5));
+        let (mut r1, mut r2) = (tcb(3), tcb(7));
+
+        // A reader holds the lock and a writer queues behind it
+        assert!(lock.try_read());
+        wait(&mut lock, &mut w1, OsPendOn::RwLockWrite);
+
+        // New readers no longer get in
+        assert!(!lock.try_read());
+        wait(&mut lock, &mut r1, OsPendOn::RwLockRead);
+        wait(&mut lock, &mut r2, OsPendOn::RwLockRead);
+
+        // Last reader leaves: the writer goes first
+        lock.readers -= 1;
+        let mut granted = 0;
+        assert!(lock.grant(false, opt::NONE, |t| {
+            assert_eq!(t, NonNull::from(&mut w1));
+            granted += 1;
+        }));
+        assert_eq!(granted, 1);
+
+        // Another writer queues; the writer's release admits both readers
+        wait(&mut lock, &mut w2, OsPendOn::RwLockWrite);
+        lock.writer = None;
+        granted = 0;
+        assert!(lock.grant(true, opt::NONE, |_| granted += 1));
+        assert_eq!(granted, 2);
+        assert_eq!(lock.readers(), 2);
+        assert_eq!(lock.waiters(), 1);
+
+        // The next writer waits for them, then gets the lock
+        lock.readers -= 1;
+        assert!(!lock.grant(false, opt::NONE, |_| panic!()));
+        lock.readers -= 1;
+        assert!(lock.grant(false, opt::NONE, |_| {}));
+        assert_eq!(lock.writer, Some(NonNull::from(&mut w2)));
+    }
+
+    #[test]
+    fn test_writer_timeout_admits_readers() {
+        let mut lock = OsRwLock::new();
+        let (mut w, mut r) = (tcb(5), tcb(6));
+
+        assert!(lock.try_read());
+        wait(&mut lock, &mut w, OsPendOn::RwLockWrite);
+        wait(&mut lock, &mut r, OsPendOn::RwLockRead);
+
+        // The writer gives up; the reader behind it no longer has to wait
+        let r_ptr = NonNull::from(&mut r);
+        let mut granted = 0;
+        lock.unlink(NonNull::from(&mut w), |t| {
+            assert_eq!(t, r_ptr);
+            granted += 1;
+        });
+        assert_eq!(granted, 1);
+        assert_eq!(lock.readers(), 2);
+        assert_eq!(lock.waiters(), 0);
+    }
+}
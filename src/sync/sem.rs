@@ -4,19 +4,37 @@
 
 use core::ptr::NonNull;
 
+#[cfg(feature = "obj-pool")]
+use crate::config::CFG_SEM_POOL_SIZE;
+use crate::core::os_list_chk;
+#[cfg(feature = "obj-pool")]
+use crate::core::cs_cell::CsCell;
 use crate::critical::{critical_section, is_isr_context};
+#[cfg(feature = "obj-pool")]
+use crate::critical::CriticalSection;
 use crate::error::{OsError, OsResult};
 use crate::kernel;
 use crate::sched;
+#[cfg(feature = "obj-stats")]
+use crate::sync::stats::{OsObjStats, SemStats};
+use crate::sync::{pend_block, pend_end, pend_wait, PendObject, Pended};
+#[cfg(feature = "obj-pool")]
+use crate::sync::{ObjHandle, ObjPool};
 use crate::task::OsTcb;
-use crate::types::{OsObjType, OsOpt, OsPendOn, OsPendStatus, OsSemCtr, OsTaskState, OsTick, opt};
+use crate::types::{obj_name, obj_name_str, ObjName, OsObjType, OsOpt, OsPendOn, OsPendStatus, OsPrio, OsSemCtr, OsTick, PostOpt, Timeout, opt};
 
 /// Pend list for tasks waiting on a kernel object
+///
+/// Neither `Send` nor `Sync`, see below.
+///
+/// ```compile_fail
+/// fn send<T: Send>() {}
+/// send::<ucosiii::sem::OsSem>();
+/// ```
 #[derive(Debug)]
 pub struct PendList {
     head: Option<NonNull<OsTcb>>,
     tail: Option<NonNull<OsTcb>>,
-    #[cfg(feature = "defmt")]
     count: usize,
 }
 
@@ -26,7 +44,6 @@ impl PendList {
         PendList {
             head: None,
             tail: None,
-            #[cfg(feature = "defmt")]
             count: 0,
         }
     }
@@ -35,10 +52,7 @@ impl PendList {
     pub fn init(&mut self) {
         self.head = None;
         self.tail = None;
-        #[cfg(feature = "defmt")]
-        {
-            self.count = 0;
-        }
+        self.count = 0;
     }
 
     /// Check if list is empty
@@ -53,8 +67,44 @@ impl PendList {
         self.head
     }
 
+    /// Get tail of list
+    #[inline(always)]
+    pub fn tail(&self) -> Option<NonNull<OsTcb>> {
+        self.tail
+    }
+
+    /// Number of waiting tasks
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Waiter a post with `post_opt` should wake
+    ///
+    /// The list is ordered by priority and FIFO within a priority, so
+    /// `POST_FIFO` takes the head and `POST_LIFO` the last waiter sharing
+    /// the head's priority.
+    pub fn next_waiter(&self, post_opt: OsOpt) -> Option<NonNull<OsTcb>> {
+        let head = self.head?;
+        if post_opt & opt::POST_LIFO == 0 {
+            return Some(head);
+        }
+
+        let prio = unsafe { head.as_ref() }.prio();
+        let mut last = head;
+        while let Some(next) = unsafe { last.as_ref() }.pend_next_ptr {
+            if unsafe { next.as_ref() }.prio() != prio {
+                break;
+            }
+            last = next;
+        }
+        Some(last)
+    }
+
     /// Insert TCB at tail
     pub fn insert(&mut self, tcb: NonNull<OsTcb>) {
+        os_list_chk!(link, tcb, Pend, self, "PendList::insert");
+
         let tcb_ref = unsafe { &mut *tcb.as_ptr() };
         
         tcb_ref.pend_next_ptr = None;
@@ -71,23 +121,24 @@ impl PendList {
 
         self.tail = Some(tcb);
 
-        #[cfg(feature = "defmt")]
-        {
-            self.count += 1;
-        }
+        self.count += 1;
+
+        os_list_chk!(walk, self.head, Some(self.tail), self.chk_count(), Pend, self, "PendList::insert");
     }
 
     /// Insert in priority order
     pub fn insert_by_prio(&mut self, tcb: NonNull<OsTcb>) {
+        os_list_chk!(link, tcb, Pend, self, "PendList::insert_by_prio");
+
         let tcb_ref = unsafe { tcb.as_ref() };
-        let prio = tcb_ref.prio;
+        let prio = tcb_ref.prio();
 
         let mut current = self.head;
         let mut prev: Option<NonNull<OsTcb>> = None;
 
         while let Some(cur_ptr) = current {
             let cur_ref = unsafe { cur_ptr.as_ref() };
-            if prio < cur_ref.prio {
+            if prio < cur_ref.prio() {
                 break;
             }
             prev = current;
@@ -116,14 +167,15 @@ impl PendList {
             }
         }
 
-        #[cfg(feature = "defmt")]
-        {
-            self.count += 1;
-        }
+        self.count += 1;
+
+        os_list_chk!(walk, self.head, Some(self.tail), self.chk_count(), Pend, self, "PendList::insert_by_prio");
     }
 
     /// Remove specific TCB from list
     pub fn remove(&mut self, tcb: NonNull<OsTcb>) {
+        os_list_chk!(unlink, tcb, Pend, self, "PendList::remove");
+
         let tcb_ref = unsafe { &mut *tcb.as_ptr() };
 
         match tcb_ref.pend_prev_ptr {
@@ -147,10 +199,21 @@ impl PendList {
         tcb_ref.pend_prev_ptr = None;
         tcb_ref.pend_next_ptr = None;
 
-        #[cfg(feature = "defmt")]
-        {
-            self.count = self.count.saturating_sub(1);
-        }
+        self.count = self.count.saturating_sub(1);
+
+        os_list_chk!(walk, self.head, Some(self.tail), self.chk_count(), Pend, self, "PendList::remove");
+    }
+
+    /// Move a waiter to its place after its priority changed
+    pub fn resort(&mut self, tcb: NonNull<OsTcb>) {
+        self.remove(tcb);
+        self.insert_by_prio(tcb);
+    }
+
+    /// Entry count for list checks
+    #[cfg(feature = "paranoid")]
+    fn chk_count(&self) -> Option<usize> {
+        Some(self.count)
     }
 }
 
@@ -160,20 +223,31 @@ impl Default for PendList {
     }
 }
 
-unsafe impl Send for PendList {}
-unsafe impl Sync for PendList {}
+// Not `Send` or `Sync`: the waiters point back at the object holding the
+// list, which only the kernel links and unlinks under a critical section.
+// The raw objects (`OsSem`, `OsMutex`, ...) inherit this; share them
+// through their wrappers.
 
 /// Counting semaphore
+#[repr(C)]
 pub struct OsSem {
     /// Object type marker
-    obj_type: OsObjType,
+    pub(crate) obj_type: OsObjType,
     /// List of tasks waiting on this semaphore
     pend_list: PendList,
+    /// Waiters that timed out and have not run yet; a post still reaches them
+    late: PendList,
     /// Current count
     count: OsSemCtr,
+    /// Highest count
+    max: OsSemCtr,
+    /// Post behaviour at `max`
+    full: SemFull,
+    /// Pend/post counters
+    #[cfg(feature = "obj-stats")]
+    stats: OsObjStats,
     /// Name for debugging
-    #[cfg(feature = "defmt")]
-    name: &'static str,
+    name: ObjName,
 }
 
 impl OsSem {
@@ -186,40 +260,83 @@ impl OsSem {
         OsSem {
             obj_type: OsObjType::Sem,
             pend_list: PendList::new(),
+            late: PendList::new(),
             count,
-            #[cfg(feature = "defmt")]
-            name: "",
+            max: OsSemCtr::MAX,
+            full: SemFull::Error,
+            #[cfg(feature = "obj-stats")]
+            stats: OsObjStats::new(),
+            name: obj_name(""),
         }
     }
 
     /// Initialize/create the semaphore
-    pub fn create(&mut self, count: OsSemCtr, _name: &'static str) -> OsResult<()> {
+    pub fn create(&mut self, count: OsSemCtr, name: &'static str) -> OsResult<()> {
+        self.create_bounded(count, OsSemCtr::MAX, SemFull::Error, name)
+    }
+
+    /// Initialize/create a semaphore that counts up to `max`
+    ///
+    /// `max = 1` gives a binary semaphore.
+    ///
+    /// # Arguments
+    /// * `count` - Initial count value
+    /// * `max` - Highest count
+    /// * `full` - What a post does when the count is at `max`
+    /// * `name` - Semaphore name
+    ///
+    /// # Returns
+    /// * `Err(OsError::SemMaxInvalid)` - `max` is 0 or below `count`
+    pub fn create_bounded(&mut self, count: OsSemCtr, max: OsSemCtr, full: SemFull, name: &'static str) -> OsResult<()> {
+        if max == 0 || count > max {
+            return Err(OsError::SemMaxInvalid);
+        }
+
         if is_isr_context() {
             return Err(OsError::CreateIsr);
         }
 
-        critical_section(|_cs| {
+        if kernel::KERNEL.is_safety_critical() {
+            return Err(OsError::IllegalCreateRunTime);
+        }
+
+        critical_section(|cs| {
+            kernel::registry::register_cs(cs, OsObjType::Sem, name, self as *const _ as *const ());
             self.obj_type = OsObjType::Sem;
             self.pend_list.init();
+            self.late.init();
             self.count = count;
-            #[cfg(feature = "defmt")]
+            self.max = max;
+            self.full = full;
+            #[cfg(feature = "obj-stats")]
             {
-                self.name = _name;
+                self.stats = OsObjStats::new();
             }
+            self.name = obj_name(name);
             Ok(())
         })
     }
 
     /// Wait on (pend) the semaphore
     ///
+    /// With `PEND_CONSUME_ALL` a nonzero count is taken whole, for a task
+    /// that only cares whether an event happened since it last looked. A
+    /// task that had to wait also takes what was posted between its wake-up
+    /// and its return.
+    ///
     /// # Arguments
     /// * `timeout` - Maximum ticks to wait (0 = forever)
-    /// * `opt` - Pend options
+    /// * `opt` - Pend options (`PEND_NON_BLOCKING`, `PEND_CONSUME_ALL`)
     ///
     /// # Returns
-    /// * `Ok(count)` - Semaphore acquired, returns current count
+    /// * `Ok(count)` - Semaphore acquired, returns current count, or the
+    ///   count taken with `PEND_CONSUME_ALL`
     /// * `Err(OsError::Timeout)` - Timeout expired
     /// * `Err(OsError::PendWouldBlock)` - Non-blocking and not available
+    /// * `Err(OsError::SchedLocked)` - Would block with the scheduler locked
+    /// * `Err(OsError::OsNotRunning)` - Called before `os_start`
+    /// * `Err(OsError::OptInvalid)` - `pend_opt` has a bit other than
+    ///   `PEND_NON_BLOCKING` or `PEND_CONSUME_ALL`
     pub fn pend(&mut self, timeout: OsTick, pend_opt: OsOpt) -> OsResult<OsSemCtr> {
         if is_isr_context() {
             return Err(OsError::PendIsr);
@@ -229,124 +346,298 @@ impl OsSem {
             return Err(OsError::OsNotRunning);
         }
 
+        opt::check(pend_opt, opt::SEM_PEND_MASK)?;
+
         if self.obj_type != OsObjType::Sem {
             return Err(OsError::ObjType);
         }
 
-        critical_section(|_cs| {
-            if self.count > 0 {
-                self.count -= 1;
-                return Ok(self.count);
-            }
+        crate::core::os_trace!(SemPend { obj: self as *const _ as u32 });
+        let consume_all = pend_opt & opt::PEND_CONSUME_ALL != 0;
 
-            if pend_opt & opt::PEND_NON_BLOCKING != 0 {
-                return Err(OsError::PendWouldBlock);
-            }
-
-            if kernel::KERNEL.sched_lock_nesting() > 0 {
-                return Err(OsError::SchedLocked);
+        let pended = critical_section(|_cs| {
+            #[cfg(feature = "obj-stats")]
+            {
+                self.stats.pends = self.stats.pends.wrapping_add(1);
             }
 
-            // Block current task
             unsafe {
-                if let Some(cur_tcb_ptr) = kernel::tcb_cur_ptr() {
-                    let cur_tcb = &mut *cur_tcb_ptr.as_ptr();
-
-                    sched::os_rdy_list_remove(cur_tcb_ptr);
-
-                    cur_tcb.pend_on = OsPendOn::Semaphore;
-                    cur_tcb.pend_status = OsPendStatus::Ok;
-                    cur_tcb.pend_obj_ptr = self as *const _ as *const ();
-                    cur_tcb.tick_remain = timeout;
-                    
-                    if timeout > 0 {
-                        cur_tcb.task_state = OsTaskState::PendTimeout;
-                    } else {
-                        cur_tcb.task_state = OsTaskState::Pend;
-                    }
-
-                    self.pend_list.insert_by_prio(cur_tcb_ptr);
-                }
+                pend_block(self, OsPendOn::Semaphore, timeout, pend_opt, |sem, _| {
+                    (sem.count > 0).then(|| Ok(sem.take(consume_all)))
+                })
             }
+        })?;
+        match pended {
+            Pended::Acquired(count) => Ok(count),
+            Pended::Blocked(cur) => unsafe {
+                pend_wait(cur, |result| {
+                    #[cfg(feature = "obj-stats")]
+                    self.stats.woke(&result);
 
-            sched::os_sched();
+                    result.map(|()| self.taken_on_wake(consume_all))
+                })
+            },
+        }
+    }
 
-            unsafe {
-                if let Some(cur_tcb_ptr) = kernel::tcb_cur_ptr() {
-                    let cur_tcb = cur_tcb_ptr.as_ref();
-                    
-                    match cur_tcb.pend_status {
-                        OsPendStatus::Ok => Ok(self.count),
-                        OsPendStatus::Timeout => Err(OsError::Timeout),
-                        OsPendStatus::Abort => Err(OsError::PendAbort),
-                        OsPendStatus::Del => Err(OsError::ObjDel),
-                    }
-                } else {
-                    Err(OsError::TcbInvalid)
-                }
+    /// Take one count, or all of it; returns what `pend` reports
+    fn take(&mut self, all: bool) -> OsSemCtr {
+        if all {
+            core::mem::take(&mut self.count)
+        } else {
+            self.count -= 1;
+            self.count
+        }
+    }
+
+    /// What a woken `pend` reports: the post handed over one count directly
+    fn taken_on_wake(&mut self, all: bool) -> OsSemCtr {
+        if all {
+            1 + core::mem::take(&mut self.count)
+        } else {
+            self.count
+        }
+    }
+
+    /// Take the whole count without waiting
+    ///
+    /// Callable from ISRs and before `os_start`.
+    ///
+    /// # Returns
+    /// * `Ok(count)` - The count taken, 0 if there was none
+    pub fn accept(&mut self) -> OsResult<OsSemCtr> {
+        if self.obj_type != OsObjType::Sem {
+            return Err(OsError::ObjType);
+        }
+
+        critical_section(|_cs| {
+            #[cfg(feature = "obj-stats")]
+            {
+                self.stats.pends = self.stats.pends.wrapping_add(1);
             }
+            Ok(self.take(true))
         })
     }
 
     /// Signal (post) the semaphore
     ///
+    /// Callable from ISRs and before `os_start`, where posts only count up.
+    ///
     /// # Arguments
-    /// * `opt` - Post options
+    /// * `opt` - Post options (`POST_FIFO`/`POST_LIFO` pick among waiters of
+    ///   the highest priority, `POST_ALL` wakes every waiter, `POST_FRONT`
+    ///   readies them ahead of their peers, `POST_NO_SCHED`)
+    ///
+    /// The work under the critical section is constant for the default
+    /// `POST_FIFO`: the head of the pend list is already the highest
+    /// waiting priority, it is unlinked in place and readied with one
+    /// bitmap set; a timed waiter leaves the tick queue the same way.
+    /// `POST_LIFO` walks the waiters sharing the head's priority and
+    /// `POST_ALL` readies every waiter, so prefer `POST_FIFO` from an ISR.
+    ///
+    /// A waiter whose timeout expired but that has not run yet still takes
+    /// the post when nobody else waits: its `pend` returns `Ok` instead of
+    /// `Timeout`, and the count is not raised. Once it has run, the timeout
+    /// stands and the post counts up.
     ///
     /// # Returns
-    /// * `Ok(count)` - New count after post
-    /// * `Err(OsError::SemOvf)` - Counter overflow
-    pub fn post(&mut self, post_opt: OsOpt) -> OsResult<OsSemCtr> {
+    /// * `Ok(PostResult::WokeTask(prio))` - Handed to a waiter; the count is unchanged
+    /// * `Ok(PostResult::Count(count))` - No waiter; the count after the post
+    /// * `Err(OsError::SemOvf)` - Count already at its maximum, unless created
+    ///   with `SemFull::Saturate`
+    /// * `Err(OsError::OptInvalid)` - `post_opt` has a non-`POST_*` bit
+    pub fn post(&mut self, post_opt: OsOpt) -> OsResult<PostResult> {
+        opt::check(post_opt, opt::POST_MASK)?;
+
         if self.obj_type != OsObjType::Sem {
             return Err(OsError::ObjType);
         }
 
-        critical_section(|_cs| {
-            if let Some(tcb_ptr) = self.pend_list.head() {
-                let tcb = unsafe { &mut *tcb_ptr.as_ptr() };
+        crate::core::os_trace!(SemPost { obj: self as *const _ as u32 });
 
-                self.pend_list.remove(tcb_ptr);
-
-                tcb.pend_on = OsPendOn::Nothing;
-                tcb.pend_status = OsPendStatus::Ok;
-                tcb.pend_obj_ptr = core::ptr::null();
-                tcb.tick_remain = 0;
-                tcb.task_state = OsTaskState::Ready;
-
-                unsafe { sched::os_rdy_list_insert(tcb_ptr) };
+        critical_section(|_cs| {
+            #[cfg(feature = "obj-stats")]
+            {
+                self.stats.posts = self.stats.posts.wrapping_add(1);
+            }
 
-                if post_opt & opt::POST_NO_SCHED == 0 && !is_isr_context() {
+            let front = post_opt & opt::POST_FRONT != 0;
+            if let Some(prio) = self.wake(post_opt, |tcb_ptr| unsafe { sched::os_rdy_list_insert_woken(tcb_ptr, front) }) {
+                if post_opt & opt::POST_NO_SCHED == 0 {
                     sched::os_sched();
                 }
+                return Ok(PostResult::WokeTask(prio));
+            }
+            if let Some(prio) = self.rescue(post_opt) {
+                return Ok(PostResult::WokeTask(prio));
+            }
 
-                Ok(self.count)
-            } else {
-                if self.count == OsSemCtr::MAX {
-                    return Err(OsError::SemOvf);
-                }
-                self.count += 1;
-                Ok(self.count)
+            if self.count >= self.max {
+                return match self.full {
+                    SemFull::Error => Err(OsError::SemOvf),
+                    SemFull::Saturate => Ok(PostResult::Count(self.max)),
+                };
             }
+            self.count += 1;
+            Ok(PostResult::Count(self.count))
         })
     }
 
+    /// Hand a post to the waiters `post_opt` selects, readying each with `ready`
+    ///
+    /// Returns the priority of the first task woken, which is the highest
+    /// waiting priority.
+    fn wake(&mut self, post_opt: OsOpt, mut ready: impl FnMut(NonNull<OsTcb>)) -> Option<OsPrio> {
+        let first = self.pend_list.next_waiter(post_opt)?;
+        let prio = unsafe { first.as_ref() }.prio();
+
+        let mut next = Some(first);
+        while let Some(tcb_ptr) = next {
+            self.pend_list.remove(tcb_ptr);
+            if unsafe { pend_end(tcb_ptr, OsPendStatus::Ok) } {
+                ready(tcb_ptr);
+            }
+
+            next = match post_opt & opt::POST_ALL {
+                0 => None,
+                _ => self.pend_list.next_waiter(post_opt),
+            };
+        }
+        Some(prio)
+    }
+
+    /// Hand a post to the waiters `post_opt` selects among the late ones
+    ///
+    /// They are already ready; their pend now reports `Ok`. Returns the
+    /// priority of the first.
+    fn rescue(&mut self, post_opt: OsOpt) -> Option<OsPrio> {
+        let first = self.late.next_waiter(post_opt)?;
+        let prio = unsafe { first.as_ref() }.prio();
+
+        let mut next = Some(first);
+        while let Some(tcb_ptr) = next {
+            self.late.remove(tcb_ptr);
+            let tcb = unsafe { &mut *tcb_ptr.as_ptr() };
+            tcb.pend_status = OsPendStatus::Ok;
+            tcb.pend_obj_ptr = core::ptr::null();
+
+            next = match post_opt & opt::POST_ALL {
+                0 => None,
+                _ => self.late.next_waiter(post_opt),
+            };
+        }
+        Some(prio)
+    }
+
+    /// Keep a waiter that just timed out within reach of a post
+    ///
+    /// # Safety
+    /// Called with the critical section held, `tcb` off the pend list.
+    pub(crate) unsafe fn late_insert(&mut self, tcb: NonNull<OsTcb>) {
+        unsafe { (*tcb.as_ptr()).pend_obj_ptr = self as *const _ as *const () };
+        self.late.insert_by_prio(tcb);
+    }
+
+    /// Forget a late waiter, which now keeps its timeout
+    ///
+    /// # Safety
+    /// Called with the critical section held, `tcb` in the late list.
+    pub(crate) unsafe fn late_remove(&mut self, tcb: NonNull<OsTcb>) {
+        self.late.remove(tcb);
+        unsafe { (*tcb.as_ptr()).pend_obj_ptr = core::ptr::null() };
+    }
+
+    /// Re-sort a waiter whose priority changed
+    #[cfg(any(feature = "mutex", feature = "rwlock"))]
+    pub(crate) fn pend_prio_change(&mut self, tcb: NonNull<OsTcb>) {
+        self.pend_list.resort(tcb);
+    }
+
     /// Get current semaphore count
     #[inline(always)]
     pub fn count(&self) -> OsSemCtr {
         self.count
     }
 
-    /// Set semaphore count
+    /// Highest count
+    #[inline(always)]
+    pub fn max(&self) -> OsSemCtr {
+        self.max
+    }
+
+    /// Name given at creation
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        obj_name_str(self.name)
+    }
+
+    /// Number of tasks waiting on the semaphore
+    #[inline(always)]
+    pub fn waiters(&self) -> usize {
+        self.pend_list.len()
+    }
+
+    /// Copy of the pend/post counters
+    #[cfg(feature = "obj-stats")]
+    pub fn stats(&self) -> SemStats {
+        critical_section(|_cs| self.stats.snapshot(self.pend_list.len()))
+    }
+
+    /// Set semaphore count, clamped to the maximum
+    ///
+    /// Meant for initializing or resetting an event counter before its
+    /// consumers start waiting. A count handed to blocked tasks would leave
+    /// them blocked with resources available, so use `post` once anyone
+    /// waits.
+    ///
+    /// # Returns
+    /// * `Err(OsError::TaskWaiting)` - Tasks are waiting; the count is unchanged
+    /// * `Err(OsError::AcceptIsr)` - Called from an ISR
     pub fn set(&mut self, count: OsSemCtr) -> OsResult<()> {
         if is_isr_context() {
             return Err(OsError::AcceptIsr);
         }
 
         critical_section(|_cs| {
-            self.count = count;
+            if !self.pend_list.is_empty() {
+                return Err(OsError::TaskWaiting);
+            }
+            self.count = count.min(self.max);
             Ok(())
         })
     }
+
+    /// Wake every waiter with `ObjDel` and unregister, as a delete with
+    /// `DEL_ALWAYS` would; returns the number of waiters
+    #[cfg(feature = "obj-pool")]
+    fn del_always(&mut self, cs: &CriticalSection) -> usize {
+        let mut woken = 0;
+        while let Some(tcb_ptr) = self.pend_list.head() {
+            self.pend_list.remove(tcb_ptr);
+            if unsafe { pend_end(tcb_ptr, OsPendStatus::Del) } {
+                unsafe { sched::os_rdy_list_insert(tcb_ptr) };
+            }
+            woken += 1;
+        }
+        while let Some(tcb_ptr) = self.late.head() {
+            unsafe { self.late_remove(tcb_ptr) };
+        }
+        kernel::registry::unregister_cs(cs, self as *const _ as *const ());
+        self.obj_type = OsObjType::None;
+        woken
+    }
+}
+
+impl PendObject for OsSem {
+    fn pend_list(&mut self, _on: OsPendOn) -> &mut PendList {
+        &mut self.pend_list
+    }
+
+    #[cfg(feature = "obj-stats")]
+    unsafe fn pend_blocking(&mut self, _tcb: NonNull<OsTcb>) -> OsResult<()> {
+        self.stats.blocked(self.pend_list.len() + 1);
+        Ok(())
+    }
 }
 
 impl Default for OsSem {
@@ -355,6 +646,44 @@ impl Default for OsSem {
     }
 }
 
+/// What a post does when the count is at its maximum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemFull {
+    /// Fail with `OsError::SemOvf`
+    Error,
+    /// Leave the count at the maximum and succeed
+    Saturate,
+}
+
+/// Outcome of a semaphore post
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostResult {
+    /// Handed straight to a waiting task of this priority
+    WokeTask(OsPrio),
+    /// No task was waiting; the count after the post
+    Count(OsSemCtr),
+}
+
+impl PostResult {
+    /// Count after the post, 0 when a task took it
+    #[inline]
+    pub const fn count(self) -> OsSemCtr {
+        match self {
+            PostResult::WokeTask(_) => 0,
+            PostResult::Count(count) => count,
+        }
+    }
+
+    /// Priority of the woken task, if any
+    #[inline]
+    pub const fn woke(self) -> Option<OsPrio> {
+        match self {
+            PostResult::WokeTask(prio) => Some(prio),
+            PostResult::Count(_) => None,
+        }
+    }
+}
+
 // ============ Safe Wrapper ============
 
 use core::cell::UnsafeCell;
@@ -363,6 +692,9 @@ pub struct Semaphore {
     inner: UnsafeCell<OsSem>,
 }
 
+// SAFETY: the kernel object is only mutated inside a critical section on a
+// single core, so any task or ISR may share it; a pending task borrows the
+// wrapper, so it cannot move while waited on.
 unsafe impl Sync for Semaphore {}
 unsafe impl Send for Semaphore {}
 
@@ -377,18 +709,76 @@ impl Semaphore {
         unsafe { (*self.inner.get()).create(count, name) }
     }
 
-    pub fn wait(&self, timeout: OsTick, opt: OsOpt) -> OsResult<OsSemCtr> {
-        unsafe { (*self.inner.get()).pend(timeout, opt) }
+    /// Create a semaphore that counts up to `max`
+    pub fn create_bounded(&self, count: OsSemCtr, max: OsSemCtr, full: SemFull, name: &'static str) -> OsResult<()> {
+        unsafe { (*self.inner.get()).create_bounded(count, max, full, name) }
+    }
+
+    /// Wait for the semaphore
+    pub fn wait(&self, timeout: impl Into<Timeout>) -> OsResult<OsSemCtr> {
+        let (ticks, opt) = timeout.into().to_raw();
+        unsafe { (*self.inner.get()).pend(ticks, opt) }
+    }
+
+    /// Wait for the semaphore and take its whole count
+    ///
+    /// Returns the count taken; see `PEND_CONSUME_ALL`.
+    pub fn wait_consume(&self, timeout: impl Into<Timeout>) -> OsResult<OsSemCtr> {
+        let (ticks, opt) = timeout.into().to_raw();
+        unsafe { (*self.inner.get()).pend(ticks, opt | opt::PEND_CONSUME_ALL) }
     }
 
-    pub fn signal(&self, opt: OsOpt) -> OsResult<OsSemCtr> {
-        unsafe { (*self.inner.get()).post(opt) }
+    /// Take the whole count without waiting, 0 if there was none
+    pub fn accept(&self) -> OsResult<OsSemCtr> {
+        unsafe { (*self.inner.get()).accept() }
+    }
+
+    /// Signal the semaphore with the default options
+    pub fn signal(&self) -> OsResult<PostResult> {
+        self.signal_opt(PostOpt::FIFO)
+    }
+
+    /// Signal the semaphore with post options
+    pub fn signal_opt(&self, opt: PostOpt) -> OsResult<PostResult> {
+        unsafe { (*self.inner.get()).post(opt.bits()) }
+    }
+
+    /// Wake every waiting task, or bump the count if none is waiting
+    pub fn broadcast(&self) -> OsResult<PostResult> {
+        unsafe { (*self.inner.get()).post(opt::POST_ALL) }
+    }
+
+    /// Set the count while nobody waits; see [`OsSem::set`]
+    pub fn set(&self, count: OsSemCtr) -> OsResult<()> {
+        unsafe { (*self.inner.get()).set(count) }
     }
 
     #[inline]
     pub fn count(&self) -> OsSemCtr {
         unsafe { (*self.inner.get()).count() }
     }
+
+    #[inline]
+    pub fn max(&self) -> OsSemCtr {
+        unsafe { (*self.inner.get()).max() }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        unsafe { (*self.inner.get()).name() }
+    }
+
+    /// Number of tasks waiting on the semaphore
+    #[inline]
+    pub fn waiters(&self) -> usize {
+        unsafe { (*self.inner.get()).waiters() }
+    }
+
+    /// Copy of the pend/post counters
+    #[cfg(feature = "obj-stats")]
+    pub fn stats(&self) -> SemStats {
+        unsafe { (*self.inner.get()).stats() }
+    }
 }
 
 impl Default for Semaphore {
@@ -396,3 +786,248 @@ impl Default for Semaphore {
         Self::new(0)
     }
 }
+
+// ============ Kernel Pool ============
+
+#[cfg(feature = "obj-pool")]
+const FREE_SEM: OsSem = OsSem::new(0);
+
+#[cfg(feature = "obj-pool")]
+static SEM_POOL: CsCell<ObjPool<OsSem, CFG_SEM_POOL_SIZE>> = CsCell::new(ObjPool::new([FREE_SEM; CFG_SEM_POOL_SIZE]));
+
+#[cfg(feature = "obj-pool")]
+pub(crate) fn pool_reset() {
+    critical_section(|cs| SEM_POOL.get(cs).reset());
+}
+
+/// Semaphore allocated from the kernel pool (`obj-pool`)
+///
+/// Copyable; every use checks that the semaphore was not freed since.
+#[cfg(feature = "obj-pool")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemHandle(ObjHandle);
+
+/// Allocate a semaphore with a count of 0 from the kernel pool
+///
+/// # Returns
+/// * `Err(OsError::ObjPoolEmpty)` - All `CFG_SEM_POOL_SIZE` semaphores are allocated
+/// * `Err(OsError::CreateIsr)` - Called from an ISR
+#[cfg(feature = "obj-pool")]
+pub fn alloc() -> OsResult<SemHandle> {
+    critical_section(|cs| {
+        let pool = SEM_POOL.get(cs);
+        let (handle, sem) = pool.alloc()?;
+        if let Err(err) = unsafe { (*sem).create(0, "") } {
+            let _ = pool.release(handle);
+            return Err(err);
+        }
+        Ok(SemHandle(handle))
+    })
+}
+
+/// Return a semaphore to the kernel pool
+///
+/// Tasks still waiting on it are readied with `OsError::ObjDel`, as by a
+/// delete with `DEL_ALWAYS`, and the handle and its copies go stale.
+///
+/// # Returns
+/// * `Ok(n)` - Number of waiters readied
+/// * `Err(OsError::ObjDel)` - Already freed
+/// * `Err(OsError::DelIsr)` - Called from an ISR
+#[cfg(feature = "obj-pool")]
+pub fn free(handle: SemHandle) -> OsResult<usize> {
+    if is_isr_context() {
+        return Err(OsError::DelIsr);
+    }
+
+    critical_section(|cs| {
+        let sem = SEM_POOL.get(cs).release(handle.0)?;
+        let woken = unsafe { (*sem).del_always(cs) };
+        if woken > 0 {
+            sched::os_sched();
+        }
+        Ok(woken)
+    })
+}
+
+#[cfg(feature = "obj-pool")]
+impl SemHandle {
+    /// Run `f` on the semaphore unless it was freed
+    fn with<R>(self, f: impl FnOnce(&mut OsSem) -> OsResult<R>) -> OsResult<R> {
+        critical_section(|cs| {
+            let sem = SEM_POOL.get(cs).get(self.0)?;
+            f(unsafe { &mut *sem })
+        })
+    }
+
+    /// Wait for the semaphore
+    pub fn wait(self, timeout: impl Into<Timeout>) -> OsResult<OsSemCtr> {
+        let (ticks, opt) = timeout.into().to_raw();
+        self.with(|sem| sem.pend(ticks, opt))
+    }
+
+    /// Take the whole count without waiting, 0 if there was none
+    pub fn accept(self) -> OsResult<OsSemCtr> {
+        self.with(|sem| sem.accept())
+    }
+
+    /// Signal the semaphore with the default options
+    pub fn signal(self) -> OsResult<PostResult> {
+        self.signal_opt(PostOpt::FIFO)
+    }
+
+    /// Signal the semaphore with post options
+    pub fn signal_opt(self, opt: PostOpt) -> OsResult<PostResult> {
+        self.with(|sem| sem.post(opt.bits()))
+    }
+
+    pub fn count(self) -> OsResult<OsSemCtr> {
+        self.with(|sem| Ok(sem.count()))
+    }
+
+    /// Number of tasks waiting on the semaphore
+    pub fn waiters(self) -> OsResult<usize> {
+        self.with(|sem| Ok(sem.waiters()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tcb(prio: OsPrio) -> OsTcb {
+        let mut tcb = OsTcb::new();
+        tcb.prio = prio;
+        tcb.base_prio = prio;
+        tcb
+    }
+
+    /// Block `tcb` on `sem` the way `pend()` does, without the scheduler
+    fn wait(sem: &mut OsSem, tcb: &mut OsTcb) {
+        tcb.set_pending(sem as *const _ as *const (), OsPendOn::Semaphore, 0);
+        sem.pend_list.insert_by_prio(NonNull::from(tcb));
+    }
+
+    #[test]
+    fn test_wake_reports_highest_prio() {
+        let mut sem = OsSem::new(0);
+        let (mut a, mut b, mut c) = (tcb(6), tcb(4), tcb(6));
+        wait(&mut sem, &mut a);
+        wait(&mut sem, &mut b);
+        wait(&mut sem, &mut c);
+
+        let mut woken = 0;
+        assert_eq!(sem.wake(opt::POST_FIFO, |t| { assert_eq!(t, NonNull::from(&mut b)); woken += 1; }), Some(4));
+        assert_eq!(woken, 1);
+        assert_eq!(b.pend_status, OsPendStatus::Ok);
+        assert_eq!(b.pend_on(), OsPendOn::Nothing);
+
+        // POST_ALL drains the rest; the count is untouched
+        assert_eq!(sem.wake(opt::POST_ALL, |_| woken += 1), Some(6));
+        assert_eq!(woken, 3);
+        assert_eq!(sem.waiters(), 0);
+        assert_eq!(sem.count(), 0);
+        assert_eq!(sem.wake(opt::POST_ALL, |_| panic!()), None);
+    }
+
+    #[test]
+    fn test_bounded_post_at_max() {
+        let mut sem = OsSem { max: 2, ..OsSem::new(1) };
+        assert_eq!(sem.post(opt::NONE), Ok(PostResult::Count(2)));
+        assert_eq!(sem.post(opt::NONE), Err(OsError::SemOvf));
+        assert_eq!(sem.post(opt::POST_ALL), Err(OsError::SemOvf));
+        assert_eq!(sem.count(), 2);
+
+        sem.full = SemFull::Saturate;
+        assert_eq!(sem.post(opt::NONE), Ok(PostResult::Count(2)));
+        assert_eq!(sem.post(opt::POST_ALL | opt::POST_NO_SCHED), Ok(PostResult::Count(2)));
+        assert_eq!(sem.count(), 2);
+
+        sem.set(7).unwrap();
+        assert_eq!(sem.count(), 2);
+        sem.set(0).unwrap();
+
+        // Setting a count under waiters would strand them
+        let mut a = tcb(4);
+        wait(&mut sem, &mut a);
+        assert_eq!(sem.set(1), Err(OsError::TaskWaiting));
+        assert_eq!(sem.set(0), Err(OsError::TaskWaiting));
+        assert_eq!(sem.count(), 0);
+        assert_eq!(sem.wake(opt::POST_FIFO, |_| {}), Some(4));
+        sem.set(1).unwrap();
+        assert_eq!(sem.count(), 1);
+        sem.set(0).unwrap();
+
+        // Waiters take a post whatever the maximum
+        let mut binary = OsSem { max: 1, ..OsSem::new(0) };
+        let (mut a, mut b) = (tcb(3), tcb(5));
+        wait(&mut binary, &mut a);
+        wait(&mut binary, &mut b);
+        assert_eq!(binary.wake(opt::POST_ALL, |_| {}), Some(3));
+        assert_eq!(binary.count(), 0);
+        assert_eq!(binary.post(opt::NONE), Ok(PostResult::Count(1)));
+        assert_eq!(binary.post(opt::NONE), Err(OsError::SemOvf));
+    }
+
+    #[cfg(feature = "mutex")]
+    #[test]
+    fn test_waiter_prio_change_resorts() {
+        let mut sem = OsSem::new(0);
+        let (mut a, mut b) = (tcb(4), tcb(8));
+        wait(&mut sem, &mut a);
+        wait(&mut sem, &mut b);
+
+        unsafe { crate::sync::prio_change(NonNull::from(&mut b), 2) };
+        assert_eq!(sem.pend_list.head(), Some(NonNull::from(&mut b)));
+        unsafe { crate::sync::prio_change(NonNull::from(&mut b), 9) };
+        assert_eq!(sem.pend_list.head(), Some(NonNull::from(&mut a)));
+        assert_eq!(sem.waiters(), 2);
+    }
+
+    #[test]
+    fn test_consume_all_burst_then_pend() {
+        let mut sem = OsSem::new(0);
+        for _ in 0..3 {
+            sem.post(opt::NONE).unwrap();
+        }
+        assert_eq!(sem.take(true), 3);
+        assert_eq!(sem.count(), 0);
+        assert_eq!(sem.accept(), Ok(0));
+
+        sem.post(opt::NONE).unwrap();
+        sem.post(opt::NONE).unwrap();
+        assert_eq!(sem.take(false), 1);
+        assert_eq!(sem.accept(), Ok(1));
+        assert_eq!(sem.count(), 0);
+        assert_eq!(OsSem { obj_type: OsObjType::None, ..OsSem::new(1) }.accept(), Err(OsError::ObjType));
+    }
+
+    #[test]
+    fn test_consume_all_pend_then_burst() {
+        let mut sem = OsSem::new(0);
+        let mut a = tcb(5);
+        wait(&mut sem, &mut a);
+
+        // The first post goes to the waiter, the rest pile up until it runs
+        assert_eq!(sem.wake(opt::POST_FIFO, |_| {}), Some(5));
+        sem.post(opt::NONE).unwrap();
+        sem.post(opt::NONE).unwrap();
+        assert_eq!(sem.taken_on_wake(true), 3);
+        assert_eq!(sem.count(), 0);
+
+        // A plain pend leaves the backlog
+        wait(&mut sem, &mut a);
+        assert_eq!(sem.wake(opt::POST_FIFO, |_| {}), Some(5));
+        sem.post(opt::NONE).unwrap();
+        assert_eq!(sem.taken_on_wake(false), 1);
+        assert_eq!(sem.count(), 1);
+    }
+
+    #[test]
+    fn test_post_result_count() {
+        assert_eq!(PostResult::WokeTask(4).count(), 0);
+        assert_eq!(PostResult::WokeTask(4).woke(), Some(4));
+        assert_eq!(PostResult::Count(3).count(), 3);
+        assert_eq!(PostResult::Count(3).woke(), None);
+    }
+}
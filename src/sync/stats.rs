@@ -0,0 +1,83 @@
+//! Per-object statistics (`obj-stats` feature)
+//!
+//! Semaphores and mutexes count their pends, posts and wake-up results
+//! inside the critical sections the pend and post paths already hold.
+//! Read a copy with `OsSem::stats` / `OsMutex::stats`, or for every
+//! registered object with `kernel::os_obj_registry_dump`.
+
+// Helpers are only reached from the objects that are compiled in
+#![cfg_attr(not(all(feature = "sem", feature = "mutex")), allow(dead_code))]
+
+use crate::error::{OsError, OsResult};
+use crate::types::OsTick;
+
+/// Counters of one semaphore or mutex
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OsObjStats {
+    /// Pend calls
+    pub pends: u32,
+    /// Pends that had to block
+    pub pends_blocked: u32,
+    /// Post calls
+    pub posts: u32,
+    /// Blocked pends that timed out
+    pub timeouts: u32,
+    /// Blocked pends that were aborted
+    pub aborts: u32,
+    /// Tasks waiting when the copy was taken
+    pub waiters: usize,
+    /// Most tasks ever waiting at once
+    pub waiters_max: usize,
+    /// Longest time the mutex was held, in ticks (0 for semaphores)
+    pub held_max: OsTick,
+}
+
+/// Semaphore statistics
+pub type SemStats = OsObjStats;
+
+/// Mutex statistics
+pub type MutexStats = OsObjStats;
+
+impl OsObjStats {
+    pub const fn new() -> Self {
+        Self {
+            pends: 0,
+            pends_blocked: 0,
+            posts: 0,
+            timeouts: 0,
+            aborts: 0,
+            waiters: 0,
+            waiters_max: 0,
+            held_max: 0,
+        }
+    }
+
+    /// A pend is about to block, `waiters` now waiting
+    #[inline]
+    pub(crate) fn blocked(&mut self, waiters: usize) {
+        self.pends_blocked = self.pends_blocked.wrapping_add(1);
+        self.waiters_max = self.waiters_max.max(waiters);
+    }
+
+    /// A blocked pend woke with `result`
+    #[inline]
+    pub(crate) fn woke<T>(&mut self, result: &OsResult<T>) {
+        match result {
+            Err(OsError::Timeout) => self.timeouts = self.timeouts.wrapping_add(1),
+            Err(OsError::PendAbort) => self.aborts = self.aborts.wrapping_add(1),
+            _ => {}
+        }
+    }
+
+    /// A mutex held since `since` was released at `now`
+    #[inline]
+    pub(crate) fn released(&mut self, since: OsTick, now: OsTick) {
+        self.held_max = self.held_max.max(now.wrapping_sub(since));
+    }
+
+    /// Copy with the current number of waiters
+    #[inline]
+    pub(crate) fn snapshot(&self, waiters: usize) -> Self {
+        Self { waiters, ..*self }
+    }
+}
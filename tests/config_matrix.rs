@@ -0,0 +1,66 @@
+//! Build matrix for the kernel sizing features
+//!
+//! Type-checks the library for a set of `prio-*`, `tick-*`, `tickwheel-*`
+//! and tick queue backend combinations so a bad combination fails its
+//! `const` assertions here instead of in a user's build. Slow, so ignored
+//! by default:
+//!
+//! ```text
+//! cargo test --test config_matrix -- --ignored
+//! ```
+
+#[cfg(test)]
+mod config_matrix_tests {
+    use std::process::Command;
+
+    const PRIO: &[&str] = &["prio-8", "prio-16", "prio-32", "prio-64", "prio-128", "prio-256"];
+    const TICK: &[&str] = &["tick-100hz", "tick-250hz", "tick-500hz", "tick-1khz"];
+    const WHEEL: &[&str] = &["tickwheel-8", "tickwheel-16", "tickwheel-32", "tickwheel-64"];
+
+    fn check(features: &str) -> bool {
+        let status = Command::new(env!("CARGO"))
+            .args(["check", "--lib", "--quiet", "--no-default-features", "--features"])
+            .arg(features)
+            .arg("--target-dir")
+            .arg(concat!(env!("CARGO_TARGET_TMPDIR"), "/config_matrix"))
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .status()
+            .expect("failed to run cargo");
+        status.success()
+    }
+
+    #[test]
+    #[ignore]
+    fn test_sizing_matrix() {
+        let mut failed = Vec::new();
+
+        // Every value of each group once, paired with a rotating partner
+        for (i, prio) in PRIO.iter().enumerate() {
+            let tick = TICK[i % TICK.len()];
+            let wheel = WHEEL[i % WHEEL.len()];
+            let features = format!("full {prio} {tick} {wheel}");
+            if !check(&features) {
+                failed.push(features);
+            }
+        }
+
+        // Each tick queue backend
+        for features in ["full tick-list", "full tick-wheel tickwheel-8"] {
+            if !check(features) {
+                failed.push(features.into());
+            }
+        }
+
+        assert!(failed.is_empty(), "failed combinations: {failed:?}");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_conflicting_features_rejected() {
+        assert!(!check("full prio-8 prio-16"));
+        assert!(!check("full tick-100hz tick-1khz"));
+        assert!(!check("full tickwheel-8 tickwheel-64"));
+        assert!(!check("full tick-wheel tick-list"));
+        assert!(!check("full tick-list tickwheel-32"));
+    }
+}
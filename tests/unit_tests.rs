@@ -194,3 +194,1194 @@ mod config_tests {
         assert_eq!(CFG_PRIO_IDLE, (CFG_PRIO_MAX - 1) as u8);
     }
 }
+
+#[cfg(test)]
+mod version_tests {
+    use ucosiii::kernel::version::*;
+    use ucosiii::kernel::{os_features, os_version};
+
+    #[test]
+    fn test_version_encoding() {
+        let parts: Vec<u32> = os_version().split('.').map(|p| p.parse().unwrap()).collect();
+        assert_eq!(os_version(), env!("CARGO_PKG_VERSION"));
+        assert_eq!(OS_VERSION, parts[0] * 10_000 + parts[1] * 100 + parts[2]);
+        assert_eq!(OS_VERSION_SYM, OS_VERSION);
+    }
+
+    #[test]
+    fn test_features_match_build() {
+        let features = os_features();
+        assert_eq!(features & OS_FEATURE_SEM != 0, cfg!(feature = "sem"));
+        assert_eq!(features & OS_FEATURE_MUTEX != 0, cfg!(feature = "mutex"));
+        assert_eq!(features & OS_FEATURE_RWLOCK != 0, cfg!(feature = "rwlock"));
+        assert_eq!(features & OS_FEATURE_BARRIER != 0, cfg!(feature = "barrier"));
+        assert_eq!(features & OS_FEATURE_RING != 0, cfg!(feature = "ring"));
+        assert_eq!(features & OS_FEATURE_TICK_TASK != 0, cfg!(feature = "tick-task"));
+        assert_eq!(features & OS_FEATURE_TRACE != 0, cfg!(feature = "trace"));
+        assert_eq!(features & OS_FEATURE_TASK_TABLE != 0, cfg!(feature = "task-table"));
+        assert_eq!(features & OS_FEATURE_NO_NAMES != 0, cfg!(feature = "no-names"));
+        assert_eq!(features & OS_FEATURE_DEINIT != 0, cfg!(feature = "deinit"));
+        assert_eq!(features & OS_FEATURE_BUDGET != 0, cfg!(feature = "budget"));
+        assert_eq!(features >> 29, 0, "unassigned bits set");
+    }
+}
+
+#[cfg(test)]
+mod task_reg_tests {
+    use ucosiii::config::CFG_TASK_REG_TBL_SIZE;
+    use ucosiii::error::OsError;
+    use ucosiii::task::*;
+
+    ucosiii::task_local! {
+        static PREFIX: &'static str = "none";
+    }
+
+    #[test]
+    fn test_reg_id_invalid() {
+        let id = CFG_TASK_REG_TBL_SIZE as OsRegId;
+        assert_eq!(os_task_reg_set(None, id, 1), Err(OsError::RegIdInvalid));
+        assert_eq!(os_task_reg_get(None, id), Err(OsError::RegIdInvalid));
+    }
+
+    #[test]
+    fn test_task_local_without_task() {
+        // No task is running on the host: reads fall back to the default
+        assert_eq!(PREFIX.get(), "none");
+        assert_eq!(PREFIX.set("net"), Err(OsError::TcbInvalid));
+    }
+}
+
+#[cfg(test)]
+mod task_ext_tests {
+    use core::ptr::NonNull;
+    use ucosiii::error::OsError;
+    use ucosiii::task::*;
+    use ucosiii::types::OsObjType;
+
+    struct DriverCtx(u32);
+    struct Arena;
+
+    ucosiii::task_ext!(DriverCtx);
+    ucosiii::task_ext!(Arena);
+
+    static DRIVER: DriverCtx = DriverCtx(7);
+
+    #[test]
+    fn test_ext_typed() {
+        let mut tcb = OsTcb::new();
+        let ptr = NonNull::from(&mut tcb);
+        assert_eq!(os_task_ext_set(Some(ptr), &DRIVER), Err(OsError::TcbInvalid));
+
+        tcb.obj_type = OsObjType::Task;
+        let ptr = NonNull::from(&mut tcb);
+        assert!(os_task_ext_get::<DriverCtx>(Some(ptr)).is_none());
+
+        os_task_ext_set(Some(ptr), &DRIVER).unwrap();
+        assert_eq!(os_task_ext_get::<DriverCtx>(Some(ptr)).map(|c| c.0), Some(7));
+        // Another type's tag never matches
+        assert!(os_task_ext_get::<Arena>(Some(ptr)).is_none());
+
+        os_task_ext_clear(Some(ptr)).unwrap();
+        assert!(os_task_ext_get::<DriverCtx>(Some(ptr)).is_none());
+    }
+}
+
+#[cfg(test)]
+mod task_info_tests {
+    use ucosiii::error::OsError;
+    use ucosiii::task::*;
+
+    #[test]
+    fn test_info_uninit_tcb() {
+        let mut tcb = OsTcb::new();
+        let ptr = core::ptr::NonNull::from(&mut tcb);
+        assert_eq!(os_task_info(Some(ptr)).err(), Some(OsError::TcbInvalid));
+        assert_eq!(os_task_info_self().err(), Some(OsError::TcbInvalid));
+    }
+
+    #[test]
+    fn test_tcb_getters() {
+        use ucosiii::types::{OsPendOn, OsTaskState};
+
+        let tcb = OsTcb::new();
+        assert_eq!(tcb.name(), "");
+        assert_eq!(tcb.prio(), tcb.base_prio());
+        assert_eq!(tcb.state(), OsTaskState::Ready);
+        assert_eq!(tcb.pend_on(), OsPendOn::Nothing);
+    }
+
+    #[test]
+    fn test_current_before_start() {
+        use ucosiii::config::CFG_PRIO_IDLE;
+        use ucosiii::kernel::{current_prio, current_task_name};
+
+        assert_eq!(current(), None);
+        assert_eq!(current_task_name(), "main");
+        assert_eq!(current_prio(), CFG_PRIO_IDLE);
+    }
+}
+
+#[cfg(test)]
+mod rdy_queues_tests {
+    use core::ptr::NonNull;
+    use ucosiii::sched::ReadyQueues;
+    use ucosiii::task::OsTcb;
+
+    #[test]
+    fn test_shared_prio_keeps_bit() {
+        let mut a = OsTcb::new();
+        let mut b = OsTcb::new();
+        a.prio = 7;
+        b.prio = 7;
+        let (a, b) = (NonNull::from(&mut a), NonNull::from(&mut b));
+
+        let mut rdy = ReadyQueues::new();
+        rdy.insert(a);
+        rdy.insert(b);
+        assert_eq!(rdy.highest_tcb(), Some((7, a)));
+
+        // Removing one of two tasks must not hide the other
+        rdy.remove(a);
+        assert_eq!(rdy.highest_tcb(), Some((7, b)));
+
+        rdy.remove(b);
+        assert_eq!(rdy.highest(), None);
+    }
+
+    #[test]
+    fn test_change_prio() {
+        let mut a = OsTcb::new();
+        a.prio = 9;
+        let a = NonNull::from(&mut a);
+
+        let mut rdy = ReadyQueues::new();
+        rdy.insert(a);
+        rdy.change_prio(a, 2);
+        assert_eq!(rdy.highest_tcb(), Some((2, a)));
+        assert!(rdy.list(9).is_empty());
+    }
+
+    fn order(rdy: &ReadyQueues, prio: u8) -> Vec<NonNull<OsTcb>> {
+        let mut out = Vec::new();
+        let mut next = rdy.list(prio).head();
+        while let Some(t) = next {
+            out.push(t);
+            next = unsafe { t.as_ref() }.next_ptr;
+        }
+        out
+    }
+
+    /// Woken tasks with slice left go ahead of waiting peers, behind a
+    /// running peer; others queue at the tail
+    #[cfg(not(feature = "rr-precise"))]
+    #[test]
+    fn test_woken_order() {
+        let mut tcbs = [OsTcb::new(), OsTcb::new(), OsTcb::new(), OsTcb::new()];
+        for t in tcbs.iter_mut() {
+            t.prio = 5;
+            t.time_quanta = 10;
+            t.time_quanta_ctr = 10;
+        }
+        // `partial` blocked 4 ticks into its slice
+        tcbs[2].time_quanta_ctr = 6;
+        let [run, peer, partial, fresh] = tcbs.each_mut().map(NonNull::from);
+
+        let mut rdy = ReadyQueues::new();
+        rdy.insert(run);
+        rdy.insert(peer);
+        rdy.insert_woken(fresh, false, Some(run));
+        rdy.insert_woken(partial, false, Some(run));
+        assert_eq!(order(&rdy, 5), [run, partial, peer, fresh]);
+
+        // With nothing running at that priority, straight to the head
+        rdy.remove(run);
+        rdy.remove(partial);
+        rdy.insert_woken(partial, false, None);
+        assert_eq!(order(&rdy, 5), [partial, peer, fresh]);
+
+        // POST_FRONT overrides a fresh slice
+        rdy.remove(fresh);
+        rdy.insert_woken(fresh, true, Some(partial));
+        assert_eq!(order(&rdy, 5), [partial, fresh, peer]);
+        assert_eq!(rdy.list(5).tail(), Some(peer));
+        assert_eq!(rdy.list(5).count(), 3);
+    }
+
+    #[cfg(not(feature = "rr-precise"))]
+    #[test]
+    fn test_slice_started() {
+        let mut t = OsTcb::new();
+        assert!(!t.slice_started());
+        t.time_quanta = 3;
+        t.time_quanta_ctr = 3;
+        assert!(!t.slice_started());
+        t.time_quanta_ctr = 1;
+        assert!(t.slice_started());
+    }
+}
+
+#[cfg(test)]
+mod timeout_tests {
+    use ucosiii::types::{opt, Timeout};
+
+    #[test]
+    fn test_to_raw() {
+        assert_eq!(Timeout::Forever.to_raw(), (0, opt::PEND_BLOCKING));
+        assert_eq!(Timeout::Ticks(5).to_raw(), (5, opt::PEND_BLOCKING));
+        assert_eq!(Timeout::Ticks(0).to_raw(), (0, opt::PEND_NON_BLOCKING));
+        assert_eq!(Timeout::NoWait.to_raw(), (0, opt::PEND_NON_BLOCKING));
+    }
+}
+
+#[cfg(test)]
+mod duration_tests {
+    use ucosiii::time::{millis_to_ticks, Instant, TickDuration};
+
+    const PERIOD: u32 = TickDuration::from_millis(10).ticks_at(100);
+
+    #[test]
+    fn test_round_up() {
+        assert_eq!(PERIOD, 1);
+        assert_eq!(millis_to_ticks(1, 100), 1);
+        assert_eq!(millis_to_ticks(0, 100), 0);
+        assert_eq!(millis_to_ticks(15, 100), 2);
+        assert_eq!(TickDuration::from_micros(1).ticks_at(1000), 1);
+        assert_eq!(TickDuration::from_ticks(7).ticks_at(100), 7);
+    }
+
+    #[test]
+    fn test_instant_wraps() {
+        let before = Instant::from_ticks(u32::MAX - 1);
+        let after = Instant::from_ticks(3);
+        assert!(before < after);
+        assert_eq!(after.ticks_since(before), 5);
+    }
+
+    #[cfg(feature = "fugit")]
+    #[test]
+    fn test_fugit() {
+        use fugit::ExtU32;
+        assert_eq!(TickDuration::from(1.millis()).ticks_at(100), 1);
+        assert_eq!(TickDuration::from(2.secs()).ticks_at(100), 200);
+    }
+}
+
+#[cfg(all(test, feature = "embedded-hal"))]
+mod delay_tests {
+    use embedded_hal::delay::DelayNs;
+    use ucosiii::time::OsDelay;
+
+    #[test]
+    fn test_busy_wait_before_start() {
+        // The kernel is not running, so this spins on the timestamp counter
+        let mut delay = OsDelay::new();
+        delay.delay_ns(500);
+        delay.delay_us(1);
+    }
+}
+
+#[cfg(test)]
+mod critical_tests {
+    use ucosiii::critical::critical_section;
+
+    #[test]
+    fn test_nested_and_foreign_sections() {
+        let v = critical_section(|_outer| {
+            let inner = critical_section(|_inner| 1);
+            inner + ::critical_section::with(|_cs| 2)
+        });
+        assert_eq!(v, 3);
+    }
+}
+
+#[cfg(test)]
+mod os_config_tests {
+    use ucosiii::config::{CFG_CPU_CLK_HZ, CFG_PRIO_IDLE, CFG_STK_SIZE_MIN, CFG_TICK_RATE_HZ, CFG_TIME_QUANTA_DEFAULT};
+    use ucosiii::core::cs_cell::SyncUnsafeCell;
+    use ucosiii::error::OsError;
+    use ucosiii::kernel::{os_init_with, IdleStorage, OsConfig, SchedMode};
+    use ucosiii::task::OsTcb;
+    use ucosiii::types::OsStkElement;
+
+    #[test]
+    fn test_default_matches_cfg() {
+        let cfg = OsConfig::default();
+        assert_eq!(cfg.cpu_clk_hz, CFG_CPU_CLK_HZ);
+        assert_eq!(cfg.tick_rate_hz, CFG_TICK_RATE_HZ);
+        assert_eq!(cfg.time_quanta_default, CFG_TIME_QUANTA_DEFAULT);
+        assert_eq!(cfg.idle_wfi, !cfg!(any(feature = "idle-nop", feature = "defmt")));
+        assert_eq!(cfg.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_tick_rate_rejected() {
+        let zero = OsConfig { tick_rate_hz: 0, ..OsConfig::default() };
+        assert_eq!(os_init_with(&zero), Err(OsError::CfgTickRateInvalid));
+
+        let fast = OsConfig { tick_rate_hz: CFG_TICK_RATE_HZ + 1, ..OsConfig::default() };
+        assert_eq!(fast.validate(), Err(OsError::CfgTickRateInvalid));
+    }
+
+    #[test]
+    fn test_zero_quanta() {
+        let rr = OsConfig { time_quanta_default: 0, ..OsConfig::default() };
+        assert_eq!(os_init_with(&rr), Err(OsError::CfgTimeQuantaInvalid));
+
+        let no_rr = OsConfig { round_robin_en: false, ..rr };
+        assert_eq!(no_rr.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_cooperative_needs_round_robin_off() {
+        let coop = OsConfig { sched_mode: SchedMode::Cooperative, round_robin_en: true, ..OsConfig::default() };
+        assert_eq!(os_init_with(&coop), Err(OsError::CfgSchedModeInvalid));
+
+        let coop = OsConfig { round_robin_en: false, ..coop };
+        assert_eq!(coop.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_idle_stack_too_small() {
+        static TCB: SyncUnsafeCell<OsTcb> = SyncUnsafeCell::new(OsTcb::new());
+        static STK: SyncUnsafeCell<[OsStkElement; CFG_STK_SIZE_MIN]> = SyncUnsafeCell::new([0; CFG_STK_SIZE_MIN]);
+        static SMALL: IdleStorage = IdleStorage::new(&TCB, STK.get().cast(), CFG_STK_SIZE_MIN - 1);
+        static FITS: IdleStorage = IdleStorage::new(&TCB, STK.get().cast(), CFG_STK_SIZE_MIN);
+
+        let small = OsConfig { idle: Some(&SMALL), ..OsConfig::default() };
+        assert_eq!(os_init_with(&small), Err(OsError::CfgIdleStkInvalid));
+        assert_eq!(OsConfig { idle: Some(&FITS), ..small }.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_kernel_task_prios_checked() {
+        // Only the kernel tasks that are built and enabled count
+        let at_idle = OsConfig { tick_task_prio: CFG_PRIO_IDLE, ..OsConfig::default() };
+        let shared = OsConfig { wdog_task_prio: 7, self_check_task_prio: 7, ..OsConfig::default() };
+        if cfg!(feature = "tick-task") {
+            assert_eq!(at_idle.validate(), Err(OsError::CfgPrioInvalid));
+        }
+        assert_eq!(OsConfig { tick_task_en: false, ..at_idle }.validate(), Ok(()));
+        if cfg!(all(feature = "wdog", feature = "self-check")) {
+            assert_eq!(shared.validate(), Err(OsError::CfgPrioInvalid));
+        }
+        assert_eq!(OsConfig { wdog_en: false, ..shared }.validate(), Ok(()));
+        assert_eq!(OsConfig { wdog_task_prio: 8, ..shared }.validate(), Ok(()));
+    }
+}
+
+#[cfg(all(test, feature = "sem"))]
+mod registry_tests {
+    use ucosiii::error::OsError;
+    use ucosiii::kernel::{os_obj_registry_iter, os_safety_critical_start};
+    use ucosiii::sem::Semaphore;
+    use ucosiii::types::OsObjType;
+
+    static SEM: Semaphore = Semaphore::new(0);
+    #[cfg(feature = "mutex")]
+    static MUTEX: ucosiii::mutex::Mutex = ucosiii::mutex::Mutex::new();
+
+    #[test]
+    fn test_register_then_lock_creation() {
+        SEM.create(1, "reg").unwrap();
+        let found = os_obj_registry_iter()
+            .any(|e| e.obj_type == OsObjType::Sem && e.name == "reg");
+        assert!(found);
+        // Kept without defmt too
+        assert_eq!(SEM.name(), "reg");
+
+        #[cfg(feature = "mutex")]
+        {
+            assert_eq!(MUTEX.name(), "");
+            MUTEX.create("reg-mutex").unwrap();
+            assert_eq!(MUTEX.name(), "reg-mutex");
+        }
+
+        os_safety_critical_start();
+        assert_eq!(SEM.create(1, "reg"), Err(OsError::IllegalCreateRunTime));
+    }
+}
+
+#[cfg(test)]
+mod task_sem_tests {
+    use core::ptr::NonNull;
+    use ucosiii::error::OsError;
+    use ucosiii::task::{os_task_sem_pend, os_task_sem_post, OsTcb};
+    use ucosiii::types::opt;
+
+    #[test]
+    fn test_post_counts_when_not_pending() {
+        let mut tcb = OsTcb::new();
+        let ptr = NonNull::from(&mut tcb);
+
+        assert_eq!(os_task_sem_post(ptr, opt::NONE), Ok(1));
+        assert_eq!(os_task_sem_post(ptr, opt::NONE), Ok(2));
+        assert_eq!(tcb.sem_ctr, 2);
+    }
+
+    #[test]
+    fn test_pend_before_start() {
+        assert_eq!(os_task_sem_pend(0, opt::PEND_BLOCKING), Err(OsError::OsNotRunning));
+    }
+}
+
+#[cfg(all(test, feature = "sem"))]
+mod pend_list_tests {
+    use core::ptr::NonNull;
+    use ucosiii::sem::PendList;
+    use ucosiii::task::OsTcb;
+    use ucosiii::types::opt;
+
+    fn tcb(prio: u8) -> OsTcb {
+        let mut t = OsTcb::new();
+        t.prio = prio;
+        t
+    }
+
+    #[test]
+    fn test_fifo_within_priority() {
+        let mut tcbs = [tcb(5), tcb(5), tcb(3), tcb(5)];
+        let [a, b, hi, c] = tcbs.each_mut().map(NonNull::from);
+        let mut list = PendList::new();
+
+        for t in [a, b, hi, c] {
+            list.insert_by_prio(t);
+        }
+        assert_eq!(list.len(), 4);
+
+        // Higher priority first, then arrival order
+        let mut order = [None; 4];
+        for slot in order.iter_mut() {
+            let next = list.next_waiter(opt::POST_FIFO).unwrap();
+            list.remove(next);
+            *slot = Some(next);
+        }
+        assert_eq!(order, [Some(hi), Some(a), Some(b), Some(c)]);
+        assert!(list.is_empty());
+        assert_eq!(list.len(), 0);
+    }
+
+    #[test]
+    fn test_lifo_within_priority() {
+        let mut tcbs = [tcb(5), tcb(5), tcb(7), tcb(5)];
+        let [a, b, lo, c] = tcbs.each_mut().map(NonNull::from);
+        let mut list = PendList::new();
+
+        for t in [a, b, lo, c] {
+            list.insert_by_prio(t);
+        }
+
+        // Most recent of the highest priority, never the lower one
+        let mut order = [None; 4];
+        for slot in order.iter_mut() {
+            let next = list.next_waiter(opt::POST_LIFO).unwrap();
+            list.remove(next);
+            *slot = Some(next);
+        }
+        assert_eq!(order, [Some(c), Some(b), Some(a), Some(lo)]);
+    }
+
+    #[test]
+    fn test_empty_list_has_no_waiter() {
+        let list = PendList::new();
+        assert_eq!(list.next_waiter(opt::POST_LIFO), None);
+        assert_eq!(list.len(), 0);
+    }
+}
+
+#[cfg(test)]
+mod time_quanta_tests {
+    use core::ptr::NonNull;
+    use ucosiii::config::CFG_TIME_QUANTA_MAX;
+    use ucosiii::error::OsError;
+    use ucosiii::task::{os_task_time_quanta_set, OsTcb};
+
+    #[test]
+    fn test_shorter_quanta_clamps_slice() {
+        let mut tcb = OsTcb::new();
+        tcb.time_quanta = 10;
+        tcb.time_quanta_ctr = 7;
+        let ptr = NonNull::from(&mut tcb);
+
+        os_task_time_quanta_set(Some(ptr), 3).unwrap();
+        assert_eq!((tcb.time_quanta, tcb.time_quanta_ctr), (3, 3));
+
+        // Longer quanta leaves the running slice alone
+        os_task_time_quanta_set(Some(NonNull::from(&mut tcb)), 50).unwrap();
+        assert_eq!((tcb.time_quanta, tcb.time_quanta_ctr), (50, 3));
+    }
+
+    #[test]
+    fn test_exemption_round_trip() {
+        let mut tcb = OsTcb::new();
+        tcb.time_quanta = 10;
+        tcb.time_quanta_ctr = 4;
+
+        os_task_time_quanta_set(Some(NonNull::from(&mut tcb)), 0).unwrap();
+        assert_eq!((tcb.time_quanta, tcb.time_quanta_ctr), (0, 0));
+
+        os_task_time_quanta_set(Some(NonNull::from(&mut tcb)), 5).unwrap();
+        assert_eq!((tcb.time_quanta, tcb.time_quanta_ctr), (5, 5));
+    }
+
+    #[test]
+    fn test_quanta_above_max() {
+        let mut tcb = OsTcb::new();
+        assert_eq!(
+            os_task_time_quanta_set(Some(NonNull::from(&mut tcb)), CFG_TIME_QUANTA_MAX + 1),
+            Err(OsError::SchedInvalidTimeSlice)
+        );
+        assert_eq!(tcb.time_quanta, 0);
+    }
+}
+
+#[cfg(test)]
+mod task_create_tests {
+    extern crate std;
+    use std::boxed::Box;
+
+    use ucosiii::config::{CFG_PRIO_IDLE, CFG_PRIO_MAX, CFG_PRIO_RESERVED};
+    use ucosiii::error::OsError;
+    use ucosiii::task::{os_task_create, OsTcb};
+    use ucosiii::types::OsStkElement;
+
+    fn spin(_: *mut ()) -> ! {
+        loop {}
+    }
+
+    fn create(prio: u8) -> Result<(), OsError> {
+        let tcb = Box::leak(Box::new(OsTcb::new()));
+        let stk: &'static mut [OsStkElement] = Box::leak(Box::new([0; 256]));
+        os_task_create(tcb, stk, "app", spin, prio)
+    }
+
+    #[test]
+    fn test_reserved_prio_rejected() {
+        assert!(CFG_PRIO_RESERVED.contains(&CFG_PRIO_IDLE));
+        for &prio in CFG_PRIO_RESERVED {
+            assert_eq!(create(prio), Err(OsError::PrioInvalid));
+        }
+        if CFG_PRIO_MAX <= u8::MAX as usize {
+            assert_eq!(create(CFG_PRIO_MAX as u8), Err(OsError::PrioInvalid));
+        }
+    }
+}
+
+#[cfg(test)]
+mod preempt_threshold_tests {
+    use core::ptr::NonNull;
+    use ucosiii::error::OsError;
+    use ucosiii::task::{os_task_set_preempt_threshold, OsTcb};
+
+    fn tcb(prio: u8) -> OsTcb {
+        let mut tcb = OsTcb::new();
+        tcb.prio = prio;
+        tcb.base_prio = prio;
+        tcb.preempt_threshold = prio;
+        tcb
+    }
+
+    #[test]
+    fn test_threshold_filters_preemption() {
+        let mut tcb = tcb(10);
+        assert!(tcb.preempted_by(7));
+
+        assert_eq!(os_task_set_preempt_threshold(Some(NonNull::from(&mut tcb)), 5), Ok(10));
+        assert!(!tcb.preempted_by(7));
+        assert!(!tcb.preempted_by(5));
+        assert!(tcb.preempted_by(4));
+    }
+
+    #[test]
+    fn test_threshold_above_prio() {
+        let mut tcb = tcb(10);
+        assert_eq!(
+            os_task_set_preempt_threshold(Some(NonNull::from(&mut tcb)), 11),
+            Err(OsError::PreemptThresholdInvalid)
+        );
+        assert_eq!(tcb.preempt_threshold(), 10);
+    }
+
+    #[test]
+    fn test_boost_above_threshold() {
+        let mut tcb = tcb(10);
+        tcb.preempt_threshold = 5;
+
+        // Inherited priority 3 beats the threshold
+        tcb.prio = 3;
+        assert!(!tcb.preempted_by(4));
+        assert!(tcb.preempted_by(2));
+    }
+}
+
+#[cfg(all(test, feature = "task-group"))]
+mod task_group_tests {
+    use core::ptr::NonNull;
+    use ucosiii::error::OsError;
+    use ucosiii::task::{Group, OsTcb};
+    use ucosiii::types::opt;
+
+    #[test]
+    fn test_attach_moves_between_groups() {
+        // Not created: another test may have started safety-critical mode
+        static NORMAL: Group = Group::new();
+        static SAFE: Group = Group::new();
+
+        let mut tcbs = [OsTcb::new(), OsTcb::new()];
+        let [a, b] = tcbs.each_mut().map(NonNull::from);
+        NORMAL.attach(a).unwrap();
+        NORMAL.attach(b).unwrap();
+        NORMAL.attach(b).unwrap();
+        assert_eq!(NORMAL.len(), 2);
+        assert!(unsafe { b.as_ref() }.group_name().is_some());
+
+        SAFE.attach(b).unwrap();
+        assert_eq!((NORMAL.len(), SAFE.len()), (1, 1));
+        assert!(SAFE.contains(b) && !NORMAL.contains(b));
+
+        assert_eq!(NORMAL.detach(b), Err(OsError::TaskNotInGroup));
+        SAFE.detach(b).unwrap();
+        assert!(SAFE.is_empty());
+        assert_eq!(unsafe { b.as_ref() }.group_name(), None);
+    }
+
+    #[test]
+    fn test_kernel_task_rejected() {
+        static GROUP: Group = Group::new();
+
+        let mut tcb = OsTcb::new();
+        tcb.opt = opt::TASK_KERNEL | opt::TASK_CRITICAL;
+        assert_eq!(GROUP.attach(NonNull::from(&mut tcb)), Err(OsError::TaskGroupKernel));
+        assert!(GROUP.is_empty());
+    }
+}
+
+#[cfg(all(test, feature = "task-table"))]
+mod task_table_tests {
+    use ucosiii::os_task_table;
+    use ucosiii::task::{task_table_prios_unique, TaskSpec};
+    use ucosiii::types::opt;
+
+    fn idle(_: *mut ()) -> ! {
+        loop {}
+    }
+
+    const SPECS: &[TaskSpec] = &[
+        TaskSpec::new("Sensor", idle, 4, 256),
+        TaskSpec::new("Log", idle, 12, 127).opt(opt::TASK_STK_CHK),
+        TaskSpec::new("Ui", idle, 8, 200),
+    ];
+    const _: () = assert!(task_table_prios_unique(SPECS));
+
+    os_task_table!(static TASKS = SPECS;);
+
+    #[test]
+    fn test_arena_covers_table() {
+        assert_eq!(TASKS.stk_words(), 256 + 128 + 200);
+        assert_eq!(TASKS.specs()[1].opt, opt::TASK_STK_CHK);
+        assert_eq!(TASKS.error(), None);
+    }
+
+    #[test]
+    fn test_tcb_lookup() {
+        assert_eq!(TASKS.tcb_by_name("Ui"), TASKS.tcb(2));
+        assert_ne!(TASKS.tcb(0), TASKS.tcb(1));
+        assert_eq!(TASKS.tcb(3), None);
+        assert_eq!(TASKS.tcb_by_name("Missing"), None);
+    }
+}
+
+#[cfg(test)]
+mod sched_introspection_tests {
+    use ucosiii::config::CFG_PRIO_IDLE;
+    use ucosiii::sched::{os_highest_ready_prio, os_next_wake_ticks, os_ready_task_count};
+
+    #[test]
+    fn test_snapshot_before_start() {
+        // Only the idle task (and kernel tasks) can be ready here
+        assert!(os_highest_ready_prio() <= CFG_PRIO_IDLE);
+        assert!(os_ready_task_count(true) <= os_ready_task_count(false));
+        assert_eq!(os_next_wake_ticks(), None);
+    }
+}
+
+#[cfg(all(test, feature = "latency-stats"))]
+mod latency_stats_tests {
+    use ucosiii::critical::critical_section;
+    use ucosiii::kernel::{os_stats, os_stats_reset};
+
+    #[test]
+    fn test_longest_critical_section_located() {
+        os_stats_reset();
+
+        critical_section(|_cs| {
+            // The host timestamp counter advances once per read
+            for _ in 0..1000 {
+                let _ = ucosiii::port::os_cpu_ts_get();
+            }
+        });
+        let line = line!() - 6;
+
+        let stats = os_stats();
+        assert!(stats.cs_max >= 1000);
+        let at = stats.cs_max_at.unwrap();
+        assert!(at.file().ends_with("unit_tests.rs"));
+        assert_eq!(at.line(), line);
+        assert_eq!(stats.ctx_sw.count, 0);
+        assert_eq!(stats.ctx_sw.avg(), 0);
+    }
+}
+
+#[cfg(all(test, feature = "fault-recovery"))]
+mod fault_recovery_tests {
+    use core::ptr::NonNull;
+    use ucosiii::task::{os_task_critical_set, OsTcb};
+    use ucosiii::types::opt;
+
+    #[test]
+    fn test_critical_flag_round_trip() {
+        let mut tcb = OsTcb::new();
+        tcb.opt = opt::TASK_STK_CHK;
+
+        os_task_critical_set(Some(NonNull::from(&mut tcb)), true).unwrap();
+        assert_eq!(tcb.opt, opt::TASK_STK_CHK | opt::TASK_CRITICAL);
+
+        os_task_critical_set(Some(NonNull::from(&mut tcb)), false).unwrap();
+        assert_eq!(tcb.opt, opt::TASK_STK_CHK);
+        assert!(tcb.fault.is_none());
+    }
+}
+
+#[cfg(test)]
+mod isr_tests {
+    use ucosiii::os_isr;
+
+    #[test]
+    fn test_os_isr_brackets_body() {
+        let v = os_isr! {
+            let a = 20;
+            a + 22
+        };
+        assert_eq!(v, 42);
+
+        // Not running: enter/exit are no-ops and stay balanced
+        ucosiii::os_int_enter();
+        ucosiii::os_int_exit();
+        ucosiii::os_int_exit();
+    }
+}
+
+#[cfg(test)]
+mod time_hmsm_tests {
+    use ucosiii::error::OsError;
+    use ucosiii::time::os_time_dly_hmsm;
+    use ucosiii::types::opt::{TIME_HMSM_NON_STRICT as NON_STRICT, TIME_HMSM_STRICT as STRICT};
+
+    // Valid arguments get as far as the delay, which needs a running kernel
+    const VALID: Result<(), OsError> = Err(OsError::OsNotRunning);
+
+    #[test]
+    fn test_strict_boundaries() {
+        assert_eq!(os_time_dly_hmsm(99, 59, 59, 999, STRICT), VALID);
+        assert_eq!(os_time_dly_hmsm(1000, 0, 0, 0, STRICT), Err(OsError::TimeInvalidHours));
+        assert_eq!(os_time_dly_hmsm(0, 60, 0, 0, STRICT), Err(OsError::TimeInvalidMinutes));
+        assert_eq!(os_time_dly_hmsm(0, 0, 60, 0, STRICT), Err(OsError::TimeInvalidSeconds));
+        assert_eq!(os_time_dly_hmsm(0, 0, 0, 1000, STRICT), Err(OsError::TimeInvalidMilliseconds));
+        assert_eq!(os_time_dly_hmsm(0, 0, 0, 0, STRICT), Err(OsError::TimeZeroDly));
+    }
+
+    #[test]
+    fn test_non_strict_boundaries() {
+        assert_eq!(os_time_dly_hmsm(0, 0, 90, 0, NON_STRICT), VALID);
+        assert_eq!(os_time_dly_hmsm(0, 9999, 65535, 1500, NON_STRICT), VALID);
+        assert_eq!(os_time_dly_hmsm(1000, 0, 0, 0, NON_STRICT), Err(OsError::TimeInvalidHours));
+        assert_eq!(os_time_dly_hmsm(0, 10_000, 0, 0, NON_STRICT), Err(OsError::TimeInvalidMinutes));
+        assert_eq!(os_time_dly_hmsm(0, 0, 0, 0, NON_STRICT), Err(OsError::TimeZeroDly));
+    }
+}
+
+#[cfg(all(test, feature = "sem"))]
+mod opt_tests {
+    use core::ptr::NonNull;
+    use ucosiii::error::OsError;
+    use ucosiii::sem::{OsSem, PostResult, Semaphore};
+    use ucosiii::task::{os_task_sem_post, OsTcb};
+    use ucosiii::types::{opt, PostOpt};
+
+    #[test]
+    fn test_unknown_bits_rejected() {
+        assert_eq!(opt::check(opt::PEND_NON_BLOCKING, opt::PEND_MASK), Ok(()));
+        assert_eq!(opt::check(opt::FLAG_CONSUME, opt::PEND_MASK), Err(OsError::OptInvalid));
+        assert_eq!(opt::check(opt::TASK_KERNEL, opt::TASK_MASK), Err(OsError::OptInvalid));
+
+        let mut sem = OsSem::new(0);
+        assert_eq!(sem.post(opt::FLAG_CONSUME), Err(OsError::OptInvalid));
+        assert_eq!(sem.post(opt::POST_LIFO | opt::POST_NO_SCHED), Ok(PostResult::Count(1)));
+        assert_eq!(sem.count(), 1);
+
+        let mut tcb = OsTcb::new();
+        let ptr = NonNull::from(&mut tcb);
+        assert_eq!(os_task_sem_post(ptr, opt::POST_LIFO), Err(OsError::OptInvalid));
+        assert_eq!(tcb.sem_ctr, 0);
+    }
+
+    #[test]
+    fn test_typed_post_opt() {
+        assert_eq!(PostOpt::default(), PostOpt::FIFO);
+        assert_eq!((PostOpt::LIFO | PostOpt::NO_SCHED).bits(), opt::POST_LIFO | opt::POST_NO_SCHED);
+
+        let sem = Semaphore::new(0);
+        assert_eq!(sem.signal_opt(PostOpt::LIFO | PostOpt::NO_SCHED), Ok(PostResult::Count(1)));
+        // No waiters: a broadcast counts like a plain post
+        assert_eq!(sem.broadcast(), Ok(PostResult::Count(2)));
+    }
+}
+
+#[cfg(all(test, feature = "sem"))]
+mod bounded_sem_tests {
+    use ucosiii::error::OsError;
+    use ucosiii::sem::{OsSem, SemFull, Semaphore};
+    use ucosiii::types::OsSemCtr;
+
+    #[test]
+    fn test_max_checked_at_create() {
+        let mut sem = OsSem::new(0);
+        assert_eq!(sem.max(), OsSemCtr::MAX);
+        assert_eq!(sem.create_bounded(0, 0, SemFull::Error, "zero"), Err(OsError::SemMaxInvalid));
+        assert_eq!(sem.create_bounded(3, 2, SemFull::Saturate, "over"), Err(OsError::SemMaxInvalid));
+        assert_eq!(sem.max(), OsSemCtr::MAX);
+
+        let sem = Semaphore::new(0);
+        assert_eq!(sem.create_bounded(2, 1, SemFull::Error, "bin"), Err(OsError::SemMaxInvalid));
+        assert_eq!(sem.max(), OsSemCtr::MAX);
+    }
+}
+
+#[cfg(all(test, feature = "obj-stats", feature = "sem"))]
+mod obj_stats_tests {
+    use ucosiii::error::OsError;
+    use ucosiii::sem::OsSem;
+    use ucosiii::sync::stats::SemStats;
+    use ucosiii::types::opt;
+
+    #[test]
+    fn test_sem_counts_posts() {
+        let mut sem = OsSem::new(0);
+        assert_eq!(sem.stats(), SemStats::default());
+
+        sem.post(opt::NONE).unwrap();
+        sem.post(opt::NONE).unwrap();
+
+        // Rejected before the critical section, so not counted
+        assert_eq!(sem.pend(0, opt::PEND_NON_BLOCKING), Err(OsError::OsNotRunning));
+
+        let st = sem.stats();
+        assert_eq!(st.posts, 2);
+        assert_eq!(st.pends, 0);
+        assert_eq!(st.waiters, 0);
+        assert_eq!(st.waiters_max, 0);
+    }
+}
+
+#[cfg(all(test, feature = "rwlock"))]
+mod rwlock_tests {
+    use ucosiii::error::OsError;
+    use ucosiii::rwlock::RwLock;
+    use ucosiii::types::Timeout;
+
+    #[test]
+    fn test_unlocked_state() {
+        let lock = RwLock::new();
+        assert_eq!(lock.readers(), 0);
+        assert!(!lock.is_write_locked());
+        assert_eq!(lock.waiters(), 0);
+
+        assert_eq!(lock.read_unlock(), Err(OsError::RwLockNotLocked));
+        assert_eq!(lock.read_lock(Timeout::NoWait), Err(OsError::OsNotRunning));
+        assert_eq!(lock.write_lock(Timeout::Forever), Err(OsError::OsNotRunning));
+        assert!(lock.write(Timeout::NoWait).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "barrier"))]
+mod barrier_tests {
+    use ucosiii::barrier::Barrier;
+    use ucosiii::error::OsError;
+    use ucosiii::types::Timeout;
+
+    static BARRIER: Barrier = Barrier::new(3);
+
+    #[test]
+    fn test_idle_barrier() {
+        assert_eq!(BARRIER.count(), 3);
+        assert_eq!(BARRIER.waiters(), 0);
+        assert_eq!(BARRIER.reset(), Ok(0));
+        assert_eq!(BARRIER.generation(), 1);
+        assert_eq!(BARRIER.wait(Timeout::NoWait), Err(OsError::OsNotRunning));
+    }
+
+    #[test]
+    fn test_zero_count_rejected() {
+        let barrier = Barrier::new(1);
+        assert_eq!(barrier.create(0, "none"), Err(OsError::BarrierCountInvalid));
+    }
+}
+
+#[cfg(all(test, feature = "ring"))]
+mod ring_tests {
+    extern crate std;
+
+    use core::ptr::NonNull;
+    use ucosiii::ring::SpscRing;
+    use ucosiii::task::OsTcb;
+
+    #[test]
+    fn test_wraps_in_order() {
+        let ring = SpscRing::<8>::new();
+        for b in 0..8 {
+            assert!(ring.push_from_isr(b));
+        }
+        assert!(!ring.push_from_isr(8));
+        assert_eq!(ring.len(), 8);
+
+        let mut buf = [0; 3];
+        assert_eq!(ring.read(&mut buf), 3);
+        assert_eq!(buf, [0, 1, 2]);
+
+        assert_eq!(ring.write(&[8, 9, 10, 11]), 3);
+
+        let mut buf = [0; 16];
+        assert_eq!(ring.read(&mut buf), 8);
+        assert_eq!(buf[..8], [3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(ring.read(&mut buf), 0);
+        assert_eq!(ring.pop_from_isr(), None);
+    }
+
+    #[test]
+    fn test_notify_on_transitions_only() {
+        let ring = SpscRing::<4>::new();
+        let mut tcb = OsTcb::new();
+        ring.set_notify(Some(NonNull::from(&mut tcb)));
+
+        // RX: only the push into an empty ring posts
+        assert!(ring.push_from_isr(1));
+        assert!(ring.push_from_isr(2));
+        assert_eq!(tcb.sem_ctr, 1);
+        let mut buf = [0; 4];
+        assert_eq!(ring.read(&mut buf), 2);
+        assert!(ring.push_from_isr(3));
+        assert_eq!(tcb.sem_ctr, 2);
+        assert_eq!(ring.read(&mut buf), 1);
+
+        // TX: only the pop out of a full ring posts
+        assert_eq!(ring.write(&[1, 2, 3, 4, 5]), 4);
+        assert_eq!(ring.pop_from_isr(), Some(1));
+        assert_eq!(ring.pop_from_isr(), Some(2));
+        assert_eq!(tcb.sem_ctr, 3);
+
+        ring.set_notify(None);
+        assert_eq!(ring.write(&[6, 7]), 2);
+        assert_eq!(ring.pop_from_isr(), Some(3));
+        assert_eq!(tcb.sem_ctr, 3);
+    }
+
+    #[test]
+    fn test_threads_keep_sequence() {
+        const COUNT: usize = 50_000;
+        let ring = SpscRing::<64>::new();
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let mut next = 0usize;
+                while next < COUNT {
+                    if ring.push_from_isr(next as u8) {
+                        next += 1;
+                    } else {
+                        std::thread::yield_now();
+                    }
+                }
+            });
+
+            let mut expect = 0usize;
+            let mut buf = [0; 24];
+            while expect < COUNT {
+                let n = ring.read(&mut buf);
+                if n == 0 {
+                    std::thread::yield_now();
+                }
+                for &b in &buf[..n] {
+                    assert_eq!(b, expect as u8);
+                    expect += 1;
+                }
+            }
+        });
+
+        assert!(ring.is_empty());
+    }
+}
+
+#[cfg(all(test, feature = "shell"))]
+mod shell_tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::sync::Mutex;
+    use ucosiii::shell::{exec, register, ShellOut};
+
+    static OUT: Mutex<String> = Mutex::new(String::new());
+
+    fn capture(bytes: &[u8]) {
+        OUT.lock().unwrap().push_str(core::str::from_utf8(bytes).unwrap());
+    }
+
+    fn run(line: &str) -> String {
+        OUT.lock().unwrap().clear();
+        exec(line, capture);
+        OUT.lock().unwrap().clone()
+    }
+
+    fn add(args: &str, out: &mut ShellOut) {
+        let mut sum = 0;
+        for n in args.split_whitespace() {
+            sum += n.parse::<u32>().unwrap_or(0);
+        }
+        out.str("sum=");
+        out.u32_pad(sum, 6);
+        out.nl();
+    }
+
+    #[test]
+    fn test_commands() {
+        register("add", add).unwrap();
+
+        assert_eq!(run("  add 40   2 "), "sum=    42\r\n");
+        assert_eq!(run("frob x"), "unknown command: frob\r\n");
+        assert_eq!(run("   "), "");
+        assert_eq!(run("kill"), "usage: kill <task>\r\n");
+        assert_eq!(run("kill Nobody"), "no task Nobody\r\n");
+        assert!(run("ps").starts_with("   ID PRIO STATE    NAME\r\n"));
+        assert!(run("stacks").starts_with(" USED  SIZE NAME\r\n"));
+        assert!(run("uptime").ends_with(" ticks, 0.000 s\r\n"));
+
+        let help = run("help");
+        assert!(help.starts_with("help\r\nps\r\nstacks\r\nsem\r\nuptime\r\nkill\r\n"));
+        assert!(help.contains("add\r\n"));
+    }
+}
+
+#[cfg(all(test, feature = "os3-compat"))]
+mod compat_tests {
+    use core::ptr::null_mut;
+    use ucosiii::compat::*;
+    use ucosiii::error::OsError;
+
+    /// The C argument order, with `p_err` last
+    #[test]
+    fn test_signatures() {
+        let _: fn(&mut OS_ERR) = OSInit;
+        let _: fn(&mut OS_ERR) = OSStart;
+        let _: fn(&mut OS_ERR) = OSSchedLock;
+        let _: fn(&mut OS_ERR) = OSSchedUnlock;
+        let _: fn(OS_TICK, OS_OPT, &mut OS_ERR) = OSTimeDly;
+        let _: fn(u16, u16, u16, u32, OS_OPT, &mut OS_ERR) = OSTimeDlyHMSM;
+        let _: fn(&mut OS_ERR) -> OS_TICK = OSTimeGet;
+        let _: fn(*mut OS_TCB, &mut OS_ERR) = OSTaskDel;
+        let _: fn(*mut OS_TCB, &mut OS_ERR) = OSTaskSuspend;
+        let _: fn(*mut OS_TCB, &mut OS_ERR) = OSTaskResume;
+        let _: fn(*mut OS_TCB, OS_OPT, &mut OS_ERR) -> OS_SEM_CTR = OSTaskSemPost;
+        let _: unsafe fn(OS_TICK, OS_OPT, *mut CPU_TS, &mut OS_ERR) -> OS_SEM_CTR = OSTaskSemPend;
+        #[allow(clippy::type_complexity)]
+        let _: unsafe fn(
+            *mut OS_TCB, &'static str, OS_TASK_PTR, *mut (), OS_PRIO, *mut CPU_STK,
+            CPU_STK_SIZE, CPU_STK_SIZE, u16, OS_TICK, *mut (), OS_OPT, &mut OS_ERR,
+        ) = OSTaskCreate;
+        #[cfg(feature = "sem")]
+        {
+            let _: fn(&mut OS_SEM, &'static str, OS_SEM_CTR, &mut OS_ERR) = OSSemCreate;
+            let _: unsafe fn(&mut OS_SEM, OS_TICK, OS_OPT, *mut CPU_TS, &mut OS_ERR) -> OS_SEM_CTR = OSSemPend;
+            let _: fn(&mut OS_SEM, OS_OPT, &mut OS_ERR) -> OS_SEM_CTR = OSSemPost;
+        }
+        #[cfg(feature = "mutex")]
+        {
+            let _: fn(&mut OS_MUTEX, &'static str, &mut OS_ERR) = OSMutexCreate;
+            let _: unsafe fn(&mut OS_MUTEX, OS_TICK, OS_OPT, *mut CPU_TS, &mut OS_ERR) = OSMutexPend;
+            let _: fn(&mut OS_MUTEX, OS_OPT, &mut OS_ERR) = OSMutexPost;
+        }
+    }
+
+    #[test]
+    fn test_error_codes_match_c() {
+        let mut err = OS_ERR_NONE;
+        OSTimeDly(0, OS_OPT_TIME_DLY, &mut err);
+        assert_eq!(err as u16, 29310); // OS_ERR_TIME_ZERO_DLY
+        OSTimeDly(10, 0x0008, &mut err);
+        assert_eq!(err as u16, 24101); // OS_ERR_OPT_INVALID
+        OSTimeDly(10, OS_OPT_TIME_DLY, &mut err);
+        assert_eq!(err, OsError::OsNotRunning);
+        OSSchedLock(&mut err);
+        assert_eq!(err, OsError::OsNotRunning);
+
+        err = OsError::Timeout;
+        let _ = OSTimeGet(&mut err);
+        assert_eq!(err, OS_ERR_NONE);
+    }
+
+    #[test]
+    fn test_task_sem() {
+        let mut tcb = OS_TCB::new();
+        let mut err = OS_ERR_NONE;
+        assert_eq!(OSTaskSemPost(&mut tcb, OS_OPT_POST_NO_SCHED, &mut err), 1);
+        assert_eq!(err, OS_ERR_NONE);
+        assert_eq!(unsafe { OSTaskSemPend(0, OS_OPT_PEND_BLOCKING, null_mut(), &mut err) }, 0);
+        assert_eq!(err, OsError::OsNotRunning);
+        OSTaskResume(null_mut(), &mut err);
+        assert_eq!(err, OsError::TcbInvalid);
+    }
+
+    #[cfg(feature = "sem")]
+    #[test]
+    fn test_sem() {
+        let mut sem = OS_SEM::new(0);
+        let mut err = OsError::Timeout;
+        OSSemCreate(&mut sem, "compat", 2, &mut err);
+        assert_eq!(err, OS_ERR_NONE);
+        assert_eq!(OSSemPost(&mut sem, OS_OPT_POST_1, &mut err), 3);
+        OSSemSet(&mut sem, 7, &mut err);
+        assert_eq!((err, sem.count()), (OS_ERR_NONE, 7));
+
+        let mut ts: CPU_TS = 0;
+        assert_eq!(unsafe { OSSemPend(&mut sem, 0, OS_OPT_PEND_BLOCKING, &mut ts, &mut err) }, 0);
+        assert_eq!(err, OsError::OsNotRunning);
+    }
+
+    #[cfg(feature = "mutex")]
+    #[test]
+    fn test_mutex() {
+        let mut mutex = OS_MUTEX::new();
+        let mut err = OsError::Timeout;
+        OSMutexCreate(&mut mutex, "compat", &mut err);
+        assert_eq!(err, OS_ERR_NONE);
+        unsafe { OSMutexPend(&mut mutex, 0, OS_OPT_PEND_BLOCKING, null_mut(), &mut err) };
+        assert_ne!(err, OS_ERR_NONE);
+    }
+}
+
+#[cfg(test)]
+mod auto_trait_tests {
+    fn send_sync<T: Send + Sync>() {}
+
+    /// Wrappers and handles may be shared between tasks; the negative
+    /// cases are `compile_fail` doc tests on the types
+    #[test]
+    fn test_shared_types() {
+        send_sync::<ucosiii::task::OsTcb>();
+        send_sync::<ucosiii::task::TaskHandle>();
+        #[cfg(feature = "sem")]
+        send_sync::<ucosiii::sem::Semaphore>();
+        #[cfg(feature = "mutex")]
+        send_sync::<ucosiii::mutex::Mutex>();
+        #[cfg(feature = "rwlock")]
+        send_sync::<ucosiii::rwlock::RwLock>();
+        #[cfg(feature = "barrier")]
+        send_sync::<ucosiii::barrier::Barrier>();
+    }
+}
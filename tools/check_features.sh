@@ -0,0 +1,11 @@
+#!/bin/sh
+# Type-check features that only build on a port, which host tests miss.
+#
+#   $ tools/check_features.sh
+
+set -e
+
+for target in thumbv7m-none-eabi thumbv7em-none-eabi thumbv8m.main-none-eabi; do
+    echo "fault-recovery: $target"
+    cargo check --lib --target "$target" --features fault-recovery
+done
@@ -0,0 +1,104 @@
+# GDB helper listing ucosiii-rs tasks from a halted target.
+#
+# Requires firmware built with the `kernel-awareness` feature.
+#
+#   (gdb) source tools/ucos_tasks.py
+#   (gdb) ucos-tasks
+
+import gdb
+
+SUPPORTED_VERSION = 1
+
+TASK_STATES = [
+    "Ready",
+    "Delayed",
+    "Pend",
+    "PendTimeout",
+    "Suspended",
+    "DelayedSuspended",
+    "PendSuspended",
+    "PendTimeoutSuspended",
+    "Faulted",
+]
+
+
+def _inferior():
+    return gdb.selected_inferior()
+
+
+def _read_u(addr, size):
+    data = _inferior().read_memory(addr, size).tobytes()
+    return int.from_bytes(data, "little")
+
+
+def _read_str(addr, length):
+    if addr == 0 or length == 0:
+        return ""
+    return _inferior().read_memory(addr, length).tobytes().decode("utf-8", "replace")
+
+
+class OsKaDesc:
+    """Decoded `OS_KA_DESC` descriptor (32-bit targets)."""
+
+    def __init__(self):
+        base = int(gdb.parse_and_eval("&OS_KA_DESC").cast(gdb.lookup_type("long")))
+        self.version = _read_u(base, 4)
+        if self.version != SUPPORTED_VERSION:
+            raise gdb.GdbError(
+                "OS_KA_DESC version %d not supported (expected %d)"
+                % (self.version, SUPPORTED_VERSION)
+            )
+        self.task_list = _read_u(base + 8, 4)
+        self.tcb_cur = _read_u(base + 12, 4)
+        self.tick_ctr = _read_u(base + 16, 4)
+        offs = [_read_u(base + 20 + 2 * i, 2) for i in range(8)]
+        (
+            self.off_name,
+            self.off_prio,
+            self.off_base_prio,
+            self.off_state,
+            self.off_stk_ptr,
+            self.off_stk_base,
+            self.off_stk_size,
+            self.off_dbg_next,
+        ) = offs
+
+
+class UcosTasks(gdb.Command):
+    """List all ucosiii-rs tasks: ucos-tasks"""
+
+    def __init__(self):
+        super().__init__("ucos-tasks", gdb.COMMAND_USER)
+
+    def invoke(self, arg, from_tty):
+        desc = OsKaDesc()
+        cur = _read_u(desc.tcb_cur, 4)
+        print("tick: %d" % _read_u(desc.tick_ctr, 4))
+        print("%-2s %-10s %-16s %5s %5s %-20s %10s %10s %6s"
+              % ("", "TCB", "Name", "Prio", "Base", "State", "SP", "Stk base", "Size"))
+
+        tcb = _read_u(desc.task_list, 4)
+        seen = set()
+        while tcb != 0 and tcb not in seen:
+            seen.add(tcb)
+            if desc.off_name == 0xFFFF:
+                name = "-"
+            else:
+                name = _read_str(_read_u(tcb + desc.off_name, 4), _read_u(tcb + desc.off_name + 4, 4))
+            state = _read_u(tcb + desc.off_state, 1)
+            state_name = TASK_STATES[state] if state < len(TASK_STATES) else str(state)
+            print("%-2s 0x%08x %-16s %5d %5d %-20s 0x%08x 0x%08x %6d" % (
+                "*" if tcb == cur else "",
+                tcb,
+                name,
+                _read_u(tcb + desc.off_prio, 1),
+                _read_u(tcb + desc.off_base_prio, 1),
+                state_name,
+                _read_u(tcb + desc.off_stk_ptr, 4),
+                _read_u(tcb + desc.off_stk_base, 4),
+                _read_u(tcb + desc.off_stk_size, 4),
+            ))
+            tcb = _read_u(tcb + desc.off_dbg_next, 4)
+
+
+UcosTasks()